@@ -0,0 +1,98 @@
+//! Incremental analysis benchmark: a small edit to a large file, against
+//! scanning and parsing the file again.
+//!
+//! Run with `cargo bench --bench incremental [size...]`. The time of an edit
+//! should stay flat as the file grows, while a full analysis grows with it.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use codecrafters_interpreter::dialect::Dialect;
+use codecrafters_interpreter::incremental::{Document, Edit, Work};
+
+/// Number of timed passes for each measurement; the fastest one is reported.
+const ITERATIONS: usize = 20;
+
+/// The number of statements in each generated file.
+const SIZES: [(&str, usize); 3] = [("1k", 1_000), ("10k", 10_000), ("100k", 100_000)];
+
+/// A program of `statements` declarations and prints, with a block every
+/// ten lines.
+fn program(statements: usize) -> String {
+    let mut source = String::new();
+    for i in 0..statements {
+        match i % 10 {
+            0 => source.push_str(&format!(
+                "{{\n  var local_{i} = {i};\n  print local_{i};\n}}\n"
+            )),
+            n if n % 2 == 0 => source.push_str(&format!("print value_{} * 2 + {i};\n", i - 1)),
+            _ => source.push_str(&format!("var value_{i} = \"item {i}\" + \"!\";\n")),
+        }
+    }
+    source
+}
+
+/// Runs `f` [`ITERATIONS`] times and returns the fastest time and what the
+/// last run returned.
+fn best<T>(mut f: impl FnMut() -> T) -> (Duration, T) {
+    let mut best = Duration::MAX;
+    let mut result = None;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        result = Some(black_box(f()));
+        best = best.min(start.elapsed());
+    }
+    (best, result.expect("at least one iteration"))
+}
+
+fn report(name: &str, time: Duration, work: Work) {
+    println!(
+        "{name:<28} {:>9.3} ms {:>10} bytes {:>7} parsed {:>7} reused",
+        time.as_secs_f64() * 1e3,
+        work.scanned,
+        work.parsed,
+        work.reused,
+    );
+}
+
+fn bench(size: &str, statements: usize) {
+    let source = program(statements);
+    let dialect = Dialect::default();
+    let (time, document) = best(|| Document::new(black_box(source.as_str()), dialect));
+    let work = Work {
+        scanned: source.len(),
+        parsed: document.ast().len(),
+        reused: 0,
+    };
+    report(&format!("{size} full analysis"), time, work);
+
+    // types a character into a name in the middle of the file, then deletes it
+    let mut document = document;
+    let middle = source[source.len() / 2..].find("value_").unwrap() + source.len() / 2;
+    let (time, work) = best(|| {
+        document.edit(Edit::new(middle..middle, "x"));
+        document.edit(Edit::new(middle..middle + 1, ""))
+    });
+    report(&format!("{size} edit within a line"), time, work);
+
+    // adds a line, which moves every line after it
+    let (time, work) = best(|| {
+        document.edit(Edit::new(middle..middle, "\n"));
+        document.edit(Edit::new(middle..middle + 1, ""))
+    });
+    report(&format!("{size} edit adding a line"), time, work);
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`; any other argument selects sizes by name.
+    let filters = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+
+    for (name, statements) in SIZES {
+        if filters.is_empty() || filters.iter().any(|f| name == f) {
+            bench(name, statements);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Scanner throughput benchmark.
+//!
+//! Run with `cargo bench --bench scanner [corpus...]`. Each corpus is generated
+//! deterministically so numbers are comparable across machines and commits.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use codecrafters_interpreter::scanner::{ScanItem, Scanner};
+
+/// Approximate size of every generated corpus.
+const CORPUS_BYTES: usize = 4 * 1024 * 1024;
+
+/// Number of timed passes over each corpus; the fastest one is reported.
+const ITERATIONS: usize = 5;
+
+const WORDS: &[&str] = &[
+    "alpha", "beta", "gamma", "delta", "epsilon", "zeta", "theta", "lambda", "sigma", "omega",
+];
+
+/// A tiny linear congruential generator, good enough to vary the corpora.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (self.0 >> 33) as usize
+    }
+
+    fn word(&mut self) -> &'static str {
+        WORDS[self.next() % WORDS.len()]
+    }
+}
+
+/// Builds a corpus by appending generated lines until it reaches [`CORPUS_BYTES`].
+fn generate(mut line: impl FnMut(&mut Lcg, &mut String)) -> String {
+    let mut rng = Lcg(0x5eed);
+    let mut source = String::with_capacity(CORPUS_BYTES + 256);
+    while source.len() < CORPUS_BYTES {
+        line(&mut rng, &mut source);
+    }
+    source
+}
+
+/// Declarations and arithmetic dominated by identifiers and keywords.
+fn identifiers_heavy() -> String {
+    generate(|rng, out| {
+        let (a, b, c) = (rng.word(), rng.word(), rng.word());
+        let n = rng.next() % 1000;
+        out.push_str(&format!(
+            "var {a}_{n} = {b} + {c}_value * {a}{n};\nprint {b}_{n} == {c};\n"
+        ));
+    })
+}
+
+/// Print statements with long string literals.
+fn string_heavy() -> String {
+    generate(|rng, out| {
+        out.push_str("print \"");
+        for _ in 0..12 {
+            out.push_str(rng.word());
+            out.push(' ');
+        }
+        out.push_str("\";\n");
+    })
+}
+
+/// Mostly line comments with the occasional statement.
+fn comment_heavy() -> String {
+    generate(|rng, out| {
+        out.push_str("// ");
+        for _ in 0..10 {
+            out.push_str(rng.word());
+            out.push(' ');
+        }
+        out.push('\n');
+        if rng.next() % 4 == 0 {
+            out.push_str("print nil;\n");
+        }
+    })
+}
+
+/// A named corpus generator.
+type Corpus = (&'static str, fn() -> String);
+
+/// Scans `source` once and returns the number of tokens produced.
+fn scan(source: &str) -> usize {
+    Scanner::new(source)
+        .scan_tokens()
+        .filter(|item| matches!(item, Ok(ScanItem::Token(_))))
+        .count()
+}
+
+fn bench(name: &str, source: &str) {
+    let mut best = Duration::MAX;
+    let mut tokens = 0;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        tokens = black_box(scan(black_box(source)));
+        best = best.min(start.elapsed());
+    }
+
+    let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+    println!(
+        "{name:<20} {megabytes:>6.2} MB {tokens:>9} tokens {:>9.2} ms {:>8.2} MB/s",
+        best.as_secs_f64() * 1e3,
+        megabytes / best.as_secs_f64(),
+    );
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`; any other argument selects corpora by name.
+    let filters = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+
+    let corpora: [Corpus; 3] = [
+        ("identifiers-heavy", identifiers_heavy),
+        ("string-heavy", string_heavy),
+        ("comment-heavy", comment_heavy),
+    ];
+
+    for (name, generate) in corpora {
+        if filters.is_empty() || filters.iter().any(|f| name.contains(f.as_str())) {
+            bench(name, &generate());
+        }
+    }
+}
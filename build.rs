@@ -0,0 +1,63 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Exposes build metadata to the crate as compile-time environment variables.
+fn main() {
+    // builds from a source archive, without a repository, can pass the hash in
+    println!("cargo:rerun-if-env-changed=RSLOX_GIT_HASH");
+    let git_hash = env::var("RSLOX_GIT_HASH")
+        .ok()
+        .or_else(|| git(&["rev-parse", "--short", "HEAD"]))
+        .unwrap_or_else(|| "unknown".into());
+    println!("cargo:rustc-env=RSLOX_GIT_HASH={git_hash}");
+
+    let mut features = env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(str::to_string))
+        .map(|feature| feature.to_lowercase().replace('_', "-"))
+        .collect::<Vec<_>>();
+    features.sort();
+    println!("cargo:rustc-env=RSLOX_FEATURES={}", features.join(","));
+
+    for file in git_files() {
+        println!("cargo:rerun-if-changed={}", file.display());
+    }
+}
+
+/// Runs git with `args` and returns what it printed, or `None` if it failed.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    Some(stdout.trim().to_string())
+}
+
+/// Returns the files git changes when `HEAD` moves to another commit: the
+/// `HEAD` of the worktree, the branch it points to, and the packed refs
+/// shared by all worktrees, which hold the branch once it is packed.
+fn git_files() -> Vec<PathBuf> {
+    let Some(dirs) = git(&["rev-parse", "--git-dir", "--git-common-dir"]) else {
+        return Vec::new();
+    };
+    let Some((git_dir, common_dir)) = dirs.split_once('\n') else {
+        return Vec::new();
+    };
+    let (git_dir, common_dir) = (Path::new(git_dir), Path::new(common_dir));
+
+    let mut files = vec![git_dir.join("HEAD"), common_dir.join("packed-refs")];
+    // a detached HEAD names no branch
+    if let Some(branch) = git(&["symbolic-ref", "-q", "HEAD"]) {
+        let branch = common_dir.join(branch);
+        // a packed branch has no file of its own until it moves, which
+        // creates one in the directory of its name
+        match branch.parent() {
+            Some(dir) if !branch.exists() => files.push(dir.to_path_buf()),
+            _ => files.push(branch),
+        }
+    }
+    // a missing file would make cargo rerun the script on every build
+    files.retain(|file| file.exists());
+    files
+}
@@ -0,0 +1,260 @@
+//! Timing programs over repeated runs, phase by phase, for tracking the
+//! performance of the interpreter in CI.
+
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum as _;
+use serde_json::{Value as Json, json};
+
+use crate::compiler;
+use crate::dialect::Dialect;
+use crate::error::Report;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::scanner::{ScanItem, Scanner};
+use crate::vm::{Backend, Vm};
+
+/// How long each phase of one run of a program took.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Sample {
+    pub scan: Duration,
+    pub parse: Duration,
+    /// Resolving locals to stack slots and emitting bytecode, which only
+    /// the VM backend does
+    pub compile: Duration,
+    pub execute: Duration,
+}
+
+impl Sample {
+    /// Returns the time the whole run took.
+    pub fn total(&self) -> Duration {
+        self.scan + self.parse + self.compile + self.execute
+    }
+}
+
+/// Scans, parses and runs `source` with `interpreter` on `backend`, timing
+/// each phase. The whole source is scanned before parsing starts, so that
+/// the two are timed apart.
+///
+/// Returns the first error that stops the program.
+pub fn sample(
+    source: &str,
+    dialect: Dialect,
+    backend: Backend,
+    interpreter: &mut Interpreter,
+) -> Result<Sample, Report> {
+    let mut sample = Sample::default();
+
+    let started = Instant::now();
+    let mut tokens = Vec::new();
+    let scanner = Scanner::new(source)
+        .with_dialect(dialect)
+        .with_interner(interpreter.interner().clone());
+    for item in scanner.scan_tokens() {
+        if let ScanItem::Token(token) = item? {
+            tokens.push(token);
+        }
+    }
+    sample.scan = started.elapsed();
+
+    let started = Instant::now();
+    let ast = Parser::from(tokens).with_dialect(dialect).parse()?;
+    sample.parse = started.elapsed();
+
+    match backend {
+        Backend::Tree => {
+            let started = Instant::now();
+            interpreter.interpret(&ast)?;
+            sample.execute = started.elapsed();
+        }
+        Backend::Vm => {
+            let started = Instant::now();
+            let chunk = compiler::compile(&ast)?;
+            sample.compile = started.elapsed();
+
+            let started = Instant::now();
+            Vm::new(interpreter).run(&chunk)?;
+            sample.execute = started.elapsed();
+        }
+    }
+    Ok(sample)
+}
+
+/// The fastest, mean and median of a set of durations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub min: Duration,
+    pub mean: Duration,
+    pub median: Duration,
+}
+
+impl Summary {
+    /// Summarizes `durations`, or returns zeros if there are none. The
+    /// median of an even number of durations is the mean of the middle two.
+    pub fn of(durations: impl IntoIterator<Item = Duration>) -> Self {
+        let mut durations = durations.into_iter().collect::<Vec<_>>();
+        if durations.is_empty() {
+            return Self::default();
+        }
+        durations.sort();
+
+        let len = durations.len();
+        let middle = len / 2;
+        let median = if len % 2 == 0 {
+            (durations[middle - 1] + durations[middle]) / 2
+        } else {
+            durations[middle]
+        };
+        Self {
+            min: durations[0],
+            mean: durations.iter().sum::<Duration>() / len as u32,
+            median,
+        }
+    }
+
+    /// Describes the summary as a JSON object, in seconds.
+    pub fn json(&self) -> Json {
+        json!({
+            "min": self.min.as_secs_f64(),
+            "mean": self.mean.as_secs_f64(),
+            "median": self.median.as_secs_f64(),
+        })
+    }
+}
+
+/// The summaries of a benchmark: of each phase, and of whole runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Results {
+    pub iterations: usize,
+    pub backend: Backend,
+    /// Every phase `backend` runs, in order
+    pub phases: Vec<(&'static str, Summary)>,
+    pub total: Summary,
+}
+
+impl Results {
+    /// Summarizes `samples`, all taken with `backend`.
+    pub fn new(samples: &[Sample], backend: Backend) -> Self {
+        let phase = |name, duration: fn(&Sample) -> Duration| {
+            (name, Summary::of(samples.iter().map(duration)))
+        };
+        let mut phases = vec![
+            phase("scan", |s| s.scan),
+            phase("parse", |s| s.parse),
+            phase("compile", |s| s.compile),
+            phase("execute", |s| s.execute),
+        ];
+        if backend == Backend::Tree {
+            phases.retain(|(name, _)| *name != "compile");
+        }
+
+        Self {
+            iterations: samples.len(),
+            backend,
+            phases,
+            total: Summary::of(samples.iter().map(Sample::total)),
+        }
+    }
+
+    /// Describes the results as a JSON object, with times in seconds.
+    pub fn json(&self) -> Json {
+        let phases = self
+            .phases
+            .iter()
+            .map(|(name, s)| (name.to_string(), s.json()));
+        json!({
+            "iterations": self.iterations,
+            "backend": self.backend.to_possible_value().map(|v| v.get_name().to_string()),
+            "phases": phases.collect::<serde_json::Map<_, _>>(),
+            "total": self.total.json(),
+        })
+    }
+}
+
+impl Display for Results {
+    /// Writes a table of the summaries, in milliseconds.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        writeln!(f, "{} iterations", self.iterations)?;
+        writeln!(
+            f,
+            "{:<8} {:>12} {:>12} {:>12}",
+            "phase", "min (ms)", "mean (ms)", "median (ms)"
+        )?;
+        let total = ("total", self.total);
+        let rows = self.phases.iter().chain([&total]);
+        for (name, summary) in rows {
+            writeln!(
+                f,
+                "{name:<8} {:>12.3} {:>12.3} {:>12.3}",
+                ms(summary.min),
+                ms(summary.mean),
+                ms(summary.median)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn ms(millis: &[u64]) -> Vec<Duration> {
+        millis.iter().copied().map(Duration::from_millis).collect()
+    }
+
+    #[rstest]
+    #[case(&[], [0, 0, 0])]
+    #[case(&[5, 1, 3], [1, 3, 3])]
+    #[case(&[4, 1, 2, 9], [1, 4, 3])]
+    fn test_summary(#[case] durations: &[u64], #[case] expected: [u64; 3]) {
+        let [min, mean, median] = expected.map(Duration::from_millis);
+        let expected = Summary { min, mean, median };
+        assert_eq!(expected, Summary::of(ms(durations)));
+    }
+
+    #[rstest]
+    #[case(Backend::Tree, vec!["scan", "parse", "execute"])]
+    #[case(Backend::Vm, vec!["scan", "parse", "compile", "execute"])]
+    fn test_sample_phases(#[case] backend: Backend, #[case] expected: Vec<&str>) {
+        let source = "var a = 1; var b = a + 2; print b;";
+        let dialect = Dialect::default();
+        let mut interpreter = Interpreter::new().with_muted_output(true);
+        let sample = sample(source, dialect, backend, &mut interpreter).unwrap();
+        assert_eq!(sample.total(), Results::new(&[sample], backend).total.min);
+
+        let results = Results::new(&[sample, sample], backend);
+        assert_eq!(2, results.iterations);
+        let phases = results.phases.iter().map(|(name, _)| *name);
+        assert_eq!(expected, phases.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_sample_errors() {
+        let mut interpreter = Interpreter::new().with_muted_output(true);
+        let err = sample(
+            "print x;",
+            Dialect::default(),
+            Backend::Tree,
+            &mut interpreter,
+        );
+        assert_eq!(
+            "Undefined variable 'x'.\n[line 1]",
+            err.unwrap_err().to_string()
+        );
+        let err = sample(
+            "print @;",
+            Dialect::default(),
+            Backend::Tree,
+            &mut interpreter,
+        );
+        assert_eq!(
+            "[line 1] Error: Unexpected character: @",
+            err.unwrap_err().to_string()
+        );
+    }
+}
@@ -0,0 +1,13 @@
+//! Metadata about how this binary was built, captured by `build.rs`.
+
+/// The crate version.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The abbreviated git commit hash, or `unknown` outside a git checkout.
+/// Setting `RSLOX_GIT_HASH` when building overrides it.
+pub const GIT_HASH: &str = env!("RSLOX_GIT_HASH");
+
+/// Returns the cargo features enabled in this build.
+pub fn features() -> impl Iterator<Item = &'static str> {
+    env!("RSLOX_FEATURES").split(',').filter(|f| !f.is_empty())
+}
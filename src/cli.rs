@@ -1,15 +1,273 @@
 use std::path::PathBuf;
 
+use crate::demo::Demo;
+use crate::diagnostic::{DiagnosticFormat, Level, Levels};
+use crate::dialect::{Dialect, Extension};
+use crate::interpreter::limits::Limit;
+use crate::interpreter::{ArithmeticCheck, StringUnit};
+use crate::parser::printer::ParseFormat;
+use crate::scanner::record::TokenFormat;
+use crate::vm::Backend;
+
 #[derive(Debug, clap::Parser)]
 pub struct Args {
     #[clap(subcommand)]
     pub subcommand: Command,
+
+    /// Enable a non-standard language extension (comma-separated or repeated)
+    #[clap(
+        long = "enable",
+        value_name = "EXTENSION",
+        value_delimiter = ',',
+        global = true
+    )]
+    pub extensions: Vec<Extension>,
+
+    /// Report NaN, overflow to infinity and negative zero produced by arithmetic
+    #[clap(
+        long = "arithmetic-checks",
+        value_name = "MODE",
+        default_value = "off",
+        global = true
+    )]
+    pub arithmetic_check: ArithmeticCheck,
+
+    /// What len, substr and indexOf count strings in
+    #[clap(
+        long = "string-units",
+        value_name = "UNIT",
+        default_value = "chars",
+        global = true
+    )]
+    pub string_unit: StringUnit,
+
+    /// Deny scripts access to the filesystem
+    #[clap(long, global = true)]
+    pub sandbox: bool,
+
+    /// Abort once the program executes more statements or calls, or its
+    /// source holds more tokens or bytes, than allowed (comma-separated)
+    #[clap(long, value_name = "RESOURCE=N", value_delimiter = ',', global = true)]
+    pub limits: Vec<Limit>,
+
+    /// How errors and warnings are written to stderr
+    #[clap(
+        long = "diagnostics",
+        value_name = "FORMAT",
+        default_value = "plain",
+        global = true
+    )]
+    pub diagnostics: DiagnosticFormat,
+
+    /// Drop warnings with CODE, or all of them with `warnings`
+    /// (comma-separated or repeated)
+    #[clap(long, value_name = "CODE", value_delimiter = ',', global = true)]
+    pub allow: Vec<String>,
+
+    /// Report warnings with CODE, even if the lint config allows or denies
+    /// them (comma-separated or repeated)
+    #[clap(long, value_name = "CODE", value_delimiter = ',', global = true)]
+    pub warn: Vec<String>,
+
+    /// Report warnings with CODE as errors, failing the command
+    /// (comma-separated or repeated)
+    #[clap(long, value_name = "CODE", value_delimiter = ',', global = true)]
+    pub deny: Vec<String>,
+
+    /// Read warning levels from a JSON file with `allow`, `warn` and `deny`
+    /// lists of codes, which the flags override
+    #[clap(long = "lint-config", value_name = "PATH", global = true)]
+    pub lint_config: Option<PathBuf>,
+
+    /// Also run a reference implementation as `PATH <subcommand> <filename>`
+    /// and report where its output differs
+    #[clap(long = "compare-reference", value_name = "PATH", global = true)]
+    pub compare_reference: Option<PathBuf>,
+}
+
+impl Args {
+    /// Builds the [`Dialect`] selected by the `--enable` flags.
+    pub fn dialect(&self) -> Dialect {
+        self.extensions.iter().copied().collect()
+    }
+
+    /// Sets the levels of the `--allow`, `--warn` and `--deny` flags on top
+    /// of `config`, the levels read from the lint config.
+    pub fn levels(&self, config: Levels) -> Levels {
+        let flags = [
+            (Level::Allow, &self.allow),
+            (Level::Warn, &self.warn),
+            (Level::Deny, &self.deny),
+        ];
+        let flags = flags
+            .into_iter()
+            .flat_map(|(level, codes)| codes.iter().map(move |code| (code, level)));
+        flags.fold(config, |levels, (code, level)| levels.with(code, level))
+    }
 }
 
 #[derive(Debug, clap::Subcommand)]
 pub enum Command {
-    Tokenize { filename: PathBuf },
-    Parse { filename: PathBuf },
-    Evaluate { filename: PathBuf },
-    Run { filename: PathBuf },
+    Tokenize {
+        filename: PathBuf,
+        /// How the tokens are printed
+        #[clap(long, value_name = "FORMAT", default_value = "text")]
+        format: TokenFormat,
+        /// Instead of printing the tokens, check that writing them back out
+        /// with the whitespace and comments between them gives the source
+        #[clap(long)]
+        verify_roundtrip: bool,
+    },
+    Parse {
+        filename: PathBuf,
+        /// How the syntax tree is printed
+        #[clap(long, value_name = "FORMAT", default_value = "sexpr")]
+        format: ParseFormat,
+        /// Tag each literal with its kind, as in (str "a") and (num 1.0),
+        /// in the sexpr format
+        #[clap(long)]
+        typed_literals: bool,
+    },
+    Evaluate {
+        filename: PathBuf,
+        /// Write each statement as it starts, and each expression with its
+        /// value as it finishes, to stderr or to PATH
+        #[clap(
+            long,
+            value_name = "PATH",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "-"
+        )]
+        trace: Option<PathBuf>,
+    },
+    Run {
+        /// The program to run, or `-` to read it from stdin
+        #[clap(required_unless_present_any = ["demo", "eval"])]
+        filename: Option<PathBuf>,
+        /// Run a program bundled with the interpreter instead of a file
+        #[clap(long, value_name = "NAME", conflicts_with = "filename")]
+        demo: Option<Demo>,
+        /// Run CODE instead of a file
+        #[clap(
+            short = 'e',
+            long,
+            value_name = "CODE",
+            conflicts_with_all = ["filename", "demo"]
+        )]
+        eval: Option<String>,
+        /// Write each statement as it starts, and each expression with its
+        /// value as it finishes, to stderr or to PATH, with the tree backend
+        #[clap(
+            long,
+            value_name = "PATH",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "-"
+        )]
+        trace: Option<PathBuf>,
+        /// Run the file again each time it or a module it imports changes,
+        /// clearing the terminal first, until interrupted
+        #[clap(long, requires = "filename", conflicts_with_all = ["demo", "eval"])]
+        watch: bool,
+        /// Look for imported modules not found next to the program in DIR,
+        /// which may name environment variables as `${NAME}`. Repeat to
+        /// search several directories in order
+        #[clap(long, value_name = "DIR")]
+        module_path: Vec<PathBuf>,
+        /// Write the results of natives that read input, such as readLine,
+        /// to PATH so the run can be replayed
+        #[clap(long, value_name = "PATH", conflicts_with = "replay")]
+        record: Option<PathBuf>,
+        /// Answer natives that read input from a log written by --record
+        #[clap(long, value_name = "PATH")]
+        replay: Option<PathBuf>,
+        /// Print what the lint subcommand warns about to stderr before running.
+        /// Warnings denied with --deny or the lint config stop the program
+        /// even without it
+        #[clap(long)]
+        warnings: bool,
+        /// Run the program by walking its syntax tree, or by compiling it to
+        /// bytecode for the virtual machine
+        #[clap(long, value_name = "BACKEND", default_value = "tree")]
+        backend: Backend,
+        /// Print the bytecode the program compiles to on stderr before
+        /// running it, which implies --backend vm
+        #[clap(long)]
+        dump_bytecode: bool,
+        /// Fold constant expressions and drop branches that never run before
+        /// running the program
+        #[clap(long)]
+        optimize: bool,
+        /// Evaluate constant expressions inside loops only once
+        #[clap(long)]
+        hoist: bool,
+        /// Print each expression cached by --hoist to stderr before running
+        #[clap(long, requires = "hoist")]
+        show_hoisted: bool,
+    },
+    /// Run a program repeatedly, discarding what it prints, and report the
+    /// fastest, mean and median time of each phase
+    Bench {
+        filename: PathBuf,
+        /// How many times to run the program
+        #[clap(
+            long,
+            short = 'n',
+            value_name = "N",
+            default_value_t = 10,
+            value_parser = clap::value_parser!(u32).range(1..)
+        )]
+        iterations: u32,
+        /// Run the program by walking its syntax tree, or by compiling it to
+        /// bytecode for the virtual machine
+        #[clap(long, value_name = "BACKEND", default_value = "tree")]
+        backend: Backend,
+        /// Emit the report as JSON, with times in seconds
+        #[clap(long)]
+        json: bool,
+    },
+    /// Run a program in an interactive debugger reading commands from stdin,
+    /// with breakpoints, stepping and printing variables and expressions
+    Debug { filename: PathBuf },
+    /// Serve the Debug Adapter Protocol on stdin and stdout, so that editors
+    /// can launch and debug programs
+    Dap,
+    /// Serve the Language Server Protocol on stdin and stdout, so that
+    /// editors can show errors and navigate programs as they are written
+    Lsp,
+    /// Run each line of FILE as if typed into a REPL, printing
+    /// `input => result` for each
+    Repl {
+        #[clap(long, value_name = "FILE")]
+        batch: PathBuf,
+    },
+    /// Warn about expressions that always fail, locals that are never read
+    /// and other suspicious code, without running the program
+    Lint { filename: PathBuf },
+    /// Rewrite FILE in the canonical layout, keeping its comments
+    Fmt {
+        filename: PathBuf,
+        /// Instead of rewriting the file, print the lines that would change
+        /// and exit with code 3 if there are any
+        #[clap(long)]
+        check: bool,
+    },
+    /// Print the tokens removed from OLD and added in NEW, ignoring
+    /// whitespace and comments
+    Diff { old: PathBuf, new: PathBuf },
+    /// Print a shell completion script to stdout
+    Completions { shell: clap_complete::Shell },
+    /// Describe the subcommands, flags and diagnostics of this build
+    Introspect {
+        /// Emit the description as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Print version information
+    Version {
+        /// Also print the git commit, enabled features and available extensions
+        #[clap(long, short)]
+        verbose: bool,
+    },
 }
@@ -0,0 +1,165 @@
+//! Differential testing against a reference implementation, such as jlox, to
+//! find where this interpreter's output departs from the spec.
+
+use std::fmt::Display;
+use std::io;
+use std::process::Command;
+
+/// What a process wrote and how it exited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` if the process was terminated by a signal
+    pub exit_code: Option<i32>,
+}
+
+impl Outcome {
+    /// Runs `command` to completion and captures its output.
+    pub fn capture(command: &mut Command) -> io::Result<Self> {
+        let output = command.output()?;
+        Ok(Self {
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            exit_code: output.status.code(),
+        })
+    }
+}
+
+/// A difference between this interpreter's [`Outcome`] and the reference's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    ExitCode {
+        ours: Option<i32>,
+        reference: Option<i32>,
+    },
+    /// The first line at which a stream differs, `None` where it has ended
+    Line {
+        stream: &'static str,
+        line: usize,
+        ours: Option<String>,
+        reference: Option<String>,
+    },
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ExitCode { ours, reference } => {
+                let code = |code: &Option<i32>| code.map_or("signal".into(), |c| c.to_string());
+                write!(
+                    f,
+                    "exit code: {} (ours) vs {} (reference)",
+                    code(ours),
+                    code(reference)
+                )
+            }
+            Self::Line {
+                stream,
+                line,
+                ours,
+                reference,
+            } => {
+                let text = |text: &Option<String>| {
+                    text.as_deref().unwrap_or("<end of output>").to_string()
+                };
+                writeln!(f, "{stream} line {line}:")?;
+                writeln!(f, "  ours:      {}", text(ours))?;
+                write!(f, "  reference: {}", text(reference))
+            }
+        }
+    }
+}
+
+/// Lists every way `ours` differs from `reference`, with at most one line
+/// mismatch per stream.
+pub fn compare(ours: &Outcome, reference: &Outcome) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    if ours.exit_code != reference.exit_code {
+        mismatches.push(Mismatch::ExitCode {
+            ours: ours.exit_code,
+            reference: reference.exit_code,
+        });
+    }
+
+    let streams = [
+        ("stdout", &ours.stdout, &reference.stdout),
+        ("stderr", &ours.stderr, &reference.stderr),
+    ];
+    for (stream, ours, reference) in streams {
+        if let Some(mismatch) = first_different_line(stream, ours, reference) {
+            mismatches.push(mismatch);
+        }
+    }
+    mismatches
+}
+
+fn first_different_line(stream: &'static str, ours: &str, reference: &str) -> Option<Mismatch> {
+    let (mut ours, mut reference) = (ours.lines(), reference.lines());
+    for line in 1.. {
+        match (ours.next(), reference.next()) {
+            (None, None) => return None,
+            (a, b) if a == b => continue,
+            (a, b) => {
+                return Some(Mismatch::Line {
+                    stream,
+                    line,
+                    ours: a.map(String::from),
+                    reference: b.map(String::from),
+                });
+            }
+        }
+    }
+    unreachable!("the loop only ends by returning")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(stdout: &str, stderr: &str, exit_code: i32) -> Outcome {
+        Outcome {
+            stdout: stdout.into(),
+            stderr: stderr.into(),
+            exit_code: Some(exit_code),
+        }
+    }
+
+    #[test]
+    fn test_compare_identical() {
+        let a = outcome("NUMBER 1 1.0\nEOF  null\n", "", 0);
+        assert_eq!(Vec::<Mismatch>::new(), compare(&a, &a.clone()));
+    }
+
+    #[test]
+    fn test_compare_reports_first_line_per_stream() {
+        let ours = outcome("a\nb\nc\n", "", 0);
+        let reference = outcome("a\nB\nC\n", "oops\n", 65);
+        assert_eq!(
+            vec![
+                "exit code: 0 (ours) vs 65 (reference)",
+                "stdout line 2:\n  ours:      b\n  reference: B",
+                "stderr line 1:\n  ours:      <end of output>\n  reference: oops",
+            ],
+            compare(&ours, &reference)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_compare_missing_trailing_line() {
+        let ours = outcome("a\nb\n", "", 0);
+        let reference = outcome("a\n", "", 0);
+        assert_eq!(
+            vec![Mismatch::Line {
+                stream: "stdout",
+                line: 2,
+                ours: Some("b".into()),
+                reference: None,
+            }],
+            compare(&ours, &reference)
+        );
+    }
+}
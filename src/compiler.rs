@@ -0,0 +1,399 @@
+//! Lowering the AST into bytecode for the [`vm`](crate::vm).
+//!
+//! Locals live in stack slots resolved here, and globals are looked up by
+//! name at runtime, like in clox. Operators and calls are carried out by the
+//! same code as in the tree-walking interpreter, so the backends agree on
+//! every result and error message.
+
+use std::rc::Rc;
+
+use crate::Value;
+use crate::error::StaticError;
+use crate::parser::ast::{Ast, AstArena, ExprId, StmtId};
+use crate::parser::expr::ExprNode;
+use crate::parser::stmt::{ForIn, StmtNode};
+use crate::scanner::token::{Token, TokenType};
+use crate::symbol::Symbol;
+use crate::vm::chunk::{Chunk, OpCode};
+
+/// Compiles `program` into a chunk that ends with [`OpCode::Return`].
+///
+/// Returns a [`StaticError`] for constructs the VM does not support yet, or
+/// when the program outgrows the operands of an instruction.
+pub fn compile(program: &Ast) -> Result<Chunk, StaticError> {
+    let mut compiler = Compiler::default();
+    for &stmt in &program.statements {
+        compiler.statement(&program.arena, stmt)?;
+    }
+    compiler.emit(OpCode::Return);
+    Ok(compiler.chunk)
+}
+
+/// A local variable and the depth of the block that declared it.
+struct Local {
+    name: Symbol,
+    depth: usize,
+}
+
+struct Compiler {
+    chunk: Chunk,
+    /// The locals in scope, in stack slot order
+    locals: Vec<Local>,
+    /// The number of blocks around the code being compiled
+    depth: usize,
+    /// The line of the last token compiled, which the next bytes are
+    /// attributed to, since literals do not record theirs
+    line: u32,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self {
+            chunk: Chunk::default(),
+            locals: Vec::new(),
+            depth: 0,
+            line: 1,
+        }
+    }
+}
+
+impl Compiler {
+    fn statement(&mut self, ast: &AstArena, stmt: StmtId) -> Result<(), StaticError> {
+        if let Some(lines) = ast.lines(stmt) {
+            self.line = lines.first;
+        }
+        self.emit(OpCode::Statement);
+        match &ast[stmt] {
+            StmtNode::Print(print) => {
+                self.expression(ast, print.expr)?;
+                self.emit(OpCode::Print);
+            }
+            StmtNode::Expression(expression) => {
+                self.expression(ast, expression.expr)?;
+                self.emit(OpCode::Pop);
+            }
+            StmtNode::Var(var) => {
+                self.line = var.name.line;
+                match &var.initializer {
+                    Some(initializer) => self.expression(ast, *initializer)?,
+                    None => self.emit(OpCode::Nil),
+                }
+                self.line = var.name.line;
+                if self.depth > 0 {
+                    self.add_local(var.name.symbol(), &var.name)?;
+                } else if var.constant {
+                    self.emit_token(OpCode::DefineConstant, var.name.clone())?;
+                } else {
+                    self.emit_token(OpCode::DefineGlobal, var.name.clone())?;
+                }
+            }
+            StmtNode::Block(block) => {
+                self.depth += 1;
+                for &stmt in &block.statements {
+                    self.statement(ast, stmt)?;
+                }
+                self.depth -= 1;
+                while self.locals.last().is_some_and(|l| l.depth > self.depth) {
+                    self.locals.pop();
+                    self.emit(OpCode::Pop);
+                }
+            }
+            StmtNode::DoWhile(do_while) => {
+                let start = self.chunk.code.len();
+                self.statement(ast, do_while.body)?;
+                self.expression(ast, do_while.condition)?;
+                let exit = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit(OpCode::Pop);
+                self.emit_loop(start)?;
+                self.patch_jump(exit)?;
+                self.emit(OpCode::Pop);
+            }
+            StmtNode::VarUnpack(unpack) => return self.unsupported(&unpack.paren, "destructuring"),
+            StmtNode::ForIn(for_in) => match &ast[for_in.iterable] {
+                ExprNode::Binary(binary)
+                    if matches!(
+                        binary.operator.typ,
+                        TokenType::DotDot | TokenType::DotDotEqual
+                    ) =>
+                {
+                    self.for_range(ast, for_in)?
+                }
+                _ => return self.unsupported(&for_in.keyword, "'for' outside of ranges"),
+            },
+            StmtNode::Throw(throw) => return self.unsupported(&throw.keyword, "'throw'"),
+            StmtNode::Assert(assert) => return self.unsupported(&assert.keyword, "'assert'"),
+            StmtNode::Import(import) => return self.unsupported(&import.path, "'import'"),
+            StmtNode::Try(_) => return self.unsupported_here("'try'"),
+            StmtNode::Match(_) => return self.unsupported_here("'match'"),
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self, ast: &AstArena, expr: ExprId) -> Result<(), StaticError> {
+        match &ast[expr] {
+            ExprNode::Literal(literal) => match &literal.value {
+                Value::Nil => self.emit(OpCode::Nil),
+                Value::Boolean(true) => self.emit(OpCode::True),
+                Value::Boolean(false) => self.emit(OpCode::False),
+                value => {
+                    let index = self.chunk.add_constant(value.clone());
+                    self.emit_index(OpCode::Constant, index, "constants")?;
+                }
+            },
+            ExprNode::Grouping(grouping) => self.expression(ast, grouping.expression)?,
+            ExprNode::Cached(cached) => self.expression(ast, cached.expr)?,
+            ExprNode::Unary(unary) => {
+                self.expression(ast, unary.right)?;
+                self.emit_token(OpCode::Unary, Rc::clone(&unary.operator))?;
+            }
+            ExprNode::Binary(binary) => {
+                self.expression(ast, binary.left)?;
+                self.expression(ast, binary.right)?;
+                self.emit_token(OpCode::Binary, Rc::clone(&binary.operator))?;
+            }
+            ExprNode::Coalesce(coalesce) => {
+                self.expression(ast, coalesce.left)?;
+                self.line = coalesce.operator.line;
+                let end = self.emit_jump(OpCode::JumpIfNotNil);
+                self.emit(OpCode::Pop);
+                self.expression(ast, coalesce.right)?;
+                self.patch_jump(end)?;
+            }
+            ExprNode::Variable(variable) => match self.resolve(&variable.name) {
+                Some(slot) => self.emit_slot(OpCode::GetLocal, slot),
+                None => self.emit_token(OpCode::GetGlobal, variable.name.clone())?,
+            },
+            ExprNode::Assign(assign) => {
+                self.expression(ast, assign.value)?;
+                match self.resolve(&assign.name) {
+                    Some(slot) => self.emit_slot(OpCode::SetLocal, slot),
+                    None => self.emit_token(OpCode::SetGlobal, assign.name.clone())?,
+                }
+            }
+            ExprNode::Call(call) => {
+                self.expression(ast, call.callee)?;
+                for &argument in &call.arguments {
+                    self.expression(ast, argument)?;
+                }
+                let Ok(count) = u8::try_from(call.arguments.len()) else {
+                    return Err(self.error(&call.paren, "Can't have more than 255 arguments."));
+                };
+                self.emit_token(OpCode::Call, call.paren.clone())?;
+                self.chunk.write(count, self.line);
+            }
+            ExprNode::AssignUnpack(unpack) => {
+                return self.unsupported(&unpack.paren, "destructuring");
+            }
+            ExprNode::Tuple(tuple) => return self.unsupported(&tuple.paren, "tuples"),
+            ExprNode::Spread(spread) => return self.unsupported(&spread.ellipsis, "'...'"),
+            ExprNode::Map(map) => return self.unsupported(&map.brace, "maps"),
+            ExprNode::Index(index) => return self.unsupported(&index.bracket, "indexing"),
+            ExprNode::SetIndex(set) => return self.unsupported(&set.bracket, "indexing"),
+            ExprNode::List(_) => return self.unsupported_here("lists"),
+        }
+        Ok(())
+    }
+
+    /// Compiles a loop over `a..b` or `a..=b` into a counting loop that keeps
+    /// the numbers left in a hidden local, rather than iterating the range
+    /// through the interpreter.
+    fn for_range(&mut self, ast: &AstArena, for_in: &ForIn) -> Result<(), StaticError> {
+        self.depth += 1;
+        self.expression(ast, for_in.iterable)?;
+        // an empty name never resolves, so scripts cannot reach the range
+        self.add_local(Symbol::from(""), &for_in.keyword)?;
+
+        self.line = for_in.keyword.line;
+        let start = self.chunk.code.len();
+        let exit = self.emit_jump(OpCode::ForRange);
+        self.add_local(for_in.name.symbol(), &for_in.name)?;
+        self.statement(ast, for_in.body)?;
+        self.locals.pop();
+        self.emit(OpCode::Pop);
+        self.emit_loop(start)?;
+        self.patch_jump(exit)?;
+
+        self.depth -= 1;
+        self.locals.pop();
+        self.emit(OpCode::Pop);
+        Ok(())
+    }
+
+    /// Makes the value on top of the stack the local `name`, reporting
+    /// errors at `token`.
+    fn add_local(&mut self, name: Symbol, token: &Token) -> Result<(), StaticError> {
+        if self.locals.len() > u8::MAX as usize {
+            return Err(self.error(token, "Too many local variables in one chunk."));
+        }
+        self.locals.push(Local {
+            name,
+            depth: self.depth,
+        });
+        Ok(())
+    }
+
+    /// Returns the stack slot of the innermost local called `name`, or
+    /// `None` for a global.
+    fn resolve(&mut self, name: &Token) -> Option<u8> {
+        self.line = name.line;
+        let name = name.symbol();
+        let slot = self.locals.iter().rposition(|l| l.name == name)?;
+        Some(slot as u8)
+    }
+
+    fn emit(&mut self, op: OpCode) {
+        self.chunk.write(op as u8, self.line);
+    }
+
+    fn emit_slot(&mut self, op: OpCode, slot: u8) {
+        self.emit(op);
+        self.chunk.write(slot, self.line);
+    }
+
+    /// Emits `op` with `token` as its operand, at the token's line.
+    fn emit_token(&mut self, op: OpCode, token: impl Into<Rc<Token>>) -> Result<(), StaticError> {
+        let token = token.into();
+        self.line = token.line;
+        let index = self.chunk.add_token(token);
+        self.emit_index(op, index, "names and operators")
+    }
+
+    fn emit_index(&mut self, op: OpCode, index: usize, table: &str) -> Result<(), StaticError> {
+        let Ok(index) = u16::try_from(index) else {
+            let message = format!("Too many {table} in one chunk.");
+            return Err(StaticError::error_at_line(self.line, message));
+        };
+        self.emit(op);
+        self.write_u16(index);
+        Ok(())
+    }
+
+    fn write_u16(&mut self, value: u16) {
+        for byte in value.to_be_bytes() {
+            self.chunk.write(byte, self.line);
+        }
+    }
+
+    /// Emits a forward jump with a placeholder offset, and returns where the
+    /// offset is so that [`Self::patch_jump`] can fill it in.
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit(op);
+        self.write_u16(u16::MAX);
+        self.chunk.code.len() - 2
+    }
+
+    /// Points the jump whose offset is at `offset` to the next instruction.
+    fn patch_jump(&mut self, offset: usize) -> Result<(), StaticError> {
+        let distance = self.chunk.code.len() - offset - 2;
+        let Ok(distance) = u16::try_from(distance) else {
+            return Err(StaticError::error_at_line(
+                self.line,
+                "Too much code to jump over.".into(),
+            ));
+        };
+        self.chunk.code[offset..offset + 2].copy_from_slice(&distance.to_be_bytes());
+        Ok(())
+    }
+
+    /// Emits a jump back to the instruction at `start`.
+    fn emit_loop(&mut self, start: usize) -> Result<(), StaticError> {
+        self.emit(OpCode::Loop);
+        let distance = self.chunk.code.len() - start + 2;
+        let Ok(distance) = u16::try_from(distance) else {
+            return Err(StaticError::error_at_line(
+                self.line,
+                "Loop body too large.".into(),
+            ));
+        };
+        self.write_u16(distance);
+        Ok(())
+    }
+
+    fn error(&self, token: &Token, message: &str) -> StaticError {
+        StaticError::error_at_token(token, message.into())
+    }
+
+    fn unsupported(&self, token: &Token, what: &str) -> Result<(), StaticError> {
+        let message = format!("The vm backend does not support {what} yet.");
+        Err(StaticError::error_at_token(token, message))
+    }
+
+    /// Like [`Self::unsupported`], for constructs without a token to point at.
+    fn unsupported_here(&self, what: &str) -> Result<(), StaticError> {
+        let message = format!("The vm backend does not support {what} yet.");
+        Err(StaticError::error_at_line(self.line, message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::dialect::{Dialect, Extension};
+    use crate::validate::validate_with;
+
+    fn compile_source(source: &str) -> Result<Chunk, StaticError> {
+        let dialect: Dialect = [Extension::ForIn, Extension::Ranges, Extension::Lists]
+            .into_iter()
+            .collect();
+        compile(&validate_with(source, dialect).ast.unwrap())
+    }
+
+    #[test]
+    fn test_compile_locals_use_slots() {
+        let chunk = compile_source("var g = 1; { var a = g; var b = a; }").unwrap();
+        let op = |op: OpCode| op as u8;
+        assert_eq!(
+            vec![
+                op(OpCode::Statement),
+                op(OpCode::Constant),
+                0,
+                0,
+                op(OpCode::DefineGlobal),
+                0,
+                0,
+                op(OpCode::Statement),
+                op(OpCode::Statement),
+                op(OpCode::GetGlobal),
+                0,
+                1,
+                op(OpCode::Statement),
+                op(OpCode::GetLocal),
+                0,
+                op(OpCode::Pop),
+                op(OpCode::Pop),
+                op(OpCode::Return),
+            ],
+            chunk.code
+        );
+        assert_eq!(chunk.code.len(), chunk.lines.len());
+    }
+
+    #[test]
+    fn test_compile_shares_operators_with_ast() {
+        let ast = validate_with("print 1 + 2;", Dialect::new()).ast.unwrap();
+        let chunk = compile(&ast).unwrap();
+        let Some(StmtNode::Print(print)) = ast.iter().next() else {
+            panic!("expected a print statement");
+        };
+        let ExprNode::Binary(binary) = &ast.arena[print.expr] else {
+            panic!("expected a binary expression");
+        };
+        assert!(Rc::ptr_eq(&binary.operator, &chunk.tokens[0]));
+    }
+
+    #[rstest]
+    #[case(
+        "for (i in [1, 2]) print i;",
+        "[line 1] Error at 'for': The vm backend does not support 'for' outside of ranges yet."
+    )]
+    #[case(
+        "print 1;\nvar xs = [1];",
+        "[line 2] Error: The vm backend does not support lists yet."
+    )]
+    fn test_compile_unsupported(#[case] source: &str, #[case] expected: &str) {
+        let err = compile_source(source).unwrap_err();
+        assert_eq!(expected, err.to_string());
+    }
+}
@@ -0,0 +1,607 @@
+//! A Debug Adapter Protocol server, so that editors such as VS Code can set
+//! breakpoints, step through programs and inspect their variables.
+//!
+//! The server reads requests from its input and writes responses and events
+//! to its output, framed by `Content-Length` headers. It debugs one program
+//! per session, which it runs itself once launched: there is no running
+//! interpreter to attach to. The program is paused and stepped like in the
+//! [debugger](crate::debugger), and what it prints is sent as `output`
+//! events instead of written to stdout.
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use serde_json::{Value as Json, json};
+
+use crate::debugger::{Resume, evaluate};
+use crate::error::Report;
+use crate::interpreter::Interpreter;
+use crate::interpreter::hook::Hook;
+use crate::parser::Parser;
+use crate::parser::ast::{AstArena, StmtId};
+use crate::scanner::Scanner;
+use crate::{Value, framing};
+
+/// The only thread of a program, as reported to the client.
+const THREAD: u32 = 1;
+
+/// The `variablesReference`s of the two scopes a paused program has.
+const LOCALS: u64 = 1;
+const GLOBALS: u64 = 2;
+
+/// What to do after answering a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    /// Read the next request
+    Wait,
+    /// Start the launched program
+    Run,
+    /// Resume the paused program
+    Resume,
+    /// Stop the program and end the session
+    Disconnect,
+}
+
+/// The state of a debugging session, and the [`Hook`] that pauses its
+/// program.
+struct Session<R, W> {
+    input: R,
+    output: W,
+    /// The sequence number of the next message sent
+    seq: u64,
+    /// The path and source of the launched program
+    program: Option<(PathBuf, String)>,
+    /// The lines to pause on, by the path of the source they are set in
+    breakpoints: HashMap<PathBuf, BTreeSet<u32>>,
+    resume: Resume,
+    /// Whether the program has paused yet, to tell stopping on entry apart
+    /// from stepping
+    paused: bool,
+    /// Whether the client disconnected, or its input ended
+    disconnected: bool,
+}
+
+/// Serves a debugging session over `input` and `output`, running the
+/// launched program with `interpreter`.
+///
+/// Returns once the client disconnects or its input ends. Fails if a
+/// message cannot be read or written.
+pub fn serve<R: BufRead + 'static, W: Write + 'static>(
+    input: R,
+    output: W,
+    interpreter: Interpreter,
+) -> io::Result<()> {
+    let session = Rc::new(RefCell::new(Session::new(input, output)));
+    loop {
+        match session.borrow_mut().next(None)? {
+            Flow::Run => break,
+            Flow::Disconnect => return Ok(()),
+            Flow::Wait | Flow::Resume => {}
+        }
+    }
+
+    let (path, source) = session.borrow().program.clone().expect("launched");
+    let mut interpreter = interpreter.with_script(&path).with_hook(session.clone());
+    let result = launch(&source, &mut interpreter);
+
+    let mut session = session.borrow_mut();
+    if session.disconnected {
+        return Ok(());
+    }
+    let exit_code = match result {
+        Ok(()) => 0,
+        Err(report) => {
+            let output = format!("{report}\n");
+            session.event("output", json!({ "category": "stderr", "output": output }))?;
+            report.code().exit_code
+        }
+    };
+    session.event("exited", json!({ "exitCode": exit_code }))?;
+    session.event("terminated", json!({}))?;
+    while session.next(None)? != Flow::Disconnect {}
+    Ok(())
+}
+
+/// Parses `source` as a program and runs it with `interpreter`.
+fn launch(source: &str, interpreter: &mut Interpreter) -> Result<(), Report> {
+    let dialect = interpreter.dialect();
+    let scanner = Scanner::new(source)
+        .with_dialect(dialect)
+        .with_interner(interpreter.interner().clone());
+    let program = Parser::new(scanner.scan_tokens())
+        .with_dialect(dialect)
+        .parse()?;
+    Ok(interpreter.interpret(&program)?)
+}
+
+impl<R: BufRead, W: Write> Session<R, W> {
+    fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            seq: 1,
+            program: None,
+            breakpoints: HashMap::new(),
+            resume: Resume::Continue,
+            paused: false,
+            disconnected: false,
+        }
+    }
+
+    /// Reads the next message, or returns `None` at the end of the input.
+    fn read(&mut self) -> io::Result<Option<Json>> {
+        framing::read(&mut self.input)
+    }
+
+    /// Writes `message`, numbering it.
+    fn send(&mut self, mut message: Json) -> io::Result<()> {
+        message["seq"] = self.seq.into();
+        self.seq += 1;
+        framing::write(&mut self.output, &message)
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    /// Answers `request` with `body`, or with the error message it fails
+    /// with.
+    fn respond(&mut self, request: &Json, body: Result<Json, String>) -> io::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": body.is_ok(),
+        });
+        match body {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = message.into(),
+        }
+        self.send(response)
+    }
+
+    /// Reads a request and answers it. `paused` is the interpreter of the
+    /// program and the line it paused on, if it is paused.
+    fn next(&mut self, paused: Option<(&mut Interpreter, u32)>) -> io::Result<Flow> {
+        let Some(request) = self.read()? else {
+            return Ok(Flow::Disconnect);
+        };
+        let command = request["command"].as_str().unwrap_or_default();
+        let (flow, body) = self.request(command, &request["arguments"], paused);
+        self.respond(&request, body)?;
+        if command == "initialize" {
+            self.event("initialized", json!({}))?;
+        }
+        Ok(flow)
+    }
+
+    /// Carries out the request `command`, and returns what to do next and
+    /// the body of the response.
+    fn request(
+        &mut self,
+        command: &str,
+        arguments: &Json,
+        paused: Option<(&mut Interpreter, u32)>,
+    ) -> (Flow, Result<Json, String>) {
+        let not_paused = || Err("The program is not paused.".to_string());
+        let body = match (command, paused) {
+            ("initialize", _) => Ok(json!({ "supportsConfigurationDoneRequest": true })),
+            ("launch", _) => self.launch(arguments),
+            ("attach", _) => Err(
+                "Attaching is not supported, as programs only run inside the server. Launch the \
+                 program instead."
+                    .to_string(),
+            ),
+            ("setBreakpoints", _) => Ok(self.set_breakpoints(arguments)),
+            ("configurationDone", Some(_)) => Ok(Json::Null),
+            ("configurationDone", None) if self.program.is_some() => {
+                return (Flow::Run, Ok(Json::Null));
+            }
+            ("configurationDone", None) => Err("Launch a program first.".to_string()),
+            ("threads", _) => Ok(json!({ "threads": [{ "id": THREAD, "name": "main" }] })),
+            ("disconnect", _) => return (Flow::Disconnect, Ok(Json::Null)),
+            ("stackTrace", Some((_, line))) => Ok(self.stack_trace(line)),
+            ("scopes", Some(_)) => Ok(json!({ "scopes": [
+                { "name": "Locals", "variablesReference": LOCALS, "expensive": false },
+                { "name": "Globals", "variablesReference": GLOBALS, "expensive": false },
+            ]})),
+            ("variables", Some((interpreter, _))) => Ok(variables(
+                interpreter,
+                arguments["variablesReference"].as_u64(),
+            )),
+            ("evaluate", Some((interpreter, _))) => {
+                let expression = arguments["expression"].as_str().unwrap_or_default();
+                evaluate(interpreter, expression)
+                    .map(|result| json!({ "result": result, "variablesReference": 0 }))
+            }
+            ("continue" | "next" | "stepIn" | "stepOut", Some((interpreter, _))) => {
+                let depth = interpreter.depth();
+                self.resume = match command {
+                    "continue" => Resume::Continue,
+                    "next" => Resume::Next { depth },
+                    "stepIn" => Resume::Step,
+                    _ => Resume::Next {
+                        depth: depth.saturating_sub(1),
+                    },
+                };
+                return (Flow::Resume, Ok(json!({ "allThreadsContinued": true })));
+            }
+            (
+                "stackTrace" | "scopes" | "variables" | "evaluate" | "continue" | "next" | "stepIn"
+                | "stepOut",
+                None,
+            ) => not_paused(),
+            (command, _) => Err(format!("Unsupported request '{command}'.")),
+        };
+        (Flow::Wait, body)
+    }
+
+    /// Reads the program to debug, and whether to pause before its first
+    /// statement.
+    fn launch(&mut self, arguments: &Json) -> Result<Json, String> {
+        if self.program.is_some() {
+            return Err("A program is already launched.".to_string());
+        }
+        let Some(path) = arguments["program"].as_str() else {
+            return Err("Expected the path of the program to launch.".to_string());
+        };
+        let source = fs::read_to_string(path).map_err(|_| format!("Failed to read file {path}"))?;
+        self.program = Some((PathBuf::from(path), source));
+        if arguments["stopOnEntry"].as_bool().unwrap_or(false) {
+            self.resume = Resume::Step;
+        }
+        Ok(Json::Null)
+    }
+
+    /// Replaces the breakpoints of a source. Those outside the launched
+    /// program are kept but never hit, as imported modules run without
+    /// pausing.
+    fn set_breakpoints(&mut self, arguments: &Json) -> Json {
+        let path = PathBuf::from(arguments["source"]["path"].as_str().unwrap_or_default());
+        let lines = arguments["breakpoints"].as_array().into_iter().flatten();
+        let lines = lines
+            .filter_map(|breakpoint| breakpoint["line"].as_u64()?.try_into().ok())
+            .collect::<BTreeSet<u32>>();
+
+        let verified = self
+            .program
+            .as_ref()
+            .is_none_or(|(program, _)| *program == path);
+        let breakpoints = lines
+            .iter()
+            .map(|line| json!({ "verified": verified, "line": line }))
+            .collect::<Vec<_>>();
+        self.breakpoints.insert(path, lines);
+        json!({ "breakpoints": breakpoints })
+    }
+
+    /// Describes the only frame of the program, paused on `line`.
+    fn stack_trace(&self, line: u32) -> Json {
+        let path = self.program.as_ref().map(|(path, _)| path.as_path());
+        let name = path
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy());
+        json!({
+            "stackFrames": [{
+                "id": 1,
+                "name": "main",
+                "line": line,
+                "column": 1,
+                "source": { "name": name, "path": path },
+            }],
+            "totalFrames": 1,
+        })
+    }
+
+    /// Tells the client the program paused on `line`, then answers requests
+    /// until one resumes or stops it.
+    fn pause(&mut self, interpreter: &mut Interpreter, line: u32, reason: &str) -> ControlFlow<()> {
+        self.paused = true;
+        let stopped = json!({ "reason": reason, "threadId": THREAD, "allThreadsStopped": true });
+        if self.event("stopped", stopped).is_err() {
+            self.disconnected = true;
+            return ControlFlow::Break(());
+        }
+        loop {
+            match self.next(Some((interpreter, line))) {
+                Ok(Flow::Resume) => return ControlFlow::Continue(()),
+                Ok(Flow::Wait | Flow::Run) => {}
+                // a client that cannot be talked to cannot resume the program
+                Ok(Flow::Disconnect) | Err(_) => {
+                    self.disconnected = true;
+                    return ControlFlow::Break(());
+                }
+            }
+        }
+    }
+}
+
+/// Describes the variables of the scope `reference` names, innermost
+/// first. The natives are left out of the globals, as they are always there.
+fn variables(interpreter: &Interpreter, reference: Option<u64>) -> Json {
+    let scopes = interpreter.scopes();
+    let (locals, globals) = scopes.split_at(scopes.len() - 1);
+    let variables = match reference {
+        Some(LOCALS) => locals.iter().flatten().collect(),
+        Some(GLOBALS) => globals
+            .iter()
+            .flatten()
+            .filter(|(_, value)| !matches!(value, Value::NativeFunction(_)))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let variables = variables.into_iter().map(|(name, value)| {
+        json!({ "name": name.as_str(), "value": value.to_string(), "variablesReference": 0 })
+    });
+    json!({ "variables": variables.collect::<Vec<_>>() })
+}
+
+impl<R: BufRead, W: Write> Hook for Session<R, W> {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        ast: &AstArena,
+        stmt: StmtId,
+    ) -> ControlFlow<()> {
+        let Some(lines) = ast.lines(stmt) else {
+            return ControlFlow::Continue(());
+        };
+        let Some((path, _)) = &self.program else {
+            return ControlFlow::Continue(());
+        };
+        if interpreter.script() != Some(path.as_path()) {
+            return ControlFlow::Continue(());
+        }
+
+        let line = lines.first;
+        let breakpoint = self
+            .breakpoints
+            .get(path)
+            .is_some_and(|lines| lines.contains(&line));
+        let reason = if breakpoint {
+            "breakpoint"
+        } else if !self.resume.pauses(interpreter.depth()) {
+            return ControlFlow::Continue(());
+        } else if self.paused {
+            "step"
+        } else {
+            "entry"
+        };
+        self.pause(interpreter, line, reason)
+    }
+
+    fn print(&mut self, line: &str) -> bool {
+        // output that cannot be sent must still not be mixed into the protocol
+        let _ = self.event(
+            "output",
+            json!({ "category": "stdout", "output": format!("{line}\n") }),
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use tempdir::TempDir;
+
+    use super::*;
+
+    /// A sink the test can read back after the server has written it.
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    const PROGRAM: &str =
+        "var a = 1;\n{\n  var b = a + 2;\n  print b;\n}\nvar c = a * 10;\nprint c;";
+
+    /// Frames `requests` as a client would send them, numbering them and
+    /// passing `program` to them as `$PROGRAM`.
+    fn frame(requests: &[Json], program: &Path) -> Vec<u8> {
+        let mut input = Vec::new();
+        for (seq, request) in requests.iter().enumerate() {
+            let mut request = request.clone();
+            request["seq"] = (seq + 1).into();
+            request["type"] = "request".into();
+            let request = request
+                .to_string()
+                .replace("$PROGRAM", program.to_str().unwrap());
+            framing::write(&mut input, &serde_json::from_str(&request).unwrap()).unwrap();
+        }
+        input
+    }
+
+    /// Serves `requests` for a session debugging `source`, and summarizes
+    /// each message sent back on a line.
+    fn session(source: &str, requests: &[Json]) -> Vec<String> {
+        let tempdir = TempDir::new("rslox-dap").expect("should create temp dir");
+        let path = tempdir.path().join("main.lox");
+        fs::write(&path, source).unwrap();
+        let input = Cursor::new(frame(requests, &path));
+        let output = Shared::default();
+        serve(input, output.clone(), Interpreter::new()).unwrap();
+
+        let mut output = Cursor::new(output.0.take());
+        let mut messages = Vec::new();
+        while let Some(message) = framing::read(&mut output).unwrap() {
+            messages.push(summarize(&message));
+        }
+        messages
+    }
+
+    /// Describes `message` by its name and the parts of its body that say
+    /// what the program did.
+    fn summarize(message: &Json) -> String {
+        let body = &message["body"];
+        match message["type"].as_str() {
+            Some("event") => match message["event"].as_str().unwrap() {
+                "stopped" => format!("stopped {}", body["reason"].as_str().unwrap()),
+                "output" => format!("{} {}", body["category"], body["output"]),
+                "exited" => format!("exited {}", body["exitCode"]),
+                event => event.to_string(),
+            },
+            _ if message["success"] == false => format!(
+                "{} failed: {}",
+                message["command"].as_str().unwrap(),
+                message["message"].as_str().unwrap()
+            ),
+            _ => match message["command"].as_str().unwrap() {
+                "stackTrace" => format!("stackTrace {}", body["stackFrames"][0]["line"]),
+                "variables" => {
+                    let variables = body["variables"].as_array().unwrap().iter();
+                    let variables = variables.map(|v| {
+                        format!(
+                            "{}={}",
+                            v["name"].as_str().unwrap(),
+                            v["value"].as_str().unwrap()
+                        )
+                    });
+                    format!("variables {}", variables.collect::<Vec<_>>().join(" "))
+                }
+                "evaluate" => format!("evaluate {}", body["result"].as_str().unwrap()),
+                command => command.to_string(),
+            },
+        }
+    }
+
+    fn request(command: &str, arguments: Json) -> Json {
+        json!({ "command": command, "arguments": arguments })
+    }
+
+    #[test]
+    fn test_dap_session() {
+        let messages = session(
+            PROGRAM,
+            &[
+                request("initialize", json!({ "adapterID": "rslox" })),
+                request(
+                    "launch",
+                    json!({ "program": "$PROGRAM", "stopOnEntry": true }),
+                ),
+                request(
+                    "setBreakpoints",
+                    json!({
+                        "source": { "path": "$PROGRAM" },
+                        "breakpoints": [{ "line": 4 }],
+                    }),
+                ),
+                request("configurationDone", json!({})),
+                request("stackTrace", json!({ "threadId": 1 })),
+                request("next", json!({ "threadId": 1 })),
+                request("next", json!({ "threadId": 1 })),
+                request("scopes", json!({ "frameId": 1 })),
+                request("variables", json!({ "variablesReference": LOCALS })),
+                request("variables", json!({ "variablesReference": GLOBALS })),
+                request("evaluate", json!({ "expression": "b + a" })),
+                request("evaluate", json!({ "expression": "c" })),
+                request("continue", json!({ "threadId": 1 })),
+                request("disconnect", json!({})),
+            ],
+        );
+        assert_eq!(
+            vec![
+                "initialize",
+                "initialized",
+                "launch",
+                "setBreakpoints",
+                "configurationDone",
+                "stopped entry",
+                "stackTrace 1",
+                "next",
+                "stopped step",
+                "next",
+                "stopped breakpoint",
+                "scopes",
+                "variables b=3",
+                "variables a=1",
+                "evaluate 4",
+                "evaluate failed: Error: Undefined variable 'c'.",
+                "continue",
+                "\"stdout\" \"3\\n\"",
+                "\"stdout\" \"10\\n\"",
+                "exited 0",
+                "terminated",
+                "disconnect",
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn test_dap_errors() {
+        let messages = session(
+            "var a = 1;\nprint a + b;",
+            &[
+                request("attach", json!({})),
+                request("configurationDone", json!({})),
+                request("evaluate", json!({ "expression": "1" })),
+                request("launch", json!({})),
+                request("launch", json!({ "program": "/nonexistent/main.lox" })),
+                request("launch", json!({ "program": "$PROGRAM" })),
+                request("pause", json!({ "threadId": 1 })),
+                request("configurationDone", json!({})),
+            ],
+        );
+        assert_eq!(
+            vec![
+                "attach failed: Attaching is not supported, as programs only run inside the \
+                 server. Launch the program instead.",
+                "configurationDone failed: Launch a program first.",
+                "evaluate failed: The program is not paused.",
+                "launch failed: Expected the path of the program to launch.",
+                "launch failed: Failed to read file /nonexistent/main.lox",
+                "launch",
+                "pause failed: Unsupported request 'pause'.",
+                "configurationDone",
+                "\"stderr\" \"Undefined variable 'b'.\\n[line 2]\\n\"",
+                "exited 70",
+                "terminated",
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn test_dap_disconnect_stops_program() {
+        let messages = session(
+            PROGRAM,
+            &[
+                request("launch", json!({ "program": "$PROGRAM" })),
+                request(
+                    "setBreakpoints",
+                    json!({
+                        "source": { "path": "$PROGRAM" },
+                        "breakpoints": [{ "line": 3 }],
+                    }),
+                ),
+                request("configurationDone", json!({})),
+                request("disconnect", json!({})),
+            ],
+        );
+        assert_eq!(
+            vec![
+                "launch",
+                "setBreakpoints",
+                "configurationDone",
+                "stopped breakpoint",
+                "disconnect",
+            ],
+            messages
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! An interactive debugger that pauses a program before its statements and
+//! reads commands, such as `step` or `print x`, from its input.
+//!
+//! It pauses before the first statement, then wherever the last command
+//! says to and at every breakpoint. Statements of imported modules run
+//! without pausing.
+
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+
+use crate::Value;
+use crate::interpreter::Interpreter;
+use crate::interpreter::hook::Hook;
+use crate::parser::Parser;
+use crate::parser::ast::{AstArena, StmtId};
+use crate::scanner::Scanner;
+use crate::symbol::Symbol;
+
+/// The commands of the debugger, for `help` and unknown commands.
+const HELP: &str = "\
+step, s         run to the next statement
+next, n         run to the next statement outside the current one
+continue, c     run to the next breakpoint
+break, b LINE   pause before the statements on LINE
+clear LINE      remove the breakpoint on LINE
+locals          print the variables of the enclosing blocks
+globals         print the global variables
+print, p EXPR   evaluate EXPR where the program paused
+quit, q         stop the program";
+
+/// Where a debugger pauses next, besides at breakpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Resume {
+    /// Before the next statement
+    Step,
+    /// Before the next statement at most `depth` statements deep
+    Next { depth: usize },
+    /// Only at breakpoints
+    Continue,
+}
+
+impl Resume {
+    /// Checks whether to pause before a statement `depth` statements deep,
+    /// breakpoints aside.
+    pub(crate) fn pauses(self, depth: usize) -> bool {
+        match self {
+            Resume::Step => true,
+            Resume::Next { depth: max } => depth <= max,
+            Resume::Continue => false,
+        }
+    }
+}
+
+/// A [`Hook`] that pauses a program and reads commands from `input`,
+/// writing what they print to `output`.
+pub struct Debugger<R, W> {
+    input: R,
+    output: W,
+    /// The lines of the program being debugged
+    lines: Vec<String>,
+    /// The file the program was read from, telling its statements apart from
+    /// those of the modules it imports
+    script: Option<PathBuf>,
+    breakpoints: BTreeSet<u32>,
+    resume: Resume,
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    /// Creates a debugger for the program in `source`, read from `script`
+    /// if it came from a file.
+    pub fn new(source: &str, script: Option<PathBuf>, input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            lines: source.lines().map(str::to_string).collect(),
+            script,
+            breakpoints: BTreeSet::new(),
+            resume: Resume::Step,
+        }
+    }
+
+    /// Returns where the debugger writes, such as a buffer it filled.
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    /// Reads and runs commands until one resumes or stops the program.
+    fn pause(&mut self, interpreter: &mut Interpreter, line: u32) -> ControlFlow<()> {
+        let text = self
+            .lines
+            .get(line as usize - 1)
+            .map_or("", |l| l.trim())
+            .to_string();
+        self.write(format_args!("[line {line}] {text}"));
+        loop {
+            let _ = write!(self.output, "(debug) ");
+            let _ = self.output.flush();
+            let mut command = String::new();
+            // the end of the input quits, as there is no one left to resume
+            if matches!(self.input.read_line(&mut command), Ok(0) | Err(_)) {
+                return ControlFlow::Break(());
+            }
+            if let Some(flow) = self.command(interpreter, command.trim()) {
+                return flow;
+            }
+        }
+    }
+
+    /// Runs `command`, and returns how the program goes on if the command
+    /// resumes or stops it.
+    fn command(&mut self, interpreter: &mut Interpreter, command: &str) -> Option<ControlFlow<()>> {
+        let (command, argument) = command.split_once(' ').unwrap_or((command, ""));
+        let argument = argument.trim();
+        let resume = match command {
+            "step" | "s" => Resume::Step,
+            "next" | "n" => Resume::Next {
+                depth: interpreter.depth(),
+            },
+            "continue" | "c" => Resume::Continue,
+            "quit" | "q" => return Some(ControlFlow::Break(())),
+            "break" | "b" | "clear" => {
+                match argument.parse::<u32>() {
+                    Ok(line) if command == "clear" => _ = self.breakpoints.remove(&line),
+                    Ok(line) => _ = self.breakpoints.insert(line),
+                    Err(_) => self.write(format_args!("Expected a line number.")),
+                }
+                return None;
+            }
+            "locals" => {
+                let scopes = interpreter.scopes();
+                self.variables(scopes[..scopes.len() - 1].iter().flatten());
+                return None;
+            }
+            "globals" => {
+                let scopes = interpreter.scopes();
+                // the natives are always there, so they are left out
+                let globals = scopes.last().into_iter().flatten();
+                self.variables(
+                    globals.filter(|(_, value)| !matches!(value, Value::NativeFunction(_))),
+                );
+                return None;
+            }
+            "print" | "p" => {
+                let value = evaluate(interpreter, argument);
+                self.write(format_args!("{}", value.unwrap_or_else(|err| err)));
+                return None;
+            }
+            _ => {
+                self.write(format_args!("{HELP}"));
+                return None;
+            }
+        };
+        self.resume = resume;
+        Some(ControlFlow::Continue(()))
+    }
+
+    /// Writes `name = value` for each of `variables`.
+    fn variables<'a>(&mut self, variables: impl IntoIterator<Item = &'a (Symbol, Value)>) {
+        for (name, value) in variables {
+            self.write(format_args!("{name} = {value}"));
+        }
+    }
+
+    // a debugger that cannot write can still be stepped through
+    fn write(&mut self, line: std::fmt::Arguments) {
+        let _ = writeln!(self.output, "{line}");
+    }
+}
+
+impl<R: BufRead, W: Write> Hook for Debugger<R, W> {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        ast: &AstArena,
+        stmt: StmtId,
+    ) -> ControlFlow<()> {
+        let Some(lines) = ast.lines(stmt) else {
+            return ControlFlow::Continue(());
+        };
+        if interpreter.script() != self.script.as_deref() {
+            return ControlFlow::Continue(());
+        }
+
+        if self.resume.pauses(interpreter.depth()) || self.breakpoints.contains(&lines.first) {
+            return self.pause(interpreter, lines.first);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Evaluates the expression in `source` where `interpreter` is, and
+/// describes its value.
+///
+/// Returns the error message if the expression does not parse or fails.
+pub(crate) fn evaluate(interpreter: &mut Interpreter, source: &str) -> Result<String, String> {
+    let dialect = interpreter.dialect();
+    let scanner = Scanner::new(source)
+        .with_dialect(dialect)
+        .with_interner(interpreter.interner().clone());
+    let (ast, expr) = Parser::new(scanner.scan_tokens())
+        .with_dialect(dialect)
+        .parse_expression()
+        .map_err(|err| err.to_string())?;
+    let value = interpreter
+        .evaluate(&ast, expr)
+        .map_err(|err| format!("Error: {}", err.message()))?;
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::dialect::{Dialect, Extension};
+    use crate::interpreter::error::RuntimeError;
+
+    /// Runs `source` under a debugger given `commands`, and returns what the
+    /// debugger wrote, without its prompts, and how the program ended.
+    fn debug(source: &str, commands: &str) -> (String, Result<(), RuntimeError>) {
+        let dialect = Dialect::new().with(Extension::Exceptions);
+        let scanner = Scanner::new(source).with_dialect(dialect);
+        let program = Parser::new(scanner.scan_tokens())
+            .with_dialect(dialect)
+            .parse()
+            .unwrap();
+        let input = Cursor::new(commands.to_string());
+        let debugger = Rc::new(RefCell::new(Debugger::new(source, None, input, Vec::new())));
+        let mut interpreter = Interpreter::new()
+            .with_dialect(dialect)
+            .with_muted_output(true)
+            .with_hook(debugger.clone());
+
+        let result = interpreter.interpret(&program);
+        let output = String::from_utf8(debugger.borrow().output().clone()).unwrap();
+        (output.replace("(debug) ", ""), result)
+    }
+
+    const PROGRAM: &str =
+        "var a = 1;\n{\n  var b = a + 2;\n  print b;\n}\nvar c = a * 10;\nprint c;";
+
+    #[rstest]
+    #[case(
+        "s\ns\ns\nlocals\nglobals\nc\n",
+        "[line 1] var a = 1;\n[line 2] {\n[line 3] var b = a + 2;\n[line 4] print b;\nb = 3\na = 1\n"
+    )]
+    #[case(
+        "n\nn\nn\nc\n",
+        "[line 1] var a = 1;\n[line 2] {\n[line 6] var c = a * 10;\n[line 7] print c;\n"
+    )]
+    #[case(
+        "b 4\nb 7\nclear 7\nc\np b + a\nc\n",
+        "[line 1] var a = 1;\n[line 4] print b;\n4\n"
+    )]
+    #[case(
+        "p a\np 1 +\nb x\ns\np a = 5\nc\n",
+        "[line 1] var a = 1;\nError: Undefined variable 'a'.\n[line 1] Error at end: Expect expression\nExpected a line number.\n[line 2] {\n5\n"
+    )]
+    fn test_debugger_commands(#[case] commands: &str, #[case] expected: &str) {
+        let (output, result) = debug(PROGRAM, commands);
+        assert_eq!(expected, output);
+        result.unwrap();
+    }
+
+    #[rstest]
+    #[case("s\nq\n")]
+    #[case("s\n")]
+    fn test_debugger_quit_stops_program(#[case] commands: &str) {
+        // quitting is not an error the program can catch
+        let source = "var a = 1;\ntry { print a; } catch (e) { print e; }";
+        let (output, result) = debug(source, commands);
+        assert_eq!(
+            "[line 1] var a = 1;\n[line 2] try { print a; } catch (e) { print e; }\n",
+            output
+        );
+        assert!(result.unwrap_err().is_stopped());
+    }
+}
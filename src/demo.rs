@@ -0,0 +1,72 @@
+//! Classic Lox programs compiled into the binary, so that `run --demo NAME`
+//! exercises and benchmarks the interpreter without any files.
+
+use crate::dialect::{Dialect, Extension};
+
+/// A bundled program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Demo {
+    /// Print the Fibonacci numbers that fit in an integer
+    Fib,
+    /// Build a complete binary tree of lists and count its nodes
+    BinaryTrees,
+    /// Draw the Mandelbrot set in ASCII
+    Mandelbrot,
+}
+
+impl Demo {
+    /// Returns the source code of the program.
+    pub fn source(self) -> &'static str {
+        match self {
+            Demo::Fib => include_str!("../demos/fib.lox"),
+            Demo::BinaryTrees => include_str!("../demos/binary_trees.lox"),
+            Demo::Mandelbrot => include_str!("../demos/mandelbrot.lox"),
+        }
+    }
+
+    /// Returns `dialect` with the extensions the program is written in
+    /// enabled as well.
+    pub fn dialect(self, dialect: Dialect) -> Dialect {
+        let extensions: &[Extension] = match self {
+            Demo::Fib => &[Extension::Integers, Extension::ForIn, Extension::Ranges],
+            Demo::BinaryTrees => &[
+                Extension::Integers,
+                Extension::ForIn,
+                Extension::Ranges,
+                Extension::Lists,
+                Extension::Spread,
+                Extension::Match,
+                Extension::Interpolation,
+            ],
+            Demo::Mandelbrot => &[
+                Extension::Integers,
+                Extension::ForIn,
+                Extension::Ranges,
+                Extension::Match,
+            ],
+        };
+        extensions.iter().copied().fold(dialect, Dialect::with)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum as _;
+
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::validate::validate_with;
+
+    #[test]
+    fn test_demos_run() {
+        for demo in Demo::value_variants() {
+            let dialect = demo.dialect(Dialect::new());
+            let validation = validate_with(demo.source(), dialect);
+            assert!(validation.diagnostics.is_empty(), "{demo:?}");
+
+            let program = validation.ast.unwrap();
+            let mut interpreter = Interpreter::new().with_dialect(dialect);
+            assert!(interpreter.interpret(&program).is_ok(), "{demo:?}");
+        }
+    }
+}
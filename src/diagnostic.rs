@@ -0,0 +1,650 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Write as _};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{self, LIMIT_EXCEEDED, RUNTIME_ERROR, Report, STATIC_ERROR, StaticError};
+use crate::interpreter::error::RuntimeError;
+use crate::scanner::token::Token;
+use crate::source::{SourceFile, SourceId, SourceMap, Span};
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// An error or warning about a program, independent of how it is displayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The diagnostic category, such as `static` or `runtime`
+    pub code: &'static str,
+    pub message: String,
+    pub line: Option<u32>,
+    /// The 1-based column of the token the diagnostic points at
+    pub column: Option<u32>,
+    /// What the diagnostic points at, such as `at ';'` or `at end`
+    pub location: Option<String>,
+    /// The bytes of the token the diagnostic points at
+    pub span: Option<Span>,
+    /// The file the diagnostic is about, if there is more than one
+    pub file: Option<String>,
+}
+
+impl Diagnostic {
+    /// Creates a warning with category `code` pointing at `token`.
+    pub fn warning(code: &'static str, token: &Token, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code,
+            message: message.into(),
+            line: Some(token.line),
+            column: (token.column > 0).then_some(token.column),
+            location: Some(error::location(token)),
+            span: token.span,
+            file: None,
+        }
+    }
+
+    /// Creates a warning with category `code` about the whole of `line`, for
+    /// code with no single token to point at.
+    pub fn warning_at_line(code: &'static str, line: u32, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code,
+            message: message.into(),
+            line: Some(line),
+            column: None,
+            location: None,
+            span: None,
+            file: None,
+        }
+    }
+
+    /// Returns this diagnostic attributed to `file`.
+    pub fn with_file(mut self, file: impl Into<String>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    /// Orders diagnostics by file, line, column and then phase, so that
+    /// static errors come before anything found while running at the same
+    /// place. Diagnostics without a line come last in their file.
+    fn sort_key(&self) -> (Option<&str>, u32, u32, bool) {
+        (
+            self.file.as_deref(),
+            self.line.unwrap_or(u32::MAX),
+            self.column.unwrap_or(0),
+            self.code != STATIC_ERROR.code,
+        )
+    }
+}
+
+/// Sorts `diagnostics` into a reproducible order, see [`Diagnostic::sort_key`].
+///
+/// The sort is stable, so diagnostics at the same place keep the order they
+/// were reported in.
+pub fn sort(diagnostics: &mut [Diagnostic]) {
+    diagnostics.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+}
+
+/// What happens to the warnings with a given code, set with `--allow`,
+/// `--warn` and `--deny`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// Drop the warnings.
+    Allow,
+    /// Report the warnings and carry on.
+    Warn,
+    /// Report the warnings as errors.
+    Deny,
+}
+
+/// The [`Level`] of each warning code that has one other than the default.
+///
+/// The pseudo-code `warnings` sets the level of every code without a level
+/// of its own, so `--deny warnings` turns all warnings into errors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Levels {
+    levels: HashMap<String, Level>,
+}
+
+/// The lint config file: lists of warning codes for each [`Level`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LevelsConfig {
+    allow: Vec<String>,
+    warn: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl Levels {
+    /// The code whose level applies to codes without one of their own.
+    pub const ALL: &str = "warnings";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the levels in a JSON object such as `{"deny": ["nil-safety"]}`,
+    /// with `allow`, `warn` and `deny` lists applied in that order.
+    pub fn with_config(self, json: &str) -> Result<Self, serde_json::Error> {
+        let config: LevelsConfig = serde_json::from_str(json)?;
+        let levels = [
+            (Level::Allow, config.allow),
+            (Level::Warn, config.warn),
+            (Level::Deny, config.deny),
+        ];
+        let levels = levels
+            .into_iter()
+            .flat_map(|(level, codes)| codes.into_iter().map(move |code| (code, level)));
+        Ok(levels.fold(self, |levels, (code, level)| levels.with(code, level)))
+    }
+
+    /// Sets the level of `code`, replacing any it had.
+    pub fn with(mut self, code: impl Into<String>, level: Level) -> Self {
+        self.levels.insert(code.into(), level);
+        self
+    }
+
+    /// Returns the level set for `code` itself, if any.
+    pub fn get(&self, code: &str) -> Option<Level> {
+        self.levels.get(code).copied()
+    }
+
+    /// Returns the level that applies to warnings with `code`.
+    pub fn level(&self, code: &str) -> Level {
+        self.get(code)
+            .or_else(|| self.get(Self::ALL))
+            .unwrap_or(Level::Warn)
+    }
+
+    /// Checks whether any code, or every code through [`Self::ALL`], is
+    /// denied.
+    pub fn denies_any(&self) -> bool {
+        self.levels.values().any(|&level| level == Level::Deny)
+    }
+
+    /// Returns `diagnostic` as its level makes it, or `None` if its code is
+    /// allowed. Errors are returned unchanged.
+    pub fn apply(&self, mut diagnostic: Diagnostic) -> Option<Diagnostic> {
+        if diagnostic.severity == Severity::Error {
+            return Some(diagnostic);
+        }
+        match self.level(diagnostic.code) {
+            Level::Allow => return None,
+            Level::Warn => {}
+            Level::Deny => diagnostic.severity = Severity::Error,
+        }
+        Some(diagnostic)
+    }
+}
+
+impl From<&StaticError> for Diagnostic {
+    fn from(err: &StaticError) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: match err.exceeded_limit() {
+                Some(_) => LIMIT_EXCEEDED.code,
+                None => STATIC_ERROR.code,
+            },
+            message: err.message().to_string(),
+            line: Some(err.line()),
+            column: err.column(),
+            location: err.location().map(str::to_string),
+            span: err.span(),
+            file: None,
+        }
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(err: &RuntimeError) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: match err.exceeded_limit() {
+                Some(_) => LIMIT_EXCEEDED.code,
+                None => RUNTIME_ERROR.code,
+            },
+            message: err.message(),
+            line: err.line(),
+            column: err.column(),
+            location: None,
+            span: err.span(),
+            file: err.file().map(str::to_string),
+        }
+    }
+}
+
+impl From<&Report> for Diagnostic {
+    fn from(report: &Report) -> Self {
+        match report {
+            Report::Runtime(err) => err.into(),
+            Report::Static(err) => err.into(),
+        }
+    }
+}
+
+/// Turns a [`Diagnostic`] into the text written to stderr.
+///
+/// Errors are built the same way whichever renderer displays them, so
+/// embedders can supply their own implementation.
+pub trait DiagnosticRenderer: Debug {
+    fn render(&self, diagnostic: &Diagnostic) -> String;
+}
+
+/// Renders diagnostics exactly like the reference implementation: the
+/// message followed by `[line 1]` for runtime errors, and
+/// `[line 1] Error at ';': message` for everything else. A diagnostic about
+/// another file than the script names it as `[line 1 in lib.lox]`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Plain;
+
+impl DiagnosticRenderer for Plain {
+    fn render(&self, diagnostic: &Diagnostic) -> String {
+        let Diagnostic {
+            severity,
+            code,
+            message,
+            line,
+            location,
+            file,
+            ..
+        } = diagnostic;
+
+        let Some(line) = line else {
+            return message.clone();
+        };
+        let line = match file {
+            Some(file) => format!("{line} in {file}"),
+            None => line.to_string(),
+        };
+        if *code == RUNTIME_ERROR.code {
+            return format!("{message}\n[line {line}]");
+        }
+
+        let label = match severity {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        };
+        match location {
+            Some(location) => format!("[line {line}] {label} {location}: {message}"),
+            None => format!("[line {line}] {label}: {message}"),
+        }
+    }
+}
+
+/// Renders diagnostics with the offending source line and a caret under the
+/// column they point at, optionally colored with ANSI escape codes.
+#[derive(Debug, Clone)]
+pub struct Rich {
+    sources: SourceMap,
+    colors: bool,
+}
+
+impl Rich {
+    /// Creates a renderer quoting snippets of `source`, without colors.
+    pub fn new(source: impl Into<String>) -> Self {
+        let sources = SourceMap::new();
+        sources.add("", source);
+        Self::with_sources(sources)
+    }
+
+    /// Creates a renderer quoting snippets of the file each diagnostic is
+    /// attributed to, or of the first file for diagnostics without one.
+    pub fn with_sources(sources: SourceMap) -> Self {
+        Self {
+            sources,
+            colors: false,
+        }
+    }
+
+    /// Colors the output with ANSI escape codes when `colors` is true.
+    pub fn with_colors(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Returns the file `diagnostic` is about, if it is one of the sources.
+    fn source(&self, diagnostic: &Diagnostic) -> Option<Rc<SourceFile>> {
+        let by_span = diagnostic
+            .span
+            .and_then(|span| self.sources.get(span.source));
+        by_span.or_else(|| match &diagnostic.file {
+            Some(file) => self.sources.find(file),
+            None => self.sources.get(SourceId::default()),
+        })
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.colors {
+            format!("\x1b[{style}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+impl DiagnosticRenderer for Rich {
+    fn render(&self, diagnostic: &Diagnostic) -> String {
+        let style = match diagnostic.severity {
+            Severity::Error => "1;31",
+            Severity::Warning => "1;33",
+        };
+        let header = format!("{}[{}]", diagnostic.severity.as_str(), diagnostic.code);
+        let mut out = format!("{}: {}", self.paint(style, &header), diagnostic.message);
+
+        let Some(line) = diagnostic.line else {
+            return out;
+        };
+        let gutter = " ".repeat(line.to_string().len());
+        let arrow = self.paint("1;34", "-->");
+        let file = diagnostic
+            .file
+            .as_ref()
+            .map(|file| format!("{file}:"))
+            .unwrap_or_default();
+        match diagnostic.column {
+            Some(column) => write!(out, "\n{gutter}{arrow} {file}{line}:{column}").unwrap(),
+            None if file.is_empty() => write!(out, "\n{gutter}{arrow} line {line}").unwrap(),
+            None => write!(out, "\n{gutter}{arrow} {file}{line}").unwrap(),
+        }
+
+        let Some(source) = self.source(diagnostic) else {
+            return out;
+        };
+        let Some(text) = source.line(line) else {
+            return out;
+        };
+        let bar = self.paint("1;34", "|");
+        let number = self.paint("1;34", &line.to_string());
+        write!(out, "\n{gutter} {bar}\n{number} {bar} {text}").unwrap();
+        if let Some(column) = diagnostic.column {
+            let padding = " ".repeat(column as usize - 1);
+            // underline the whole token when it is on this one line
+            let token = diagnostic
+                .span
+                .filter(|span| span.source == source.id())
+                .and_then(|span| source.text().get(span.range()))
+                .filter(|token| !token.contains('\n'));
+            let width = token.map_or(1, |token| token.chars().count().max(1));
+            let carets = self.paint(style, &"^".repeat(width));
+            write!(out, "\n{gutter} {bar} {padding}{carets}").unwrap();
+        }
+        out
+    }
+}
+
+/// Renders each diagnostic as a single-line JSON object, for tools that
+/// consume the interpreter's errors.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl DiagnosticRenderer for Json {
+    fn render(&self, diagnostic: &Diagnostic) -> String {
+        serde_json::json!({
+            "severity": diagnostic.severity.as_str(),
+            "code": diagnostic.code,
+            "file": diagnostic.file,
+            "line": diagnostic.line,
+            "column": diagnostic.column,
+            "location": diagnostic.location,
+            "message": diagnostic.message,
+        })
+        .to_string()
+    }
+}
+
+/// The built-in renderers, selectable with `--diagnostics`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DiagnosticFormat {
+    /// `[line 1] Error at ';': ...`, like the reference implementation.
+    #[default]
+    Plain,
+    /// Source snippets with a caret under the offending token.
+    Rich,
+    /// One JSON object per line.
+    Json,
+}
+
+impl DiagnosticFormat {
+    /// Creates the renderer for this format. The files of `sources` are
+    /// quoted by the [`Rich`] renderer, which is also colored when `colors`
+    /// is true.
+    pub fn renderer(self, sources: &SourceMap, colors: bool) -> Rc<dyn DiagnosticRenderer> {
+        match self {
+            DiagnosticFormat::Plain => Rc::new(Plain),
+            DiagnosticFormat::Rich => {
+                Rc::new(Rich::with_sources(sources.clone()).with_colors(colors))
+            }
+            DiagnosticFormat::Json => Rc::new(Json),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::scanner::token::TokenType;
+
+    fn token(typ: TokenType, lexeme: &str, line: u32, column: u32) -> Token {
+        let mut token = Token::new(typ, lexeme.into(), None, line);
+        token.column = column;
+        token
+    }
+
+    fn static_error() -> Report {
+        let semicolon = token(TokenType::Semicolon, ";", 2, 9);
+        StaticError::error_at_token(&semicolon, "Expect expression.".into()).into()
+    }
+
+    fn runtime_error() -> Report {
+        let minus = token(TokenType::Minus, "-", 1, 7);
+        RuntimeError::new(minus, "Operand must be a number.").into()
+    }
+
+    #[rstest]
+    #[case(static_error())]
+    #[case(runtime_error())]
+    #[case(StaticError::error_at_line(3, "Unterminated string.".into()).into())]
+    fn test_plain_matches_display(#[case] report: Report) {
+        assert_eq!(report.to_string(), Plain.render(&(&report).into()));
+    }
+
+    #[test]
+    fn test_plain_warning() {
+        let star = token(TokenType::Star, "*", 4, 3);
+        let warning = Diagnostic::warning("arithmetic", &star, "Arithmetic produced NaN.");
+        assert_eq!(
+            "[line 4] Warning at '*': Arithmetic produced NaN.",
+            Plain.render(&warning)
+        );
+    }
+
+    #[rstest]
+    #[case(
+        static_error(),
+        "error[static]: Expect expression.\n \
+         --> 2:9\n  \
+         |\n\
+         2 | print 1 +;\n  \
+         |         ^"
+    )]
+    #[case(
+        runtime_error(),
+        "error[runtime]: Operand must be a number.\n \
+         --> 1:7\n  \
+         |\n\
+         1 | var a;\n  \
+         |       ^"
+    )]
+    fn test_rich_snippet(#[case] report: Report, #[case] expected: &str) {
+        let rich = Rich::new("var a;\nprint 1 +;\n");
+        assert_eq!(expected, rich.render(&(&report).into()));
+    }
+
+    #[test]
+    fn test_rich_quotes_the_file_of_each_diagnostic() {
+        let sources = SourceMap::new();
+        sources.add("main.lox", "import \"lib.lox\";\nprint 1 +;\n");
+        let lib = sources.add("lib.lox", "var a;\nvar b = -a;\n");
+        let rich = Rich::with_sources(sources);
+
+        let minus = token(TokenType::Minus, "-", 2, 9);
+        let report = Report::from(RuntimeError::new(minus, "Operand must be a number."));
+        let rendered = rich.render(&Diagnostic::from(&report).with_file("lib.lox"));
+        assert!(
+            rendered.ends_with("2 | var b = -a;\n  |         ^"),
+            "{rendered}"
+        );
+
+        let rendered = rich.render(&(&static_error()).into());
+        assert!(
+            rendered.ends_with("2 | print 1 +;\n  |         ^"),
+            "{rendered}"
+        );
+
+        let rendered = rich.render(&Diagnostic::from(&static_error()).with_file("other.lox"));
+        assert!(rendered.ends_with("--> other.lox:2:9"), "{rendered}");
+
+        // a span finds the file by id, and is underlined whole
+        let b = token(TokenType::Identifier, "b", 2, 5).with_span(Some(Span::new(lib, 11, 12)));
+        let a = token(TokenType::Identifier, "var", 1, 1).with_span(Some(Span::new(lib, 0, 3)));
+        let rendered = rich.render(&Diagnostic::warning("unused", &a, "Unused."));
+        assert!(rendered.ends_with("1 | var a;\n  | ^^^"), "{rendered}");
+        let rendered = rich.render(&Diagnostic::warning("unused", &b, "Unused."));
+        assert!(
+            rendered.ends_with("2 | var b = -a;\n  |     ^"),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn test_rich_colors() {
+        let rich = Rich::new("print 1 +;").with_colors(true);
+        let rendered = rich.render(&(&static_error()).into());
+        assert!(rendered.starts_with("\x1b[1;31merror[static]\x1b[0m: Expect expression."));
+        assert!(
+            !Rich::new("")
+                .render(&(&static_error()).into())
+                .contains('\x1b')
+        );
+    }
+
+    #[test]
+    fn test_sort() {
+        let at = |file: Option<&str>, line: Option<u32>, column: Option<u32>, code| Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: String::new(),
+            line,
+            column,
+            location: None,
+            span: None,
+            file: file.map(String::from),
+        };
+        let mut diagnostics = vec![
+            at(Some("b.lox"), Some(1), Some(1), "static"),
+            at(None, None, None, "limit"),
+            at(None, Some(2), Some(5), "runtime"),
+            at(None, Some(2), Some(5), "static"),
+            at(None, Some(2), None, "static"),
+            at(Some("a.lox"), Some(9), Some(1), "runtime"),
+            at(None, Some(1), Some(8), "static"),
+        ];
+
+        sort(&mut diagnostics);
+        let order = diagnostics
+            .iter()
+            .map(|d| (d.file.as_deref(), d.line, d.column, d.code))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (None, Some(1), Some(8), "static"),
+                (None, Some(2), None, "static"),
+                (None, Some(2), Some(5), "static"),
+                (None, Some(2), Some(5), "runtime"),
+                (None, None, None, "limit"),
+                (Some("a.lox"), Some(9), Some(1), "runtime"),
+                (Some("b.lox"), Some(1), Some(1), "static"),
+            ],
+            order
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let rendered = Json.render(&(&static_error()).into());
+        let json: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "severity": "error",
+                "code": "static",
+                "file": null,
+                "line": 2,
+                "column": 9,
+                "location": "at ';'",
+                "message": "Expect expression.",
+            }),
+            json
+        );
+        assert!(!rendered.contains('\n'));
+    }
+
+    #[rstest]
+    #[case(Levels::new(), Some(Severity::Warning))]
+    #[case(Levels::new().with("unused-variable", Level::Allow), None)]
+    #[case(Levels::new().with("unused-variable", Level::Deny), Some(Severity::Error))]
+    #[case(Levels::new().with("warnings", Level::Deny), Some(Severity::Error))]
+    #[case(
+        Levels::new()
+            .with("warnings", Level::Deny)
+            .with("unused-variable", Level::Warn),
+        Some(Severity::Warning)
+    )]
+    #[case(Levels::new().with("nil-safety", Level::Allow), Some(Severity::Warning))]
+    fn test_levels_apply(#[case] levels: Levels, #[case] expected: Option<Severity>) {
+        let x = token(TokenType::Identifier, "x", 1, 5);
+        let warning = Diagnostic::warning("unused-variable", &x, "Local 'x' is never read.");
+        let applied = levels.apply(warning);
+        assert_eq!(expected, applied.map(|d| d.severity));
+    }
+
+    #[test]
+    fn test_levels_keep_errors() {
+        let levels = Levels::new().with("static", Level::Allow);
+        let error = Diagnostic::from(&static_error());
+        assert_eq!(Some(error.clone()), levels.apply(error));
+    }
+
+    #[test]
+    fn test_levels_with_config() {
+        let levels = Levels::new().with("empty-block", Level::Allow);
+        let levels = levels
+            .with_config(r#"{"allow": ["arithmetic"], "deny": ["warnings", "arithmetic"]}"#)
+            .unwrap();
+        assert_eq!(Level::Deny, levels.level("arithmetic"));
+        assert_eq!(Level::Deny, levels.level("nil-safety"));
+        assert_eq!(None, levels.get("nil-safety"));
+        assert_eq!(Level::Allow, levels.level("empty-block"));
+        assert!(levels.denies_any());
+        assert!(!Levels::new().with("warnings", Level::Warn).denies_any());
+
+        assert!(Levels::new().with_config(r#"{"forbid": []}"#).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt::Display;
+
+use clap::ValueEnum as _;
+
+/// Opt-in language extensions layered on top of standard Lox.
+///
+/// Every extension is disabled by default so the scanner, parser and
+/// interpreter behave exactly like the reference implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum Extension {
+    /// `"ab" * 3` evaluates to `"ababab"`.
+    StringRepetition,
+    /// `&`, `|`, `^`, `<<` and `>>` on whole numbers.
+    Bitwise,
+    /// Number literals without a decimal point scan as integers, and integer
+    /// arithmetic stays integral until it overflows or divides.
+    Integers,
+    /// `"sum: ${a + b}"` embeds expressions in string literals. The parser
+    /// desugars them into `+` concatenations of the pieces and of `str`
+    /// called on each expression, so `+` itself is unchanged.
+    Interpolation,
+    /// Hexadecimal (`0xFF`), binary (`0b1010`) and exponent (`1.5e-3`)
+    /// number literals, with `_` digit separators (`1_000_000.50`).
+    NumericLiterals,
+    /// Identifiers may use any Unicode letters (`XID_Start`) and continue with
+    /// Unicode letters, digits and combining marks (`XID_Continue`).
+    UnicodeIdentifiers,
+    /// List literals (`[1, 2, 3]`) with index get and set (`xs[0] = xs[1]`).
+    Lists,
+    /// Triple-quoted `"""` string blocks that span lines verbatim, without
+    /// interpolation. A newline right after the opening quotes is dropped.
+    TextBlocks,
+    /// Map literals (`{"k": v}`) in expression position, read and written by
+    /// key with the index syntax (`m["k"] = v`), which this extension enables
+    /// on its own.
+    Maps,
+    /// A trailing comma after the last argument of a call or the last element
+    /// of a list or map literal (`[1, 2,]`).
+    TrailingCommas,
+    /// `for (x in collection) body` loops over the elements of a list, the
+    /// keys of a map, the characters of a string, or the numbers below `n`.
+    ForIn,
+    /// `...xs` expands a list into the arguments of a call (`f(...args)`) or
+    /// the elements of a list literal (`[1, ...rest]`).
+    Spread,
+    /// Ranges of whole numbers, `0..n` excluding and `0..=n` including the
+    /// end, that count down when the start is greater than the end.
+    Ranges,
+    /// `throw value;` statements and `try { } catch (e) { } finally { }`
+    /// blocks, which also catch runtime errors. `try`, `catch`, `finally` and
+    /// `throw` become reserved words.
+    Exceptions,
+    /// `assert condition, message;` statements, which raise a runtime error
+    /// quoting the condition when it is falsey. The message is optional and
+    /// `assert` becomes a reserved word.
+    Assertions,
+    /// `import "path.lox";` runs another file once, and `import a, b from
+    /// "path.lox";` also binds its top-level variables `a` and `b`. Paths
+    /// are relative to the importing file and `import` becomes a reserved
+    /// word.
+    Modules,
+    /// `match (value) { 1, 2 => stmt, "a" => stmt, else => stmt }` runs the
+    /// first arm with a literal pattern equal to the value, or the optional
+    /// `else` arm. `match` becomes a reserved word.
+    Match,
+    /// `do body while (condition);` runs the body once, then again for as
+    /// long as the condition is truthy. `do` becomes a reserved word.
+    DoWhile,
+    /// `a ?? b` produces `a` unless it is nil, and only then evaluates `b`.
+    NilCoalescing,
+    /// `var (a, b) = xs;` declares a variable for each element of a list, and
+    /// `(a, b) = (b, a);` assigns several variables at once. A parenthesized
+    /// `(x, y)` with a comma evaluates to a list.
+    Destructuring,
+    /// `const x = 10;` declares a variable that cannot be assigned again.
+    /// Assignments the parser can see are errors before the program runs,
+    /// and any other is a runtime error. `const` becomes a reserved word.
+    Constants,
+}
+
+impl Display for Extension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = self
+            .to_possible_value()
+            .expect("no extension is skipped on the command line");
+        f.write_str(value.get_name())
+    }
+}
+
+/// The set of [`Extension`]s enabled for a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Dialect {
+    enabled: u32,
+}
+
+impl Dialect {
+    /// Creates the standard Lox dialect with no extensions enabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns this dialect with `extension` enabled.
+    pub fn with(mut self, extension: Extension) -> Self {
+        self.enable(extension);
+        self
+    }
+
+    /// Enables `extension` in place.
+    pub fn enable(&mut self, extension: Extension) {
+        self.enabled |= Self::bit(extension);
+    }
+
+    /// Checks whether `extension` is enabled.
+    pub fn allows(&self, extension: Extension) -> bool {
+        self.enabled & Self::bit(extension) != 0
+    }
+
+    fn bit(extension: Extension) -> u32 {
+        1 << extension as u32
+    }
+}
+
+impl FromIterator<Extension> for Dialect {
+    fn from_iter<T: IntoIterator<Item = Extension>>(iter: T) -> Self {
+        iter.into_iter().fold(Self::new(), Self::with)
+    }
+}
@@ -0,0 +1,183 @@
+//! Token-level diffs between two versions of a source file. Whitespace and
+//! comments never reach the token stream, so changes to them are ignored.
+//!
+//! Line-level diffs, for changes that are only whitespace and comments, are
+//! made the same way.
+
+use std::fmt::Display;
+
+use crate::scanner::token::Token;
+
+/// A token removed from the old version or added in the new one.
+#[derive(Debug, Clone)]
+pub enum Edit {
+    Removed(Token),
+    Added(Token),
+}
+
+impl Edit {
+    /// Returns the token that was removed or added.
+    pub fn token(&self) -> &Token {
+        match self {
+            Edit::Removed(token) | Edit::Added(token) => token,
+        }
+    }
+}
+
+impl Display for Edit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = match self {
+            Edit::Removed(_) => '-',
+            Edit::Added(_) => '+',
+        };
+        let token = self.token();
+        write!(f, "{sign} {}:{} {token}", token.line, token.column)
+    }
+}
+
+/// Lists the edits that turn `old` into `new`, in source order with removals
+/// before additions at the same place.
+///
+/// Tokens are equal when their types and lexemes are, wherever they are in
+/// the file, so reindenting or moving code across lines is not a change.
+pub fn diff_tokens(old: &[Token], new: &[Token]) -> Vec<Edit> {
+    let same = |a: &Token, b: &Token| a.typ == b.typ && a.lexeme == b.lexeme;
+    let steps = diff_by(old, new, same).into_iter();
+    steps
+        .map(|step| match step {
+            Step::Removed(i) => Edit::Removed(old[i].clone()),
+            Step::Added(j) => Edit::Added(new[j].clone()),
+        })
+        .collect()
+}
+
+/// A line removed from the old version or added in the new one, with its
+/// 1-based number in that version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineEdit<'a> {
+    Removed(usize, &'a str),
+    Added(usize, &'a str),
+}
+
+impl Display for LineEdit<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineEdit::Removed(line, text) => write!(f, "- {line}: {text}"),
+            LineEdit::Added(line, text) => write!(f, "+ {line}: {text}"),
+        }
+    }
+}
+
+/// Lists the lines removed from `old` and added in `new`, in the same order
+/// as [`diff_tokens`].
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<LineEdit<'a>> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+    let steps = diff_by(&old, &new, |a, b| a == b).into_iter();
+    steps
+        .map(|step| match step {
+            Step::Removed(i) => LineEdit::Removed(i + 1, old[i]),
+            Step::Added(j) => LineEdit::Added(j + 1, new[j]),
+        })
+        .collect()
+}
+
+/// An item removed from the old version or added in the new one, by its
+/// index in that version.
+enum Step {
+    Removed(usize),
+    Added(usize),
+}
+
+/// Lists the steps that turn `old` into `new` along a longest common
+/// subsequence of items that are the `same`.
+fn diff_by<T>(old: &[T], new: &[T], same: impl Fn(&T, &T) -> bool) -> Vec<Step> {
+    // trim the common ends so that the quadratic table only covers the edit
+    let prefix = old.iter().zip(new).take_while(|(a, b)| same(a, b)).count();
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let suffix = old_rest
+        .iter()
+        .rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let old = &old_rest[..old_rest.len() - suffix];
+    let new = &new_rest[..new_rest.len() - suffix];
+
+    // common[i][j] is the length of the longest common subsequence of
+    // `old[i..]` and `new[j..]`
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if same(&old[i], &new[j]) {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut steps = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && same(&old[i], &new[j]) {
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            steps.push(Step::Removed(prefix + i));
+            i += 1;
+        } else {
+            steps.push(Step::Added(prefix + j));
+            j += 1;
+        }
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::scanner::{ScanItem, Scanner};
+
+    fn tokens(source: &str) -> Vec<Token> {
+        Scanner::new(source)
+            .scan_tokens()
+            .filter_map(|item| match item.unwrap() {
+                ScanItem::Token(token) => Some(token),
+                ScanItem::Ignore => None,
+            })
+            .collect()
+    }
+
+    fn diff(old: &str, new: &str) -> Vec<String> {
+        diff_tokens(&tokens(old), &tokens(new))
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[rstest]
+    #[case("print 1;", "print 1;")]
+    #[case("print 1;", "print   1 ; // one")]
+    #[case("var a = 1;\nprint a;", "var a =\n  1; print a;")]
+    fn test_diff_ignores_layout(#[case] old: &str, #[case] new: &str) {
+        assert!(diff(old, new).is_empty());
+    }
+
+    #[rstest]
+    #[case("print 1;", "print 2;", vec!["- 1:7 NUMBER 1 1.0", "+ 1:7 NUMBER 2 2.0"])]
+    #[case("print a;", "print a + b;", vec!["+ 1:9 PLUS + null", "+ 1:11 IDENTIFIER b null"])]
+    #[case("print a;\nprint b;", "print b;", vec!["- 1:7 IDENTIFIER a null", "- 1:8 SEMICOLON ; null", "- 2:1 PRINT print null"])]
+    fn test_diff_changes(#[case] old: &str, #[case] new: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(expected, diff(old, new));
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let edits = diff_lines("a\nb\nc\nd", "a\nc\nx\nd");
+        let edits = edits.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(vec!["- 2: b", "+ 3: x"], edits);
+    }
+}
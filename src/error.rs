@@ -1,14 +1,26 @@
 use std::fmt::Display;
+use std::iter;
 
+use crate::diagnostic::{self, Diagnostic, DiagnosticRenderer, Plain};
 use crate::interpreter::error::RuntimeError;
+use crate::interpreter::limits::Limit;
 use crate::scanner::token::{Token, TokenType};
+use crate::source::Span;
 
 #[derive(Debug, thiserror::Error)]
 /// Represents a scan/parse-time error with source line and optional token location.
 pub struct StaticError {
     line: u32,
+    /// The 1-based column of the token the error is reported at, if known
+    column: Option<u32>,
     location: Option<String>,
+    /// The bytes of the token the error is reported at, if known
+    span: Option<Span>,
     message: String,
+    /// The limit on the size of the source that was exceeded, if any
+    limit: Option<Limit>,
+    /// The errors found along with this one, reported after it
+    others: Vec<StaticError>,
 }
 
 impl StaticError {
@@ -16,38 +28,158 @@ impl StaticError {
     pub fn error_at_line(line: u32, message: String) -> Self {
         Self {
             line,
+            column: None,
             location: None,
+            span: None,
             message,
+            limit: None,
+            others: Vec::new(),
         }
     }
 
     /// Creates a static error at a specific token location.
     pub fn error_at_token(token: &Token, message: String) -> Self {
-        let location = if token.typ == TokenType::Eof {
-            " at end".into()
-        } else {
-            format!(" at '{}'", token.lexeme)
-        };
-
         Self {
             line: token.line,
-            location: Some(location),
+            column: (token.column > 0).then_some(token.column),
+            location: Some(location(token)),
+            span: token.span,
             message,
+            limit: None,
+            others: Vec::new(),
+        }
+    }
+
+    /// Creates the error that stops scanning a source once it exceeds
+    /// `limit`, at the line reached.
+    pub fn limit_exceeded(line: u32, limit: Limit) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Self::error_at_line(line, format!("Input too large: {limit}."))
+        }
+    }
+
+    /// Combines `errors` into the first of them, which reports the others
+    /// after itself, or returns `None` if there are none.
+    pub fn combine(errors: impl IntoIterator<Item = StaticError>) -> Option<Self> {
+        let mut errors = errors.into_iter();
+        let first = errors.next()?;
+        Some(Self {
+            others: errors.collect(),
+            ..first
+        })
+    }
+
+    /// Returns this error and the others found along with it, see
+    /// [`Self::combine`].
+    pub fn errors(&self) -> impl Iterator<Item = &StaticError> {
+        iter::once(self).chain(&self.others)
+    }
+
+    /// Returns the limit this error reports exceeding, if any.
+    pub fn exceeded_limit(&self) -> Option<Limit> {
+        self.limit
+    }
+
+    /// Returns the source line the error was reported on.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Returns the column of the token the error is reported at, if known.
+    pub fn column(&self) -> Option<u32> {
+        self.column
+    }
+
+    /// Returns the bytes of the token the error is reported at, if known.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// Returns what the error is reported at, such as `at ';'` or `at end`.
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    /// Returns the error message without its line and location.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Moves the error `lines` lines and `bytes` bytes down the source, or up
+    /// if negative.
+    pub(crate) fn shift(&mut self, lines: i32, bytes: isize) {
+        self.line = self.line.saturating_add_signed(lines);
+        self.span = self.span.map(|span| span.shift(bytes));
+        for other in &mut self.others {
+            other.shift(lines, bytes);
         }
     }
 }
 
+/// Describes where `token` is, as `at end` or `at '<lexeme>'`.
+pub(crate) fn location(token: &Token) -> String {
+    if token.typ == TokenType::Eof {
+        "at end".into()
+    } else {
+        format!("at '{}'", token.lexeme)
+    }
+}
+
 impl Display for StaticError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let location = self.location.as_deref().unwrap_or_default();
-        write!(
-            f,
-            "[line {}] Error{}: {}",
-            self.line, location, self.message
-        )
+        let rendered = self.errors().map(|err| Plain.render(&err.into()));
+        f.write_str(&rendered.collect::<Vec<_>>().join("\n"))
     }
 }
 
+/// A category of diagnostic and the process exit code the CLI reports it with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct DiagnosticCode {
+    pub code: &'static str,
+    pub exit_code: i32,
+    pub description: &'static str,
+}
+
+pub const STATIC_ERROR: DiagnosticCode = DiagnosticCode {
+    code: "static",
+    exit_code: 65,
+    description: "the source could not be scanned or parsed",
+};
+
+pub const RUNTIME_ERROR: DiagnosticCode = DiagnosticCode {
+    code: "runtime",
+    exit_code: 70,
+    description: "an error was raised while executing the program",
+};
+
+pub const IO_ERROR: DiagnosticCode = DiagnosticCode {
+    code: "io",
+    exit_code: 1,
+    description: "an input file could not be read",
+};
+
+pub const LIMIT_EXCEEDED: DiagnosticCode = DiagnosticCode {
+    code: "limit",
+    exit_code: 75,
+    description: "the program or its source exceeded a limit set with --limits",
+};
+
+pub const MISMATCH: DiagnosticCode = DiagnosticCode {
+    code: "mismatch",
+    exit_code: 3,
+    description: "the output differed from the reference implementation, or a round trip from the source",
+};
+
+/// Every diagnostic category the CLI can report.
+pub const DIAGNOSTIC_CODES: [DiagnosticCode; 5] = [
+    STATIC_ERROR,
+    RUNTIME_ERROR,
+    LIMIT_EXCEEDED,
+    IO_ERROR,
+    MISMATCH,
+];
+
 /// Application error report used across parsing and runtime stages.
 ///
 /// `Report` wraps:
@@ -63,21 +195,100 @@ pub enum Report {
 }
 
 impl Report {
-    /// Prints the error to stderr and terminates the process with a stage-specific code.
-    ///
-    /// Exit codes:
-    /// - `70` for runtime errors
-    /// - `65` for static (scan/parse) errors
-    pub fn exit(&self) -> ! {
+    /// Returns the diagnostic category of this report.
+    pub fn code(&self) -> DiagnosticCode {
         match self {
-            Report::Runtime(err) => {
-                eprintln!("{err}");
-                std::process::exit(70);
-            }
-            Report::Static(err) => {
-                eprintln!("{err}");
-                std::process::exit(65);
+            Report::Runtime(err) if err.exceeded_limit().is_some() => LIMIT_EXCEEDED,
+            Report::Runtime(_) => RUNTIME_ERROR,
+            Report::Static(err) if err.errors().any(|err| err.exceeded_limit().is_some()) => {
+                LIMIT_EXCEEDED
             }
+            Report::Static(_) => STATIC_ERROR,
+        }
+    }
+
+    /// Returns the diagnostics this report is made of: the error itself and
+    /// any errors that caused it, in the order [`diagnostic::sort`] puts
+    /// them.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = match self {
+            Report::Runtime(err) => err.diagnostics(),
+            Report::Static(err) => err.errors().map(Diagnostic::from).collect(),
+        };
+        diagnostic::sort(&mut diagnostics);
+        diagnostics
+    }
+}
+
+/// A failed CLI subcommand: what is left to write to stderr, and the code
+/// the process exits with.
+///
+/// The message is empty when the subcommand has already written everything
+/// worth saying, such as the lines that differ or the denied lint warnings.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message}")]
+pub struct LoxError {
+    pub exit_code: i32,
+    pub message: String,
+}
+
+impl LoxError {
+    /// Creates an error in the category `code` described by `message`.
+    pub fn new(code: DiagnosticCode, message: impl Into<String>) -> Self {
+        Self {
+            exit_code: code.exit_code,
+            message: message.into(),
         }
     }
+
+    /// Creates an error in the category `code` that has already been
+    /// written out.
+    pub fn reported(code: DiagnosticCode) -> Self {
+        Self::new(code, "")
+    }
+
+    /// Creates an error for a command line naming a subcommand or flags that
+    /// do not work together, which exits with code `2` like clap does.
+    pub fn usage(message: impl Into<String>) -> Self {
+        Self {
+            exit_code: 2,
+            message: message.into(),
+        }
+    }
+
+    /// Renders each diagnostic of `report` with `renderer`, exiting with the
+    /// code of its stage: `65` for static (scan/parse) errors, `70` for
+    /// runtime errors and `75` when a limit set with `--limits` is exceeded.
+    pub fn report(report: impl Into<Report>, renderer: &dyn DiagnosticRenderer) -> Self {
+        let report = report.into();
+        let rendered = report
+            .diagnostics()
+            .into_iter()
+            .map(|d| renderer.render(&d));
+        Self::new(report.code(), rendered.collect::<Vec<_>>().join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::limits::{Limit, Resource};
+
+    #[test]
+    fn test_lox_error_exit_codes() {
+        let err = LoxError::report(StaticError::error_at_line(1, "Bad.".into()), &Plain);
+        assert_eq!(65, err.exit_code);
+        assert_eq!("[line 1] Error: Bad.", err.message);
+
+        let limit = Limit {
+            resource: Resource::Calls,
+            max: 1,
+        };
+        let err = LoxError::report(RuntimeError::limit_exceeded(limit), &Plain);
+        assert_eq!(LIMIT_EXCEEDED.exit_code, err.exit_code);
+
+        assert_eq!(3, LoxError::reported(MISMATCH).exit_code);
+        assert_eq!("", LoxError::reported(MISMATCH).message);
+        assert_eq!(2, LoxError::usage("No.").exit_code);
+    }
 }
@@ -0,0 +1,68 @@
+use std::any::Any;
+use std::fmt::{Debug, Display};
+use std::rc::Rc;
+
+/// An opaque Rust value, such as a database handle, that an embedder passes
+/// into a script and gets back in its native functions.
+///
+/// Scripts can store and pass the value around but not look inside it.
+/// Copies share the value and compare equal by identity.
+#[derive(Clone)]
+pub struct Foreign {
+    type_name: &'static str,
+    value: Rc<dyn Any>,
+}
+
+impl Foreign {
+    /// Wraps `value`, which `type(v)` reports as `type_name`.
+    pub fn new<T: Any>(type_name: &'static str, value: T) -> Self {
+        Self {
+            type_name,
+            value: Rc::new(value),
+        }
+    }
+
+    /// Returns the type name the value was wrapped with.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns the wrapped value if it is a `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+}
+
+impl PartialEq for Foreign {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.value, &other.value)
+    }
+}
+
+impl Debug for Foreign {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Display for Foreign {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{}>", self.type_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_foreign_downcast_and_identity() {
+        let handle = Foreign::new("Connection", 42_u32);
+        assert_eq!(Some(&42), handle.downcast_ref::<u32>());
+        assert_eq!(None, handle.downcast_ref::<i64>());
+
+        assert_eq!(handle, handle.clone());
+        assert_ne!(handle, Foreign::new("Connection", 42_u32));
+        assert_eq!("<Connection>", handle.to_string());
+    }
+}
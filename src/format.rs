@@ -0,0 +1,582 @@
+//! Writing a program back out as canonical source: one statement per line,
+//! indented by two spaces per block, with single spaces around binary
+//! operators.
+//!
+//! Comments are kept. One on a line of its own stays before the statement it
+//! preceded, and one after code stays at the end of the line of the statement
+//! ending there. A comment inside a statement spanning several lines moves to
+//! the line after it. Runs of blank lines between statements become one.
+//!
+//! Literals are written as they were in the source. One the optimizer made
+//! up is written from its value, with numbers in decimal, and a string that
+//! only a text block can hold is an error.
+
+use std::iter::Peekable;
+use std::vec;
+
+use crate::Value;
+use crate::dialect::{Dialect, Extension};
+use crate::error::StaticError;
+use crate::parser::ast::{Ast, AstArena, ExprId, Lines, StmtId};
+use crate::parser::expr::{
+    self, Assign, AssignUnpack, Binary, Cached, Call, Coalesce, Expr, ExprNode, Grouping, Index,
+    List, Literal, Map, SetIndex, Spread, Tuple, Unary, Variable,
+};
+use crate::parser::stmt::{
+    self, Assert, Block, DoWhile, Expression, ForIn, Import, Match, Print, Stmt, StmtNode, Throw,
+    Try, Var, VarUnpack,
+};
+use crate::scanner::Scanner;
+use crate::scanner::token::{Token, TokenType};
+use crate::scanner::trivia::Piece;
+use crate::source::{SourceFile, SourceMap};
+
+/// Formats `program`, parsed from `source` in `dialect`, keeping the comments
+/// of `source`.
+///
+/// Returns an error at the statement holding a string literal that cannot be
+/// written between plain quotes.
+pub fn format(source: &str, program: &Ast, dialect: Dialect) -> Result<String, StaticError> {
+    let sources = SourceMap::new();
+    let id = sources.add("", source);
+    let file = &*sources.get(id).expect("the file was just added");
+
+    let mut formatter = Formatter {
+        file,
+        dialect,
+        comments: comments(file, dialect).into_iter().peekable(),
+        out: String::new(),
+        indent: 0,
+        lines: None,
+        line: 1,
+        error: None,
+    };
+    formatter.statements(&program.arena, &program.statements, u32::MAX);
+    match formatter.error {
+        Some(err) => Err(err),
+        None => Ok(formatter.out),
+    }
+}
+
+/// A `//` comment, without the line break ending it.
+#[derive(Debug)]
+struct Comment {
+    line: u32,
+    text: String,
+    /// Whether only whitespace comes before the comment on its line
+    own_line: bool,
+}
+
+/// Returns the comments of `file`, in order.
+fn comments(file: &SourceFile, dialect: Dialect) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let mut offset = 0;
+    let pieces = Scanner::new(file.text())
+        .with_dialect(dialect)
+        .scan_pieces();
+    for piece in pieces.flatten() {
+        if let Piece::Trivia(trivia) = &piece {
+            let mut rest = trivia.as_str();
+            let mut at = offset;
+            // trivia is whitespace and comments, so `//` always starts one
+            while let Some(start) = rest.find("//") {
+                let len = rest[start..].find('\n').unwrap_or(rest.len() - start);
+                let (line, column) = file.location(at + start);
+                let before = file.line(line).unwrap_or_default().chars();
+                comments.push(Comment {
+                    line,
+                    text: rest[start..start + len].trim_end().to_string(),
+                    own_line: before.take(column as usize - 1).all(char::is_whitespace),
+                });
+                rest = &rest[start + len..];
+                at += start + len;
+            }
+        }
+        offset += piece.text().len();
+    }
+    comments
+}
+
+struct Formatter<'a> {
+    /// The formatted source, which interpolated strings are recognized in
+    file: &'a SourceFile,
+    dialect: Dialect,
+    /// The comments not written yet
+    comments: Peekable<vec::IntoIter<Comment>>,
+    out: String,
+    /// The number of blocks around the statement being written
+    indent: usize,
+    /// The lines of the statement being visited, if known
+    lines: Option<Lines>,
+    /// The first line of the innermost statement with known lines, which
+    /// errors are reported at
+    line: u32,
+    /// The first literal that could not be written
+    error: Option<StaticError>,
+}
+
+impl Formatter<'_> {
+    /// Writes `statements` one per line, each followed by the comment at the
+    /// end of its last line, then the comments before line `end`.
+    fn statements(&mut self, ast: &AstArena, statements: &[StmtId], end: u32) {
+        // the last line of the source written so far in this block
+        let mut previous = None;
+        for &stmt in statements {
+            let lines = ast.lines(stmt);
+            if let Some(lines) = lines {
+                self.comments_before(lines.first, &mut previous);
+                self.separate(previous, lines.first);
+            }
+
+            self.write_indent();
+            self.statement(ast, stmt);
+            let Some(lines) = lines else {
+                self.out.push('\n');
+                continue;
+            };
+
+            let mut inside = Vec::new();
+            while let Some(comment) = self.comments.next_if(|c| c.line < lines.last) {
+                inside.push(comment);
+            }
+            let trailing = |c: &Comment| c.line == lines.last && !c.own_line;
+            if let Some(comment) = self.comments.next_if(trailing) {
+                self.out.push(' ');
+                self.out.push_str(&comment.text);
+            }
+            self.out.push('\n');
+            for comment in inside {
+                self.write_indent();
+                self.out.push_str(&comment.text);
+                self.out.push('\n');
+            }
+            previous = Some(lines.last);
+        }
+        self.comments_before(end, &mut previous);
+    }
+
+    /// Writes each comment before line `end` on a line of its own.
+    fn comments_before(&mut self, end: u32, previous: &mut Option<u32>) {
+        while let Some(comment) = self.comments.next_if(|c| c.line < end) {
+            self.separate(*previous, comment.line);
+            self.write_indent();
+            self.out.push_str(&comment.text);
+            self.out.push('\n');
+            *previous = Some(comment.line);
+        }
+    }
+
+    /// Writes a blank line if the source had one between line `previous` and
+    /// line `next`.
+    fn separate(&mut self, previous: Option<u32>, next: u32) {
+        if previous.is_some_and(|previous| next > previous + 1) {
+            self.out.push('\n');
+        }
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str("  ");
+        }
+    }
+
+    /// Writes `stmt` from the current position, without a line break after.
+    fn statement(&mut self, ast: &AstArena, stmt: StmtId) {
+        self.lines = ast.lines(stmt);
+        if let Some(lines) = self.lines {
+            self.line = lines.first;
+        }
+        stmt.accept(ast, self);
+    }
+
+    /// Writes the body of a loop: a block after a space, or another
+    /// statement indented on the next line.
+    fn body(&mut self, ast: &AstArena, body: StmtId) {
+        if let StmtNode::Block(_) = ast[body] {
+            self.out.push(' ');
+            self.statement(ast, body);
+        } else {
+            self.indent += 1;
+            self.out.push('\n');
+            self.write_indent();
+            self.statement(ast, body);
+            self.indent -= 1;
+        }
+    }
+
+    /// Formats `exprs` separated by commas.
+    fn list(&mut self, ast: &AstArena, exprs: &[ExprId]) -> String {
+        let exprs = exprs.iter().map(|expr| expr.accept(ast, self));
+        exprs.collect::<Vec<_>>().join(", ")
+    }
+
+    /// Formats a literal `value` that was not written in the source the way
+    /// the scanner reads it back.
+    fn literal(&mut self, value: &Value) -> String {
+        match value {
+            Value::String(s) => {
+                let interpolates = self.dialect.allows(Extension::Interpolation);
+                if s.contains('"') || (interpolates && s.contains("${")) {
+                    self.fail("Cannot format a string that needs a text block.");
+                }
+                format!("\"{s}\"")
+            }
+            // keep numbers with a whole value from reading back as integers
+            Value::Number(n) if self.dialect.allows(Extension::Integers) => {
+                let number = n.to_string();
+                match number.contains('.') {
+                    true => number,
+                    false => number + ".0",
+                }
+            }
+            other => other.to_string(),
+        }
+    }
+
+    fn fail(&mut self, message: &str) {
+        if self.error.is_none() {
+            self.error = Some(StaticError::error_at_line(self.line, message.into()));
+        }
+    }
+
+    /// Checks whether `operator` is a `+` the parser added to join the pieces
+    /// of an interpolated string, rather than one written in the source.
+    fn is_interpolation(&self, operator: &Token) -> bool {
+        if operator.typ != TokenType::Plus || operator.column == 0 {
+            return false;
+        }
+        let line = self.file.line(operator.line).unwrap_or_default();
+        line.chars().nth(operator.column as usize - 1) != Some('+')
+    }
+
+    /// Formats an interpolated string, whose pieces the parser joined with
+    /// `+` from left to right, starting with `expr`.
+    fn interpolation(&mut self, ast: &AstArena, expr: &Binary) -> String {
+        let mut parts = vec![expr.right];
+        let mut left = expr.left;
+        while let ExprNode::Binary(binary) = &ast[left]
+            && self.is_interpolation(&binary.operator)
+        {
+            parts.push(binary.right);
+            left = binary.left;
+        }
+        parts.push(left);
+
+        // the pieces are string literals, between the interpolated expressions
+        // which the parser wrapped in a call to `str`
+        let mut string = String::from("\"");
+        for (i, &part) in parts.iter().rev().enumerate() {
+            match &ast[part] {
+                ExprNode::Literal(Literal {
+                    value: Value::String(s),
+                    ..
+                }) if i % 2 == 0 => string.push_str(s),
+                ExprNode::Call(call) => {
+                    let inner = call.arguments[0].accept(ast, self);
+                    string.push_str(&format!("${{{inner}}}"));
+                }
+                _ => unreachable!("interpolated expressions are wrapped in a call"),
+            }
+        }
+        string.push('"');
+        string
+    }
+}
+
+impl stmt::Visitor for Formatter<'_> {
+    type Output = ();
+
+    fn visit_print_stmt(&mut self, ast: &AstArena, stmt: &Print) {
+        let expr = stmt.expr.accept(ast, self);
+        self.out.push_str(&format!("print {expr};"));
+    }
+
+    fn visit_expression_stmt(&mut self, ast: &AstArena, stmt: &Expression) {
+        let expr = stmt.expr.accept(ast, self);
+        self.out.push_str(&format!("{expr};"));
+    }
+
+    fn visit_var_stmt(&mut self, ast: &AstArena, stmt: &Var) {
+        let keyword = if stmt.constant { "const" } else { "var" };
+        let name = &stmt.name.lexeme;
+        let declaration = match stmt.initializer {
+            Some(init) => format!("{keyword} {name} = {};", init.accept(ast, self)),
+            None => format!("{keyword} {name};"),
+        };
+        self.out.push_str(&declaration);
+    }
+
+    fn visit_var_unpack_stmt(&mut self, ast: &AstArena, stmt: &VarUnpack) {
+        let names = stmt.names.iter().map(|name| name.lexeme.as_str());
+        let names = names.collect::<Vec<_>>().join(", ");
+        let value = stmt.value.accept(ast, self);
+        self.out.push_str(&format!("var ({names}) = {value};"));
+    }
+
+    fn visit_block_stmt(&mut self, ast: &AstArena, stmt: &Block) {
+        let end = self.lines.take().map_or(0, |lines| lines.last);
+        let has_comments = self.comments.peek().is_some_and(|c| c.line < end);
+        if stmt.statements.is_empty() && !has_comments {
+            self.out.push_str("{}");
+            return;
+        }
+
+        self.out.push_str("{\n");
+        self.indent += 1;
+        self.statements(ast, &stmt.statements, end);
+        self.indent -= 1;
+        self.write_indent();
+        self.out.push('}');
+    }
+
+    fn visit_for_in_stmt(&mut self, ast: &AstArena, stmt: &ForIn) {
+        let iterable = stmt.iterable.accept(ast, self);
+        let name = &stmt.name.lexeme;
+        self.out.push_str(&format!("for ({name} in {iterable})"));
+        self.body(ast, stmt.body);
+    }
+
+    fn visit_do_while_stmt(&mut self, ast: &AstArena, stmt: &DoWhile) {
+        self.out.push_str("do");
+        self.body(ast, stmt.body);
+        let condition = stmt.condition.accept(ast, self);
+        if let StmtNode::Block(_) = ast[stmt.body] {
+            self.out.push(' ');
+        } else {
+            self.out.push('\n');
+            self.write_indent();
+        }
+        self.out.push_str(&format!("while ({condition});"));
+    }
+
+    fn visit_try_stmt(&mut self, ast: &AstArena, stmt: &Try) {
+        self.out.push_str("try ");
+        self.statement(ast, stmt.body);
+        if let Some((name, body)) = &stmt.catch {
+            self.out.push_str(&format!(" catch ({}) ", name.lexeme));
+            self.statement(ast, *body);
+        }
+        if let Some(body) = stmt.finally {
+            self.out.push_str(" finally ");
+            self.statement(ast, body);
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, ast: &AstArena, stmt: &Throw) {
+        let value = stmt.value.accept(ast, self);
+        self.out.push_str(&format!("throw {value};"));
+    }
+
+    fn visit_assert_stmt(&mut self, ast: &AstArena, stmt: &Assert) {
+        let condition = stmt.condition.accept(ast, self);
+        let assertion = match stmt.message {
+            Some(message) => format!("assert {condition}, {};", message.accept(ast, self)),
+            None => format!("assert {condition};"),
+        };
+        self.out.push_str(&assertion);
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &Import) {
+        let path = &stmt.path.lexeme;
+        if stmt.names.is_empty() {
+            self.out.push_str(&format!("import {path};"));
+            return;
+        }
+        let names = stmt.names.iter().map(|name| name.lexeme.as_str());
+        let names = names.collect::<Vec<_>>().join(", ");
+        self.out.push_str(&format!("import {names} from {path};"));
+    }
+
+    fn visit_match_stmt(&mut self, ast: &AstArena, stmt: &Match) {
+        let value = stmt.value.accept(ast, self);
+        self.out.push_str(&format!("match ({value}) {{\n"));
+        self.indent += 1;
+        for (patterns, body) in &stmt.arms {
+            let patterns = patterns.iter().map(|pattern| self.literal(pattern));
+            let patterns = patterns.collect::<Vec<_>>().join(", ");
+            self.write_indent();
+            self.out.push_str(&format!("{patterns} => "));
+            self.statement(ast, *body);
+            self.out.push('\n');
+        }
+        if let Some(body) = stmt.otherwise {
+            self.write_indent();
+            self.out.push_str("else => ");
+            self.statement(ast, body);
+            self.out.push('\n');
+        }
+        self.indent -= 1;
+        self.write_indent();
+        self.out.push('}');
+    }
+}
+
+impl expr::Visitor for Formatter<'_> {
+    type Output = String;
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> String {
+        match &expr.token {
+            Some(token) => token.lexeme.to_string(),
+            None => self.literal(&expr.value),
+        }
+    }
+
+    fn visit_grouping_expr(&mut self, ast: &AstArena, expr: &Grouping) -> String {
+        format!("({})", expr.expression.accept(ast, self))
+    }
+
+    fn visit_unary_expr(&mut self, ast: &AstArena, expr: &Unary) -> String {
+        format!("{}{}", expr.operator.lexeme, expr.right.accept(ast, self))
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> String {
+        expr.name.lexeme.to_string()
+    }
+
+    fn visit_assign_expr(&mut self, ast: &AstArena, expr: &Assign) -> String {
+        format!("{} = {}", expr.name.lexeme, expr.value.accept(ast, self))
+    }
+
+    fn visit_assign_unpack_expr(&mut self, ast: &AstArena, expr: &AssignUnpack) -> String {
+        let names = expr.names.iter().map(|name| name.lexeme.as_str());
+        let names = names.collect::<Vec<_>>().join(", ");
+        format!("({names}) = {}", expr.value.accept(ast, self))
+    }
+
+    fn visit_binary_expr(&mut self, ast: &AstArena, expr: &Binary) -> String {
+        if self.is_interpolation(&expr.operator) {
+            return self.interpolation(ast, expr);
+        }
+        let left = expr.left.accept(ast, self);
+        let right = expr.right.accept(ast, self);
+        let operator = &expr.operator.lexeme;
+        match expr.operator.typ {
+            TokenType::DotDot | TokenType::DotDotEqual => format!("{left}{operator}{right}"),
+            _ => format!("{left} {operator} {right}"),
+        }
+    }
+
+    fn visit_coalesce_expr(&mut self, ast: &AstArena, expr: &Coalesce) -> String {
+        let left = expr.left.accept(ast, self);
+        let right = expr.right.accept(ast, self);
+        format!("{left} {} {right}", expr.operator.lexeme)
+    }
+
+    fn visit_cached_expr(&mut self, ast: &AstArena, expr: &Cached) -> String {
+        expr.expr.accept(ast, self)
+    }
+
+    fn visit_call_expr(&mut self, ast: &AstArena, expr: &Call) -> String {
+        let callee = expr.callee.accept(ast, self);
+        format!("{callee}({})", self.list(ast, &expr.arguments))
+    }
+
+    fn visit_list_expr(&mut self, ast: &AstArena, expr: &List) -> String {
+        format!("[{}]", self.list(ast, &expr.elements))
+    }
+
+    fn visit_tuple_expr(&mut self, ast: &AstArena, expr: &Tuple) -> String {
+        match expr.elements[..] {
+            [element] => format!("({},)", element.accept(ast, self)),
+            _ => format!("({})", self.list(ast, &expr.elements)),
+        }
+    }
+
+    fn visit_spread_expr(&mut self, ast: &AstArena, expr: &Spread) -> String {
+        format!("...{}", expr.expression.accept(ast, self))
+    }
+
+    fn visit_map_expr(&mut self, ast: &AstArena, expr: &Map) -> String {
+        let entries = expr.entries.iter().map(|(key, value)| {
+            let key = key.accept(ast, self);
+            format!("{key}: {}", value.accept(ast, self))
+        });
+        format!("{{{}}}", entries.collect::<Vec<_>>().join(", "))
+    }
+
+    fn visit_index_expr(&mut self, ast: &AstArena, expr: &Index) -> String {
+        let object = expr.object.accept(ast, self);
+        format!("{object}[{}]", expr.index.accept(ast, self))
+    }
+
+    fn visit_set_index_expr(&mut self, ast: &AstArena, expr: &SetIndex) -> String {
+        let object = expr.object.accept(ast, self);
+        let index = expr.index.accept(ast, self);
+        format!("{object}[{index}] = {}", expr.value.accept(ast, self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum as _;
+    use rstest::rstest;
+
+    use super::*;
+    use crate::parser::Parser;
+
+    fn dialect() -> Dialect {
+        Extension::value_variants().iter().copied().collect()
+    }
+
+    fn format_source(source: &str) -> Result<String, StaticError> {
+        let scanner = Scanner::new(source).with_dialect(dialect());
+        let mut parser = Parser::new(scanner.scan_tokens()).with_dialect(dialect());
+        format(source, &parser.parse().unwrap(), dialect())
+    }
+
+    #[rstest]
+    #[case("print   1+2 ;", "print 1 + 2;\n")]
+    #[case(
+        "var a=[1,2];{var b=a[0];\n\n\n  print -b;}",
+        "var a = [1, 2];\n{\n  var b = a[0];\n\n  print -b;\n}\n"
+    )]
+    #[case(
+        "for(x in 1..=3)print x;do{}while(false);",
+        "for (x in 1..=3)\n  print x;\ndo {} while (false);\n"
+    )]
+    #[case(
+        "try{throw 1;}catch(e){print e;}finally{print \"done\";}",
+        "try {\n  throw 1;\n} catch (e) {\n  print e;\n} finally {\n  print \"done\";\n}\n"
+    )]
+    #[case(
+        "match(x){1,-2=>print \"a\";else=>{print nil;}}",
+        "match (x) {\n  1, -2 => print \"a\";\n  else => {\n    print nil;\n  }\n}\n"
+    )]
+    #[case(
+        "import a,b from \"lib.lox\";assert a==b,\"same\";var (c,d)=(1,2);",
+        "import a, b from \"lib.lox\";\nassert a == b, \"same\";\nvar (c, d) = (1, 2);\n"
+    )]
+    #[case(
+        "print \"a${x+1}b${ {\"k\":2}[\"k\"] }\";",
+        "print \"a${x + 1}b${{\"k\": 2}[\"k\"]}\";\n"
+    )]
+    #[case("print 1.0 + 2 + \"a\" + (3);", "print 1.0 + 2 + \"a\" + (3);\n")]
+    fn test_format(#[case] source: &str, #[case] expected: &str) {
+        let formatted = format_source(source).unwrap();
+        assert_eq!(expected, formatted);
+        assert_eq!(formatted, format_source(&formatted).unwrap());
+    }
+
+    #[test]
+    fn test_format_keeps_comments() {
+        let source = "// header\n\nvar a = 1; // one\n{\n  // inside\n  print [a, // first\n    2];\n  // last\n} // after\n// end\n";
+        let expected = "// header\n\nvar a = 1; // one\n{\n  // inside\n  print [a, 2];\n  // first\n  // last\n} // after\n// end\n";
+        let formatted = format_source(source).unwrap();
+        assert_eq!(expected, formatted);
+        assert_eq!(formatted, format_source(&formatted).unwrap());
+    }
+
+    #[rstest]
+    #[case("print 0xFF+1_000;", "print 0xFF + 1_000;\n")]
+    #[case("print 0b101*1e3 ;", "print 0b101 * 1e3;\n")]
+    #[case("print 1.50+2;", "print 1.50 + 2;\n")]
+    #[case(
+        "print 1;\nprint \"\"\"\n  say \"hi\" to ${name}\n  \"\"\";",
+        "print 1;\nprint \"\"\"\n  say \"hi\" to ${name}\n  \"\"\";\n"
+    )]
+    fn test_format_keeps_literals(#[case] source: &str, #[case] expected: &str) {
+        let formatted = format_source(source).unwrap();
+        assert_eq!(expected, formatted);
+        assert_eq!(formatted, format_source(&formatted).unwrap());
+    }
+}
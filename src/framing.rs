@@ -0,0 +1,70 @@
+//! JSON messages framed by a `Content-Length` header, as the Debug Adapter
+//! and Language Server protocols exchange them over stdin and stdout.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value as Json;
+
+/// Reads the next message, or returns `None` at the end of the input.
+/// Headers other than `Content-Length` are skipped.
+///
+/// Fails if the input cannot be read or the message is not JSON.
+pub(crate) fn read(input: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        match header.trim_end() {
+            "" if length.is_some() => break,
+            "" => {}
+            header => {
+                if let Some(value) = header.strip_prefix("Content-Length:") {
+                    let value = value.trim().parse::<usize>();
+                    length = Some(value.map_err(io::Error::other)?);
+                }
+            }
+        }
+    }
+    let mut body = vec![0; length.expect("read before the blank line")];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(io::Error::other)
+}
+
+/// Writes `message` and flushes it, so that the other side sees it at once.
+pub(crate) fn write(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_framing() {
+        let mut output = Vec::new();
+        write(&mut output, &json!({ "id": 1 })).unwrap();
+        write(&mut output, &json!("é")).unwrap();
+        assert_eq!(
+            "Content-Length: 8\r\n\r\n{\"id\":1}Content-Length: 4\r\n\r\n\"é\"",
+            String::from_utf8(output.clone()).unwrap()
+        );
+
+        let mut input = Cursor::new(output);
+        assert_eq!(Some(json!({ "id": 1 })), read(&mut input).unwrap());
+        assert_eq!(Some(json!("é")), read(&mut input).unwrap());
+        assert_eq!(None, read(&mut input).unwrap());
+
+        let mut input = Cursor::new("Content-Type: json\r\n\r\nContent-Length: x\r\n\r\n");
+        assert!(read(&mut input).is_err());
+    }
+}
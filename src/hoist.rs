@@ -0,0 +1,236 @@
+//! An opt-in pass that caches the values of constant expressions inside
+//! loops, so that `for (i in xs) print i * (60 * 60);` multiplies the
+//! constant once rather than on every iteration.
+//!
+//! An expression is constant when it only applies operators to literals. It
+//! is still evaluated where it stands the first time the loop reaches it, so
+//! a failing expression raises its error at the same point as without the
+//! pass.
+
+use std::fmt::Display;
+use std::mem;
+
+use crate::Value;
+use crate::parser::ast::{Ast, AstArena, ExprId, StmtId};
+use crate::parser::expr::{Binary, Cached, Coalesce, ExprNode, List, Literal, Tuple};
+use crate::parser::printer::AstPrinter;
+use crate::parser::stmt::StmtNode;
+use crate::scanner::token::TokenType;
+
+/// A constant expression whose value is cached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hoisted {
+    /// The line of the first operator in the expression
+    pub line: u32,
+    /// The expression in the AST printer's notation
+    pub expr: String,
+}
+
+impl Display for Hoisted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[line {}] {}", self.line, self.expr)
+    }
+}
+
+/// Wraps every largest constant expression inside a loop of `program` in a
+/// [`Cached`] expression, and returns them in source order.
+pub fn hoist(program: &mut Ast) -> Vec<Hoisted> {
+    let mut hoisted = Vec::new();
+    for &stmt in &program.statements {
+        hoist_stmt(stmt, &mut program.arena, false, &mut hoisted);
+    }
+    hoisted
+}
+
+// The walk takes the id of a node before the arena, so that the ids of its
+// children are copied out of the node before the arena is borrowed mutably.
+fn hoist_stmt(stmt: StmtId, ast: &mut AstArena, in_loop: bool, hoisted: &mut Vec<Hoisted>) {
+    let mut expr = |expr: ExprId, ast: &mut AstArena| {
+        if in_loop {
+            hoist_expr(expr, ast, hoisted);
+        }
+    };
+    match &ast[stmt] {
+        StmtNode::Print(print) => expr(print.expr, ast),
+        StmtNode::Expression(expression) => expr(expression.expr, ast),
+        StmtNode::Var(var) => {
+            if let Some(initializer) = var.initializer {
+                expr(initializer, ast);
+            }
+        }
+        StmtNode::VarUnpack(unpack) => expr(unpack.value, ast),
+        StmtNode::Throw(throw) => expr(throw.value, ast),
+        StmtNode::Assert(assert) => {
+            let (condition, message) = (assert.condition, assert.message);
+            expr(condition, ast);
+            if let Some(message) = message {
+                expr(message, ast);
+            }
+        }
+        StmtNode::Import(_) => {}
+        StmtNode::Block(block) => {
+            for stmt in block.statements.clone() {
+                hoist_stmt(stmt, ast, in_loop, hoisted);
+            }
+        }
+        StmtNode::ForIn(for_in) => {
+            let body = for_in.body;
+            expr(for_in.iterable, ast);
+            hoist_stmt(body, ast, true, hoisted);
+        }
+        StmtNode::DoWhile(do_while) => {
+            let condition = do_while.condition;
+            hoist_stmt(do_while.body, ast, true, hoisted);
+            hoist_expr(condition, ast, hoisted);
+        }
+        StmtNode::Try(try_stmt) => {
+            let (catch, finally) = (try_stmt.catch.as_ref().map(|(_, c)| *c), try_stmt.finally);
+            hoist_stmt(try_stmt.body, ast, in_loop, hoisted);
+            for stmt in catch.into_iter().chain(finally) {
+                hoist_stmt(stmt, ast, in_loop, hoisted);
+            }
+        }
+        StmtNode::Match(match_stmt) => {
+            let bodies = match_stmt.arms.iter().map(|(_, body)| *body);
+            let bodies: Vec<_> = bodies.chain(match_stmt.otherwise).collect();
+            expr(match_stmt.value, ast);
+            for stmt in bodies {
+                hoist_stmt(stmt, ast, in_loop, hoisted);
+            }
+        }
+    }
+}
+
+fn hoist_expr(expr: ExprId, ast: &mut AstArena, hoisted: &mut Vec<Hoisted>) {
+    if is_constant(ast, expr)
+        && let Some(line) = operator_line(ast, expr)
+    {
+        hoisted.push(Hoisted {
+            line,
+            expr: AstPrinter::new().print(ast, expr),
+        });
+        // the constant moves to a new slot, and the cache takes its place
+        let constant = mem::replace(&mut ast[expr], Literal::from(Value::Nil).into());
+        let constant = ast.add_expr(constant);
+        ast[expr] = Cached::new(constant).into();
+        return;
+    }
+
+    match &ast[expr] {
+        ExprNode::Grouping(grouping) => hoist_expr(grouping.expression, ast, hoisted),
+        ExprNode::Unary(unary) => hoist_expr(unary.right, ast, hoisted),
+        ExprNode::Binary(Binary { left, right, .. })
+        | ExprNode::Coalesce(Coalesce { left, right, .. }) => {
+            let right = *right;
+            hoist_expr(*left, ast, hoisted);
+            hoist_expr(right, ast, hoisted);
+        }
+        ExprNode::Assign(assign) => hoist_expr(assign.value, ast, hoisted),
+        ExprNode::AssignUnpack(unpack) => hoist_expr(unpack.value, ast, hoisted),
+        ExprNode::Call(call) => {
+            let arguments = call.arguments.clone();
+            hoist_expr(call.callee, ast, hoisted);
+            for argument in arguments {
+                hoist_expr(argument, ast, hoisted);
+            }
+        }
+        ExprNode::List(List { elements }) | ExprNode::Tuple(Tuple { elements, .. }) => {
+            for element in elements.clone() {
+                hoist_expr(element, ast, hoisted);
+            }
+        }
+        ExprNode::Spread(spread) => hoist_expr(spread.expression, ast, hoisted),
+        ExprNode::Map(map) => {
+            for (key, value) in map.entries.clone() {
+                hoist_expr(key, ast, hoisted);
+                hoist_expr(value, ast, hoisted);
+            }
+        }
+        ExprNode::Index(index) => {
+            let index_expr = index.index;
+            hoist_expr(index.object, ast, hoisted);
+            hoist_expr(index_expr, ast, hoisted);
+        }
+        ExprNode::SetIndex(set_index) => {
+            let (index, value) = (set_index.index, set_index.value);
+            hoist_expr(set_index.object, ast, hoisted);
+            hoist_expr(index, ast, hoisted);
+            hoist_expr(value, ast, hoisted);
+        }
+        ExprNode::Variable(_) | ExprNode::Literal(_) | ExprNode::Cached(_) => {}
+    }
+}
+
+/// Checks whether `expr` only applies operators without side effects to
+/// literals. Ranges are left out, since each one is a new iterable value.
+fn is_constant(ast: &AstArena, expr: ExprId) -> bool {
+    match &ast[expr] {
+        ExprNode::Literal(_) => true,
+        ExprNode::Grouping(grouping) => is_constant(ast, grouping.expression),
+        ExprNode::Unary(unary) => is_constant(ast, unary.right),
+        ExprNode::Binary(binary) => {
+            !matches!(
+                binary.operator.typ,
+                TokenType::DotDot | TokenType::DotDotEqual
+            ) && is_constant(ast, binary.left)
+                && is_constant(ast, binary.right)
+        }
+        ExprNode::Coalesce(coalesce) => {
+            is_constant(ast, coalesce.left) && is_constant(ast, coalesce.right)
+        }
+        _ => false,
+    }
+}
+
+/// Returns the line of the first operator in a constant expression, or
+/// `None` for a lone, possibly parenthesized, literal, which is not worth
+/// caching.
+fn operator_line(ast: &AstArena, expr: ExprId) -> Option<u32> {
+    match &ast[expr] {
+        ExprNode::Grouping(grouping) => operator_line(ast, grouping.expression),
+        ExprNode::Unary(unary) => Some(unary.operator.line),
+        ExprNode::Binary(binary) => Some(binary.operator.line),
+        ExprNode::Coalesce(coalesce) => Some(coalesce.operator.line),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::dialect::{Dialect, Extension};
+    use crate::validate::validate_with;
+
+    fn dialect() -> Dialect {
+        [
+            Extension::ForIn,
+            Extension::Ranges,
+            Extension::Integers,
+            Extension::Match,
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn hoisted(source: &str) -> Vec<String> {
+        let mut program = validate_with(source, dialect()).ast.unwrap();
+        hoist(&mut program)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[rstest]
+    #[case("print 1 + 2;", vec![])]
+    #[case("for (i in 0..3) print i * (60 * 60);", vec!["[line 1] (group (* 60 60))"])]
+    #[case("for (i in 0..3) print (1 + 2) * 3;", vec!["[line 1] (* (group (+ 1 2)) 3)"])]
+    #[case("for (i in 0..3) print i + 1;", vec![])]
+    #[case("for (i in 0..3) print (1);", vec![])]
+    #[case("for (i in 0..3) { for (j in 0..2) { var x = -1; } }", vec!["[line 1] (- 1)"])]
+    #[case("for (i in 0..3)\n  match (i) { 1 => print \"a\" + \"b\"; }", vec!["[line 2] (+ a b)"])]
+    fn test_hoist(#[case] source: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(expected, hoisted(source));
+    }
+}
@@ -0,0 +1,725 @@
+//! Scanning and parsing a source again after an edit, redoing only the
+//! top-level statements the edit touches, for editors that re-check a file
+//! on every keystroke.
+//!
+//! A [`Document`] keeps the tokens, nodes and errors of each top-level
+//! statement. An edit scans and parses from the statement before it, and
+//! stops at the first statement after it that starts where an old one did,
+//! outside of any string and with the same globals constant before it. The
+//! statements from there on are kept, moved down or up by the lines the
+//! edit added or removed, which is a pass over their tokens and nodes much
+//! cheaper than scanning and parsing them again.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::dialect::Dialect;
+use crate::error::StaticError;
+use crate::parser::Parser;
+use crate::parser::ast::{Ast, AstArena, StmtId};
+use crate::parser::stmt::StmtNode;
+use crate::scanner::token::Token;
+use crate::scanner::{Borrowed, ScanItem, ScanResult, Scanner, record};
+use crate::symbol::Symbol;
+
+/// A change to the text of a [`Document`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    /// The bytes replaced, which must start and end on character boundaries
+    pub range: Range<usize>,
+    /// What replaces them
+    pub text: String,
+}
+
+impl Edit {
+    /// Creates an edit replacing the bytes in `range` with `text`.
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+}
+
+/// How much of a document was scanned and parsed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Work {
+    /// The bytes of source scanned
+    pub scanned: usize,
+    /// The top-level statements parsed
+    pub parsed: usize,
+    /// The top-level statements kept as they were
+    pub reused: usize,
+}
+
+/// A top-level statement, or the tokens skipped after one failed to parse.
+#[derive(Debug)]
+struct Item {
+    /// The byte offset of its first token
+    start: usize,
+    /// Whether its first token is outside of any string, where scanning can
+    /// start again
+    top_level: bool,
+    /// Its tokens, never empty
+    tokens: Vec<Token>,
+    /// The statement, or the error it failed to parse with
+    statement: Result<StmtId, StaticError>,
+    /// The scan errors between its tokens and just after them
+    errors: Vec<StaticError>,
+    /// The globals it declares, and whether each is a constant
+    globals: Vec<(Symbol, bool)>,
+    /// The number of nodes it added to the arena
+    nodes: usize,
+}
+
+impl Item {
+    /// Returns the line its first token starts on, which is not the line of
+    /// the token when it is a string spanning several lines.
+    fn line(&self) -> u32 {
+        let first = &self.tokens[0];
+        first.line - newlines(&first.lexeme) as u32
+    }
+
+    /// Moves the item `lines` lines and `bytes` bytes down the source, or up
+    /// if negative.
+    fn shift(&mut self, arena: &mut AstArena, lines: i32, bytes: isize) {
+        for token in &mut self.tokens {
+            token.line = token.line.saturating_add_signed(lines);
+            token.span = token.span.map(|span| span.shift(bytes));
+        }
+        for error in &mut self.errors {
+            error.shift(lines, bytes);
+        }
+        match &mut self.statement {
+            Ok(stmt) => arena.shift(*stmt, lines, bytes),
+            Err(err) => err.shift(lines, bytes),
+        }
+    }
+}
+
+/// Where scanning starts again.
+#[derive(Debug, Clone, Copy)]
+struct Start {
+    offset: usize,
+    line: u32,
+    column: u32,
+}
+
+/// Where the old items after the point scanning starts again begin, and how
+/// the edit moved them.
+#[derive(Debug, Default)]
+struct Rest {
+    /// The position of the first of them among the items
+    first: usize,
+    /// The byte offset of the old source where the edit ended, before which
+    /// no item can be kept
+    end: usize,
+    /// The last line of the old source the edit touched, before which no
+    /// item can be kept as its columns may have changed
+    edited: u32,
+    /// The bytes the edit added, or removed if negative
+    bytes: isize,
+    /// The lines the edit added, or removed if negative
+    lines: i32,
+}
+
+impl Rest {
+    /// Returns where `item` starts in the edited source.
+    fn start(&self, item: &Item) -> usize {
+        item.start
+            .checked_add_signed(self.bytes)
+            .expect("items after an edit start after its text")
+    }
+
+    /// Checks whether `item` of the old source is after the edit, on a line
+    /// it left as it was.
+    fn after(&self, item: &Item) -> bool {
+        item.start >= self.end && item.line() > self.edited
+    }
+}
+
+/// A source, scanned and parsed, that takes edits.
+///
+/// Scan errors do not stop parsing as they do for [`Parser::parse`]: they
+/// are reported, and the tokens around them parsed.
+#[derive(Debug)]
+pub struct Document {
+    source: String,
+    dialect: Dialect,
+    items: Vec<Item>,
+    /// The scan errors before the first token
+    leading: Vec<StaticError>,
+    eof: Token,
+    /// The statements that parsed, in an arena that also holds the nodes of
+    /// statements since edited away
+    ast: Ast,
+    /// The number of nodes in the arena no statement holds anymore
+    garbage: usize,
+}
+
+impl Document {
+    /// Scans and parses `source` with the language extensions in `dialect`.
+    pub fn new(source: impl Into<String>, dialect: Dialect) -> Self {
+        let mut document = Self {
+            source: source.into(),
+            dialect,
+            items: Vec::new(),
+            leading: Vec::new(),
+            eof: Token::new_eof(1),
+            ast: Ast::default(),
+            garbage: 0,
+        };
+        let start = Start {
+            offset: 0,
+            line: 1,
+            column: 1,
+        };
+        document.analyze(start, Rest::default());
+        document
+    }
+
+    /// Returns the text of the document.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the statements that parsed.
+    pub fn ast(&self) -> &Ast {
+        &self.ast
+    }
+
+    /// Returns every token, ending with `EOF`.
+    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+        let tokens = self.items.iter().flat_map(|item| &item.tokens);
+        tokens.chain([&self.eof])
+    }
+
+    /// Returns the scan and parse errors, statement by statement.
+    pub fn errors(&self) -> impl Iterator<Item = &StaticError> {
+        let errors = self
+            .items
+            .iter()
+            .flat_map(|item| item.errors.iter().chain(item.statement.as_ref().err()));
+        self.leading.iter().chain(errors)
+    }
+
+    /// Applies `edit`, and returns how much was scanned and parsed again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range of the edit is out of the source or does not
+    /// start and end on character boundaries.
+    pub fn edit(&mut self, edit: Edit) -> Work {
+        let Edit { range, text } = edit;
+        let removed = &self.source[range.clone()];
+        let bytes = text.len() as isize - removed.len() as isize;
+        let lines = newlines(&text) as i32 - newlines(removed) as i32;
+
+        // parsing starts again at a statement the edit leaves the first token
+        // of, and does not continue, as the statement before looked at that
+        // token; the statement must also start outside strings, to scan
+        // from it alone
+        let before = self.items.partition_point(|item| item.start < range.start);
+        let first = self.items[..before].iter().rposition(|item| {
+            item.top_level && item.start + item.tokens[0].lexeme.len() < range.start
+        });
+        let (first, start) = match first {
+            Some(first) => {
+                let item = &self.items[first];
+                let start = Start {
+                    offset: item.start,
+                    line: item.line(),
+                    column: item.tokens[0].column,
+                };
+                (first, start)
+            }
+            None => {
+                let start = Start {
+                    offset: 0,
+                    line: 1,
+                    column: 1,
+                };
+                (0, start)
+            }
+        };
+        let edited = start.line + newlines(&self.source[start.offset..range.end]) as u32;
+        let rest = Rest {
+            first,
+            end: range.end,
+            edited,
+            bytes,
+            lines,
+        };
+
+        self.source.replace_range(range, &text);
+        let work = self.analyze(start, rest);
+
+        // starting over keeps the arena in proportion to the source, at a
+        // cost spread over the edits that filled it with garbage
+        if self.garbage > self.ast.arena.len() / 2 {
+            let source = mem::take(&mut self.source);
+            *self = Self::new(source, self.dialect);
+            return Work {
+                scanned: work.scanned + self.source.len(),
+                parsed: work.parsed + self.items.len(),
+                reused: 0,
+            };
+        }
+        work
+    }
+
+    /// Scans and parses from `start` to the end of the source, or until
+    /// reaching an item of `rest` that can be kept.
+    fn analyze(&mut self, start: Start, rest: Rest) -> Work {
+        let source = mem::take(&mut self.source);
+        let log = Rc::new(RefCell::new(Log::default()));
+        let scanner = Scanner::new(&source[start.offset..])
+            .with_dialect(self.dialect)
+            .starting_at(start.offset, start.line, start.column);
+        let scan = Scan {
+            tokens: scanner.scan_tokens().borrowed(),
+            source: &source,
+            log: Rc::clone(&log),
+        };
+        let (before, old) = self.items.split_at(rest.first);
+        let globals = constants(before);
+        let mut parser = Parser::new(scan)
+            .with_dialect(self.dialect)
+            .with_arena(mem::take(&mut self.ast.arena))
+            .with_globals(globals.clone());
+
+        let mut parsed = Vec::new();
+        let mut declared = Vec::new();
+        // the position among the old items of the first one kept
+        let mut resync = old.len();
+        let mut candidate = 0;
+        let mut end = source.len();
+        while !parser.is_at_end() {
+            let next = parser.consumed();
+            let (offset, top_level) = log.borrow().starts[next];
+            while old
+                .get(candidate)
+                .is_some_and(|item| !rest.after(item) || rest.start(item) < offset)
+            {
+                candidate += 1;
+            }
+            if top_level
+                && old.get(candidate).is_some_and(|item| {
+                    let old = old[..candidate].iter().flat_map(|item| &item.globals);
+                    let moved = rest.start(item) == offset && item.top_level;
+                    moved && same_constants(&globals, &declared, old)
+                })
+            {
+                resync = candidate;
+                end = offset;
+                break;
+            }
+
+            let nodes = parser.arena().len();
+            let statement = parser.parse_declaration().expect("checked not at end");
+            let globals = statement
+                .as_ref()
+                .map_or(Vec::new(), |&stmt| declares(parser.arena(), stmt));
+            declared.extend(globals.iter().cloned());
+            let item = Item {
+                start: offset,
+                top_level,
+                tokens: Vec::new(),
+                statement,
+                errors: Vec::new(),
+                globals,
+                nodes: parser.arena().len() - nodes,
+            };
+            parsed.push((next..parser.consumed(), item));
+        }
+        self.ast.arena = parser.into_arena();
+        self.source = source;
+
+        let Log { tokens, errors, .. } = log.take();
+        if start.offset == 0 {
+            self.leading.clear();
+        }
+        for (scanned, error) in errors {
+            // an error belongs to the item of the token before it
+            let Some(token) = scanned.checked_sub(1) else {
+                self.leading.push(error);
+                continue;
+            };
+            let owner = parsed.partition_point(|(range, _)| range.end <= token);
+            let (_, item) = parsed
+                .get_mut(owner)
+                .expect("every token but EOF belongs to an item");
+            item.errors.push(error);
+        }
+
+        let replaced = rest.first..rest.first + resync;
+        let work = Work {
+            scanned: end - start.offset,
+            parsed: parsed.len(),
+            reused: self.items.len() - replaced.end,
+        };
+        let parsed = parsed.into_iter().map(|(range, item)| Item {
+            tokens: tokens[range].to_vec(),
+            ..item
+        });
+        let replaced = self.items.splice(replaced, parsed);
+        self.garbage += replaced.map(|item| item.nodes).sum::<usize>();
+        let kept = rest.first + work.parsed;
+        for item in &mut self.items[kept..] {
+            item.start = rest.start(item);
+            if rest.lines != 0 || rest.bytes != 0 {
+                item.shift(&mut self.ast.arena, rest.lines, rest.bytes);
+            }
+        }
+        if end == self.source.len() {
+            self.eof = tokens.last().expect("scanning ends with EOF").clone();
+        } else {
+            self.eof.line = self.eof.line.saturating_add_signed(rest.lines);
+            self.eof.span = self.eof.span.map(|span| span.shift(rest.bytes));
+        }
+
+        let statements = self.items.iter();
+        self.ast.statements = statements
+            .filter_map(|item| item.statement.as_ref().ok().copied())
+            .collect();
+        work
+    }
+}
+
+/// Returns the number of line breaks in `text`.
+fn newlines(text: &str) -> usize {
+    text.bytes().filter(|&byte| byte == b'\n').count()
+}
+
+/// Returns the globals the top-level statement `stmt` declares, the way the
+/// parser tracks them.
+fn declares(arena: &AstArena, stmt: StmtId) -> Vec<(Symbol, bool)> {
+    match &arena[stmt] {
+        StmtNode::Var(var) => vec![(var.name.symbol(), var.constant)],
+        StmtNode::VarUnpack(unpack) => unpack.names.iter().map(|n| (n.symbol(), false)).collect(),
+        StmtNode::Import(import) => import.names.iter().map(|n| (n.symbol(), false)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the globals `items` declare constant at some point, and whether
+/// each still is after the last of them, which is all the parser tells
+/// globals apart by.
+fn constants(items: &[Item]) -> HashMap<Symbol, bool> {
+    let declared = || items.iter().flat_map(|item| &item.globals);
+    let mut constants = declared()
+        .filter(|(_, constant)| *constant)
+        .map(|(name, _)| (name.clone(), true))
+        .collect::<HashMap<_, _>>();
+    if !constants.is_empty() {
+        for (name, constant) in declared() {
+            if let Some(last) = constants.get_mut(name) {
+                *last = *constant;
+            }
+        }
+    }
+    constants
+}
+
+/// Checks whether declaring the globals `new` or `old` after `globals`
+/// leaves the same ones constant, which is all that parsing later
+/// statements depends on.
+fn same_constants<'a>(
+    globals: &HashMap<Symbol, bool>,
+    new: &[(Symbol, bool)],
+    old: impl Iterator<Item = &'a (Symbol, bool)>,
+) -> bool {
+    let new = new.iter().cloned().collect::<HashMap<_, _>>();
+    let old = old.cloned().collect::<HashMap<_, _>>();
+    new.keys().chain(old.keys()).all(|name| {
+        let constant = |declared: &HashMap<_, _>| {
+            declared.get(name).or(globals.get(name)).copied() == Some(true)
+        };
+        constant(&new) == constant(&old)
+    })
+}
+
+/// What a [`Scan`] saw, for the document to split among items once parsing
+/// stops.
+#[derive(Debug, Default)]
+struct Log {
+    /// The byte offset of each token in the full source, and whether it is
+    /// outside of any string
+    starts: Vec<(usize, bool)>,
+    tokens: Vec<Token>,
+    /// The scan errors, each with the number of tokens scanned before it
+    errors: Vec<(usize, StaticError)>,
+}
+
+/// The tokens of a source sliced from the middle of a document, yielded to
+/// a parser and logged along with the errors between them, which the
+/// parser never sees.
+struct Scan<'src> {
+    tokens: Borrowed<'src>,
+    /// The full source, which the scanned one is a slice of
+    source: &'src str,
+    log: Rc<RefCell<Log>>,
+}
+
+impl Iterator for Scan<'_> {
+    type Item = ScanResult;
+
+    fn next(&mut self) -> Option<ScanResult> {
+        loop {
+            let top_level = self.tokens.at_top_level();
+            let item = self.tokens.next()?;
+            let mut log = self.log.borrow_mut();
+            match item {
+                Ok(ScanItem::Token(token)) => {
+                    let start = record::span(&token, self.source).start;
+                    let token = token.into_owned();
+                    log.starts.push((start, top_level));
+                    log.tokens.push(token.clone());
+                    return Some(Ok(ScanItem::Token(token)));
+                }
+                Ok(ScanItem::Ignore) => {}
+                Err(err) => {
+                    let scanned = log.tokens.len();
+                    log.errors.push((scanned, err));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::dialect::Extension;
+    use crate::lsp::index::Index;
+    use crate::validate::validate_with;
+
+    fn dialect() -> Dialect {
+        Dialect::new()
+            .with(Extension::Interpolation)
+            .with(Extension::Constants)
+            .with(Extension::ForIn)
+            .with(Extension::Ranges)
+    }
+
+    /// Describes the tokens, errors and statements of `document`, with
+    /// where each name resolves to.
+    fn describe(document: &Document) -> Vec<String> {
+        let position = |token: &Token| format!("{}:{}", token.line, token.column);
+        let tokens = document
+            .tokens()
+            .map(|token| format!("{} {:?} {token}", position(token), token.span));
+        let errors = document.errors().map(|err| err.to_string());
+        let ast = document.ast();
+        let lines = ast.statements.iter().map(|&stmt| {
+            let lines = ast.arena.lines(stmt).unwrap();
+            format!("statement {}-{}", lines.first, lines.last)
+        });
+        let index = Index::new(ast);
+        let references = index.references.iter().map(|reference| {
+            let definition = reference.definition.map(|d| &index.definitions[d].name);
+            let definition = definition.map_or("?".to_string(), position);
+            format!("{} -> {definition}", position(&reference.name))
+        });
+        tokens
+            .chain(errors)
+            .chain(lines)
+            .chain(references)
+            .collect()
+    }
+
+    /// Applies `edits` to `source` one after the other, checking that each
+    /// leaves the document as analyzing its text from scratch would.
+    fn apply(source: &str, edits: &[(Range<usize>, &str)]) -> (Document, Vec<Work>) {
+        let mut document = Document::new(source, dialect());
+        let mut work = Vec::new();
+        for (range, text) in edits {
+            work.push(document.edit(Edit::new(range.clone(), *text)));
+            let fresh = Document::new(document.source(), dialect());
+            assert_eq!(describe(&fresh), describe(&document), "after {range:?}");
+        }
+        (document, work)
+    }
+
+    #[rstest]
+    #[case::insert_statement("var a = 1;\nprint a;\n", &[(11..11, "a = 2;\n")])]
+    #[case::continue_identifier("var a = 1;\nprint a;\nprint a;", &[(18..18, "b")])]
+    #[case::join_lines("print 1;\nprint 2;\nprint 3;", &[(8..9, ""), (16..17, " ")])]
+    #[case::remove_everything("print 1;\nprint 2;", &[(0..17, "")])]
+    #[case::before_first("\nprint 1;\nprint 2;", &[(0..0, "@ var x = 1;"), (0..1, "")])]
+    #[case::open_string("print 1;\nprint 2;\nprint 3;", &[(15..15, "\""), (15..16, "")])]
+    #[case::open_block("print 1;\nprint 2;\nprint 3;", &[(9..9, "{\n"), (0..0, "}")])]
+    #[case::interpolation("print \"${1}\";\nprint 2;", &[(10..10, " + \"${2"), (10..17, "")])]
+    #[case::constant(
+        "var c = 1;\nc = 2;\nprint c;",
+        &[(0..3, "const"), (0..5, "var"), (7..7, "d")]
+    )]
+    #[case::parse_error("print (1;\nprint 2;\nprint 3;", &[(8..8, ")"), (16..17, "(")])]
+    #[case::scan_error("print 1;\nprint 2; #\nprint 3;", &[(17..19, ""), (9..9, "$\n")])]
+    #[case::string_spanning_the_edit("\n\"var a = 1;\n\"", &[(0..8, ";")])]
+    #[case::string_after_the_edit(
+        "print a;print var a = 1;\n\";\nvar a = 1;\n\"s\";\n",
+        &[(26..43, "")]
+    )]
+    #[case::interpolation_split_by_recovery(
+        "print \"${a; print b}\";\nprint c;",
+        &[(6..9, ""), (6..6, "\"${")]
+    )]
+    #[case::nested(
+        "var a = 1;\n{\n  var b = a;\n  print b;\n}\nfor (i in 0..a) print i;",
+        &[(26..26, " + a"), (11..11, "a = a;\n"), (0..10, "")]
+    )]
+    fn test_edits_match_full_analysis(
+        #[case] source: &str,
+        #[case] edits: &[(Range<usize>, &str)],
+    ) {
+        apply(source, edits);
+    }
+
+    #[test]
+    fn test_every_deletion_matches_full_analysis() {
+        let source = "const a = 1;\n{\n  var s = \"x${a}\";\n  print s;\n} // end\nfor (i in 0..a)\n  \
+                      print i;\nprint (a;\nprint @a;\n";
+        for (at, c) in source.char_indices() {
+            let end = at + c.len_utf8();
+            apply(source, &[(at..end, ""), (at..at, &source[at..end])]);
+        }
+    }
+
+    /// Pieces of source that random edits are made of, chosen to open and
+    /// close strings, interpolations, blocks and comments.
+    const FRAGMENTS: &[&str] = &[
+        "print ", "var ", "const ", "a", "b", " = ", "1", ";", "\n", "\"", "${", "}", "{", " ",
+        "+ ", "(", ")", "//", "@", "é",
+    ];
+
+    /// A tiny linear congruential generator, good enough to vary the edits.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (self.0 >> 33) as usize % n
+        }
+
+        fn text(&mut self, fragments: usize) -> String {
+            let count = self.below(fragments + 1);
+            (0..count)
+                .map(|_| FRAGMENTS[self.below(FRAGMENTS.len())])
+                .collect()
+        }
+
+        /// Returns a character boundary of `text`.
+        fn boundary(&mut self, text: &str) -> usize {
+            let boundaries = text.char_indices().map(|(at, _)| at).chain([text.len()]);
+            let boundaries = boundaries.collect::<Vec<_>>();
+            boundaries[self.below(boundaries.len())]
+        }
+    }
+
+    #[test]
+    fn test_random_edits_match_full_analysis() {
+        const SEEDS: u64 = 500;
+        for seed in 0..SEEDS {
+            let mut rng = Lcg(seed);
+            let mut document = Document::new(rng.text(40), dialect());
+            for step in 0..20 {
+                let source = document.source().to_string();
+                let (a, b) = (rng.boundary(&source), rng.boundary(&source));
+                let edit = Edit::new(a.min(b)..a.max(b), rng.text(4));
+                document.edit(edit.clone());
+                let fresh = Document::new(document.source(), dialect());
+                assert_eq!(
+                    describe(&fresh),
+                    describe(&document),
+                    "seed {seed}, step {step}: {edit:?} on {source:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_document_matches_parser() {
+        let source = "var a = 1;\n{\n  print a + 2;\n}\nconst b = -a;\nprint \"${a}${b}\";";
+        let document = Document::new(source, dialect());
+        let program = validate_with(source, dialect()).ast.unwrap();
+        assert_eq!(
+            Index::new(&program).references.len(),
+            Index::new(document.ast()).references.len()
+        );
+        let lines = |ast: &Ast| -> Vec<_> {
+            let lines = ast.statements.iter().map(|&stmt| ast.arena.lines(stmt));
+            lines.collect()
+        };
+        assert_eq!(lines(&program), lines(document.ast()));
+        assert_eq!(0, document.errors().count());
+    }
+
+    /// Returns a program of `statements` statements of the same length, each
+    /// on its own line.
+    fn program(statements: usize) -> String {
+        (0..statements)
+            .map(|i| format!("var v{i:04} = {i:>4} * 2;\n"))
+            .collect()
+    }
+
+    #[test]
+    fn test_edit_work_is_local() {
+        let edits = |statements: usize| {
+            let source = program(statements);
+            let middle = source
+                .find(&format!("var v{:04} ", statements / 2))
+                .unwrap();
+            let (_, work) = apply(
+                &source,
+                &[
+                    (middle + 4..middle + 4, "w"),
+                    (middle..middle, "print 1;\n"),
+                    (middle..middle + 9, ""),
+                ],
+            );
+            work
+        };
+
+        let small = edits(10);
+        let large = edits(1000);
+        assert_eq!(
+            small
+                .iter()
+                .map(|w| (w.scanned, w.parsed))
+                .collect::<Vec<_>>(),
+            large
+                .iter()
+                .map(|w| (w.scanned, w.parsed))
+                .collect::<Vec<_>>()
+        );
+        assert!(
+            large
+                .iter()
+                .all(|work| work.parsed <= 3 && work.reused > 490)
+        );
+    }
+
+    #[test]
+    fn test_edit_without_resync_parses_to_the_end() {
+        let (document, work) = apply(&program(10), &[(0..0, "print \"")]);
+        assert!(work[0].scanned >= document.source().len());
+        assert_eq!(0, work[0].reused);
+    }
+
+    #[test]
+    fn test_garbage_starts_over() {
+        let mut document = Document::new(program(4), dialect());
+        for _ in 0..20 {
+            document.edit(Edit::new(4..5, "w"));
+        }
+        // four nodes a statement, and at most as many of garbage
+        assert!(document.ast.arena.len() <= 2 * 4 * 4);
+        assert_eq!(4, document.ast().len());
+    }
+}
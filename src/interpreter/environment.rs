@@ -1,12 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::Value;
 use crate::interpreter::error::RuntimeError;
 use crate::scanner::token::Token;
+use crate::symbol::Symbol;
 
 #[derive(Debug, Clone, Default)]
 pub(super) struct Environment {
-    pub(super) values: HashMap<String, Value>,
+    pub(super) values: HashMap<Symbol, Value>,
+    /// The names in `values` declared with `const`
+    constants: HashSet<Symbol>,
     pub(super) enclosing: Option<Box<Environment>>,
 }
 
@@ -15,6 +18,7 @@ impl Environment {
     pub(super) fn new() -> Self {
         Self {
             values: HashMap::new(),
+            constants: HashSet::new(),
             enclosing: None,
         }
     }
@@ -23,13 +27,41 @@ impl Environment {
     pub(super) fn with_enclosing(env: Box<Environment>) -> Self {
         Self {
             values: HashMap::new(),
+            constants: HashSet::new(),
             enclosing: Some(env),
         }
     }
 
     /// Defines a new variable in the environment by inserting the key-value pair.
-    pub(super) fn define(&mut self, key: String, value: Value) {
+    ///
+    /// Returns an error message if `key` is a constant of this scope.
+    pub(super) fn define(&mut self, key: Symbol, value: Value) -> Result<(), String> {
+        self.check_redeclaration(&key)?;
         self.values.insert(key, value);
+        Ok(())
+    }
+
+    /// Defines a variable like [`Self::define`] that cannot be assigned again.
+    pub(super) fn define_constant(&mut self, key: Symbol, value: Value) -> Result<(), String> {
+        self.check_redeclaration(&key)?;
+        self.values.insert(key.clone(), value);
+        self.constants.insert(key);
+        Ok(())
+    }
+
+    fn check_redeclaration(&self, key: &Symbol) -> Result<(), String> {
+        match self.constants.contains(key) {
+            true => Err(format!("Cannot redeclare constant '{key}'.")),
+            false => Ok(()),
+        }
+    }
+
+    /// Returns the outermost, global scope.
+    pub(super) fn globals_mut(&mut self) -> &mut Environment {
+        match self.enclosing {
+            Some(ref mut enclosing) => enclosing.globals_mut(),
+            None => self,
+        }
     }
 
     /// Retrieves the value of a variable from the environment.
@@ -52,8 +84,12 @@ impl Environment {
     pub(super) fn assign(&mut self, token: &Token, value: Value) -> Result<(), RuntimeError> {
         let var_name = &token.lexeme;
 
-        if self.values.contains_key(var_name) {
-            self.values.insert(var_name.clone(), value);
+        if self.constants.contains(var_name) {
+            let msg = format!("Cannot assign to constant '{}'.", var_name);
+            return Err(RuntimeError::new(token.clone(), msg));
+        }
+        if let Some(slot) = self.values.get_mut(var_name) {
+            *slot = value;
             return Ok(());
         }
 
@@ -1,17 +1,256 @@
+use std::fmt::Display;
+
+use crate::Value;
+use crate::diagnostic::{Diagnostic, DiagnosticRenderer as _, Plain};
+use crate::interpreter::limits::Limit;
 use crate::scanner::token::Token;
+use crate::source::Span;
+
+/// An error raised while executing a program.
+///
+/// The payload is boxed so that `Result<Value, RuntimeError>`, returned by
+/// every evaluation step, stays no larger than the value it carries.
+#[derive(Debug)]
+pub struct RuntimeError(Box<Error>);
+
+#[derive(Debug)]
+struct Error {
+    kind: Kind,
+    /// The module the error was raised in, or `None` for the script being
+    /// run
+    file: Option<String>,
+    /// The errors of a module that failed to parse, reported after this one
+    causes: Vec<Diagnostic>,
+}
 
-#[derive(Debug, thiserror::Error)]
-#[error("{message}\n[line {}]", token.line)]
-pub struct RuntimeError {
-    token: Token,
-    message: String,
+#[derive(Debug)]
+enum Kind {
+    /// An error raised by the interpreter. A `column` of 0 is unknown.
+    Raised {
+        line: u32,
+        column: u32,
+        span: Option<Span>,
+        message: String,
+    },
+    /// A value raised by a `throw` statement
+    Thrown {
+        line: u32,
+        column: u32,
+        span: Option<Span>,
+        value: Value,
+    },
+    /// Not tied to a line, since statements do not carry a token
+    LimitExceeded(Limit),
+    /// A [`Hook`](crate::interpreter::hook::Hook) stopped the program
+    Stopped,
+}
+
+impl From<Kind> for RuntimeError {
+    fn from(kind: Kind) -> Self {
+        Self(Box::new(Error {
+            kind,
+            file: None,
+            causes: Vec::new(),
+        }))
+    }
 }
 
 impl RuntimeError {
     pub fn new(token: Token, message: impl Into<String>) -> Self {
-        Self {
-            token,
+        Kind::Raised {
+            line: token.line,
+            column: token.column,
+            span: token.span,
             message: message.into(),
         }
+        .into()
+    }
+
+    /// Creates the error raised by `throw value`, where `keyword` is the
+    /// `throw` token. Rethrowing a caught runtime error raises it again as it
+    /// was.
+    pub fn thrown(keyword: &Token, value: Value) -> Self {
+        let kind = match value {
+            Value::Error(error) => Kind::Raised {
+                line: error.line,
+                column: 0,
+                span: None,
+                message: error.message,
+            },
+            value => Kind::Thrown {
+                line: keyword.line,
+                column: keyword.column,
+                span: keyword.span,
+                value,
+            },
+        };
+        kind.into()
+    }
+
+    /// Creates the error that aborts a program once it exceeds `limit`.
+    pub fn limit_exceeded(limit: Limit) -> Self {
+        Kind::LimitExceeded(limit).into()
+    }
+
+    /// Creates the error that ends a program a hook stopped, such as when
+    /// the user quits the debugger.
+    pub fn stopped() -> Self {
+        Kind::Stopped.into()
+    }
+
+    /// Checks whether a hook stopped the program.
+    pub fn is_stopped(&self) -> bool {
+        matches!(self.0.kind, Kind::Stopped)
+    }
+
+    /// Returns the limit this error reports exceeding, if any.
+    pub fn exceeded_limit(&self) -> Option<Limit> {
+        match self.0.kind {
+            Kind::LimitExceeded(limit) => Some(limit),
+            _ => None,
+        }
+    }
+
+    /// Returns the source line the error was raised on, if known.
+    pub fn line(&self) -> Option<u32> {
+        match self.0.kind {
+            Kind::Raised { line, .. } | Kind::Thrown { line, .. } => Some(line),
+            Kind::LimitExceeded(_) | Kind::Stopped => None,
+        }
+    }
+
+    /// Returns the column of the token the error was raised at, if known.
+    pub fn column(&self) -> Option<u32> {
+        match self.0.kind {
+            Kind::Raised { column, .. } | Kind::Thrown { column, .. } => {
+                (column > 0).then_some(column)
+            }
+            Kind::LimitExceeded(_) | Kind::Stopped => None,
+        }
+    }
+
+    /// Returns the bytes of the token the error was raised at, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self.0.kind {
+            Kind::Raised { span, .. } | Kind::Thrown { span, .. } => span,
+            Kind::LimitExceeded(_) | Kind::Stopped => None,
+        }
+    }
+
+    /// Returns this error attributed to the module `file`, unless it already
+    /// is to a module imported from there.
+    pub fn in_file(mut self, file: impl Into<String>) -> Self {
+        self.0.file.get_or_insert_with(|| file.into());
+        self
+    }
+
+    /// Returns this error reporting `causes` after it, such as the errors
+    /// that kept a module from parsing.
+    pub fn with_causes(mut self, causes: impl IntoIterator<Item = Diagnostic>) -> Self {
+        self.0.causes.extend(causes);
+        self
+    }
+
+    /// Returns the module the error was raised in, if it was not the script
+    /// being run.
+    pub fn file(&self) -> Option<&str> {
+        self.0.file.as_deref()
+    }
+
+    /// Returns this error as a diagnostic, followed by the errors it was
+    /// caused by.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![Diagnostic::from(self)];
+        diagnostics.extend(self.0.causes.iter().cloned());
+        diagnostics
+    }
+
+    /// Returns the error message without its line.
+    pub fn message(&self) -> String {
+        match &self.0.kind {
+            Kind::Raised { message, .. } => message.clone(),
+            Kind::Thrown { value, .. } => value.to_string(),
+            Kind::LimitExceeded(limit) => format!("Limit exceeded: {limit}."),
+            Kind::Stopped => "Program stopped.".to_string(),
+        }
+    }
+
+    /// Converts this error into the value a `catch` block receives: the
+    /// thrown value, or an [`ErrorValue`] for an error raised by the
+    /// interpreter.
+    ///
+    /// Returns the error itself if it cannot be caught.
+    pub fn into_catchable(self) -> Result<Value, Self> {
+        match self.0.kind {
+            Kind::Raised { line, message, .. } => Ok(Value::Error(ErrorValue { message, line })),
+            Kind::Thrown { value, .. } => Ok(value),
+            Kind::LimitExceeded(_) | Kind::Stopped => Err(self),
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rendered = self.diagnostics().into_iter().map(|d| Plain.render(&d));
+        f.write_str(&rendered.collect::<Vec<_>>().join("\n"))
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// A runtime error caught by a `catch` block, as a Lox value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorValue {
+    pub message: String,
+    pub line: u32,
+}
+
+impl Display for ErrorValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+
+    use super::*;
+    use crate::interpreter::limits::Resource;
+    use crate::scanner::token::TokenType;
+
+    #[test]
+    fn test_runtime_error_is_pointer_sized() {
+        assert_eq!(size_of::<usize>(), size_of::<RuntimeError>());
+        assert_eq!(size_of::<Value>(), size_of::<Result<Value, RuntimeError>>());
+    }
+
+    #[test]
+    fn test_rethrown_error_keeps_its_line() {
+        let keyword = Token::new(TokenType::Throw, "throw".into(), None, 9);
+        let slash = Token::new(TokenType::Slash, "/".into(), None, 2);
+        let caught = RuntimeError::new(slash, "Division by 0")
+            .into_catchable()
+            .unwrap();
+
+        let rethrown = RuntimeError::thrown(&keyword, caught);
+        assert_eq!("Division by 0\n[line 2]", rethrown.to_string());
+        assert_eq!(
+            "oops\n[line 9]",
+            RuntimeError::thrown(&keyword, "oops".into()).to_string()
+        );
+    }
+
+    #[test]
+    fn test_limit_exceeded_is_not_catchable() {
+        let limit = Limit {
+            resource: Resource::Calls,
+            max: 1,
+        };
+        let err = RuntimeError::limit_exceeded(limit)
+            .into_catchable()
+            .unwrap_err();
+        assert_eq!(Some(limit), err.exceeded_limit());
+        assert_eq!("Limit exceeded: calls=1.", err.to_string());
     }
 }
@@ -0,0 +1,44 @@
+//! Callbacks the interpreter makes as it runs a program, for tools such as
+//! the debugger that pause it and look around.
+
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::ops::ControlFlow;
+use std::rc::Rc;
+
+use crate::interpreter::Interpreter;
+use crate::parser::ast::{AstArena, StmtId};
+
+/// Called by an [`Interpreter`] before each statement it runs, see
+/// [`Interpreter::with_hook`].
+pub trait Hook {
+    /// Called before `stmt` runs, with `interpreter` in the scope that the
+    /// statement runs in, so that it can read variables and evaluate
+    /// expressions there.
+    ///
+    /// Returns [`ControlFlow::Break`] to stop the program with
+    /// [`RuntimeError::stopped`](crate::interpreter::error::RuntimeError::stopped).
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        ast: &AstArena,
+        stmt: StmtId,
+    ) -> ControlFlow<()>;
+
+    /// Called with each line a `print` statement writes, without its line
+    /// break. Returns whether the hook took the line, which is otherwise
+    /// written to stdout.
+    fn print(&mut self, _line: &str) -> bool {
+        false
+    }
+}
+
+/// The hook of an interpreter. Clones of an interpreter call the same hook.
+#[derive(Clone)]
+pub(super) struct Hooked(pub(super) Rc<RefCell<dyn Hook>>);
+
+impl Debug for Hooked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Hooked")
+    }
+}
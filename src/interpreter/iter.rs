@@ -0,0 +1,93 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::Value;
+use crate::map::Map;
+use crate::range::{self, Range};
+
+/// The iterator protocol behind `for (x in collection)`.
+///
+/// Lists yield their elements, maps their keys, strings their characters,
+/// ranges their numbers and a whole number `n` the numbers from `0` up to,
+/// but excluding, `n`. Lists
+/// and maps are read one element at a time, so changes made by the loop body
+/// are visible to later iterations.
+pub(super) enum LoxIter {
+    List {
+        list: Rc<RefCell<Vec<Value>>>,
+        next: usize,
+    },
+    Map {
+        map: Rc<RefCell<Map>>,
+        next: usize,
+    },
+    String {
+        chars: std::vec::IntoIter<char>,
+    },
+    Range {
+        numbers: range::Iter,
+        integers: bool,
+    },
+}
+
+impl LoxIter {
+    /// Starts iterating over `value`. Numbers are yielded as integers when
+    /// `integers` is true.
+    ///
+    /// Returns an error message if `value` cannot be iterated.
+    pub(super) fn new(value: Value, integers: bool) -> Result<Self, &'static str> {
+        let iter = match value {
+            Value::List(list) => Self::List { list, next: 0 },
+            Value::Map(map) => Self::Map { map, next: 0 },
+            Value::String(s) => Self::String {
+                chars: s.chars().collect::<Vec<_>>().into_iter(),
+            },
+            Value::Range(range) => Self::Range {
+                numbers: range.into_iter(),
+                integers,
+            },
+            Value::Number(_) | Value::Integer(_) => {
+                let Some(end) = value.as_integer() else {
+                    return Err("Can only iterate up to a whole number.");
+                };
+                Self::Range {
+                    numbers: Range::new(0, end.max(0), false).into_iter(),
+                    integers,
+                }
+            }
+            _ => return Err("Can only iterate over lists, maps, strings, ranges and numbers."),
+        };
+        Ok(iter)
+    }
+}
+
+impl Iterator for LoxIter {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::List { list, next } => {
+                let element = list.borrow().get(*next).cloned()?;
+                *next += 1;
+                Some(element)
+            }
+            Self::Map { map, next } => {
+                let key = map
+                    .borrow()
+                    .get_index(*next)
+                    .map(|(k, _)| k.value().clone())?;
+                *next += 1;
+                Some(key)
+            }
+            Self::String { chars } => chars.next().map(|c| c.to_string().into()),
+            Self::Range { numbers, integers } => {
+                let n = numbers.next()?;
+                Some(if *integers {
+                    n.into()
+                } else {
+                    (n as f64).into()
+                })
+            }
+        }
+    }
+}
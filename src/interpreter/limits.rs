@@ -0,0 +1,161 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Something a program consumes as it runs and that can be limited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    /// Statements executed, counting every pass through a loop body
+    Statements,
+    /// Function calls made
+    Calls,
+    /// Tokens scanned from the source, not counting `EOF`
+    Tokens,
+    /// Bytes of source code
+    Bytes,
+}
+
+impl Display for Resource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Statements => "statements",
+            Self::Calls => "calls",
+            Self::Tokens => "tokens",
+            Self::Bytes => "bytes",
+        })
+    }
+}
+
+/// An upper bound on a [`Resource`], written `resource=max` on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limit {
+    pub resource: Resource,
+    pub max: u64,
+}
+
+impl FromStr for Limit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((resource, max)) = s.split_once('=') else {
+            return Err(format!("expected RESOURCE=N, found '{s}'"));
+        };
+        let resource = match resource {
+            "statements" => Resource::Statements,
+            "calls" => Resource::Calls,
+            "tokens" => Resource::Tokens,
+            "bytes" => Resource::Bytes,
+            _ => {
+                return Err(format!(
+                    "unknown resource '{resource}', expected statements, calls, tokens or bytes"
+                ));
+            }
+        };
+        let max = max
+            .parse()
+            .map_err(|_| format!("invalid limit '{max}', expected a non-negative integer"))?;
+        Ok(Self { resource, max })
+    }
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.resource, self.max)
+    }
+}
+
+/// What a program has used so far, as reported by the `stats()` native.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub statements: u64,
+    pub calls: u64,
+    /// The time since the interpreter was created
+    pub elapsed: Duration,
+}
+
+/// Counts the resources a program uses, and checks them against its limits.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Budget {
+    limits: Vec<Limit>,
+    statements: u64,
+    calls: u64,
+}
+
+impl Budget {
+    pub(super) fn new(limits: impl IntoIterator<Item = Limit>) -> Self {
+        Self {
+            limits: limits.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Records one more use of `resource`.
+    ///
+    /// Returns the limit that was exceeded, if any.
+    pub(super) fn spend(&mut self, resource: Resource) -> Result<(), Limit> {
+        let used = match resource {
+            Resource::Statements => &mut self.statements,
+            Resource::Calls => &mut self.calls,
+            Resource::Tokens | Resource::Bytes => {
+                unreachable!("source limits are enforced by the scanner")
+            }
+        };
+        *used += 1;
+        let used = *used;
+        match self
+            .limits
+            .iter()
+            .find(|limit| limit.resource == resource && used > limit.max)
+        {
+            Some(limit) => Err(*limit),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns how many statements and calls were spent, without `elapsed`.
+    pub(super) fn stats(&self) -> Stats {
+        Stats {
+            statements: self.statements,
+            calls: self.calls,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("statements=10", Ok(Limit { resource: Resource::Statements, max: 10 }))]
+    #[case("calls=0", Ok(Limit { resource: Resource::Calls, max: 0 }))]
+    #[case("bytes=4096", Ok(Limit { resource: Resource::Bytes, max: 4096 }))]
+    #[case("calls", Err("expected RESOURCE=N, found 'calls'"))]
+    #[case(
+        "loops=1",
+        Err("unknown resource 'loops', expected statements, calls, tokens or bytes")
+    )]
+    #[case("calls=-1", Err("invalid limit '-1', expected a non-negative integer"))]
+    fn test_limit_from_str(#[case] input: &str, #[case] expected: Result<Limit, &str>) {
+        assert_eq!(expected.map_err(String::from), input.parse());
+    }
+
+    #[test]
+    fn test_budget_spend() {
+        let limit = Limit {
+            resource: Resource::Calls,
+            max: 2,
+        };
+        let mut budget = Budget::new([limit]);
+
+        assert_eq!(Ok(()), budget.spend(Resource::Statements));
+        assert_eq!(Ok(()), budget.spend(Resource::Calls));
+        assert_eq!(Ok(()), budget.spend(Resource::Calls));
+        assert_eq!(Err(limit), budget.spend(Resource::Calls));
+
+        let stats = budget.stats();
+        assert_eq!((1, 3), (stats.statements, stats.calls));
+    }
+}
@@ -1,191 +1,885 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::io;
 use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
 
-use crate::Value;
+use crate::diagnostic::{Diagnostic, DiagnosticRenderer, Plain};
+use crate::dialect::{Dialect, Extension};
 use crate::interpreter::environment::Environment;
 use crate::interpreter::error::RuntimeError;
-use crate::parser::expr::{self, Binary, Expr, ExprNode};
-use crate::parser::stmt::{self, Stmt, StmtNode};
+use crate::interpreter::hook::{Hook, Hooked};
+use crate::interpreter::iter::LoxIter;
+use crate::interpreter::limits::{Budget, Limit, Resource, Stats};
+use crate::interpreter::module::Module;
+use crate::interpreter::operators::OperatorTable;
+use crate::interpreter::replay::{Event, EventLog};
+use crate::interpreter::trace::Tracer;
+use crate::map::{Map, MapKey};
+use crate::parser::ast::{Ast, AstArena, ExprId, StmtId};
+use crate::parser::expr::{
+    self, AssignUnpack, Binary, Call, Coalesce, Expr, ExprNode, Index, List, SetIndex, Spread,
+};
+use crate::parser::stmt::{self, Stmt};
+use crate::range::Range;
 use crate::scanner::token::{Token, TokenType};
+use crate::source::SourceMap;
+use crate::symbol::{Interner, Symbol};
+use crate::{Value, optimizer, stdlib};
 
 mod environment;
 pub mod error;
+pub mod hook;
+mod iter;
+pub mod limits;
+mod module;
+pub mod operators;
+pub mod replay;
+mod trace;
 
 impl Value {
     /// Check whether a Lox value is truthy, which is defined as
     /// `nil` is false, booleans keep their value, and all other values are true.
-    fn is_truthy(&self) -> bool {
+    pub(crate) fn is_truthy(&self) -> bool {
         match self {
             Value::Nil => false,
             Value::Boolean(b) => *b,
             _ => true,
         }
     }
+
+    /// Returns the value of a number or integer as an `f64`.
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Integer(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a number without a fractional part that fits in an
+    /// `i64`.
+    pub(crate) fn as_integer(&self) -> Option<i64> {
+        match *self {
+            Value::Integer(n) => Some(n),
+            Value::Number(n) => {
+                let in_range = n >= i64::MIN as f64 && n < i64::MAX as f64;
+                (n.fract() == 0.0 && in_range).then_some(n as i64)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the type of a value, as `type(v)` reports it.
+    /// Integers are numbers, and foreign values report the type name they
+    /// were created with.
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) | Value::Integer(_) => "number",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::NativeFunction(_) => "function",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Range(_) => "range",
+            Value::Error(_) => "error",
+            Value::Foreign(foreign) => foreign.type_name(),
+            Value::Nil => "nil",
+        }
+    }
+
+    /// Check whether two Lox values are equal, comparing integers and numbers
+    /// by their numeric value, and lists by identity.
+    pub(crate) fn is_equal(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+            (Value::Integer(a), Value::Number(b)) | (Value::Number(b), Value::Integer(a)) => {
+                *a as f64 == *b
+            }
+            _ => self == other,
+        }
+    }
 }
 
 /// Converts two runtime values into numeric operands for arithmetic/comparison.
 ///
 /// Returns a [`RuntimeError`] if either operand is not a number.
-fn check_number_operands(left: Value, right: Value, op: Token) -> Result<(f64, f64), RuntimeError> {
-    let (Value::Number(a), Value::Number(b)) = (left, right) else {
-        return Err(RuntimeError::new(op, "Operands must be numbers."));
+fn check_number_operands(
+    left: Value,
+    right: Value,
+    op: &Token,
+) -> Result<(f64, f64), RuntimeError> {
+    let (Some(a), Some(b)) = (left.as_f64(), right.as_f64()) else {
+        return Err(RuntimeError::new(op.clone(), "Operands must be numbers."));
     };
     Ok((a, b))
 }
 
+/// Converts two runtime values into whole-number operands for bitwise operators.
+///
+/// Returns a [`RuntimeError`] if either operand is not a number without a
+/// fractional part that fits in an `i64`.
+fn check_integer_operands(
+    left: Value,
+    right: Value,
+    op: &Token,
+) -> Result<(i64, i64), RuntimeError> {
+    if let (Some(a), Some(b)) = (left.as_integer(), right.as_integer()) {
+        return Ok((a, b));
+    }
+    Err(RuntimeError::new(op.clone(), "Operands must be integers."))
+}
+
+/// Checks that `object` is a list and `index` a valid position in it.
+///
+/// Returns a [`RuntimeError`] at `bracket` if `object` is not a list, `index`
+/// is not an integer, or `index` is out of bounds.
+fn check_list_index<'a>(
+    object: &'a Value,
+    index: &Value,
+    bracket: &Token,
+) -> Result<(&'a RefCell<Vec<Value>>, usize), RuntimeError> {
+    let Value::List(list) = object else {
+        return Err(RuntimeError::new(
+            bracket.clone(),
+            "Only lists and maps can be indexed.",
+        ));
+    };
+    let Some(index) = index.as_integer() else {
+        return Err(RuntimeError::new(
+            bracket.clone(),
+            "List index must be an integer.",
+        ));
+    };
+
+    let len = list.borrow().len();
+    match usize::try_from(index) {
+        Ok(i) if i < len => Ok((list, i)),
+        _ => {
+            let message = format!("List index {index} is out of bounds for length {len}.");
+            Err(RuntimeError::new(bracket.clone(), message))
+        }
+    }
+}
+
+/// Copies the elements out of `value` for a destructuring of `count`
+/// variables.
+///
+/// Returns a [`RuntimeError`] at `paren` if `value` is not a list of `count`
+/// elements.
+fn unpack(value: &Value, count: usize, paren: &Token) -> Result<Vec<Value>, RuntimeError> {
+    let Value::List(list) = value else {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            "Can only destructure lists.",
+        ));
+    };
+    let elements = list.borrow();
+    if elements.len() != count {
+        let message = format!(
+            "Expected {count} values to destructure, got {}.",
+            elements.len()
+        );
+        return Err(RuntimeError::new(paren.clone(), message));
+    }
+    Ok(elements.clone())
+}
+
+/// Converts a runtime value into a map key.
+///
+/// Returns a [`RuntimeError`] at `token` if `key` cannot be used as a map key.
+fn check_map_key(key: Value, token: &Token) -> Result<MapKey, RuntimeError> {
+    MapKey::new(key).map_err(|message| RuntimeError::new(token.clone(), message))
+}
+
+/// Checks that a shift amount is within the bit width of an `i64`.
+///
+/// Returns a [`RuntimeError`] if `amount` is negative or at least 64.
+fn check_shift_amount(amount: i64, op: &Token) -> Result<u32, RuntimeError> {
+    match u32::try_from(amount) {
+        Ok(n) if n < i64::BITS => Ok(n),
+        _ => Err(RuntimeError::new(
+            op.clone(),
+            "Shift amount must be between 0 and 63.",
+        )),
+    }
+}
+
+/// Applies an arithmetic or comparison operator to two integers for the
+/// `Integers` extension, promoting to a float when the result overflows.
+///
+/// Returns `None` for operators without integer-specific semantics, such as
+/// division, which always produces a float.
+fn integer_binary(op: TokenType, a: i64, b: i64) -> Option<Value> {
+    let promote = |checked: Option<i64>, float: f64| checked.map_or(float.into(), Value::Integer);
+
+    let value = match op {
+        TokenType::Plus => promote(a.checked_add(b), a as f64 + b as f64),
+        TokenType::Minus => promote(a.checked_sub(b), a as f64 - b as f64),
+        TokenType::Star => promote(a.checked_mul(b), a as f64 * b as f64),
+        TokenType::Greater => (a > b).into(),
+        TokenType::GreaterEqual => (a >= b).into(),
+        TokenType::Less => (a < b).into(),
+        TokenType::LessEqual => (a <= b).into(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// The longest string, in bytes, that repeating a string may build.
+const MAX_REPEATED_LEN: usize = 1 << 30;
+
+/// Repeats `s` `count` times for the `StringRepetition` extension.
+///
+/// Returns a [`RuntimeError`] if `count` is not a number, is negative or
+/// not a whole number, or would make a string longer than
+/// [`MAX_REPEATED_LEN`].
+fn repeat_string(s: &str, count: Value, op: &Token) -> Result<Value, RuntimeError> {
+    let Some(count) = count.as_f64() else {
+        return Err(RuntimeError::new(op.clone(), "Operands must be numbers."));
+    };
+    if count < 0.0 || count.fract() != 0.0 {
+        return Err(RuntimeError::new(
+            op.clone(),
+            "Repeat count must be a non-negative integer.",
+        ));
+    }
+    let count = count as usize;
+    if s.len()
+        .checked_mul(count)
+        .is_none_or(|len| len > MAX_REPEATED_LEN)
+    {
+        return Err(RuntimeError::new(
+            op.clone(),
+            "Repeated string would be too long.",
+        ));
+    }
+    Ok(s.repeat(count).into())
+}
+
+/// How the interpreter reacts when floating-point arithmetic produces a NaN,
+/// overflows to infinity or yields negative zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ArithmeticCheck {
+    /// Follow IEEE 754 silently, like standard Lox.
+    #[default]
+    Off,
+    /// Print a warning naming the operator and keep the result.
+    Warn,
+    /// Abort with a runtime error at the operator.
+    Error,
+}
+
+/// What the string natives `len`, `substr` and `indexOf` count in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum StringUnit {
+    /// UTF-8 bytes, so `len("é")` is 2.
+    Bytes,
+    /// Unicode scalar values, so `len("e\u{301}")` is 2.
+    #[default]
+    Chars,
+    /// Extended grapheme clusters, which is what a reader sees as one
+    /// character, so `len("e\u{301}")` and `len("👍🏽")` are 1.
+    Graphemes,
+}
+
 #[derive(Debug, Clone)]
 pub struct Interpreter {
     environment: Box<Environment>,
+    dialect: Dialect,
+    arithmetic_check: ArithmeticCheck,
+    string_unit: StringUnit,
+    /// Whether native functions are denied access to the filesystem
+    sandboxed: bool,
+    /// Whether `print` statements discard their values instead of writing
+    /// them to stdout
+    muted: bool,
+    /// Where `--trace` writes each statement and expression as it runs
+    trace: Option<Tracer>,
+    /// Called before each statement runs
+    hook: Option<Hooked>,
+    /// How many statements are running, counting the innermost
+    depth: usize,
+    budget: Budget,
+    /// Formats the warnings printed while running
+    renderer: Rc<dyn DiagnosticRenderer>,
+    /// The file being run, which imports are resolved relative to
+    script: Option<PathBuf>,
+    /// The directories searched for modules not found next to the script,
+    /// in order, before their `${NAME}` variables are expanded
+    module_paths: Vec<PathBuf>,
+    /// Every module imported so far, by canonical path
+    modules: HashMap<PathBuf, Module>,
+    /// Every file an import has read, including those that failed to run
+    imported: BTreeSet<PathBuf>,
+    /// The results of natives that read the outside world
+    events: EventLog,
+    /// Whether programs are simplified by the [`optimizer`] before running
+    optimize: bool,
+    /// The binary operators supplied by the embedder
+    operators: OperatorTable,
+    /// When the interpreter was created, which [`Self::stats`] measures from
+    started: Instant,
+    /// Where the names of globals and imported modules come from
+    interner: Interner,
+    /// The files of the program, which imported modules are added to
+    sources: SourceMap,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
+    /// Creates an interpreter whose global scope holds the native functions.
     pub fn new() -> Self {
+        let interner = Interner::default();
         Self {
-            environment: Box::new(Environment::new()),
+            environment: Box::new(Self::globals(&interner)),
+            dialect: Dialect::default(),
+            arithmetic_check: ArithmeticCheck::default(),
+            string_unit: StringUnit::default(),
+            sandboxed: false,
+            muted: false,
+            trace: None,
+            hook: None,
+            depth: 0,
+            budget: Budget::default(),
+            renderer: Rc::new(Plain),
+            script: None,
+            module_paths: Vec::new(),
+            modules: HashMap::new(),
+            imported: BTreeSet::new(),
+            events: EventLog::default(),
+            optimize: false,
+            operators: OperatorTable::new(),
+            started: Instant::now(),
+            interner,
+            sources: SourceMap::new(),
         }
     }
 
-    pub fn interpret(&mut self, program: &[StmtNode]) -> Result<(), RuntimeError> {
-        for statement in program {
-            self.execute(statement)?;
+    /// Creates a global scope holding the native functions.
+    fn globals(interner: &Interner) -> Environment {
+        let mut globals = Environment::new();
+        for native in stdlib::natives() {
+            globals
+                .define(interner.intern(native.name), Value::NativeFunction(native))
+                .expect("a new scope has no constants");
         }
-        Ok(())
+        globals
     }
 
-    /// Executes a single statement node.
+    /// Returns the interner the names of globals and imported modules come
+    /// from, for scanning the scripts this interpreter runs.
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    /// Enables the language extensions in `dialect` for this interpreter.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Sets how arithmetic edge cases are reported, see [`ArithmeticCheck`].
+    pub fn with_arithmetic_check(mut self, check: ArithmeticCheck) -> Self {
+        self.arithmetic_check = check;
+        self
+    }
+
+    /// Sets what string natives count in, see [`StringUnit`].
+    pub fn with_string_unit(mut self, unit: StringUnit) -> Self {
+        self.string_unit = unit;
+        self
+    }
+
+    /// Denies native functions such as `readFile` access to the filesystem
+    /// when `sandboxed` is true, for embedders running untrusted scripts.
+    pub fn with_sandbox(mut self, sandboxed: bool) -> Self {
+        self.sandboxed = sandboxed;
+        self
+    }
+
+    /// Discards what `print` statements write when `muted` is true, for
+    /// hosts that run a program for its timing rather than its output.
+    pub fn with_muted_output(mut self, muted: bool) -> Self {
+        self.muted = muted;
+        self
+    }
+
+    /// Writes each statement to `sink` as it starts, and each expression
+    /// with its value as it finishes, indented by how deeply they nest.
+    pub fn with_trace(mut self, sink: impl io::Write + 'static) -> Self {
+        self.trace = Some(Tracer::new(sink));
+        self
+    }
+
+    /// Calls `hook` before each statement runs, for tools such as debuggers.
+    /// The caller keeps its own handle to read the hook back afterwards.
+    pub fn with_hook(mut self, hook: Rc<RefCell<dyn Hook>>) -> Self {
+        self.hook = Some(Hooked(hook));
+        self
+    }
+
+    /// Aborts the program with a [`RuntimeError`] once it uses more of a
+    /// resource than one of `limits` allows.
+    pub fn with_limits(mut self, limits: impl IntoIterator<Item = Limit>) -> Self {
+        self.budget = Budget::new(limits);
+        self
+    }
+
+    /// Formats the warnings printed while running with `renderer`.
+    pub fn with_renderer(mut self, renderer: Rc<dyn DiagnosticRenderer>) -> Self {
+        self.renderer = renderer;
+        self
+    }
+
+    /// Adds the modules the program imports to `sources`, whose first file
+    /// should be the script, so that a renderer sharing them can quote the
+    /// modules in its diagnostics.
+    pub fn with_sources(mut self, sources: SourceMap) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Resolves the paths of `import` statements relative to the directory of
+    /// `script`, rather than the working directory.
+    pub fn with_script(mut self, script: impl Into<PathBuf>) -> Self {
+        self.script = Some(script.into());
+        self
+    }
+
+    /// Searches `paths` in order for the modules that `import` statements
+    /// name and that are not found relative to the script. Each path may
+    /// name environment variables as `${NAME}`, which are expanded when an
+    /// import searches it.
+    pub fn with_module_paths(mut self, paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.module_paths = paths.into_iter().collect();
+        self
+    }
+
+    /// Records the result of every call to a native that reads the outside
+    /// world, such as `readLine`, see [`Self::recorded_events`].
+    pub fn with_recording(mut self) -> Self {
+        self.events = EventLog::Recording(Vec::new());
+        self
+    }
+
+    /// Answers calls to natives that read the outside world with `events`,
+    /// in order, instead of running them, to reproduce a recorded run.
+    pub fn with_replay(mut self, events: impl IntoIterator<Item = Event>) -> Self {
+        self.events = EventLog::Replaying(events.into_iter().collect());
+        self
+    }
+
+    /// Simplifies each program with the [`optimizer`] before running it,
+    /// which costs a copy of its statements.
+    pub fn with_optimizations(mut self) -> Self {
+        self.optimize = true;
+        self
+    }
+
+    /// Applies the binary operators in `operators` to the operand types they
+    /// name before falling back to the built-in behavior.
     ///
-    /// Returns a [`RuntimeError`] if execution of the statement fails at runtime.
-    pub fn execute(&mut self, stmt: &StmtNode) -> Result<(), RuntimeError> {
-        Stmt::accept(stmt, self)
+    /// Constants folded by the [`optimizer`] only ever see built-in operators.
+    pub fn with_operators(mut self, operators: OperatorTable) -> Self {
+        self.operators = operators;
+        self
     }
 
-    /// Evaluates a single expression tree.
+    /// Defines the natives of `plugin` as globals, and adds its operators to
+    /// the ones already set with [`Self::with_operators`].
     ///
-    /// Returns the resulting value or a runtime error when evaluation fails.
-    pub fn evaluate(&mut self, expr: &ExprNode) -> Result<Value, RuntimeError> {
-        Expr::accept(expr, self)
+    /// # Panics
+    ///
+    /// Panics if a native of `plugin` is named like a global constant.
+    #[cfg(feature = "plugins")]
+    pub fn with_plugin(mut self, plugin: &dyn crate::plugin::Plugin) -> Self {
+        let globals = self.environment.globals_mut();
+        for native in plugin.natives() {
+            if let Err(message) = globals.define(native.name.into(), Value::NativeFunction(native))
+            {
+                panic!("{message}");
+            }
+        }
+        self.operators = plugin.operators(std::mem::take(&mut self.operators));
+        self
     }
-}
 
-impl stmt::Visitor for Interpreter {
-    type Output = Result<(), RuntimeError>;
+    /// Returns the canonical path of every file an import has read so far,
+    /// whether or not the module ran without error.
+    pub fn imported(&self) -> impl Iterator<Item = &Path> {
+        self.imported.iter().map(PathBuf::as_path)
+    }
 
-    fn visit_print_stmt(&mut self, stmt: &stmt::Print) -> Self::Output {
-        let value = self.evaluate(&stmt.expr)?;
+    /// Writes `value` to stdout on a line of its own, unless output is muted.
+    pub(crate) fn print(&self, value: &Value) {
+        if self.muted {
+            return;
+        }
+        if let Some(Hooked(hook)) = &self.hook
+            && let Ok(mut hook) = hook.try_borrow_mut()
+            && hook.print(&value.to_string())
+        {
+            return;
+        }
         println!("{value}");
-        Ok(())
     }
 
-    fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) -> Self::Output {
-        self.evaluate(&stmt.expr)?;
-        Ok(())
+    /// Returns the events recorded so far, or nothing if not recording.
+    pub fn recorded_events(&self) -> &[Event] {
+        match &self.events {
+            EventLog::Recording(events) => events,
+            _ => &[],
+        }
     }
 
-    fn visit_var_stmt(&mut self, stmt: &stmt::Var) -> Self::Output {
-        let value = stmt
-            .initializer
-            .as_deref()
-            .map(|e| self.evaluate(e))
-            .transpose()?
-            .unwrap_or(Value::Nil);
+    /// Runs `run` as the body of the native called `native`, whose result
+    /// depends on the outside world, recording or replaying its result.
+    pub(crate) fn nondeterministic(
+        &mut self,
+        native: &str,
+        run: impl FnOnce() -> Result<Value, String>,
+    ) -> Result<Value, String> {
+        self.events.call(native, run)
+    }
 
-        self.environment.define(stmt.name.lexeme.clone(), value);
+    /// Binds `name` to `value` in the current scope, which is the global scope
+    /// unless called from a native function.
+    ///
+    /// Embedders use this to hand scripts their own native functions and
+    /// [`Foreign`](crate::foreign::Foreign) values before running them.
+    ///
+    /// Returns an error message if `name` is a constant of the current scope.
+    pub fn define(&mut self, name: impl Into<Symbol>, value: Value) -> Result<(), String> {
+        self.environment.define(name.into(), value)
+    }
 
-        Ok(())
+    /// Binds `name` to `value` in the global scope as a constant, so that
+    /// scripts can read configuration from the host but neither assign nor
+    /// redeclare it.
+    ///
+    /// Only the binding is constant: the entries of a list or map `value`
+    /// can still be changed by indexing, and the host sees those changes.
+    ///
+    /// Returns an error message if `name` is already a global constant.
+    pub fn define_global(&mut self, name: impl Into<Symbol>, value: Value) -> Result<(), String> {
+        self.environment
+            .globals_mut()
+            .define_constant(name.into(), value)
     }
 
-    fn visit_block_stmt(&mut self, stmt: &stmt::Block) -> Self::Output {
-        let outer = std::mem::take(&mut self.environment);
-        self.environment = Box::new(Environment::with_enclosing(outer));
+    /// Declares the variable `name` in the current scope with `value`, as a
+    /// constant that refuses assignments from then on if `constant` is set.
+    ///
+    /// Returns a [`RuntimeError`] if `name` is already a constant of the
+    /// current scope.
+    pub(crate) fn declare(
+        &mut self,
+        name: &Token,
+        value: Value,
+        constant: bool,
+    ) -> Result<(), RuntimeError> {
+        let declared = match constant {
+            true => self.environment.define_constant(name.symbol(), value),
+            false => self.environment.define(name.symbol(), value),
+        };
+        declared.map_err(|message| RuntimeError::new(name.clone(), message))
+    }
+
+    /// Returns the value of the variable `name`.
+    ///
+    /// Returns a [`RuntimeError`] if no variable is called `name`.
+    pub(crate) fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        self.environment.get(name)
+    }
+
+    /// Stores `value` in the existing variable `name`.
+    ///
+    /// Returns a [`RuntimeError`] if there is no such variable or it is a
+    /// constant.
+    pub(crate) fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        self.environment.assign(name, value)
+    }
+
+    /// Returns the statements executed and calls made so far, counted since
+    /// the last [`Self::with_limits`], and the time since the interpreter was
+    /// created.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            elapsed: self.started.elapsed(),
+            ..self.budget.stats()
+        }
+    }
+
+    /// Returns the language extensions enabled for this interpreter.
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// Checks whether filesystem access is denied, see [`Self::with_sandbox`].
+    pub fn is_sandboxed(&self) -> bool {
+        self.sandboxed
+    }
+
+    /// Inspects the result of the numeric operator `op` applied to `operands`.
+    ///
+    /// Only edge cases the operator introduced are reported: a NaN or infinite
+    /// operand, or a negative zero one, is propagated without complaint.
+    /// Returns a [`RuntimeError`] when checks are set to [`ArithmeticCheck::Error`].
+    fn check_arithmetic(
+        &self,
+        op: &Token,
+        operands: &[f64],
+        result: f64,
+    ) -> Result<Value, RuntimeError> {
+        if self.arithmetic_check == ArithmeticCheck::Off {
+            return Ok(result.into());
+        }
+
+        let is_negative_zero = |n: f64| n == 0.0 && n.is_sign_negative();
+        let message = if result.is_nan() && !operands.iter().any(|n| n.is_nan()) {
+            "Arithmetic produced NaN."
+        } else if result.is_infinite() && operands.iter().all(|n| n.is_finite()) {
+            "Arithmetic overflowed to infinity."
+        } else if is_negative_zero(result) && !operands.iter().copied().any(is_negative_zero) {
+            "Arithmetic produced negative zero."
+        } else {
+            return Ok(result.into());
+        };
+
+        match self.arithmetic_check {
+            ArithmeticCheck::Error => Err(RuntimeError::new(op.clone(), message)),
+            _ => {
+                let warning = Diagnostic::warning("arithmetic", op, message);
+                eprintln!("{}", self.renderer.render(&warning));
+                Ok(result.into())
+            }
+        }
+    }
+
+    /// Returns what string natives count in.
+    pub(crate) fn string_unit(&self) -> StringUnit {
+        self.string_unit
+    }
+
+    /// Wraps a whole number such as the result of a bitwise operator, keeping
+    /// it integral when the `Integers` extension is enabled.
+    pub(crate) fn integral(&self, n: i64) -> Value {
+        if self.dialect.allows(Extension::Integers) {
+            n.into()
+        } else {
+            (n as f64).into()
+        }
+    }
 
-        for stmt in &stmt.statements {
-            if let Err(err) = self.execute(stmt) {
-                let outer = self.environment.enclosing.take().unwrap();
-                self.environment = outer;
-                return Err(err);
+    /// Evaluates call arguments or list elements in order, expanding each
+    /// spread list into its elements.
+    ///
+    /// Returns an error when a spread value is not a list.
+    fn evaluate_elements(
+        &mut self,
+        ast: &AstArena,
+        elements: &[ExprId],
+    ) -> Result<Vec<Value>, RuntimeError> {
+        let mut values = Vec::with_capacity(elements.len());
+        for &element in elements {
+            let ExprNode::Spread(spread) = &ast[element] else {
+                values.push(self.evaluate(ast, element)?);
+                continue;
+            };
+            match self.evaluate(ast, spread.expression)? {
+                Value::List(list) => values.extend(list.borrow().iter().cloned()),
+                _ => {
+                    return Err(RuntimeError::new(
+                        spread.ellipsis.clone(),
+                        "Can only spread lists.",
+                    ));
+                }
             }
         }
+        Ok(values)
+    }
+
+    /// Runs `body` in a new scope nested in the current one, restoring the
+    /// current scope afterwards even if `body` fails.
+    fn in_new_scope(
+        &mut self,
+        body: impl FnOnce(&mut Self) -> Result<(), RuntimeError>,
+    ) -> Result<(), RuntimeError> {
+        let outer = std::mem::take(&mut self.environment);
+        *self.environment = Environment::with_enclosing(outer);
+
+        let result = body(self);
 
         let outer = self.environment.enclosing.take().unwrap();
         self.environment = outer;
+        result
+    }
 
+    pub fn interpret(&mut self, program: &Ast) -> Result<(), RuntimeError> {
+        if self.optimize {
+            let mut program = program.clone();
+            optimizer::optimize(&mut program, self.dialect);
+            return program
+                .statements
+                .iter()
+                .try_for_each(|&stmt| self.execute(&program.arena, stmt));
+        }
+        for &statement in &program.statements {
+            self.execute(&program.arena, statement)?;
+        }
         Ok(())
     }
-}
 
-impl expr::Visitor for Interpreter {
-    type Output = Result<Value, RuntimeError>;
+    /// Counts one more executed statement against the statement limit.
+    ///
+    /// Returns a [`RuntimeError`] if the program has run out of statements.
+    pub(crate) fn spend_statement(&mut self) -> Result<(), RuntimeError> {
+        self.budget
+            .spend(Resource::Statements)
+            .map_err(RuntimeError::limit_exceeded)
+    }
 
-    /// Produces the value represented by a literal expression.
-    fn visit_literal_expr(&self, expr: &expr::Literal) -> Self::Output {
-        Ok(expr.value.clone())
+    /// Executes a single statement node.
+    ///
+    /// Returns a [`RuntimeError`] if execution of the statement fails at runtime.
+    pub fn execute(&mut self, ast: &AstArena, stmt: StmtId) -> Result<(), RuntimeError> {
+        self.spend_statement()?;
+        // a hook that is already running, evaluating an expression for the
+        // user, is not called again
+        if let Some(Hooked(hook)) = self.hook.clone()
+            && let Ok(mut hook) = hook.try_borrow_mut()
+            && hook.before_statement(self, ast, stmt).is_break()
+        {
+            return Err(RuntimeError::stopped());
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.enter_stmt(ast, stmt);
+        }
+        self.depth += 1;
+        let result = stmt.accept(ast, self);
+        self.depth -= 1;
+        if let Some(trace) = &mut self.trace {
+            trace.exit();
+        }
+        result
     }
 
-    /// Evaluates the expression inside grouping parentheses.
-    fn visit_grouping_expr(&mut self, expr: &expr::Grouping) -> Self::Output {
-        self.evaluate(&expr.expression)
+    /// Returns how many statements are running, which is 0 before a
+    /// top-level statement runs and 1 before a statement in its block.
+    pub fn depth(&self) -> usize {
+        self.depth
     }
 
-    /// Evaluates unary operators such as logical negation and numeric negation.
+    /// Returns the file being run, which changes to a module while an
+    /// `import` runs it.
+    pub fn script(&self) -> Option<&Path> {
+        self.script.as_deref()
+    }
+
+    /// Returns the variables of each scope that encloses the running
+    /// statement, innermost first and ending with the global scope, sorted
+    /// by name.
+    pub fn scopes(&self) -> Vec<Vec<(Symbol, Value)>> {
+        let mut scopes = Vec::new();
+        let mut scope = Some(&*self.environment);
+        while let Some(environment) = scope {
+            let mut variables = environment
+                .values
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect::<Vec<_>>();
+            variables.sort_by(|(a, _), (b, _)| a.cmp(b));
+            scopes.push(variables);
+            scope = environment.enclosing.as_deref();
+        }
+        scopes
+    }
+
+    /// Evaluates a single expression tree.
     ///
-    /// Returns an error when numeric negation is applied to a non-number.
-    fn visit_unary_expr(&mut self, expr: &expr::Unary) -> Self::Output {
-        let right = self.evaluate(&expr.right)?;
+    /// Returns the resulting value or a runtime error when evaluation fails.
+    pub fn evaluate(&mut self, ast: &AstArena, expr: ExprId) -> Result<Value, RuntimeError> {
+        let Some(trace) = &mut self.trace else {
+            return expr.accept(ast, self);
+        };
+        trace.enter_expr();
+        let result = expr.accept(ast, self);
+        if let Some(trace) = &mut self.trace {
+            trace.result(ast, expr, &result);
+        }
+        result
+    }
 
-        match expr.operator.typ {
+    /// Applies the unary operator `op` to an evaluated operand.
+    ///
+    /// Returns an error when numeric negation is applied to a non-number.
+    pub(crate) fn unary(&mut self, op: &Token, right: Value) -> Result<Value, RuntimeError> {
+        match op.typ {
             TokenType::Bang => Ok(right.is_truthy().not().into()),
-            TokenType::Minus => {
-                let Value::Number(n) = right else {
-                    return Err(RuntimeError::new(
-                        expr.operator.clone(),
-                        "Operand must be a number.",
-                    ));
-                };
-
-                let value = -n;
-                Ok(value.into())
-            }
+            TokenType::Minus => match right {
+                Value::Number(n) => self.check_arithmetic(op, &[n], -n),
+                Value::Integer(n) => {
+                    Ok(n.checked_neg().map_or((-(n as f64)).into(), Value::Integer))
+                }
+                _ => Err(RuntimeError::new(op.clone(), "Operand must be a number.")),
+            },
             _ => panic!(
                 "Unexpected token type for unary expression, found {:?}",
-                expr.operator.typ
+                op.typ
             ),
         }
     }
 
-    fn visit_variable_expr(&self, expr: &expr::Variable) -> Self::Output {
-        self.environment.get(&expr.name)
-    }
-
-    fn visit_assign_expr(&mut self, expr: &expr::Assign) -> Self::Output {
-        let value = self.evaluate(&expr.value)?;
-        self.environment.assign(&expr.name, value.clone())?;
-        Ok(value)
-    }
-
-    /// Evaluates binary operators including arithmetic, comparison, and equality.
+    /// Applies the binary operator `op` to evaluated operands.
     ///
     /// Returns an error for invalid operand types or invalid numeric operations.
-    fn visit_binary_expr(&mut self, expr: &Binary) -> Self::Output {
-        let left = self.evaluate(&expr.left)?;
-        let right = self.evaluate(&expr.right)?;
-        let op = expr.operator.clone();
+    pub(crate) fn binary(
+        &mut self,
+        op: &Token,
+        left: Value,
+        right: Value,
+    ) -> Result<Value, RuntimeError> {
+        if let Some(func) = self.operators.get(op.typ, &left, &right) {
+            return func(&left, &right).map_err(|message| RuntimeError::new(op.clone(), message));
+        }
+
+        if let (Value::Integer(a), Value::Integer(b)) = (&left, &right)
+            && let Some(value) = integer_binary(op.typ, *a, *b)
+        {
+            return Ok(value);
+        }
 
         match op.typ {
-            TokenType::BangEqual => Ok((left != right).into()),
-            TokenType::EqualEqual => Ok((left == right).into()),
+            TokenType::BangEqual => Ok((!left.is_equal(&right)).into()),
+            TokenType::EqualEqual => Ok(left.is_equal(&right).into()),
             TokenType::Minus => {
                 let (a, b) = check_number_operands(left, right, op)?;
-                Ok((a - b).into())
-            }
-            TokenType::Star => {
-                let (a, b) = check_number_operands(left, right, op)?;
-                Ok((a * b).into())
+                self.check_arithmetic(op, &[a, b], a - b)
             }
+            TokenType::Star => match (left, right) {
+                (Value::String(s), count) if self.dialect.allows(Extension::StringRepetition) => {
+                    repeat_string(&s, count, op)
+                }
+                (left, right) => {
+                    let (a, b) = check_number_operands(left, right, op)?;
+                    self.check_arithmetic(op, &[a, b], a * b)
+                }
+            },
             TokenType::Slash => {
                 let (a, b) = check_number_operands(left, right, op)?;
                 if b == 0f64 {
-                    return Err(RuntimeError::new(expr.operator.clone(), "Division by 0"));
+                    return Err(RuntimeError::new(op.clone(), "Division by 0"));
                 }
-                Ok((a / b).into())
+                self.check_arithmetic(op, &[a, b], a / b)
             }
             TokenType::Greater => {
                 let (a, b) = check_number_operands(left, right, op)?;
@@ -203,20 +897,364 @@ impl expr::Visitor for Interpreter {
                 let (a, b) = check_number_operands(left, right, op)?;
                 Ok((a <= b).into())
             }
+            TokenType::Ampersand => {
+                let (a, b) = check_integer_operands(left, right, op)?;
+                Ok(self.integral(a & b))
+            }
+            TokenType::Pipe => {
+                let (a, b) = check_integer_operands(left, right, op)?;
+                Ok(self.integral(a | b))
+            }
+            TokenType::Caret => {
+                let (a, b) = check_integer_operands(left, right, op)?;
+                Ok(self.integral(a ^ b))
+            }
+            TokenType::LessLess => {
+                let (a, b) = check_integer_operands(left, right, op)?;
+                let b = check_shift_amount(b, op)?;
+                Ok(self.integral(a << b))
+            }
+            TokenType::GreaterGreater => {
+                let (a, b) = check_integer_operands(left, right, op)?;
+                let b = check_shift_amount(b, op)?;
+                Ok(self.integral(a >> b))
+            }
+            TokenType::DotDot | TokenType::DotDotEqual => {
+                let (a, b) = check_integer_operands(left, right, op)?;
+                Ok(Range::new(a, b, op.typ == TokenType::DotDotEqual).into())
+            }
             TokenType::Plus => match (left, right) {
-                (Value::Number(a), Value::Number(b)) => Ok((a + b).into()),
                 (Value::String(a), Value::String(b)) => Ok(format!("{a}{b}").into()),
-                _ => Err(RuntimeError::new(
-                    expr.operator.clone(),
-                    "Operands must be numbers.",
-                )),
+                (left, right) => {
+                    let (a, b) = check_number_operands(left, right, op)?;
+                    self.check_arithmetic(op, &[a, b], a + b)
+                }
             },
             _ => panic!(
                 "Unexpected token type for binary expression, found {:?}",
-                expr.operator.typ
+                op.typ
             ),
         }
     }
+
+    /// Calls `callee` with evaluated arguments, reporting errors at `paren`.
+    ///
+    /// Returns an error when the callee is not callable, the number of
+    /// arguments does not match its arity, or the function itself fails.
+    pub(crate) fn call(
+        &mut self,
+        callee: Value,
+        arguments: &[Value],
+        paren: &Token,
+    ) -> Result<Value, RuntimeError> {
+        let Value::NativeFunction(native) = callee else {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                "Can only call functions and classes.",
+            ));
+        };
+
+        if arguments.len() != native.arity {
+            let message = format!(
+                "Expected {} arguments but got {}.",
+                native.arity,
+                arguments.len()
+            );
+            return Err(RuntimeError::new(paren.clone(), message));
+        }
+
+        self.budget
+            .spend(Resource::Calls)
+            .map_err(RuntimeError::limit_exceeded)?;
+        (native.func)(self, arguments).map_err(|message| RuntimeError::new(paren.clone(), message))
+    }
+}
+
+impl stmt::Visitor for Interpreter {
+    type Output = Result<(), RuntimeError>;
+
+    fn visit_print_stmt(&mut self, ast: &AstArena, stmt: &stmt::Print) -> Self::Output {
+        let value = self.evaluate(ast, stmt.expr)?;
+        self.print(&value);
+        Ok(())
+    }
+
+    fn visit_expression_stmt(&mut self, ast: &AstArena, stmt: &stmt::Expression) -> Self::Output {
+        self.evaluate(ast, stmt.expr)?;
+        Ok(())
+    }
+
+    fn visit_var_stmt(&mut self, ast: &AstArena, stmt: &stmt::Var) -> Self::Output {
+        let value = stmt
+            .initializer
+            .map(|e| self.evaluate(ast, e))
+            .transpose()?
+            .unwrap_or(Value::Nil);
+
+        self.declare(&stmt.name, value, stmt.constant)
+    }
+
+    fn visit_var_unpack_stmt(&mut self, ast: &AstArena, stmt: &stmt::VarUnpack) -> Self::Output {
+        let value = self.evaluate(ast, stmt.value)?;
+        let values = unpack(&value, stmt.names.len(), &stmt.paren)?;
+        for (name, value) in stmt.names.iter().zip(values) {
+            self.declare(name, value, false)?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_block_stmt(&mut self, ast: &AstArena, stmt: &stmt::Block) -> Self::Output {
+        self.in_new_scope(|this| {
+            stmt.statements
+                .iter()
+                .try_for_each(|&stmt| this.execute(ast, stmt))
+        })
+    }
+
+    /// Runs the try block, then the catch block if the try block raised a
+    /// catchable error, then the finally block.
+    ///
+    /// An error from the finally block replaces any error from the others.
+    fn visit_try_stmt(&mut self, ast: &AstArena, stmt: &stmt::Try) -> Self::Output {
+        let result = match (self.execute(ast, stmt.body), &stmt.catch) {
+            (Err(err), Some((name, body))) => match err.into_catchable() {
+                Ok(value) => self.in_new_scope(|this| {
+                    this.declare(name, value, false)?;
+                    this.execute(ast, *body)
+                }),
+                Err(err) => Err(err),
+            },
+            (result, _) => result,
+        };
+
+        match &stmt.finally {
+            Some(finally) => self.execute(ast, *finally).and(result),
+            None => result,
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, ast: &AstArena, stmt: &stmt::Throw) -> Self::Output {
+        let value = self.evaluate(ast, stmt.value)?;
+        Err(RuntimeError::thrown(&stmt.keyword, value))
+    }
+
+    /// Runs the loop body, then repeats it while the condition is truthy.
+    fn visit_do_while_stmt(&mut self, ast: &AstArena, stmt: &stmt::DoWhile) -> Self::Output {
+        loop {
+            self.execute(ast, stmt.body)?;
+            if !self.evaluate(ast, stmt.condition)?.is_truthy() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn visit_match_stmt(&mut self, ast: &AstArena, stmt: &stmt::Match) -> Self::Output {
+        let value = self.evaluate(ast, stmt.value)?;
+        let arm = stmt
+            .arms
+            .iter()
+            .find(|(patterns, _)| patterns.iter().any(|pattern| pattern.is_equal(&value)));
+        match (arm, &stmt.otherwise) {
+            (Some((_, body)), _) => self.execute(ast, *body),
+            (None, Some(body)) => self.execute(ast, *body),
+            (None, None) => Ok(()),
+        }
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &stmt::Import) -> Self::Output {
+        self.import(stmt)
+    }
+
+    fn visit_assert_stmt(&mut self, ast: &AstArena, stmt: &stmt::Assert) -> Self::Output {
+        if self.evaluate(ast, stmt.condition)?.is_truthy() {
+            return Ok(());
+        }
+
+        let mut message = format!("Assertion failed: {}", stmt.source);
+        if let Some(detail) = &stmt.message {
+            message = format!("{message}: {}", self.evaluate(ast, *detail)?);
+        }
+        Err(RuntimeError::new(stmt.keyword.clone(), message))
+    }
+
+    /// Runs the loop body once per item of the iterable.
+    ///
+    /// Returns an error when the iterable cannot be iterated or the body fails.
+    fn visit_for_in_stmt(&mut self, ast: &AstArena, stmt: &stmt::ForIn) -> Self::Output {
+        let iterable = self.evaluate(ast, stmt.iterable)?;
+        let integers = self.dialect.allows(Extension::Integers);
+        let iter = LoxIter::new(iterable, integers)
+            .map_err(|message| RuntimeError::new(stmt.keyword.clone(), message))?;
+
+        for value in iter {
+            // every iteration binds the loop variable in a scope of its own
+            self.in_new_scope(|this| {
+                this.declare(&stmt.name, value, false)?;
+                this.execute(ast, stmt.body)
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl expr::Visitor for Interpreter {
+    type Output = Result<Value, RuntimeError>;
+
+    /// Produces the value represented by a literal expression.
+    fn visit_literal_expr(&mut self, expr: &expr::Literal) -> Self::Output {
+        Ok(expr.value.clone())
+    }
+
+    /// Evaluates the expression inside grouping parentheses.
+    fn visit_grouping_expr(&mut self, ast: &AstArena, expr: &expr::Grouping) -> Self::Output {
+        self.evaluate(ast, expr.expression)
+    }
+
+    /// Evaluates unary operators such as logical negation and numeric negation.
+    ///
+    /// Returns an error when numeric negation is applied to a non-number.
+    fn visit_unary_expr(&mut self, ast: &AstArena, expr: &expr::Unary) -> Self::Output {
+        let right = self.evaluate(ast, expr.right)?;
+        self.unary(&expr.operator, right)
+    }
+
+    fn visit_variable_expr(&mut self, expr: &expr::Variable) -> Self::Output {
+        self.environment.get(&expr.name)
+    }
+
+    fn visit_assign_expr(&mut self, ast: &AstArena, expr: &expr::Assign) -> Self::Output {
+        let value = self.evaluate(ast, expr.value)?;
+        self.environment.assign(&expr.name, value.clone())?;
+        Ok(value)
+    }
+
+    /// Assigns each element of the list on the right to the variable in the
+    /// same position, and produces the list.
+    ///
+    /// Returns an error when the value is not a list of as many elements as
+    /// there are variables.
+    fn visit_assign_unpack_expr(&mut self, ast: &AstArena, expr: &AssignUnpack) -> Self::Output {
+        let value = self.evaluate(ast, expr.value)?;
+        let values = unpack(&value, expr.names.len(), &expr.paren)?;
+        for (name, value) in expr.names.iter().zip(values) {
+            self.environment.assign(name, value)?;
+        }
+        Ok(value)
+    }
+
+    /// Calls a function with its evaluated arguments.
+    ///
+    /// Returns an error when the callee is not callable, the number of
+    /// arguments does not match its arity, or the function itself fails.
+    fn visit_call_expr(&mut self, ast: &AstArena, expr: &Call) -> Self::Output {
+        let callee = self.evaluate(ast, expr.callee)?;
+        let arguments = self.evaluate_elements(ast, &expr.arguments)?;
+        self.call(callee, &arguments, &expr.paren)
+    }
+
+    /// Creates a new list from the evaluated elements.
+    fn visit_list_expr(&mut self, ast: &AstArena, expr: &List) -> Self::Output {
+        let elements = self.evaluate_elements(ast, &expr.elements)?;
+        Ok(elements.into())
+    }
+
+    /// Evaluates the elements of a parenthesized tuple into a new list.
+    fn visit_tuple_expr(&mut self, ast: &AstArena, expr: &expr::Tuple) -> Self::Output {
+        let elements = self.evaluate_elements(ast, &expr.elements)?;
+        Ok(elements.into())
+    }
+
+    /// Spreads are expanded by the enclosing call or list literal, so reaching
+    /// one here means it appeared anywhere else.
+    fn visit_spread_expr(&mut self, _ast: &AstArena, expr: &Spread) -> Self::Output {
+        Err(RuntimeError::new(
+            expr.ellipsis.clone(),
+            "Can only spread into calls and list literals.",
+        ))
+    }
+
+    /// Creates a new map from the evaluated entries, in order.
+    ///
+    /// Returns an error when a key is not a string, number or boolean.
+    fn visit_map_expr(&mut self, ast: &AstArena, expr: &expr::Map) -> Self::Output {
+        let mut entries = Map::with_capacity_and_hasher(expr.entries.len(), Default::default());
+        for &(key, value) in &expr.entries {
+            let key = check_map_key(self.evaluate(ast, key)?, &expr.brace)?;
+            let value = self.evaluate(ast, value)?;
+            entries.insert(key, value);
+        }
+        Ok(entries.into())
+    }
+
+    /// Reads the element of a list at an index, or the value of a map at a key.
+    ///
+    /// Returns an error when the object is neither a list nor a map, the index
+    /// is invalid, or the key is missing.
+    fn visit_index_expr(&mut self, ast: &AstArena, expr: &Index) -> Self::Output {
+        let object = self.evaluate(ast, expr.object)?;
+        let index = self.evaluate(ast, expr.index)?;
+
+        if let Value::Map(map) = &object {
+            let key = check_map_key(index, &expr.bracket)?;
+            return map.borrow().get(&key).cloned().ok_or_else(|| {
+                RuntimeError::new(expr.bracket.clone(), format!("Undefined key '{key}'."))
+            });
+        }
+
+        let (list, i) = check_list_index(&object, &index, &expr.bracket)?;
+        Ok(list.borrow()[i].clone())
+    }
+
+    /// Replaces the element of a list at an index, or sets the value of a map
+    /// at a key, and produces the new value.
+    ///
+    /// Returns an error when the object is neither a list nor a map, or the
+    /// index is invalid.
+    fn visit_set_index_expr(&mut self, ast: &AstArena, expr: &SetIndex) -> Self::Output {
+        let object = self.evaluate(ast, expr.object)?;
+        let index = self.evaluate(ast, expr.index)?;
+        let value = self.evaluate(ast, expr.value)?;
+
+        if let Value::Map(map) = &object {
+            let key = check_map_key(index, &expr.bracket)?;
+            map.borrow_mut().insert(key, value.clone());
+            return Ok(value);
+        }
+
+        let (list, i) = check_list_index(&object, &index, &expr.bracket)?;
+        list.borrow_mut()[i] = value.clone();
+        Ok(value)
+    }
+
+    /// Produces the left operand unless it is nil, in which case the right
+    /// operand is evaluated and produced instead.
+    fn visit_coalesce_expr(&mut self, ast: &AstArena, expr: &Coalesce) -> Self::Output {
+        match self.evaluate(ast, expr.left)? {
+            Value::Nil => self.evaluate(ast, expr.right),
+            value => Ok(value),
+        }
+    }
+
+    /// Produces the kept value of a cached expression, evaluating it the
+    /// first time. Errors are not kept, so a failing expression fails again.
+    fn visit_cached_expr(&mut self, ast: &AstArena, expr: &expr::Cached) -> Self::Output {
+        if let Some(value) = expr.value.get() {
+            return Ok(value.clone());
+        }
+        let value = self.evaluate(ast, expr.expr)?;
+        Ok(expr.value.get_or_init(|| value).clone())
+    }
+
+    /// Evaluates binary operators including arithmetic, comparison, and equality.
+    ///
+    /// Returns an error for invalid operand types or invalid numeric operations.
+    fn visit_binary_expr(&mut self, ast: &AstArena, expr: &Binary) -> Self::Output {
+        let left = self.evaluate(ast, expr.left)?;
+        let right = self.evaluate(ast, expr.right)?;
+        self.binary(&expr.operator, left, right)
+    }
 }
 
 #[cfg(test)]
@@ -228,7 +1266,25 @@ mod tests {
     use crate::scanner::{ScanItem, Scanner};
 
     fn eval_expr(input: &str) -> Result<Value, RuntimeError> {
+        eval_expr_with(input, Dialect::default())
+    }
+
+    fn eval_expr_with(input: &str, dialect: Dialect) -> Result<Value, RuntimeError> {
+        let (ast, expr) = parse_expr(input, dialect);
+        let mut interpreter = Interpreter::new().with_dialect(dialect);
+        interpreter.evaluate(&ast, expr)
+    }
+
+    fn eval_expr_checked(input: &str, check: ArithmeticCheck) -> Result<Value, RuntimeError> {
+        // Exponent literals make it easy to reach the edges of `f64`.
+        let (ast, expr) = parse_expr(input, Dialect::new().with(Extension::NumericLiterals));
+        let mut interpreter = Interpreter::new().with_arithmetic_check(check);
+        interpreter.evaluate(&ast, expr)
+    }
+
+    fn parse_expr(input: &str, dialect: Dialect) -> (AstArena, ExprId) {
         let tokens = Scanner::new(input)
+            .with_dialect(dialect)
             .scan_tokens()
             .filter_map(|r| match r {
                 Ok(ScanItem::Token(tkn)) => Some(tkn),
@@ -237,16 +1293,27 @@ mod tests {
             })
             .collect::<Vec<_>>();
 
-        let mut parser = Parser::from(tokens);
-        let expr = parser
+        let mut parser = Parser::from(tokens).with_dialect(dialect);
+        parser
             .parse_expression()
-            .expect("Expected a valid expression");
-        let mut interpreter = Interpreter::new();
-        interpreter.evaluate(&expr)
+            .expect("Expected a valid expression")
     }
 
     fn interpret_program(input: &str) -> Result<(), RuntimeError> {
+        interpret_program_with(input, Dialect::default()).map(|_| ())
+    }
+
+    /// Runs `input` and returns the interpreter, so tests can inspect globals.
+    fn interpret_program_with(input: &str, dialect: Dialect) -> Result<Interpreter, RuntimeError> {
+        let program = parse_program(input, dialect);
+        let mut interpreter = Interpreter::new().with_dialect(dialect);
+        interpreter.interpret(&program)?;
+        Ok(interpreter)
+    }
+
+    fn parse_program(input: &str, dialect: Dialect) -> Ast {
         let tokens = Scanner::new(input)
+            .with_dialect(dialect)
             .scan_tokens()
             .filter_map(|r| match r {
                 Ok(ScanItem::Token(tkn)) => Some(tkn),
@@ -255,10 +1322,8 @@ mod tests {
             })
             .collect::<Vec<_>>();
 
-        let mut parser = Parser::from(tokens);
-        let program = parser.parse().expect("Expected a valid program");
-        let mut interpreter = Interpreter::new();
-        interpreter.interpret(&program)
+        let mut parser = Parser::from(tokens).with_dialect(dialect);
+        parser.parse().expect("Expected a valid program")
     }
 
     #[rstest]
@@ -351,6 +1416,163 @@ mod tests {
         assert_eq!(expected_output, output);
     }
 
+    #[rstest]
+    #[case(r#""ab" * 3"#, Value::String("ababab".to_string()))]
+    #[case(r#""ab" * 0"#, Value::String("".to_string()))]
+    #[case(r#"("a" + "b") * (1 + 1)"#, Value::String("abab".to_string()))]
+    fn test_interpreter_string_repetition(#[case] input: &str, #[case] expected_output: Value) {
+        let dialect = Dialect::new().with(Extension::StringRepetition);
+        let output = eval_expr_with(input, dialect).expect("Expected evaluation to succeed");
+        assert_eq!(expected_output, output);
+    }
+
+    #[rstest]
+    #[case("1e308 * 10", "Arithmetic overflowed to infinity.\n[line 1]")]
+    #[case("-0", "Arithmetic produced negative zero.\n[line 1]")]
+    #[case("0 * -1", "Arithmetic produced negative zero.\n[line 1]")]
+    #[case(
+        "(1e308 * 10) - (1e308 * 10)",
+        "Arithmetic overflowed to infinity.\n[line 1]"
+    )]
+    fn test_interpreter_arithmetic_check_errors(#[case] input: &str, #[case] expected: &str) {
+        let err = eval_expr_checked(input, ArithmeticCheck::Error)
+            .expect_err("Expected evaluation to fail");
+        assert_eq!(expected, err.to_string());
+    }
+
+    #[rstest]
+    #[case("1e308 * 10", Value::Number(f64::INFINITY))]
+    #[case("0 * -1", Value::Number(-0.0))]
+    #[case("1 / 3 - 1 / 3", Value::Number(0.0))]
+    fn test_interpreter_arithmetic_check_keeps_results(
+        #[case] input: &str,
+        #[case] expected_output: Value,
+        #[values(ArithmeticCheck::Off, ArithmeticCheck::Warn)] check: ArithmeticCheck,
+    ) {
+        let output = eval_expr_checked(input, check).expect("Expected evaluation to succeed");
+        assert_eq!(expected_output, output);
+    }
+
+    #[rstest]
+    #[case(r#""ab" * -1"#)]
+    #[case(r#""ab" * 1.5"#)]
+    fn test_interpreter_runtime_errors_string_repetition(#[case] input: &str) {
+        let dialect = Dialect::new().with(Extension::StringRepetition);
+        let err = eval_expr_with(input, dialect).expect_err("Expected evaluation to fail");
+        assert_eq!(
+            "Repeat count must be a non-negative integer.\n[line 1]",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    #[case(r#""ab" * 100000000000000000000"#)]
+    #[case(r#""ab" * 1073741824"#)]
+    fn test_interpreter_string_repetition_too_long(#[case] input: &str) {
+        let dialect = Dialect::new().with(Extension::StringRepetition);
+        let err = eval_expr_with(input, dialect).expect_err("Expected evaluation to fail");
+        assert_eq!(
+            "Repeated string would be too long.\n[line 1]",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("12 & 10", Value::Number(8.0))]
+    #[case("12 | 3", Value::Number(15.0))]
+    #[case("6 ^ 3", Value::Number(5.0))]
+    #[case("1 << 10", Value::Number(1024.0))]
+    #[case("-16 >> 2", Value::Number(-4.0))]
+    #[case("1 | 2 & 3 << 1", Value::Number(3.0))]
+    #[case("1 + 1 << 2", Value::Number(8.0))]
+    #[case("(5 & 4) == 4", Value::Boolean(true))]
+    fn test_interpreter_bitwise_operators(#[case] input: &str, #[case] expected_output: Value) {
+        let dialect = Dialect::new().with(Extension::Bitwise);
+        let output = eval_expr_with(input, dialect).expect("Expected evaluation to succeed");
+        assert_eq!(expected_output, output);
+    }
+
+    #[rstest]
+    #[case("1.5 & 1", "Operands must be integers.\n[line 1]")]
+    #[case(r#""a" | 1"#, "Operands must be integers.\n[line 1]")]
+    #[case("1 << 64", "Shift amount must be between 0 and 63.\n[line 1]")]
+    #[case("1 >> -1", "Shift amount must be between 0 and 63.\n[line 1]")]
+    fn test_interpreter_runtime_errors_bitwise_operators(
+        #[case] input: &str,
+        #[case] expected_error: &str,
+    ) {
+        let dialect = Dialect::new().with(Extension::Bitwise);
+        let err = eval_expr_with(input, dialect).expect_err("Expected evaluation to fail");
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case("2 * 3", Value::Integer(6))]
+    #[case("7 - 10", Value::Integer(-3))]
+    #[case("-(5)", Value::Integer(-5))]
+    #[case("6 / 2", Value::Number(3.0))]
+    #[case("7 / 2", Value::Number(3.5))]
+    #[case("1 + 0.5", Value::Number(1.5))]
+    #[case("9223372036854775807 + 1", Value::Number(9223372036854775808.0))]
+    #[case("-9223372036854775807 - 2", Value::Number(-9223372036854775809.0))]
+    #[case("1 == 1.0", Value::Boolean(true))]
+    #[case("1 != 1.5", Value::Boolean(true))]
+    #[case("2 > 1.5", Value::Boolean(true))]
+    #[case("9007199254740993 > 9007199254740992", Value::Boolean(true))]
+    fn test_interpreter_integers(#[case] input: &str, #[case] expected_output: Value) {
+        let dialect = Dialect::new().with(Extension::Integers);
+        let output = eval_expr_with(input, dialect).expect("Expected evaluation to succeed");
+        assert_eq!(expected_output, output);
+    }
+
+    #[test]
+    fn test_interpreter_integers_with_bitwise() {
+        let dialect = Dialect::new()
+            .with(Extension::Integers)
+            .with(Extension::Bitwise);
+        let output = eval_expr_with("1 << 4 | 3", dialect).expect("Expected evaluation to succeed");
+        assert_eq!(Value::Integer(19), output);
+    }
+
+    #[rstest]
+    #[case(r#""x = ${1 + 2}!""#, Value::String("x = 3!".to_string()))]
+    #[case(r#""${true} and ${nil}""#, Value::String("true and nil".to_string()))]
+    #[case(r#""<${"in" + "ner"}>""#, Value::String("<inner>".to_string()))]
+    #[case(r#""${"a${1}b"}c""#, Value::String("a1bc".to_string()))]
+    #[case(r#""no interpolation""#, Value::String("no interpolation".to_string()))]
+    fn test_interpreter_interpolation(#[case] input: &str, #[case] expected_output: Value) {
+        let dialect = Dialect::new().with(Extension::Interpolation);
+        let output = eval_expr_with(input, dialect).expect("Expected evaluation to succeed");
+        assert_eq!(expected_output, output);
+    }
+
+    #[rstest]
+    #[case(r#"1 + "a""#)]
+    #[case(r#""a" + 1"#)]
+    fn test_interpreter_interpolation_keeps_plus(#[case] input: &str) {
+        let dialect = Dialect::new().with(Extension::Interpolation);
+        let err = eval_expr_with(input, dialect).expect_err("Expected evaluation to fail");
+        assert_eq!("Operands must be numbers.\n[line 1]", err.to_string());
+    }
+
+    #[test]
+    fn test_interpreter_interpolation_ignores_shadowed_str() {
+        let program = r#"var str = nil; var out = "<${1}>";"#;
+        let dialect = Dialect::new().with(Extension::Interpolation);
+        let interpreter = interpret_program_with(program, dialect).expect("Expected success");
+        let out = Token::new(TokenType::Identifier, "out".into(), None, 1);
+        assert_eq!(
+            "<1>",
+            interpreter.environment.get(&out).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn test_interpreter_string_repetition_disabled_by_default() {
+        let err = eval_expr(r#""ab" * 3"#).expect_err("Expected evaluation to fail");
+        assert_eq!("Operands must be numbers.\n[line 1]", err.to_string());
+    }
+
     #[rstest]
     #[case(r#""bar" != "world""#, Value::Boolean(true))]
     #[case(r#""bar" == "bar""#, Value::Boolean(true))]
@@ -411,6 +1633,720 @@ mod tests {
         assert_eq!("Operands must be numbers.\n[line 1]", err.to_string());
     }
 
+    #[rstest]
+    #[case("[]", "[]")]
+    #[case("[1, \"two\", [true, nil]]", "[1, two, [true, nil]]")]
+    #[case("[10, 20, 30][1]", "20")]
+    #[case("[10, 20, 30][2.0]", "30")]
+    #[case("[[1, 2], [3]][0][1]", "2")]
+    #[case("[1] == [1]", "false")]
+    fn test_interpreter_lists(#[case] input: &str, #[case] expected_output: &str) {
+        let dialect = Dialect::new().with(Extension::Lists);
+        let output = eval_expr_with(input, dialect).expect("Expected evaluation to succeed");
+        assert_eq!(expected_output, output.to_string());
+    }
+
+    #[rstest]
+    #[case("[1, 2][2]", "List index 2 is out of bounds for length 2.\n[line 1]")]
+    #[case("[1, 2][-1]", "List index -1 is out of bounds for length 2.\n[line 1]")]
+    #[case("[][0] = 1", "List index 0 is out of bounds for length 0.\n[line 1]")]
+    #[case("[1, 2][0.5]", "List index must be an integer.\n[line 1]")]
+    #[case("[1, 2][\"0\"]", "List index must be an integer.\n[line 1]")]
+    #[case("\"abc\"[0]", "Only lists and maps can be indexed.\n[line 1]")]
+    #[case("nil[0] = 1", "Only lists and maps can be indexed.\n[line 1]")]
+    fn test_interpreter_runtime_errors_lists(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::Lists);
+        let err = eval_expr_with(input, dialect).expect_err("Expected evaluation to fail");
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case("{}", "{}")]
+    #[case("{\"a\": 1, 2: [true], false: {}}", "{a: 1, 2: [true], false: {}}")]
+    #[case("{\"a\": 1, \"a\": 2}", "{a: 2}")]
+    #[case("{\"a\": 1, \"b\": 2}[\"b\"]", "2")]
+    #[case("{1: \"one\"}[1.0]", "one")]
+    #[case("{0: \"zero\"}[-0]", "zero")]
+    #[case("{} == {}", "false")]
+    fn test_interpreter_maps(#[case] input: &str, #[case] expected_output: &str) {
+        let dialect = Dialect::new().with(Extension::Lists).with(Extension::Maps);
+        let output = eval_expr_with(input, dialect).expect("Expected evaluation to succeed");
+        assert_eq!(expected_output, output.to_string());
+    }
+
+    #[rstest]
+    #[case("{}[\"k\"]", "Undefined key 'k'.\n[line 1]")]
+    #[case("{nil: 1}", "Map keys must be strings, numbers or booleans.\n[line 1]")]
+    #[case(
+        "{}[[]] = 1",
+        "Map keys must be strings, numbers or booleans.\n[line 1]"
+    )]
+    fn test_interpreter_runtime_errors_maps(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::Lists).with(Extension::Maps);
+        let err = eval_expr_with(input, dialect).expect_err("Expected evaluation to fail");
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[test]
+    fn test_interpreter_maps_without_lists() {
+        let dialect = Dialect::new().with(Extension::Maps);
+        let output = eval_expr_with("{\"a\": {\"b\": 2}}[\"a\"][\"b\"]", dialect)
+            .expect("Expected evaluation to succeed");
+        assert_eq!("2", output.to_string());
+    }
+
+    #[rstest]
+    #[case(
+        "var out = \"\"; for (x in [\"a\", 1, nil]) out = \"${out}${x}\";",
+        "a1nil"
+    )]
+    #[case("var out = \"\"; for (k in {\"a\": 1, \"b\": 2}) out = out + k;", "ab")]
+    #[case("var out = \"\"; for (c in \"héllo\") out = c + out;", "olléh")]
+    #[case("var out = 0; for (var i in 5) out = out + i;", "10")]
+    #[case("var out = 0; for (i in -1) out = out + 1;", "0")]
+    #[case(
+        "var out = 0; for (i in [1, 2]) for (j in [10, 20]) out = out + i * j;",
+        "90"
+    )]
+    fn test_interpreter_for_in(#[case] program: &str, #[case] expected: &str) {
+        let dialect = Dialect::new()
+            .with(Extension::ForIn)
+            .with(Extension::Lists)
+            .with(Extension::Maps)
+            .with(Extension::Interpolation);
+        let interpreter = interpret_program_with(program, dialect).expect("Expected success");
+        let out = Token::new(TokenType::Identifier, "out".into(), None, 1);
+        assert_eq!(
+            expected,
+            interpreter.environment.get(&out).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn test_interpreter_for_in_sees_updates_from_body() {
+        // the last iteration reads the element written by the previous ones
+        let program = "var xs = [1, 2, 3]; for (x in xs) xs[2] = x + 10;";
+        let dialect = Dialect::new().with(Extension::ForIn).with(Extension::Lists);
+        let interpreter = interpret_program_with(program, dialect).expect("Expected success");
+        let xs = Token::new(TokenType::Identifier, "xs".into(), None, 1);
+        assert_eq!(
+            "[1, 2, 22]",
+            interpreter.environment.get(&xs).unwrap().to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("var n = 0; for (i in 0..4) n = n + i * (2 + 3);", "30")]
+    #[case("var n = 0; do n = n + (1 + 1); while (n < 2 * 3);", "6")]
+    #[case(
+        "var n = 0; for (i in 0..3) try { n = n + (1 / 0); } catch (e) { n = n + 1; }",
+        "3"
+    )]
+    fn test_interpreter_hoisted_program(#[case] program: &str, #[case] expected: &str) {
+        let dialect = Dialect::new()
+            .with(Extension::ForIn)
+            .with(Extension::Ranges)
+            .with(Extension::Integers)
+            .with(Extension::DoWhile)
+            .with(Extension::Exceptions);
+        let mut program = parse_program(program, dialect);
+        assert!(!crate::hoist::hoist(&mut program).is_empty());
+
+        // each loop reaches its cached expression more than once
+        let mut interpreter = Interpreter::new().with_dialect(dialect);
+        interpreter.interpret(&program).expect("Expected success");
+        let n = Token::new(TokenType::Identifier, "n".into(), None, 1);
+        assert_eq!(
+            expected,
+            interpreter.environment.get(&n).unwrap().to_string()
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "for (x in nil) print x;",
+        "Can only iterate over lists, maps, strings, ranges and numbers.\n[line 1]"
+    )]
+    #[case(
+        "for (x in 1.5) print x;",
+        "Can only iterate up to a whole number.\n[line 1]"
+    )]
+    #[case("for (x in 2) {} print x;", "Undefined variable 'x'.\n[line 1]")]
+    fn test_interpreter_runtime_errors_for_in(#[case] program: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::ForIn);
+        let err = interpret_program_with(program, dialect).expect_err("Expected a runtime error");
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case(
+        r#"var log = ""; try { log = log + "a"; 1 / 0; log = log + "b"; }
+        catch (e) { log = log + errorMessage(e); } finally { log = log + "!"; }"#,
+        "aDivision by 0!"
+    )]
+    #[case(
+        r#"var log = ""; try { throw "boom"; } catch (e) { log = e; }"#,
+        "boom"
+    )]
+    #[case(
+        r#"var log = ""; try { throw [1]; } catch (e) { log = type(e); }"#,
+        "list"
+    )]
+    #[case(
+        "var log = \"\";\ntry {\n  nil + 1;\n} catch (e) { log = str(errorLine(e)); }",
+        "3"
+    )]
+    #[case(
+        r#"var log = ""; try { try { throw "x"; } finally { log = log + "f"; } }
+        catch (e) { log = log + e; }"#,
+        "fx"
+    )]
+    #[case(
+        r#"var log = "outer"; try { var log = "inner"; { var log = "deeper"; throw nil; } }
+        catch (e) {}"#,
+        "outer"
+    )]
+    #[case(r#"var log = "kept"; try { } catch (e) { log = "caught"; }"#, "kept")]
+    fn test_interpreter_exceptions(#[case] program: &str, #[case] expected: &str) {
+        let dialect = [Extension::Exceptions, Extension::Lists]
+            .into_iter()
+            .collect();
+        let interpreter =
+            interpret_program_with(program, dialect).expect("Expected program to run");
+        let log = Token::new(TokenType::Identifier, "log".into(), None, 1);
+        assert_eq!(
+            expected,
+            interpreter.environment.get(&log).unwrap().to_string()
+        );
+    }
+
+    #[rstest]
+    #[case(r#"throw "boom";"#, "boom\n[line 1]")]
+    #[case("try { throw 1; } catch (e) { throw e + 1; }", "2\n[line 1]")]
+    #[case(
+        "try {\n  1 / 0;\n} catch (e) {\n  throw e;\n}",
+        "Division by 0\n[line 2]"
+    )]
+    #[case(r#"try { throw "a"; } finally { throw "b"; }"#, "b\n[line 1]")]
+    #[case(
+        "try { throw 1; } catch (e) {} print e;",
+        "Undefined variable 'e'.\n[line 1]"
+    )]
+    #[case("errorMessage(1);", "Argument must be an error.\n[line 1]")]
+    fn test_interpreter_runtime_errors_exceptions(
+        #[case] program: &str,
+        #[case] expected_error: &str,
+    ) {
+        let dialect = Dialect::new().with(Extension::Exceptions);
+        let err = interpret_program_with(program, dialect).expect_err("Expected a runtime error");
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case("2", "two")]
+    #[case("3", "three or four")]
+    #[case("4.0", "three or four")]
+    #[case("-1", "negative one")]
+    #[case("\"a\"", "letter")]
+    #[case("nil", "nothing")]
+    #[case("false", "other")]
+    fn test_interpreter_match(#[case] value: &str, #[case] expected: &str) {
+        let program = format!(
+            r#"var log = "";
+            match ({value}) {{
+                2 => log = "two";
+                3, 4 => {{ log = "three or four"; }}
+                -1 => log = "negative one";
+                "a" => log = "letter";
+                nil => log = "nothing";
+                else => log = "other";
+            }}"#
+        );
+        let dialect = [Extension::Match, Extension::Integers]
+            .into_iter()
+            .collect();
+        let interpreter =
+            interpret_program_with(&program, dialect).expect("Expected program to run");
+        let log = Token::new(TokenType::Identifier, "log".into(), None, 1);
+        assert_eq!(
+            expected,
+            interpreter.environment.get(&log).unwrap().to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("var i = 0; do i = i + 1; while (i < 3);", "3")]
+    #[case("var i = 10; do i = i + 1; while (i < 3);", "11")]
+    #[case("var i = 0; do { var j = i; i = j + 2; } while (i < 5);", "6")]
+    // throwing is the only way out of a loop early
+    #[case(
+        r#"var i = 0;
+        try {
+            do { i = i + 1; match (i) { 4 => throw "stop"; } } while (true);
+        } catch (e) { }"#,
+        "4"
+    )]
+    fn test_interpreter_do_while(#[case] program: &str, #[case] expected: &str) {
+        let dialect = [Extension::DoWhile, Extension::Exceptions, Extension::Match]
+            .into_iter()
+            .collect();
+        let interpreter =
+            interpret_program_with(program, dialect).expect("Expected program to run");
+        let i = Token::new(TokenType::Identifier, "i".into(), None, 1);
+        assert_eq!(
+            expected,
+            interpreter.environment.get(&i).unwrap().to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("nil ?? 1", "1")]
+    #[case("0 ?? 1", "0")]
+    #[case("false ?? 1", "false")]
+    #[case("nil ?? nil ?? \"last\"", "last")]
+    // the right side is not evaluated unless it is needed
+    #[case("\"set\" ?? undefined", "set")]
+    fn test_interpreter_coalesce(#[case] expr: &str, #[case] expected: &str) {
+        let program = format!("var result = {expr};");
+        let dialect = [Extension::NilCoalescing, Extension::Integers]
+            .into_iter()
+            .collect();
+        let interpreter =
+            interpret_program_with(&program, dialect).expect("Expected program to run");
+        let result = Token::new(TokenType::Identifier, "result".into(), None, 1);
+        assert_eq!(
+            expected,
+            interpreter.environment.get(&result).unwrap().to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("var (a, b, c) = [1, 2, 3];", "1 2 3")]
+    #[case("var a = 1; var b = 2; var c = 3; (a, b) = (b, a);", "2 1 3")]
+    #[case("var (a, b, c) = [1, 2, 3]; (a, b, c) = (c, a, b);", "3 1 2")]
+    #[case("var a; var b; var c = (a, b) = [4, 5];", "4 5 [4, 5]")]
+    #[case("var (a) = [1]; var (b, c) = (\"x\", nil);", "1 x nil")]
+    fn test_interpreter_destructuring(#[case] program: &str, #[case] expected: &str) {
+        let dialect = [Extension::Destructuring, Extension::Lists]
+            .into_iter()
+            .collect();
+        let interpreter =
+            interpret_program_with(program, dialect).expect("Expected program to run");
+        let get = |name: &str| {
+            let name = Token::new(TokenType::Identifier, name.into(), None, 1);
+            interpreter.environment.get(&name).unwrap().to_string()
+        };
+        assert_eq!(expected, format!("{} {} {}", get("a"), get("b"), get("c")));
+    }
+
+    #[rstest]
+    #[case(
+        "var (a, b) = [1];",
+        "Expected 2 values to destructure, got 1.\n[line 1]"
+    )]
+    #[case(
+        "var (a, b) = (1, 2, 3);",
+        "Expected 2 values to destructure, got 3.\n[line 1]"
+    )]
+    #[case("var (a, b) = \"ab\";", "Can only destructure lists.\n[line 1]")]
+    #[case("(a, b) = (1, 2);", "Undefined variable 'a'.\n[line 1]")]
+    fn test_interpreter_destructuring_errors(#[case] program: &str, #[case] expected: &str) {
+        let dialect = [Extension::Destructuring, Extension::Lists]
+            .into_iter()
+            .collect();
+        let err = interpret_program_with(program, dialect).expect_err("Expected runtime error");
+        assert_eq!(expected, err.to_string());
+    }
+
+    #[test]
+    fn test_interpreter_constants() {
+        let dialect = Dialect::new().with(Extension::Constants);
+        let mut interpreter = Interpreter::new().with_dialect(dialect);
+        interpreter
+            .interpret(&parse_program("const x = 1; var y = x + 1;", dialect))
+            .unwrap();
+
+        // a separately parsed chunk hides the declaration from the parser
+        let err = interpreter
+            .interpret(&parse_program("x = 3;", dialect))
+            .unwrap_err();
+        assert_eq!("Cannot assign to constant 'x'.\n[line 1]", err.to_string());
+
+        // a block can still shadow the constant with a variable of its own
+        interpreter
+            .interpret(&parse_program(
+                "var z; { var x = 2; x = 3; z = x; }",
+                dialect,
+            ))
+            .unwrap();
+        let x = Token::new(TokenType::Identifier, "x".into(), None, 1);
+        assert_eq!("1", interpreter.environment.get(&x).unwrap().to_string());
+    }
+
+    #[rstest]
+    #[case("var x = 2;")]
+    #[case("const x = 2;")]
+    fn test_interpreter_constants_cannot_be_redeclared(#[case] program: &str) {
+        let dialect = Dialect::new().with(Extension::Constants);
+        let mut interpreter = Interpreter::new().with_dialect(dialect);
+        interpreter
+            .interpret(&parse_program("const x = 1;", dialect))
+            .unwrap();
+
+        // a separately parsed chunk hides the declaration from the parser
+        let err = interpreter
+            .interpret(&parse_program(program, dialect))
+            .unwrap_err();
+        assert_eq!("Cannot redeclare constant 'x'.\n[line 1]", err.to_string());
+        let x = Token::new(TokenType::Identifier, "x".into(), None, 1);
+        assert_eq!("1", interpreter.environment.get(&x).unwrap().to_string());
+    }
+
+    #[test]
+    fn test_interpreter_foreign_values() {
+        use crate::foreign::Foreign;
+        use crate::stdlib::NativeFunction;
+
+        struct Counter(std::cell::Cell<i64>);
+
+        static BUMP: NativeFunction = NativeFunction {
+            name: "bump",
+            arity: 1,
+            func: |_, args| match &args[0] {
+                Value::Foreign(handle) => {
+                    let counter = handle
+                        .downcast_ref::<Counter>()
+                        .ok_or("Expected a counter.")?;
+                    counter.0.set(counter.0.get() + 1);
+                    Ok(counter.0.get().into())
+                }
+                _ => Err("Expected a counter.".into()),
+            },
+        };
+
+        let handle = Foreign::new("Counter", Counter(std::cell::Cell::new(0)));
+        let program = parse_program(
+            "var c = counter; bump(c); var n = bump(counter); var t = type(c);             var same = c == counter;",
+            Dialect::new(),
+        );
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .define("counter", handle.clone().into())
+            .unwrap();
+        interpreter
+            .define("bump", Value::NativeFunction(&BUMP))
+            .unwrap();
+        interpreter.interpret(&program).unwrap();
+
+        let get = |name: &str| {
+            let name = Token::new(TokenType::Identifier, name.into(), None, 1);
+            interpreter.environment.get(&name).unwrap().to_string()
+        };
+        assert_eq!("2", get("n"));
+        assert_eq!("Counter", get("t"));
+        assert_eq!("true", get("same"));
+        assert_eq!(2, handle.downcast_ref::<Counter>().unwrap().0.get());
+
+        let err = interpreter
+            .interpret(&parse_program("bump(1);", Dialect::new()))
+            .unwrap_err();
+        assert_eq!("Expected a counter.\n[line 1]", err.to_string());
+    }
+
+    #[test]
+    fn test_interpreter_with_operators() {
+        use crate::foreign::Foreign;
+
+        fn add(left: &Value, right: &Value) -> Result<Value, String> {
+            let vector = |value: &Value| match value {
+                Value::Foreign(handle) => handle.downcast_ref::<(f64, f64)>().copied(),
+                _ => None,
+            };
+            let (Some(a), Some(b)) = (vector(left), vector(right)) else {
+                return Err("Expected vectors.".into());
+            };
+            Ok(Foreign::new("Vec2", (a.0 + b.0, a.1 + b.1)).into())
+        }
+
+        let operators = OperatorTable::new().with(TokenType::Plus, "Vec2", "Vec2", add);
+        let mut interpreter = Interpreter::new().with_operators(operators);
+        interpreter
+            .define("u", Foreign::new("Vec2", (1.0, 2.0)).into())
+            .unwrap();
+        interpreter
+            .define("v", Foreign::new("Vec2", (3.0, 4.0)).into())
+            .unwrap();
+        interpreter
+            .interpret(&parse_program(
+                "var w = u + v; var n = 1 + 2;",
+                Dialect::new(),
+            ))
+            .unwrap();
+
+        let get = |name: &str| {
+            let name = Token::new(TokenType::Identifier, name.into(), None, 1);
+            interpreter.environment.get(&name).unwrap()
+        };
+        let Value::Foreign(w) = get("w") else {
+            panic!("expected a foreign value");
+        };
+        assert_eq!(Some(&(4.0, 6.0)), w.downcast_ref::<(f64, f64)>());
+        assert_eq!("3", get("n").to_string());
+
+        // other operators and type pairs keep their built-in behavior
+        let err = interpreter
+            .interpret(&parse_program("u - v;", Dialect::new()))
+            .unwrap_err();
+        assert_eq!("Operands must be numbers.\n[line 1]", err.to_string());
+    }
+
+    #[test]
+    fn test_interpreter_with_optimizations() {
+        let dialect = Dialect::new().with(Extension::DoWhile);
+        let program = parse_program("var x = 2 * 3;\ndo x = x + 1; while (1 > 2);", dialect);
+        let mut interpreter = Interpreter::new()
+            .with_dialect(dialect)
+            .with_optimizations();
+        interpreter.interpret(&program).unwrap();
+        let x = Token::new(TokenType::Identifier, "x".into(), None, 1);
+        assert_eq!(Value::Number(7.0), interpreter.environment.get(&x).unwrap());
+
+        let err = interpreter
+            .interpret(&parse_program("print 1;\nprint -(\"a\" + \"b\");", dialect))
+            .unwrap_err();
+        assert_eq!("Operand must be a number.\n[line 2]", err.to_string());
+    }
+
+    #[test]
+    fn test_interpreter_define_global() {
+        let mut database = Map::default();
+        database.insert(MapKey::new("port".into()).unwrap(), Value::Integer(5432));
+        let mut config = Map::default();
+        config.insert(MapKey::new("db".into()).unwrap(), database.into());
+        config.insert(MapKey::new("debug".into()).unwrap(), true.into());
+
+        let dialect: Dialect = [Extension::Lists, Extension::Maps, Extension::Integers]
+            .into_iter()
+            .collect();
+        let mut interpreter = Interpreter::new().with_dialect(dialect);
+        interpreter.define_global("CONFIG", config.into()).unwrap();
+        let program = r#"var port = CONFIG["db"]["port"] + 1; { CONFIG["debug"] = false; }"#;
+        interpreter
+            .interpret(&parse_program(program, dialect))
+            .unwrap();
+
+        let get = |name: &str| {
+            let name = Token::new(TokenType::Identifier, name.into(), None, 1);
+            interpreter.environment.get(&name).unwrap().to_string()
+        };
+        assert_eq!("5433", get("port"));
+        assert_eq!("{db: {port: 5432}, debug: false}", get("CONFIG"));
+
+        let err = interpreter
+            .interpret(&parse_program("CONFIG = nil;", dialect))
+            .unwrap_err();
+        assert_eq!(
+            "Cannot assign to constant 'CONFIG'.\n[line 1]",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("var PORT = 80;")]
+    #[case("const PORT = 80;")]
+    #[case("{ PORT = 80; }")]
+    fn test_interpreter_define_global_cannot_be_replaced(#[case] program: &str) {
+        let dialect = Dialect::new().with(Extension::Constants);
+        let mut interpreter = Interpreter::new().with_dialect(dialect);
+        interpreter.define_global("PORT", 5432.0.into()).unwrap();
+
+        assert!(
+            interpreter
+                .interpret(&parse_program(program, dialect))
+                .is_err()
+        );
+        let port = Token::new(TokenType::Identifier, "PORT".into(), None, 1);
+        assert_eq!(
+            "5432",
+            interpreter.environment.get(&port).unwrap().to_string()
+        );
+        assert_eq!(
+            Err("Cannot redeclare constant 'PORT'.".to_string()),
+            interpreter.define_global("PORT", Value::Nil)
+        );
+    }
+
+    #[rstest]
+    #[case("assert 1 + 1 == 3;", "Assertion failed: 1 + 1 == 3\n[line 1]")]
+    #[case(
+        "var a = nil;\nassert a, \"a is \" + a;",
+        "Operands must be numbers.\n[line 2]"
+    )]
+    #[case("var a = 2;\nassert a < 1, a;", "Assertion failed: a < 1: 2\n[line 2]")]
+    fn test_interpreter_assert_errors(#[case] program: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::Assertions);
+        let err = interpret_program_with(program, dialect).expect_err("Expected a runtime error");
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[test]
+    fn test_interpreter_assert_is_catchable() {
+        let program = r#"var log = "";
+        assert true, log = "unused";
+        try { assert false, "bad"; } catch (e) { log = errorMessage(e); }"#;
+        let dialect = [Extension::Assertions, Extension::Exceptions]
+            .into_iter()
+            .collect();
+        let interpreter =
+            interpret_program_with(program, dialect).expect("Expected program to run");
+        let log = Token::new(TokenType::Identifier, "log".into(), None, 1);
+        assert_eq!(
+            "Assertion failed: false: bad",
+            interpreter.environment.get(&log).unwrap().to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("[0, ...[1, 2], ...[], 3]", "[0, 1, 2, 3]")]
+    #[case("[...[[1], 2]]", "[[1], 2]")]
+    #[case("approxEquals(...[1, 1.05], 0.1)", "true")]
+    #[case("isNan(...[1])", "false")]
+    fn test_interpreter_spread(#[case] input: &str, #[case] expected_output: &str) {
+        let dialect = Dialect::new()
+            .with(Extension::Lists)
+            .with(Extension::Spread);
+        let output = eval_expr_with(input, dialect).expect("Expected evaluation to succeed");
+        assert_eq!(expected_output, output.to_string());
+    }
+
+    #[rstest]
+    #[case("[...\"ab\"]", "Can only spread lists.\n[line 1]")]
+    #[case("isNan(...nil)", "Can only spread lists.\n[line 1]")]
+    #[case("isNan(...[1, 2])", "Expected 1 arguments but got 2.\n[line 1]")]
+    fn test_interpreter_runtime_errors_spread(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new()
+            .with(Extension::Lists)
+            .with(Extension::Spread);
+        let err = eval_expr_with(input, dialect).expect_err("Expected evaluation to fail");
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case("0..3", "0..3")]
+    #[case("3 - 1..=-2", "2..=-2")]
+    #[case("step(0..10, 3)", "step(0..10, 3)")]
+    #[case("contains(step(0..10, 3), 9)", "true")]
+    #[case("contains(10..0, 0)", "false")]
+    #[case("0..3 == 0..3", "true")]
+    #[case("0..3 == 0..=3", "false")]
+    #[case("step(0..3, 1) == 0..3", "true")]
+    fn test_interpreter_ranges(#[case] input: &str, #[case] expected_output: &str) {
+        let dialect = Dialect::new().with(Extension::Ranges);
+        let output = eval_expr_with(input, dialect).expect("Expected evaluation to succeed");
+        assert_eq!(expected_output, output.to_string());
+    }
+
+    #[rstest]
+    #[case("0..1.5", "Operands must be integers.\n[line 1]")]
+    #[case("nil..=1", "Operands must be integers.\n[line 1]")]
+    #[case("step(0..1, -1)", "Step must be a positive integer.\n[line 1]")]
+    #[case("contains(1, 1)", "First argument must be a range.\n[line 1]")]
+    fn test_interpreter_runtime_errors_ranges(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::Ranges);
+        let err = eval_expr_with(input, dialect).expect_err("Expected evaluation to fail");
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case("var s = \"\"; for (i in 1..4) s = \"${s}${i}\";", "s", "123")]
+    #[case("var s = \"\"; for (i in 3..=0) s = \"${s}${i}\";", "s", "3210")]
+    #[case(
+        "var s = \"\"; for (i in step(10..0, 4)) s = \"${s}${i}\";",
+        "s",
+        "1062"
+    )]
+    #[case("var s = \"\"; for (i in -1..=1) s = \"${s}${i},\";", "s", "-1,0,1,")]
+    fn test_interpreter_ranges_with_extensions(
+        #[case] program: &str,
+        #[case] name: &str,
+        #[case] expected: &str,
+    ) {
+        let dialect = [
+            Extension::Ranges,
+            Extension::ForIn,
+            Extension::Integers,
+            Extension::Interpolation,
+        ]
+        .into_iter()
+        .collect();
+        let interpreter =
+            interpret_program_with(program, dialect).expect("Expected program to run");
+        let name = Token::new(TokenType::Identifier, name.into(), None, 1);
+        assert_eq!(
+            expected,
+            interpreter.environment.get(&name).unwrap().to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("var a = 1; { print a; }", "statements=3", None)]
+    #[case("var a = 1; { print a; }", "statements=2", Some("statements=2"))]
+    #[case("for (i in 5) {}", "statements=5", Some("statements=5"))]
+    #[case("isNan(1); isNan(isNan(2));", "calls=2", Some("calls=2"))]
+    #[case("isNan(1);", "calls=0,statements=10", Some("calls=0"))]
+    #[case("try { isNan(1); } catch (e) {}", "calls=0", Some("calls=0"))]
+    fn test_interpreter_limits(
+        #[case] program: &str,
+        #[case] limits: &str,
+        #[case] exceeded: Option<&str>,
+    ) {
+        let dialect = Dialect::new()
+            .with(Extension::ForIn)
+            .with(Extension::Exceptions);
+        let program = parse_program(program, dialect);
+        let limits = limits
+            .split(',')
+            .map(|limit| limit.parse::<Limit>().unwrap());
+        let mut interpreter = Interpreter::new().with_dialect(dialect).with_limits(limits);
+
+        let result = interpreter.interpret(&program);
+        let exceeded_limit = result.err().and_then(|err| err.exceeded_limit());
+        assert_eq!(exceeded, exceeded_limit.map(|l| l.to_string()).as_deref());
+    }
+
+    #[rstest]
+    #[case("approxEquals(0.1 + 0.2, 0.3, 1e-9)", Value::Boolean(true))]
+    #[case("approxEquals(1, 1.5, 0.1)", Value::Boolean(false))]
+    #[case("isNan(1e308 * 10 - 1e308 * 10)", Value::Boolean(true))]
+    #[case("isInfinite(-1e308 * 10)", Value::Boolean(true))]
+    #[case("isInfinite(1e308)", Value::Boolean(false))]
+    #[case(r#"upper(trim(substr("  hi lox ", 1, 6)))"#, Value::from("HI LO"))]
+    #[case(r#"indexOf("lox", "x") + len("lox")"#, Value::Number(5.0))]
+    #[case("num(str(12)) + 1", Value::Number(13.0))]
+    #[case("type(isNan)", Value::from("function"))]
+    #[case("bool(num(\"x\"))", Value::Boolean(false))]
+    fn test_interpreter_native_calls(#[case] input: &str, #[case] expected_output: Value) {
+        let dialect = Dialect::new().with(Extension::NumericLiterals);
+        let output = eval_expr_with(input, dialect).expect("Expected evaluation to succeed");
+        assert_eq!(expected_output, output);
+    }
+
+    #[rstest]
+    #[case(r#""isNan"()"#, "Can only call functions and classes.\n[line 1]")]
+    #[case("isNan(1, 2)", "Expected 1 arguments but got 2.\n[line 1]")]
+    #[case(r#"isInfinite("x")"#, "Argument must be a number.\n[line 1]")]
+    #[case("approxEquals(1, nil, 1)", "Arguments must be numbers.\n[line 1]")]
+    #[case(
+        "approxEquals(1, 1, -1)",
+        "Epsilon must be a non-negative number.\n[line 1]"
+    )]
+    #[case("len(nil)", "Argument must be a string.\n[line 1]")]
+    #[case(r#"substr("ab", 1)"#, "Expected 3 arguments but got 2.\n[line 1]")]
+    fn test_interpreter_runtime_errors_calls(#[case] input: &str, #[case] expected_error: &str) {
+        let err = eval_expr(input).expect_err("Expected evaluation to fail");
+        assert_eq!(expected_error, err.to_string());
+    }
+
     #[rstest]
     #[case(
         r#"
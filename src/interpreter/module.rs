@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use crate::Value;
+use crate::diagnostic::Diagnostic;
+use crate::interpreter::Interpreter;
+use crate::interpreter::error::RuntimeError;
+use crate::parser::stmt::Import;
+use crate::scanner::Scanner;
+use crate::symbol::Symbol;
+use crate::validate::validate_scanned;
+
+/// A file run by an `import` statement.
+#[derive(Debug, Clone)]
+pub(super) enum Module {
+    /// Still running, so importing it again is a cycle
+    Loading,
+    /// Finished, with the values of its top-level variables
+    Loaded(HashMap<Symbol, Value>),
+}
+
+impl Interpreter {
+    /// Runs the module `stmt` imports, unless it already ran, and binds the
+    /// names it asks for in the current scope.
+    ///
+    /// The path may name environment variables as `${NAME}`, see [`expand`].
+    /// A module not found relative to the script is looked for in each of
+    /// the module paths in turn.
+    ///
+    /// Returns a [`RuntimeError`] at the path if it or a module path names a
+    /// variable that is not set, or the module cannot be read, fails to parse
+    /// or imports itself, and at a name the module does not define.
+    pub(super) fn import(&mut self, stmt: &Import) -> Result<(), RuntimeError> {
+        let error = |message: String| RuntimeError::new(stmt.path.clone(), message);
+        let path = match &stmt.path.literal {
+            Some(Value::String(path)) => path,
+            _ => unreachable!("the parser only accepts string literals as paths"),
+        };
+        // checked first so sandboxed scripts cannot read the environment either
+        if self.sandboxed {
+            return Err(error("Filesystem access is disabled.".into()));
+        }
+        let relative = expand(path, |name| env::var(name).ok()).map_err(error)?;
+        let relative = Path::new(&relative);
+
+        let base = self.script.as_deref().and_then(Path::parent);
+        let path = base.unwrap_or(Path::new("")).join(relative);
+        let path = match fs::canonicalize(&path) {
+            Ok(path) => path,
+            Err(err) => self
+                .search(relative)
+                .map_err(error)?
+                .ok_or_else(|| error(format!("Failed to import {}: {err}.", relative.display())))?,
+        };
+
+        let exports = match self.modules.get(&path) {
+            Some(Module::Loaded(exports)) => exports,
+            Some(Module::Loading) => {
+                return Err(error(format!(
+                    "Import cycle through {}.",
+                    relative.display()
+                )));
+            }
+            None => {
+                self.load(&path, &relative.display().to_string(), error)?;
+                let Some(Module::Loaded(exports)) = self.modules.get(&path) else {
+                    unreachable!("a module that ran without error is loaded");
+                };
+                exports
+            }
+        };
+
+        let mut bindings = Vec::new();
+        for name in &stmt.names {
+            let Some(value) = exports.get(&name.lexeme) else {
+                let message = format!(
+                    "Module {} has no variable '{}'.",
+                    relative.display(),
+                    name.lexeme
+                );
+                return Err(RuntimeError::new(name.clone(), message));
+            };
+            bindings.push((name, value.clone()));
+        }
+        for (name, value) in bindings {
+            self.declare(name, value, false)?;
+        }
+        Ok(())
+    }
+
+    /// Looks for `relative` in each of the module paths, expanding their
+    /// `${NAME}` variables, and returns the canonical path of the first file
+    /// found.
+    ///
+    /// Returns an error message if a module path names a variable that is
+    /// not set.
+    fn search(&self, relative: &Path) -> Result<Option<PathBuf>, String> {
+        for dir in &self.module_paths {
+            let dir = expand(&dir.to_string_lossy(), |name| env::var(name).ok())?;
+            if let Ok(path) = fs::canonicalize(Path::new(&dir).join(relative)) {
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Runs the file at `path`, imported as `name`, in a global scope of its
+    /// own and records its top-level variables.
+    ///
+    /// Returns the error created by `error` if the file cannot be read, or
+    /// does not parse along with every error in it. Returns the error raised
+    /// while running it attributed to `name`.
+    fn load(
+        &mut self,
+        path: &Path,
+        name: &str,
+        error: impl Fn(String) -> RuntimeError,
+    ) -> Result<(), RuntimeError> {
+        self.imported.insert(path.to_path_buf());
+        let source = fs::read_to_string(path)
+            .map_err(|err| error(format!("Failed to read {name}: {err}.")))?;
+        let file = self.sources.add(name, source.as_str());
+        let scanner = Scanner::new(&source)
+            .with_dialect(self.dialect)
+            .with_interner(self.interner.clone())
+            .with_file(file);
+        let validation = validate_scanned(scanner, self.dialect);
+        if !validation.is_valid() {
+            let errors = validation.diagnostics.iter().map(Diagnostic::from);
+            let err = error(format!("Module {name} has errors."));
+            return Err(err.with_causes(errors.map(|d| d.with_file(name))));
+        }
+        let program = validation.ast.expect("a source without errors parses");
+
+        self.modules.insert(path.to_path_buf(), Module::Loading);
+        let importer = std::mem::replace(
+            &mut self.environment,
+            Box::new(Self::globals(&self.interner)),
+        );
+        let script = self.script.replace(PathBuf::from(path));
+
+        let result = self.interpret(&program);
+
+        let module = std::mem::replace(&mut self.environment, importer);
+        self.script = script;
+        match result {
+            Ok(()) => {
+                let exports = Module::Loaded(module.values);
+                self.modules.insert(path.to_path_buf(), exports);
+                Ok(())
+            }
+            Err(err) => {
+                self.modules.remove(path);
+                Err(err.in_file(name))
+            }
+        }
+    }
+}
+
+/// Replaces each `${NAME}` in `path` with the value `lookup` gives the
+/// environment variable `NAME`, so imports such as `"${LOX_LIB}/list.lox"`,
+/// and module paths such as `${LOX_LIB}`, keep working when a shared library
+/// moves. A `$` not followed by `{` is
+/// kept as is.
+///
+/// Returns an error message if a variable is not set or a `${` is not
+/// closed.
+fn expand(path: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = path;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            return Err(format!("Unterminated '${{' in path {path}."));
+        };
+        let name = &rest[start + 2..start + end];
+        let Some(value) = lookup(name) else {
+            return Err(format!("Environment variable '{name}' is not set."));
+        };
+        expanded.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rstest::rstest;
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::dialect::{Dialect, Extension};
+    use crate::scanner::token::{Token, TokenType};
+
+    /// Writes `files` to a temporary directory and runs the first one.
+    fn run(files: &[(&str, &str)]) -> Result<Interpreter, RuntimeError> {
+        let tempdir = TempDir::new("rslox-module").expect("should create temp dir");
+        for (name, source) in files {
+            let path = tempdir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).expect("should write module");
+        }
+
+        let dialect = Dialect::new().with(Extension::Modules);
+        let (main, source) = files[0];
+        let program = crate::validate::validate_with(source, dialect).ast.unwrap();
+        let mut interpreter = Interpreter::new()
+            .with_dialect(dialect)
+            .with_script(tempdir.path().join(main));
+        interpreter.interpret(&program)?;
+        Ok(interpreter)
+    }
+
+    fn get(interpreter: &Interpreter, name: &str) -> String {
+        let name = Token::new(TokenType::Identifier, name.into(), None, 1);
+        interpreter.environment.get(&name).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_import_binds_names() {
+        let interpreter = run(&[
+            (
+                "main.lox",
+                r#"import a, b from "lib/ab.lox"; var sum = a + b;"#,
+            ),
+            (
+                "lib/ab.lox",
+                r#"import c from "c.lox"; var a = 1; var b = a + c;"#,
+            ),
+            ("lib/c.lox", "var c = 10;"),
+        ])
+        .unwrap();
+        assert_eq!("12", get(&interpreter, "sum"));
+
+        let imported = interpreter.imported().map(|path| path.file_name().unwrap());
+        assert_eq!(vec!["ab.lox", "c.lox"], imported.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_import_runs_module_once() {
+        let interpreter = run(&[
+            (
+                "main.lox",
+                r#"import n from "count.lox"; import m from "count.lox"; var total = n + m;"#,
+            ),
+            ("count.lox", "var n = 1; var m = n + 1;"),
+        ])
+        .unwrap();
+        assert_eq!("3", get(&interpreter, "total"));
+    }
+
+    #[rstest]
+    #[case(
+        &[("main.lox", r#"import x from "lib.lox";"#), ("lib.lox", "var y = 1;")],
+        "Module lib.lox has no variable 'x'.\n[line 1]"
+    )]
+    #[case(
+        &[("main.lox", r#"import "lib.lox";"#), ("lib.lox", "print;\nvar = 2;")],
+        "Module lib.lox has errors.\n[line 1]\n\
+         [line 1 in lib.lox] Error at ';': Expect expression\n\
+         [line 2 in lib.lox] Error at '=': Expect variable name."
+    )]
+    #[case(
+        &[("main.lox", r#"import "lib.lox";"#), ("lib.lox", "print main;")],
+        "Undefined variable 'main'.\n[line 1 in lib.lox]"
+    )]
+    #[case(
+        &[("main.lox", r#"import "main.lox";"#)],
+        "Import cycle through main.lox.\n[line 1 in main.lox]"
+    )]
+    #[case(
+        &[("main.lox", r#"import "${RSLOX_UNSET_LIBRARY}/lib.lox";"#)],
+        "Environment variable 'RSLOX_UNSET_LIBRARY' is not set.\n[line 1]"
+    )]
+    fn test_import_errors(#[case] files: &[(&str, &str)], #[case] expected: &str) {
+        assert_eq!(expected, run(files).unwrap_err().to_string());
+    }
+
+    #[rstest]
+    #[case("lib.lox", Ok("lib.lox"))]
+    #[case("${LIB}/list.lox", Ok("/opt/lox/list.lox"))]
+    #[case("${LIB}/${LIB}$x", Ok("/opt/lox//opt/lox$x"))]
+    #[case("${HOME}/lib.lox", Err("Environment variable 'HOME' is not set."))]
+    #[case("${LIB", Err("Unterminated '${' in path ${LIB."))]
+    fn test_expand(#[case] path: &str, #[case] expected: Result<&str, &str>) {
+        let lookup = |name: &str| (name == "LIB").then(|| "/opt/lox".to_string());
+        let expanded = expand(path, lookup);
+        assert_eq!(expected, expanded.as_deref().map_err(String::as_str));
+    }
+
+    #[test]
+    fn test_import_missing_file() {
+        let err = run(&[("main.lox", r#"import "missing.lox";"#)]).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Failed to import missing.lox: "),
+            "{err}"
+        );
+    }
+}
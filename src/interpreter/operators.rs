@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use crate::Value;
+use crate::scanner::token::TokenType;
+
+/// The implementation of a binary operator for one pair of operand types.
+/// An `Err` message is reported as a runtime error at the operator.
+pub type OperatorFn = fn(&Value, &Value) -> Result<Value, String>;
+
+/// Binary operators supplied by an embedder for specific operand types, such
+/// as `+` on two [`Foreign`](crate::foreign::Foreign) vectors.
+///
+/// Operands are matched by the type name `type(v)` reports for them. An entry
+/// takes precedence over the built-in behavior of its operator, which still
+/// applies to every other pair of types.
+#[derive(Debug, Clone, Default)]
+pub struct OperatorTable {
+    entries: HashMap<(TokenType, &'static str, &'static str), OperatorFn>,
+}
+
+impl OperatorTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Implements `op` with `func` when the left operand is of type `left`
+    /// and the right one of type `right`, replacing any earlier entry.
+    pub fn with(
+        mut self,
+        op: TokenType,
+        left: &'static str,
+        right: &'static str,
+        func: OperatorFn,
+    ) -> Self {
+        self.entries.insert((op, left, right), func);
+        self
+    }
+
+    /// Returns the implementation of `op` for the types of `left` and `right`,
+    /// if there is one.
+    pub fn get(&self, op: TokenType, left: &Value, right: &Value) -> Option<OperatorFn> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let key = (op, left.type_name(), right.type_name());
+        self.entries.get(&key).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::foreign::Foreign;
+
+    #[test]
+    fn test_operator_table_matches_type_names() {
+        let table =
+            OperatorTable::new().with(TokenType::Plus, "Vec2", "number", |_, _| Ok(Value::Nil));
+        let vector = Value::from(Foreign::new("Vec2", (1.0, 2.0)));
+        let number = Value::Integer(1);
+
+        assert!(table.get(TokenType::Plus, &vector, &number).is_some());
+        assert!(table.get(TokenType::Plus, &number, &vector).is_none());
+        assert!(table.get(TokenType::Minus, &vector, &number).is_none());
+    }
+}
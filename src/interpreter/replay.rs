@@ -0,0 +1,201 @@
+//! Recording the results of natives that read the outside world, such as
+//! `readLine`, so that a run can be reproduced exactly from its log.
+
+use std::collections::VecDeque;
+
+use serde_json::{Value as Json, json};
+
+use crate::Value;
+
+/// The result of one call to a nondeterministic native.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// The name of the native that was called
+    pub native: String,
+    pub result: Result<Value, String>,
+}
+
+impl Event {
+    /// Converts the event into a JSON object such as
+    /// `{"native": "readLine", "ok": "text"}` or `{"err": "message", "native": ...}`.
+    pub fn to_json(&self) -> Json {
+        match &self.result {
+            Ok(value) => json!({ "native": self.native, "ok": value_to_json(value) }),
+            Err(message) => json!({ "native": self.native, "err": message }),
+        }
+    }
+
+    /// Reads an event written by [`Event::to_json`].
+    ///
+    /// Returns an error message if `json` does not describe an event.
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let Some(native) = json["native"].as_str() else {
+            return Err("Event must name its native.".into());
+        };
+        let result = match (json.get("ok"), json["err"].as_str()) {
+            (Some(value), None) => Ok(value_from_json(value)?),
+            (None, Some(message)) => Err(message.to_string()),
+            _ => return Err("Event must have either an 'ok' or an 'err' result.".into()),
+        };
+        Ok(Self {
+            native: native.to_string(),
+            result,
+        })
+    }
+}
+
+/// Converts a value returned by a nondeterministic native into JSON.
+fn value_to_json(value: &Value) -> Json {
+    match value {
+        Value::Nil => Json::Null,
+        Value::Boolean(b) => json!(b),
+        Value::Integer(n) => json!(n),
+        Value::Number(n) => json!(n),
+        Value::String(s) => json!(s),
+        other => unreachable!("natives do not return {other} from outside the program"),
+    }
+}
+
+fn value_from_json(json: &Json) -> Result<Value, String> {
+    match json {
+        Json::Null => Ok(Value::Nil),
+        Json::Bool(b) => Ok((*b).into()),
+        // only numbers written without a fraction or exponent are integers
+        Json::Number(n) => match n.as_i64() {
+            Some(n) => Ok(n.into()),
+            None => Ok(n.as_f64().unwrap_or(f64::NAN).into()),
+        },
+        Json::String(s) => Ok(s.as_str().into()),
+        _ => Err(format!("Unsupported value in event: {json}.")),
+    }
+}
+
+/// Writes `events` as JSON lines, one event per line.
+pub fn write_log(events: &[Event], mut sink: impl std::io::Write) -> std::io::Result<()> {
+    for event in events {
+        writeln!(sink, "{}", event.to_json())?;
+    }
+    Ok(())
+}
+
+/// Reads a log written by [`write_log`], ignoring blank lines.
+///
+/// Returns an error message naming the first line that is not an event.
+pub fn read_log(log: &str) -> Result<Vec<Event>, String> {
+    log.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map_err(|err| err.to_string())
+                .and_then(|json| Event::from_json(&json))
+                .map_err(|err| format!("Invalid event on line {}: {err}", i + 1))
+        })
+        .collect()
+}
+
+/// Whether the interpreter records or replays nondeterministic natives.
+#[derive(Debug, Clone, Default)]
+pub(super) enum EventLog {
+    /// Natives run normally and nothing is kept
+    #[default]
+    Off,
+    Recording(Vec<Event>),
+    /// Natives return the next of these events instead of running
+    Replaying(VecDeque<Event>),
+}
+
+impl EventLog {
+    /// Runs the native called `native` with `run`, recording the result, or
+    /// returns the next replayed result without running it.
+    ///
+    /// Returns an error message if the replayed event is for another native
+    /// or the log has run out.
+    pub(super) fn call(
+        &mut self,
+        native: &str,
+        run: impl FnOnce() -> Result<Value, String>,
+    ) -> Result<Value, String> {
+        match self {
+            EventLog::Off => run(),
+            EventLog::Recording(events) => {
+                let result = run();
+                events.push(Event {
+                    native: native.to_string(),
+                    result: result.clone(),
+                });
+                result
+            }
+            EventLog::Replaying(events) => match events.pop_front() {
+                Some(event) if event.native == native => event.result,
+                Some(event) => Err(format!(
+                    "Replay log does not match: expected a call to {}, found {native}.",
+                    event.native
+                )),
+                None => Err(format!(
+                    "Replay log does not match: no event left for {native}."
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(Ok("line".into()), r#"{"native":"readLine","ok":"line"}"#)]
+    #[case(Ok(Value::Nil), r#"{"native":"readLine","ok":null}"#)]
+    #[case(Ok(Value::Integer(3)), r#"{"native":"readLine","ok":3}"#)]
+    #[case(Ok(Value::Number(0.5)), r#"{"native":"readLine","ok":0.5}"#)]
+    #[case(Err("Failed.".into()), r#"{"err":"Failed.","native":"readLine"}"#)]
+    fn test_event_round_trip(#[case] result: Result<Value, String>, #[case] expected: &str) {
+        let event = Event {
+            native: "readLine".into(),
+            result,
+        };
+        assert_eq!(expected, event.to_json().to_string());
+        assert_eq!(Ok(vec![event]), read_log(expected));
+    }
+
+    #[rstest]
+    #[case("nope", "Invalid event on line 1: expected ident at line 1 column 2")]
+    #[case(
+        "\n{\"ok\": 1}",
+        "Invalid event on line 2: Event must name its native."
+    )]
+    #[case(
+        r#"{"native": "readLine"}"#,
+        "Invalid event on line 1: Event must have either an 'ok' or an 'err' result."
+    )]
+    #[case(
+        r#"{"native": "readLine", "ok": [1]}"#,
+        "Invalid event on line 1: Unsupported value in event: [1]."
+    )]
+    fn test_read_log_errors(#[case] log: &str, #[case] expected: &str) {
+        assert_eq!(Err(expected.to_string()), read_log(log));
+    }
+
+    #[test]
+    fn test_event_log_replay() {
+        let mut recording = EventLog::Recording(Vec::new());
+        assert_eq!(
+            Ok("a".into()),
+            recording.call("readLine", || Ok("a".into()))
+        );
+        let EventLog::Recording(events) = recording else {
+            unreachable!()
+        };
+
+        let mut replaying = EventLog::Replaying(events.into());
+        let unreachable = || panic!("replayed natives should not run");
+        assert_eq!(Ok("a".into()), replaying.call("readLine", unreachable));
+        assert_eq!(
+            Err("Replay log does not match: no event left for readLine.".into()),
+            replaying.call("readLine", unreachable)
+        );
+    }
+}
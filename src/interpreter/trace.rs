@@ -0,0 +1,160 @@
+//! Execution traces written by `--trace`: a line for each statement as it
+//! starts, and one for each expression as it finishes with its value.
+//!
+//! Lines are indented by how deeply the statement or expression is nested,
+//! so the operands of an expression appear just above it, one level in.
+
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::io;
+use std::rc::Rc;
+
+use crate::Value;
+use crate::interpreter::error::RuntimeError;
+use crate::parser::ast::{AstArena, ExprId, StmtId};
+use crate::parser::printer::AstPrinter;
+use crate::parser::stmt::StmtNode;
+
+/// Writes the trace of a run. Clones of an interpreter write to the same
+/// sink.
+#[derive(Clone)]
+pub(super) struct Tracer {
+    sink: Rc<RefCell<dyn io::Write>>,
+    /// How many statements and expressions are being run
+    depth: usize,
+}
+
+impl Debug for Tracer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tracer")
+            .field("depth", &self.depth)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Tracer {
+    pub(super) fn new(sink: impl io::Write + 'static) -> Self {
+        Self {
+            sink: Rc::new(RefCell::new(sink)),
+            depth: 0,
+        }
+    }
+
+    /// Writes the statement `stmt` is about to run, and nests what runs next
+    /// inside it.
+    pub(super) fn enter_stmt(&mut self, ast: &AstArena, stmt: StmtId) {
+        let line = ast.lines(stmt).map_or("[line ?]".to_string(), |lines| {
+            format!("[line {}]", lines.first)
+        });
+        self.write(format_args!("{line} {}", describe(&ast[stmt])));
+        self.depth += 1;
+    }
+
+    /// Nests what runs next inside an expression.
+    pub(super) fn enter_expr(&mut self) {
+        self.depth += 1;
+    }
+
+    /// Ends the statement or expression entered last.
+    pub(super) fn exit(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Writes what `expr` evaluated to, after [`Self::exit`]ing it.
+    pub(super) fn result(
+        &mut self,
+        ast: &AstArena,
+        expr: ExprId,
+        result: &Result<Value, RuntimeError>,
+    ) {
+        self.exit();
+        let expr = AstPrinter::new().print(ast, expr);
+        match result {
+            Ok(value) => self.write(format_args!("{expr} => {value}")),
+            Err(err) => self.write(format_args!("{expr} => error: {}", err.message())),
+        }
+    }
+
+    // a trace that cannot be written is not worth stopping the program for
+    fn write(&self, line: std::fmt::Arguments) {
+        let indent = "  ".repeat(self.depth);
+        let _ = writeln!(self.sink.borrow_mut(), "{indent}{line}");
+    }
+}
+
+/// Names the kind of `stmt` by the keyword that starts it, followed by the
+/// name it declares if it is a variable declaration.
+fn describe(stmt: &StmtNode) -> String {
+    let keyword = match stmt {
+        StmtNode::Var(var) => {
+            let keyword = if var.constant { "const" } else { "var" };
+            return format!("{keyword} {}", var.name.lexeme);
+        }
+        StmtNode::Print(_) => "print",
+        StmtNode::Expression(_) => "expression",
+        StmtNode::VarUnpack(_) => "var",
+        StmtNode::Block(_) => "block",
+        StmtNode::ForIn(_) => "for",
+        StmtNode::DoWhile(_) => "do",
+        StmtNode::Try(_) => "try",
+        StmtNode::Throw(_) => "throw",
+        StmtNode::Assert(_) => "assert",
+        StmtNode::Import(_) => "import",
+        StmtNode::Match(_) => "match",
+    };
+    keyword.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    /// A sink the test can read back after the interpreter has written it.
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl io::Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let source = "var a = 1;\n{ print -a; }\nprint a + b;";
+        let program = Parser::new(Scanner::new(source).scan_tokens())
+            .parse()
+            .unwrap();
+        let sink = Shared::default();
+        let mut interpreter = Interpreter::new()
+            .with_muted_output(true)
+            .with_trace(sink.clone());
+        interpreter.interpret(&program).unwrap_err();
+
+        let trace = String::from_utf8(sink.0.take()).unwrap();
+        assert_eq!(
+            [
+                "[line 1] var a",
+                "  1.0 => 1",
+                "[line 2] block",
+                "  [line 2] print",
+                "      a => 1",
+                "    (- a) => -1",
+                "[line 3] print",
+                "    a => 1",
+                "    b => error: Undefined variable 'b'.",
+                "  (+ a b) => error: Undefined variable 'b'.",
+                "",
+            ]
+            .join("\n"),
+            trace
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! A machine-readable description of this build's CLI, so wrappers and editor
+//! plugins can adapt to the installed version instead of hard-coding it.
+
+use clap::{Arg, Command, CommandFactory as _, ValueEnum as _};
+use serde_json::{Value as Json, json};
+
+use crate::build_info;
+use crate::cli::Args;
+use crate::dialect::Extension;
+use crate::error::DIAGNOSTIC_CODES;
+use crate::lint::RULES;
+use crate::stdlib::{NativeFunction, natives};
+
+/// Incremented whenever a manifest field is removed or changes meaning.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Builds the interface manifest from the CLI definition.
+pub fn manifest() -> Json {
+    let command = Args::command();
+    let extensions = Extension::value_variants()
+        .iter()
+        .map(Extension::to_string)
+        .collect::<Vec<_>>();
+
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "name": command.get_name(),
+        "version": build_info::VERSION,
+        "global_flags": flags(&command),
+        "subcommands": command.get_subcommands().map(describe_command).collect::<Vec<_>>(),
+        "extensions": extensions,
+        "diagnostic_codes": DIAGNOSTIC_CODES,
+        "lint_rules": RULES,
+        "natives": natives().map(describe_native).collect::<Vec<_>>(),
+    })
+}
+
+fn describe_native(native: &NativeFunction) -> Json {
+    json!({
+        "name": native.name,
+        "arity": native.arity,
+    })
+}
+
+fn describe_command(command: &Command) -> Json {
+    json!({
+        "name": command.get_name(),
+        "about": command.get_about().map(ToString::to_string),
+        "positionals": command.get_positionals().map(describe_arg).collect::<Vec<_>>(),
+        "flags": flags(command),
+        "output_formats": output_formats(command),
+    })
+}
+
+fn flags(command: &Command) -> Vec<Json> {
+    command
+        .get_arguments()
+        .filter(|arg| !arg.is_positional())
+        .map(describe_arg)
+        .collect()
+}
+
+fn describe_arg(arg: &Arg) -> Json {
+    let values = arg
+        .get_possible_values()
+        .iter()
+        .map(|value| value.get_name().to_string())
+        .collect::<Vec<_>>();
+
+    json!({
+        "name": arg.get_id().as_str(),
+        "long": arg.get_long(),
+        "short": arg.get_short(),
+        "help": arg.get_help().map(ToString::to_string),
+        "takes_value": arg.get_action().takes_values(),
+        "values": values,
+    })
+}
+
+/// Lists the values of a subcommand's `--format` flag, or plain `text` output.
+fn output_formats(command: &Command) -> Vec<String> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_id() == "format")
+        .map(|arg| {
+            arg.get_possible_values()
+                .iter()
+                .map(|value| value.get_name().to_string())
+                .collect()
+        })
+        .unwrap_or_else(|| vec!["text".into()])
+}
@@ -1,16 +1,60 @@
+use std::cell::RefCell;
 use std::fmt::{Debug, Display};
+use std::rc::Rc;
 
+use crate::foreign::Foreign;
+use crate::interpreter::error::ErrorValue;
+use crate::map::Map;
+use crate::range::Range;
+
+pub mod bench;
+pub mod build_info;
 pub mod cli;
+pub mod compare;
+pub mod compiler;
+pub mod dap;
+pub mod debugger;
+pub mod demo;
+pub mod diagnostic;
+pub mod dialect;
+pub mod diff;
 pub mod error;
+pub mod foreign;
+pub mod format;
+mod framing;
+pub mod hoist;
+pub mod incremental;
 pub mod interpreter;
+pub mod introspect;
+pub mod lint;
+pub mod lsp;
+pub mod map;
+pub mod optimizer;
 pub mod parser;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod program;
+pub mod range;
+pub mod repl;
 pub mod scanner;
+pub mod source;
+pub mod stdlib;
+pub mod symbol;
+pub mod validate;
+pub mod vm;
 
-#[derive(Clone, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq)]
 pub enum Value {
     Number(f64),
+    Integer(i64),
     String(String),
     Boolean(bool),
+    NativeFunction(&'static stdlib::NativeFunction),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<Map>>),
+    Range(Range),
+    Error(ErrorValue),
+    Foreign(Foreign),
     Nil,
 }
 
@@ -18,8 +62,14 @@ impl Debug for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Number(n) => Debug::fmt(n, f),
+            Self::Integer(n) => Debug::fmt(n, f),
             Self::String(s) => Display::fmt(s, f), // use Display to exclude quotes
             Self::Boolean(b) => Debug::fmt(b, f),
+            Self::NativeFunction(native) => Debug::fmt(native, f),
+            Self::List(_) | Self::Map(_) => fmt_nested(self, f, &mut Vec::new()),
+            Self::Range(range) => Display::fmt(range, f),
+            Self::Error(error) => Display::fmt(error, f),
+            Self::Foreign(foreign) => Display::fmt(foreign, f),
             Self::Nil => write!(f, "nil"),
         }
     }
@@ -29,13 +79,66 @@ impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Number(n) => Display::fmt(n, f),
+            Self::Integer(n) => Display::fmt(n, f),
             Self::String(s) => Display::fmt(s, f),
             Self::Boolean(b) => Display::fmt(b, f),
+            Self::NativeFunction(native) => Display::fmt(native, f),
+            Self::List(_) | Self::Map(_) => fmt_nested(self, f, &mut Vec::new()),
+            Self::Range(range) => Display::fmt(range, f),
+            Self::Error(error) => Display::fmt(error, f),
+            Self::Foreign(foreign) => Display::fmt(foreign, f),
             Self::Nil => write!(f, "nil"),
         }
     }
 }
 
+/// Writes a value, writing lists as `[a, b]` and maps as `{k: v}`, and
+/// printing `[...]` or `{...}` where a list or map contains itself.
+///
+/// `open` holds the lists and maps currently being written, outermost first.
+fn fmt_nested(
+    value: &Value,
+    f: &mut std::fmt::Formatter<'_>,
+    open: &mut Vec<*const ()>,
+) -> std::fmt::Result {
+    let (ptr, cycle) = match value {
+        Value::List(list) => (Rc::as_ptr(list).cast(), "[...]"),
+        Value::Map(map) => (Rc::as_ptr(map).cast(), "{...}"),
+        other => return Display::fmt(other, f),
+    };
+    if open.contains(&ptr) {
+        return f.write_str(cycle);
+    }
+
+    open.push(ptr);
+    match value {
+        Value::List(list) => {
+            f.write_str("[")?;
+            for (i, element) in list.borrow().iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                fmt_nested(element, f, open)?;
+            }
+            f.write_str("]")?;
+        }
+        Value::Map(map) => {
+            f.write_str("{")?;
+            for (i, (key, element)) in map.borrow().iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{key}: ")?;
+                fmt_nested(element, f, open)?;
+            }
+            f.write_str("}")?;
+        }
+        _ => unreachable!(),
+    }
+    open.pop();
+    Ok(())
+}
+
 impl From<&str> for Value {
     fn from(s: &str) -> Self {
         Value::String(s.into())
@@ -54,8 +157,38 @@ impl From<f64> for Value {
     }
 }
 
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Integer(n)
+    }
+}
+
 impl From<bool> for Value {
     fn from(b: bool) -> Self {
         Value::Boolean(b)
     }
 }
+
+impl From<Vec<Value>> for Value {
+    fn from(elements: Vec<Value>) -> Self {
+        Value::List(Rc::new(RefCell::new(elements)))
+    }
+}
+
+impl From<Range> for Value {
+    fn from(range: Range) -> Self {
+        Value::Range(range)
+    }
+}
+
+impl From<Foreign> for Value {
+    fn from(foreign: Foreign) -> Self {
+        Value::Foreign(foreign)
+    }
+}
+
+impl From<Map> for Value {
+    fn from(entries: Map) -> Self {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+}
@@ -0,0 +1,694 @@
+//! Static checks that warn about code which cannot work, or does nothing,
+//! without running it.
+//!
+//! The checks follow what each variable is known to hold through the program
+//! in source order. Anything assigned inside a loop, a `match` arm or a `try`
+//! block is forgotten afterwards, so a warning is only raised for an
+//! expression that fails on every path reaching it.
+//!
+//! Locals declared with `var` or `const` that are never read are reported
+//! when their scope ends. Globals are left alone, since a module importing
+//! the file may read them, and so are names starting with `_`.
+//!
+//! Each warning has the code of one of the [`RULES`], whose level can be
+//! changed with `--allow`, `--warn` and `--deny`. Rules that flag code which
+//! is often written on purpose, such as empty blocks, are allowed unless
+//! asked for.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::mem;
+
+use serde::Serialize;
+
+use crate::Value;
+use crate::diagnostic::{self, Diagnostic, Level, Levels};
+use crate::parser::ast::{Ast, AstArena, StmtId};
+use crate::parser::expr::{self, Expr, ExprNode};
+use crate::parser::stmt::{self, Stmt, StmtNode};
+use crate::scanner::token::{Token, TokenType};
+use crate::symbol::Symbol;
+
+/// A check the linter runs, named by the code of its warnings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Rule {
+    pub code: &'static str,
+    pub description: &'static str,
+    /// The level of the rule unless configured otherwise
+    pub level: Level,
+}
+
+pub const RULES: [Rule; 6] = [
+    Rule {
+        code: "nil-safety",
+        description: "an expression always fails because it uses nil or cannot be called or indexed",
+        level: Level::Warn,
+    },
+    Rule {
+        code: "unused-variable",
+        description: "a local variable is never read",
+        level: Level::Warn,
+    },
+    Rule {
+        code: "unreachable-code",
+        description: "a statement follows a 'throw' in the same block",
+        level: Level::Warn,
+    },
+    Rule {
+        code: "bool-comparison",
+        description: "a value is compared with 'true' or 'false' instead of tested",
+        level: Level::Warn,
+    },
+    Rule {
+        code: "shadowed-name",
+        description: "a local variable hides a local of an enclosing scope",
+        level: Level::Allow,
+    },
+    Rule {
+        code: "empty-block",
+        description: "a block has no statements",
+        level: Level::Allow,
+    },
+];
+
+/// Returns the levels of the rules that are not warnings by default, to
+/// configure further and apply to what [`lint`] returns.
+pub fn default_levels() -> Levels {
+    let rules = RULES.iter().filter(|rule| rule.level != Level::Warn);
+    rules.fold(Levels::new(), |levels, rule| {
+        levels.with(rule.code, rule.level)
+    })
+}
+
+/// Returns a warning for each expression in `program` that always fails
+/// because it uses a value that is definitely nil or cannot be called or
+/// indexed, for each local variable that is never read, and for each
+/// violation of the other [`RULES`], in source order.
+///
+/// Warnings of rules allowed by default are included too, so callers should
+/// filter them through [`default_levels`].
+pub fn lint(program: &Ast) -> Vec<Diagnostic> {
+    let mut linter = Linter::default();
+    linter.statements(&program.arena, &program.statements);
+    diagnostic::sort(&mut linter.warnings);
+    linter.warnings
+}
+
+/// Checks whether running `stmt` always ends in a `throw`.
+fn throws(ast: &AstArena, stmt: StmtId) -> bool {
+    match &ast[stmt] {
+        StmtNode::Throw(_) => true,
+        StmtNode::Block(block) => block.statements.iter().any(|&stmt| throws(ast, stmt)),
+        _ => false,
+    }
+}
+
+/// What an expression is known to evaluate to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Known {
+    Nil,
+    Number,
+    String,
+    Boolean,
+    List,
+    Map,
+    /// Anything, including a function
+    Unknown,
+}
+
+impl Known {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Nil => Known::Nil,
+            Value::Number(_) | Value::Integer(_) => Known::Number,
+            Value::String(_) => Known::String,
+            Value::Boolean(_) => Known::Boolean,
+            _ => Known::Unknown,
+        }
+    }
+}
+
+impl Display for Known {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Known::Nil => "nil",
+            Known::Number => "a number",
+            Known::String => "a string",
+            Known::Boolean => "a boolean",
+            Known::List => "a list",
+            Known::Map => "a map",
+            Known::Unknown => "a value",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A variable in scope.
+#[derive(Debug, Clone)]
+struct Binding {
+    known: Known,
+    /// The name of a local declared with `var` or `const`, which is reported
+    /// unless it is read before its scope ends
+    local: Option<Token>,
+    read: bool,
+}
+
+impl Binding {
+    fn new(known: Known) -> Self {
+        Self {
+            known,
+            local: None,
+            read: false,
+        }
+    }
+}
+
+struct Linter {
+    /// The variables in each enclosing scope, innermost last
+    scopes: Vec<HashMap<Symbol, Binding>>,
+    /// The names assigned since the innermost loop or branch began
+    assigned: HashSet<Symbol>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            assigned: HashSet::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl Linter {
+    fn warn(&mut self, token: &Token, message: String) {
+        self.warnings
+            .push(Diagnostic::warning("nil-safety", token, message));
+    }
+
+    /// Checks `stmts`, the statements of a block, in order, warning about
+    /// the first one after a `throw`.
+    fn statements(&mut self, ast: &AstArena, stmts: &[StmtId]) {
+        let mut thrown = false;
+        let mut reported = false;
+        for &stmt in stmts {
+            if thrown
+                && !reported
+                && let Some(lines) = ast.lines(stmt)
+            {
+                let warning = Diagnostic::warning_at_line(
+                    "unreachable-code",
+                    lines.first,
+                    "Unreachable code after 'throw'.",
+                );
+                self.warnings.push(warning);
+                reported = true;
+            }
+            self.statement(ast, stmt);
+            thrown = thrown || throws(ast, stmt);
+        }
+    }
+
+    /// Checks `stmt`, warning if it is an empty block.
+    fn statement(&mut self, ast: &AstArena, stmt: StmtId) {
+        if let StmtNode::Block(block) = &ast[stmt]
+            && block.statements.is_empty()
+            && let Some(lines) = ast.lines(stmt)
+        {
+            let warning = Diagnostic::warning_at_line("empty-block", lines.first, "Empty block.");
+            self.warnings.push(warning);
+        }
+        stmt.accept(ast, self);
+    }
+
+    /// Warns if `name`, about to be declared in the innermost scope, hides a
+    /// variable of an enclosing local scope.
+    fn warn_shadowed(&mut self, name: &Token, enclosing: usize) {
+        let locals = self.scopes.get(1..enclosing).unwrap_or_default();
+        if !name.lexeme.starts_with('_')
+            && locals.iter().any(|scope| scope.contains_key(&name.lexeme))
+        {
+            let message = format!("Variable '{}' shadows an outer variable.", name.lexeme);
+            self.warnings
+                .push(Diagnostic::warning("shadowed-name", name, message));
+        }
+    }
+
+    fn declare(&mut self, name: &Token, known: Known) {
+        self.bind(name, Binding::new(known));
+    }
+
+    /// Declares a variable with `var` or `const`, which must be read if it is
+    /// a local.
+    fn declare_variable(&mut self, name: &Token, known: Known) {
+        let local = self.scopes.len() > 1 && !name.lexeme.starts_with('_');
+        self.warn_shadowed(name, self.scopes.len() - 1);
+        let binding = Binding {
+            local: local.then(|| name.clone()),
+            ..Binding::new(known)
+        };
+        self.bind(name, binding);
+    }
+
+    fn bind(&mut self, name: &Token, binding: Binding) {
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("the global scope is never popped");
+        // a redeclaration hides the previous variable for good
+        if let Some(hidden) = scope.insert(name.symbol(), binding) {
+            self.warn_unread(&hidden);
+        }
+    }
+
+    /// Records what `name` holds after an assignment to it.
+    fn assign(&mut self, name: &Token, known: Known) {
+        self.assigned.insert(name.symbol());
+        if let Some(binding) = self.lookup_mut(&name.lexeme) {
+            binding.known = known;
+        }
+    }
+
+    fn lookup_mut(&mut self, name: &Symbol) -> Option<&mut Binding> {
+        self.scopes.iter_mut().rev().find_map(|s| s.get_mut(name))
+    }
+
+    fn push_scope(&mut self, names: &[&Token]) {
+        for name in names {
+            self.warn_shadowed(name, self.scopes.len());
+        }
+        let scope = names
+            .iter()
+            .map(|name| (name.symbol(), Binding::new(Known::Unknown)));
+        self.scopes.push(scope.collect());
+    }
+
+    /// Ends the innermost scope, warning about the locals it never read.
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().expect("a scope was pushed");
+        for binding in scope.values() {
+            self.warn_unread(binding);
+        }
+    }
+
+    fn warn_unread(&mut self, binding: &Binding) {
+        if let Some(name) = &binding.local
+            && !binding.read
+        {
+            let message = format!("Local variable '{}' is never read.", name.lexeme);
+            self.warnings
+                .push(Diagnostic::warning("unused-variable", name, message));
+        }
+    }
+
+    fn in_scope(&mut self, ast: &AstArena, names: &[&Token], body: StmtId) {
+        self.push_scope(names);
+        self.statement(ast, body);
+        self.pop_scope();
+    }
+
+    /// Checks `body` as code that may or may not run, forgetting what it
+    /// assigns.
+    fn branch(&mut self, ast: &AstArena, names: &[&Token], body: StmtId) {
+        let outer = mem::take(&mut self.assigned);
+        self.in_scope(ast, names, body);
+        let assigned = mem::replace(&mut self.assigned, outer);
+        self.forget(&assigned);
+    }
+
+    /// Checks `body` as the body of a loop. Whatever it assigns is forgotten
+    /// before the body is checked, since a later iteration sees it changed.
+    fn repeat(&mut self, ast: &AstArena, names: &[&Token], body: StmtId) {
+        // a first, silent pass finds what the body assigns
+        let (scopes, warnings) = (self.scopes.clone(), mem::take(&mut self.warnings));
+        let outer = mem::take(&mut self.assigned);
+        self.in_scope(ast, names, body);
+        let assigned = mem::replace(&mut self.assigned, outer);
+        self.scopes = scopes;
+        self.warnings = warnings;
+
+        self.forget(&assigned);
+        self.branch(ast, names, body);
+    }
+
+    fn forget(&mut self, names: &HashSet<Symbol>) {
+        for name in names {
+            self.assigned.insert(name.clone());
+            if let Some(binding) = self.lookup_mut(name) {
+                binding.known = Known::Unknown;
+            }
+        }
+    }
+}
+
+impl stmt::Visitor for Linter {
+    type Output = ();
+
+    fn visit_print_stmt(&mut self, ast: &AstArena, stmt: &stmt::Print) {
+        stmt.expr.accept(ast, self);
+    }
+
+    fn visit_expression_stmt(&mut self, ast: &AstArena, stmt: &stmt::Expression) {
+        stmt.expr.accept(ast, self);
+    }
+
+    fn visit_var_stmt(&mut self, ast: &AstArena, stmt: &stmt::Var) {
+        let known = match &stmt.initializer {
+            Some(initializer) => initializer.accept(ast, self),
+            None => Known::Nil,
+        };
+        self.declare_variable(&stmt.name, known);
+    }
+
+    fn visit_var_unpack_stmt(&mut self, ast: &AstArena, stmt: &stmt::VarUnpack) {
+        stmt.value.accept(ast, self);
+        for name in &stmt.names {
+            self.declare_variable(name, Known::Unknown);
+        }
+    }
+
+    fn visit_block_stmt(&mut self, ast: &AstArena, stmt: &stmt::Block) {
+        self.push_scope(&[]);
+        self.statements(ast, &stmt.statements);
+        self.pop_scope();
+    }
+
+    fn visit_for_in_stmt(&mut self, ast: &AstArena, stmt: &stmt::ForIn) {
+        stmt.iterable.accept(ast, self);
+        self.repeat(ast, &[&stmt.name], stmt.body);
+    }
+
+    fn visit_do_while_stmt(&mut self, ast: &AstArena, stmt: &stmt::DoWhile) {
+        self.repeat(ast, &[], stmt.body);
+        stmt.condition.accept(ast, self);
+    }
+
+    fn visit_try_stmt(&mut self, ast: &AstArena, stmt: &stmt::Try) {
+        self.branch(ast, &[], stmt.body);
+        if let Some((name, body)) = &stmt.catch {
+            self.branch(ast, &[name], *body);
+        }
+        if let Some(finally) = &stmt.finally {
+            self.statement(ast, *finally);
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, ast: &AstArena, stmt: &stmt::Throw) {
+        stmt.value.accept(ast, self);
+    }
+
+    fn visit_assert_stmt(&mut self, ast: &AstArena, stmt: &stmt::Assert) {
+        stmt.condition.accept(ast, self);
+        if let Some(message) = &stmt.message {
+            message.accept(ast, self);
+        }
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &stmt::Import) {
+        for name in &stmt.names {
+            self.declare(name, Known::Unknown);
+        }
+    }
+
+    fn visit_match_stmt(&mut self, ast: &AstArena, stmt: &stmt::Match) {
+        stmt.value.accept(ast, self);
+        for (_, body) in &stmt.arms {
+            self.branch(ast, &[], *body);
+        }
+        if let Some(otherwise) = &stmt.otherwise {
+            self.branch(ast, &[], *otherwise);
+        }
+    }
+}
+
+impl expr::Visitor for Linter {
+    type Output = Known;
+
+    fn visit_literal_expr(&mut self, expr: &expr::Literal) -> Known {
+        Known::of(&expr.value)
+    }
+
+    fn visit_grouping_expr(&mut self, ast: &AstArena, expr: &expr::Grouping) -> Known {
+        expr.expression.accept(ast, self)
+    }
+
+    fn visit_unary_expr(&mut self, ast: &AstArena, expr: &expr::Unary) -> Known {
+        let right = expr.right.accept(ast, self);
+        if expr.operator.typ == TokenType::Minus && right == Known::Nil {
+            self.warn(&expr.operator, "Negating nil always fails.".into());
+        }
+        Known::Unknown
+    }
+
+    fn visit_variable_expr(&mut self, expr: &expr::Variable) -> Known {
+        let binding = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(&expr.name.lexeme));
+        match binding {
+            Some(binding) => {
+                binding.read = true;
+                binding.known
+            }
+            None => Known::Unknown,
+        }
+    }
+
+    fn visit_assign_expr(&mut self, ast: &AstArena, expr: &expr::Assign) -> Known {
+        let known = expr.value.accept(ast, self);
+        self.assign(&expr.name, known);
+        known
+    }
+
+    fn visit_assign_unpack_expr(&mut self, ast: &AstArena, expr: &expr::AssignUnpack) -> Known {
+        let known = expr.value.accept(ast, self);
+        for name in &expr.names {
+            self.assign(name, Known::Unknown);
+        }
+        known
+    }
+
+    fn visit_binary_expr(&mut self, ast: &AstArena, expr: &expr::Binary) -> Known {
+        let left = expr.left.accept(ast, self);
+        let right = expr.right.accept(ast, self);
+
+        // equality accepts anything, and `+` may convert nil to a string
+        let op = &expr.operator;
+        let accepts_nil = matches!(
+            op.typ,
+            TokenType::EqualEqual | TokenType::BangEqual | TokenType::Plus
+        );
+        if !accepts_nil && (left == Known::Nil || right == Known::Nil) {
+            self.warn(op, format!("'{}' on nil always fails.", op.lexeme));
+        }
+
+        // `x == true` is false for truthy values other than true itself
+        let is_equality = matches!(op.typ, TokenType::EqualEqual | TokenType::BangEqual);
+        let boolean = [expr.left, expr.right]
+            .into_iter()
+            .find_map(|side| match ast.expr(side) {
+                ExprNode::Literal(expr::Literal {
+                    value: Value::Boolean(b),
+                    ..
+                }) => Some(*b),
+                _ => None,
+            });
+        if is_equality && let Some(b) = boolean {
+            let message = format!("Suspicious comparison with '{b}'; test the value itself.");
+            self.warnings
+                .push(Diagnostic::warning("bool-comparison", op, message));
+        }
+        Known::Unknown
+    }
+
+    fn visit_coalesce_expr(&mut self, ast: &AstArena, expr: &expr::Coalesce) -> Known {
+        match expr.left.accept(ast, self) {
+            Known::Nil => expr.right.accept(ast, self),
+            Known::Unknown => {
+                // the right side may not run
+                let outer = mem::take(&mut self.assigned);
+                expr.right.accept(ast, self);
+                let assigned = mem::replace(&mut self.assigned, outer);
+                self.forget(&assigned);
+                Known::Unknown
+            }
+            known => known,
+        }
+    }
+
+    fn visit_cached_expr(&mut self, ast: &AstArena, expr: &expr::Cached) -> Known {
+        expr.expr.accept(ast, self)
+    }
+
+    fn visit_call_expr(&mut self, ast: &AstArena, expr: &expr::Call) -> Known {
+        let callee = expr.callee.accept(ast, self);
+        for argument in &expr.arguments {
+            argument.accept(ast, self);
+        }
+        if callee != Known::Unknown {
+            self.warn(&expr.paren, format!("Calling {callee} always fails."));
+        }
+        Known::Unknown
+    }
+
+    fn visit_list_expr(&mut self, ast: &AstArena, expr: &expr::List) -> Known {
+        for element in &expr.elements {
+            element.accept(ast, self);
+        }
+        Known::List
+    }
+
+    fn visit_tuple_expr(&mut self, ast: &AstArena, expr: &expr::Tuple) -> Known {
+        for element in &expr.elements {
+            element.accept(ast, self);
+        }
+        Known::List
+    }
+
+    fn visit_spread_expr(&mut self, ast: &AstArena, expr: &expr::Spread) -> Known {
+        expr.expression.accept(ast, self);
+        Known::Unknown
+    }
+
+    fn visit_map_expr(&mut self, ast: &AstArena, expr: &expr::Map) -> Known {
+        for (key, value) in &expr.entries {
+            key.accept(ast, self);
+            value.accept(ast, self);
+        }
+        Known::Map
+    }
+
+    fn visit_index_expr(&mut self, ast: &AstArena, expr: &expr::Index) -> Known {
+        let object = expr.object.accept(ast, self);
+        expr.index.accept(ast, self);
+        if !matches!(object, Known::List | Known::Map | Known::Unknown) {
+            self.warn(&expr.bracket, format!("Indexing {object} always fails."));
+        }
+        Known::Unknown
+    }
+
+    fn visit_set_index_expr(&mut self, ast: &AstArena, expr: &expr::SetIndex) -> Known {
+        let object = expr.object.accept(ast, self);
+        expr.index.accept(ast, self);
+        let value = expr.value.accept(ast, self);
+        if !matches!(object, Known::List | Known::Map | Known::Unknown) {
+            self.warn(&expr.bracket, format!("Indexing {object} always fails."));
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::dialect::{Dialect, Extension};
+    use crate::validate::validate_with;
+
+    /// Lints `source` with the rules at their default levels.
+    fn warnings(source: &str) -> Vec<String> {
+        warnings_with(source, &default_levels())
+    }
+
+    fn warnings_with(source: &str, levels: &Levels) -> Vec<String> {
+        let dialect: Dialect = [
+            Extension::Lists,
+            Extension::Maps,
+            Extension::ForIn,
+            Extension::Match,
+            Extension::Exceptions,
+            Extension::NilCoalescing,
+            Extension::Destructuring,
+        ]
+        .into_iter()
+        .collect();
+        let program = validate_with(source, dialect).ast.unwrap();
+        lint(&program)
+            .into_iter()
+            .filter_map(|warning| levels.apply(warning))
+            .map(|warning| format!("{}: {}", warning.line.unwrap(), warning.message))
+            .collect()
+    }
+
+    #[rstest]
+    #[case("var f; f();", vec!["1: Calling nil always fails."])]
+    #[case("var f = nil; var g = f; g(1);", vec!["1: Calling nil always fails."])]
+    #[case("\"text\"();\n1();", vec!["1: Calling a string always fails.", "2: Calling a number always fails."])]
+    #[case("var xs; print xs[0];", vec!["1: Indexing nil always fails."])]
+    #[case("var n = 1; n[0] = 2;", vec!["1: Indexing a number always fails."])]
+    #[case("var x; print -x;\nprint x * 2;", vec!["1: Negating nil always fails.", "2: '*' on nil always fails."])]
+    #[case("var f = nil ?? 1; f();", vec!["1: Calling a number always fails."])]
+    #[case("var f; { var f = g; f(); } f();", vec!["1: Calling nil always fails."])]
+    fn test_lint_warns(#[case] source: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(expected, warnings(source));
+    }
+
+    #[rstest]
+    #[case("var f = clock; f();")]
+    #[case("var f; f = clock; f();")]
+    #[case("var xs = [1]; var m = {}; print xs[0]; print m[\"k\"];")]
+    #[case("var x; print x == nil; print \"x: \" + x;")]
+    #[case("var f; for (i in xs) { f(); f = g; }")]
+    #[case("var f; match (x) { 1 => f = g; } f();")]
+    #[case("var f; try { f = g; } catch (e) { } f();")]
+    #[case("var f = g ?? h; f();")]
+    fn test_lint_allows(#[case] source: &str) {
+        assert_eq!(Vec::<String>::new(), warnings(source));
+    }
+
+    #[rstest]
+    #[case("{ var x = 1; }", vec!["1: Local variable 'x' is never read."])]
+    #[case("{ var x; x = 2; }", vec!["1: Local variable 'x' is never read."])]
+    #[case("{ var x = 1;\n var x = 2; print x; }", vec!["1: Local variable 'x' is never read."])]
+    #[case("{ var x = 1; { var x = 2; } print x; }", vec!["1: Local variable 'x' is never read."])]
+    #[case(
+        "for (i in xs) {\n  var a;\n  var b = nil; b();\n}",
+        vec!["2: Local variable 'a' is never read.", "3: Calling nil always fails."]
+    )]
+    #[case("{ var (a, b) = [1, 2]; print a; }", vec!["1: Local variable 'b' is never read."])]
+    fn test_lint_warns_unread_locals(#[case] source: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(expected, warnings(source));
+    }
+
+    #[rstest]
+    #[case("var x = 1;")]
+    #[case("{ var _x = 1; }")]
+    #[case("for (i in xs) { }")]
+    #[case("{ var x = 1; { print x; } }")]
+    #[case("{ var x = 1; for (i in xs) { print x; } }")]
+    #[case("{ var x = 1; match (y) { 1 => print x; } }")]
+    fn test_lint_allows_read_locals(#[case] source: &str) {
+        assert_eq!(Vec::<String>::new(), warnings(source));
+    }
+
+    #[rstest]
+    #[case("throw 1;\nprint 2;\nprint 3;", vec!["2: Unreachable code after 'throw'."])]
+    #[case("{ { throw 1; } print 2; }", vec!["1: Unreachable code after 'throw'."])]
+    #[case("try { throw 1; } catch (e) { print e; } print 2;", vec![])]
+    #[case(
+        "var x = 1;\nprint x == true;\nprint false != x;",
+        vec!["2: Suspicious comparison with 'true'; test the value itself.", "3: Suspicious comparison with 'false'; test the value itself."]
+    )]
+    #[case("var x = 1; print x == nil; print x and true;", vec![])]
+    fn test_lint_warns_by_default(#[case] source: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(expected, warnings(source));
+    }
+
+    #[rstest]
+    #[case("{ var x = 1; { var x = 2; print x; } print x; }", vec!["1: Variable 'x' shadows an outer variable."])]
+    #[case("for (i in xs) { for (i in ys) print i; }", vec!["1: Variable 'i' shadows an outer variable."])]
+    #[case("var x = 1; { var x = 2; print x; }", vec![])]
+    #[case("{ var _x = 1; { var _x = 2; print _x; } print _x; }", vec![])]
+    #[case("{}\ntry { print 1; } catch (e) { }", vec!["1: Empty block.", "2: Empty block."])]
+    fn test_lint_allowed_by_default(#[case] source: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(Vec::<String>::new(), warnings(source));
+
+        let levels = default_levels()
+            .with("shadowed-name", Level::Warn)
+            .with("empty-block", Level::Warn);
+        assert_eq!(expected, warnings_with(source, &levels));
+    }
+}
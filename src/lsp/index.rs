@@ -0,0 +1,354 @@
+//! What each name in a program refers to: the declarations of its variables,
+//! and every place they are read or assigned.
+//!
+//! Names resolve the way the interpreter looks them up, to the innermost
+//! declaration in scope at that point of the source. A name declared
+//! nowhere before it is used is either a native function or undefined.
+
+use std::collections::HashMap;
+
+use crate::Value;
+use crate::parser::ast::{Ast, AstArena, ExprId, StmtId};
+use crate::parser::expr::ExprNode;
+use crate::parser::stmt::StmtNode;
+use crate::scanner::token::Token;
+use crate::symbol::Symbol;
+
+/// How a variable is declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Variable,
+    Constant,
+    /// The variable of a `for (x in ...)` loop
+    Loop,
+    /// The error caught by a `catch (e)`
+    Catch,
+    /// A name bound by an `import`
+    Import,
+}
+
+/// A declaration of a variable.
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: Token,
+    pub kind: Kind,
+    /// The literal the variable is declared with, if it is declared with one
+    pub value: Option<Value>,
+}
+
+/// A use of a variable, by reading or assigning it.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: Token,
+    /// The position of the definition in [`Index::definitions`], or `None`
+    /// if the name is not declared in scope
+    pub definition: Option<usize>,
+}
+
+/// The definitions and references of a program, in source order.
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    pub definitions: Vec<Definition>,
+    pub references: Vec<Reference>,
+}
+
+/// A name at some position in the source.
+#[derive(Debug, Clone, Copy)]
+pub enum Name<'a> {
+    /// The name a definition declares
+    Definition(usize),
+    Reference(&'a Reference),
+}
+
+impl Index {
+    /// Resolves every name in `program`.
+    pub fn new(program: &Ast) -> Self {
+        let mut resolver = Resolver {
+            scopes: vec![HashMap::new()],
+            index: Index::default(),
+        };
+        for &stmt in &program.statements {
+            resolver.stmt(&program.arena, stmt);
+        }
+        resolver.index
+    }
+
+    /// Returns the name whose token covers the 1-based `column` of `line`.
+    pub fn name_at(&self, line: u32, column: u32) -> Option<Name<'_>> {
+        let covers =
+            |token: &Token| token.line == line && (token.column..end(token)).contains(&column);
+        if let Some(definition) = self.definitions.iter().position(|d| covers(&d.name)) {
+            return Some(Name::Definition(definition));
+        }
+        self.references
+            .iter()
+            .find(|reference| covers(&reference.name))
+            .map(Name::Reference)
+    }
+
+    /// Returns where the definition at `definition` is read or assigned.
+    pub fn references_to(&self, definition: usize) -> impl Iterator<Item = &Token> {
+        self.references
+            .iter()
+            .filter(move |reference| reference.definition == Some(definition))
+            .map(|reference| &reference.name)
+    }
+}
+
+/// Returns the column just past `token`, which is on a single line as names
+/// are.
+pub(super) fn end(token: &Token) -> u32 {
+    token.column + token.lexeme.chars().count() as u32
+}
+
+struct Resolver {
+    /// The definitions each enclosing scope declares, innermost last
+    scopes: Vec<HashMap<Symbol, usize>>,
+    index: Index,
+}
+
+impl Resolver {
+    fn declare(&mut self, name: &Token, kind: Kind, value: Option<Value>) {
+        let definition = self.index.definitions.len();
+        self.index.definitions.push(Definition {
+            name: name.clone(),
+            kind,
+            value,
+        });
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("the global scope is never popped");
+        scope.insert(name.symbol(), definition);
+    }
+
+    fn refer(&mut self, name: &Token) {
+        let definition = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme).copied());
+        self.index.references.push(Reference {
+            name: name.clone(),
+            definition,
+        });
+    }
+
+    /// Resolves `body` in a new scope declaring `name`, of `kind`.
+    fn scoped(&mut self, ast: &AstArena, name: Option<(&Token, Kind)>, body: StmtId) {
+        self.scopes.push(HashMap::new());
+        if let Some((name, kind)) = name {
+            self.declare(name, kind, None);
+        }
+        self.stmt(ast, body);
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, ast: &AstArena, stmt: StmtId) {
+        match &ast[stmt] {
+            StmtNode::Print(print) => self.expr(ast, print.expr),
+            StmtNode::Expression(expression) => self.expr(ast, expression.expr),
+            StmtNode::Var(var) => {
+                // the initializer still sees any variable the name hides
+                if let Some(initializer) = var.initializer {
+                    self.expr(ast, initializer);
+                }
+                let value = match var.initializer.map(|initializer| &ast[initializer]) {
+                    Some(ExprNode::Literal(literal)) => Some(literal.value.clone()),
+                    Some(_) => None,
+                    None => Some(Value::Nil),
+                };
+                let kind = if var.constant {
+                    Kind::Constant
+                } else {
+                    Kind::Variable
+                };
+                self.declare(&var.name, kind, value);
+            }
+            StmtNode::VarUnpack(unpack) => {
+                self.expr(ast, unpack.value);
+                for name in &unpack.names {
+                    self.declare(name, Kind::Variable, None);
+                }
+            }
+            StmtNode::Block(block) => {
+                self.scopes.push(HashMap::new());
+                for &stmt in &block.statements {
+                    self.stmt(ast, stmt);
+                }
+                self.scopes.pop();
+            }
+            StmtNode::ForIn(for_in) => {
+                self.expr(ast, for_in.iterable);
+                self.scoped(ast, Some((&for_in.name, Kind::Loop)), for_in.body);
+            }
+            StmtNode::DoWhile(do_while) => {
+                self.stmt(ast, do_while.body);
+                self.expr(ast, do_while.condition);
+            }
+            StmtNode::Try(try_stmt) => {
+                self.stmt(ast, try_stmt.body);
+                if let Some((name, body)) = &try_stmt.catch {
+                    self.scoped(ast, Some((name, Kind::Catch)), *body);
+                }
+                if let Some(finally) = try_stmt.finally {
+                    self.stmt(ast, finally);
+                }
+            }
+            StmtNode::Throw(throw) => self.expr(ast, throw.value),
+            StmtNode::Assert(assert) => {
+                self.expr(ast, assert.condition);
+                if let Some(message) = assert.message {
+                    self.expr(ast, message);
+                }
+            }
+            StmtNode::Import(import) => {
+                for name in &import.names {
+                    self.declare(name, Kind::Import, None);
+                }
+            }
+            StmtNode::Match(match_stmt) => {
+                self.expr(ast, match_stmt.value);
+                let bodies = match_stmt.arms.iter().map(|(_, body)| *body);
+                for body in bodies.chain(match_stmt.otherwise) {
+                    self.stmt(ast, body);
+                }
+            }
+        }
+    }
+
+    fn expr(&mut self, ast: &AstArena, expr: ExprId) {
+        match &ast[expr] {
+            ExprNode::Variable(variable) => self.refer(&variable.name),
+            ExprNode::Assign(assign) => {
+                self.expr(ast, assign.value);
+                self.refer(&assign.name);
+            }
+            ExprNode::AssignUnpack(unpack) => {
+                self.expr(ast, unpack.value);
+                for name in &unpack.names {
+                    self.refer(name);
+                }
+            }
+            ExprNode::Grouping(grouping) => self.expr(ast, grouping.expression),
+            ExprNode::Unary(unary) => self.expr(ast, unary.right),
+            ExprNode::Binary(binary) => {
+                self.expr(ast, binary.left);
+                self.expr(ast, binary.right);
+            }
+            ExprNode::Coalesce(coalesce) => {
+                self.expr(ast, coalesce.left);
+                self.expr(ast, coalesce.right);
+            }
+            ExprNode::Cached(cached) => self.expr(ast, cached.expr),
+            ExprNode::Call(call) => {
+                self.expr(ast, call.callee);
+                for &argument in &call.arguments {
+                    self.expr(ast, argument);
+                }
+            }
+            ExprNode::List(list) => {
+                for &element in &list.elements {
+                    self.expr(ast, element);
+                }
+            }
+            ExprNode::Tuple(tuple) => {
+                for &element in &tuple.elements {
+                    self.expr(ast, element);
+                }
+            }
+            ExprNode::Spread(spread) => self.expr(ast, spread.expression),
+            ExprNode::Map(map) => {
+                for &(key, value) in &map.entries {
+                    self.expr(ast, key);
+                    self.expr(ast, value);
+                }
+            }
+            ExprNode::Index(index) => {
+                self.expr(ast, index.object);
+                self.expr(ast, index.index);
+            }
+            ExprNode::SetIndex(set_index) => {
+                self.expr(ast, set_index.object);
+                self.expr(ast, set_index.index);
+                self.expr(ast, set_index.value);
+            }
+            ExprNode::Literal(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::{Dialect, Extension};
+    use crate::validate::validate_with;
+
+    fn index(source: &str) -> Index {
+        let dialect = Dialect::new()
+            .with(Extension::ForIn)
+            .with(Extension::Ranges)
+            .with(Extension::Exceptions);
+        Index::new(&validate_with(source, dialect).ast.unwrap())
+    }
+
+    /// Describes each reference as `name@line:column -> line:column` of its
+    /// definition, or `-> ?` if it has none.
+    fn resolved(index: &Index) -> Vec<String> {
+        let position = |token: &Token| format!("{}:{}", token.line, token.column);
+        let references = index.references.iter().map(|reference| {
+            let definition = reference
+                .definition
+                .map_or("?".to_string(), |d| position(&index.definitions[d].name));
+            format!(
+                "{}@{} -> {definition}",
+                reference.name.lexeme,
+                position(&reference.name)
+            )
+        });
+        references.collect()
+    }
+
+    #[test]
+    fn test_index_resolves_scopes() {
+        let index = index(
+            "var a = 1;\n{ var a = a + 1; print a; }\nfor (i in 0..a) print i;\ntry { throw a; } \
+             catch (e) { a = e; }\nprint len(b);",
+        );
+        assert_eq!(
+            vec![
+                "a@2:11 -> 1:5",
+                "a@2:24 -> 2:7",
+                "a@3:14 -> 1:5",
+                "i@3:23 -> 3:6",
+                "a@4:13 -> 1:5",
+                "e@4:34 -> 4:25",
+                "a@4:30 -> 1:5",
+                "len@5:7 -> ?",
+                "b@5:11 -> ?",
+            ],
+            resolved(&index)
+        );
+
+        let kinds = index.definitions.iter().map(|d| d.kind).collect::<Vec<_>>();
+        assert_eq!(
+            vec![Kind::Variable, Kind::Variable, Kind::Loop, Kind::Catch],
+            kinds
+        );
+        assert_eq!(Some(Value::Number(1.0)), index.definitions[0].value);
+        assert_eq!(None, index.definitions[1].value);
+    }
+
+    #[test]
+    fn test_index_name_at() {
+        let index = index("var count = 1;\nprint count + count;");
+        assert!(matches!(index.name_at(1, 5), Some(Name::Definition(0))));
+        assert!(matches!(index.name_at(1, 9), Some(Name::Definition(0))));
+        assert!(index.name_at(1, 10).is_none());
+        let Some(Name::Reference(reference)) = index.name_at(2, 19) else {
+            panic!("the second 'count' should be a reference");
+        };
+        assert_eq!((2, 15), (reference.name.line, reference.name.column));
+        assert_eq!(2, index.references_to(0).count());
+    }
+}
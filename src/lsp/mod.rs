@@ -0,0 +1,686 @@
+//! A Language Server Protocol server, so that editors can show errors while
+//! programs are typed, jump between the declaration and the uses of a
+//! variable, and describe names and literals on hover.
+//!
+//! The server reads requests and notifications from its input and writes
+//! responses and notifications to its output, framed like the
+//! [debug adapter](crate::dap)'s. Editors send the ranges of a document
+//! that change, and only the statements they touch are scanned and parsed
+//! again, see [`incremental`](crate::incremental). The document is then
+//! linted and its names resolved again in full.
+
+pub mod index;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use index::{Definition, Index, Kind, Name, end};
+use serde_json::{Value as Json, json};
+
+use crate::diagnostic::{self, Diagnostic, Severity};
+use crate::dialect::Dialect;
+use crate::incremental::{self, Edit};
+use crate::lint::{default_levels, lint};
+use crate::scanner::token::Token;
+use crate::{Value, framing, stdlib};
+
+/// The error code of a request for a method the server does not handle.
+const METHOD_NOT_FOUND: i32 = -32601;
+/// The error code of a request sent after `shutdown`.
+const INVALID_REQUEST: i32 = -32600;
+
+/// The `SymbolKind`s of the protocol the server reports.
+const VARIABLE: u32 = 13;
+const CONSTANT: u32 = 14;
+
+/// A document the editor has open, and what was found in its text.
+struct Document {
+    analysis: incremental::Document,
+    /// What lint warns about in the statements that parsed
+    warnings: Vec<Diagnostic>,
+    index: Index,
+}
+
+impl Document {
+    fn new(analysis: incremental::Document) -> Self {
+        Self {
+            warnings: lint(analysis.ast()),
+            index: Index::new(analysis.ast()),
+            analysis,
+        }
+    }
+
+    fn text(&self) -> &str {
+        self.analysis.source()
+    }
+
+    /// Converts a 1-based line and column, counted in `char`s, into a
+    /// position of the protocol, 0-based and counted in UTF-16 code units.
+    fn position(&self, line: u32, column: u32) -> Json {
+        let text = self.text().lines().nth(line.saturating_sub(1) as usize);
+        let before = text
+            .unwrap_or_default()
+            .chars()
+            .take(column.saturating_sub(1) as usize);
+        let character = before.map(char::len_utf16).sum::<usize>();
+        json!({ "line": line.saturating_sub(1), "character": character })
+    }
+
+    /// Converts a position of the protocol back into a 1-based line and
+    /// column, or returns `None` if it is past the end of the text.
+    fn location(&self, position: &Json) -> Option<(u32, u32)> {
+        let line = position["line"].as_u64()?;
+        let character = position["character"].as_u64()? as usize;
+        let text = self.text().lines().nth(line as usize)?;
+        let mut units = 0;
+        let before = text.chars().take_while(|c| {
+            units += c.len_utf16();
+            units <= character
+        });
+        Some((line as u32 + 1, before.count() as u32 + 1))
+    }
+
+    /// Returns the range `token` spans.
+    fn range(&self, token: &Token) -> Json {
+        json!({
+            "start": self.position(token.line, token.column),
+            "end": self.position(token.line, end(token)),
+        })
+    }
+
+    /// Returns the range a diagnostic points at: the token at its column, or
+    /// the whole line if it has none.
+    fn diagnostic_range(&self, diagnostic: &Diagnostic) -> Json {
+        let line = diagnostic.line.unwrap_or(1);
+        let token = self
+            .analysis
+            .tokens()
+            .find(|token| Some(token.column) == diagnostic.column && token.line == line);
+        let (start, end) = match (token, diagnostic.column) {
+            (Some(token), _) => (token.column, end(token).max(token.column + 1)),
+            (None, Some(column)) => (column, column + 1),
+            (None, None) => {
+                let text = self.text().lines().nth(line.saturating_sub(1) as usize);
+                (1, text.unwrap_or_default().chars().count() as u32 + 1)
+            }
+        };
+        json!({ "start": self.position(line, start), "end": self.position(line, end) })
+    }
+
+    /// Returns the errors and warnings to show: the syntax errors, the lint
+    /// warnings at their default levels, and the names declared nowhere.
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        let levels = default_levels();
+        let errors = self.analysis.errors().map(Diagnostic::from);
+        let warnings = self.warnings.iter().cloned();
+        let undefined = self.index.references.iter().filter(|reference| {
+            reference.definition.is_none() && native(&reference.name.lexeme).is_none()
+        });
+        let undefined = undefined.map(|reference| {
+            let message = format!("Undefined variable '{}'.", reference.name.lexeme);
+            Diagnostic::warning("undefined-variable", &reference.name, message)
+        });
+        let mut diagnostics = errors
+            .chain(warnings.chain(undefined).filter_map(|w| levels.apply(w)))
+            .collect::<Vec<_>>();
+        diagnostic::sort(&mut diagnostics);
+        diagnostics
+    }
+
+    /// Returns the definition declaring the name at `position`, or the one it
+    /// refers to.
+    fn definition_at(&self, position: &Json) -> Option<usize> {
+        let (line, column) = self.location(position)?;
+        match self.index.name_at(line, column)? {
+            Name::Definition(definition) => Some(definition),
+            Name::Reference(reference) => reference.definition,
+        }
+    }
+
+    /// Describes the name or literal at `position`.
+    fn hover(&self, position: &Json) -> Option<Json> {
+        let (line, column) = self.location(position)?;
+        let definitions = &self.index.definitions;
+        let (token, text) = match self.index.name_at(line, column) {
+            Some(Name::Definition(definition)) => {
+                let definition = &definitions[definition];
+                (&definition.name, describe(definition))
+            }
+            Some(Name::Reference(reference)) => match reference.definition {
+                Some(definition) => (&reference.name, describe(&definitions[definition])),
+                None => (&reference.name, native(&reference.name.lexeme)?),
+            },
+            None => {
+                let token = self.analysis.tokens().find(|token| {
+                    token.line == line && (token.column..end(token)).contains(&column)
+                })?;
+                (token, literal(token.literal.as_ref()?))
+            }
+        };
+        Some(json!({
+            "contents": { "kind": "plaintext", "value": text },
+            "range": self.range(token),
+        }))
+    }
+
+    /// Returns a symbol for each variable the document declares.
+    fn symbols(&self) -> Json {
+        let symbols = self.index.definitions.iter().map(|definition| {
+            let kind = match definition.kind {
+                Kind::Constant => CONSTANT,
+                _ => VARIABLE,
+            };
+            json!({
+                "name": definition.name.lexeme.as_str(),
+                "detail": describe(definition),
+                "kind": kind,
+                "range": self.range(&definition.name),
+                "selectionRange": self.range(&definition.name),
+            })
+        });
+        symbols.collect()
+    }
+}
+
+/// Converts a position of the protocol into a byte offset in `text`. A
+/// position past the end of its line is at the end of the line, and one past
+/// the last line at the end of the text.
+fn offset(text: &str, position: &Json) -> Option<usize> {
+    let line = position["line"].as_u64()? as usize;
+    let character = position["character"].as_u64()? as usize;
+    let start = text
+        .split_inclusive('\n')
+        .take(line)
+        .map(str::len)
+        .sum::<usize>();
+    let rest = text[start..].split('\n').next().unwrap_or_default();
+    let mut units = 0;
+    let past = rest.char_indices().find(|(_, c)| {
+        units += c.len_utf16();
+        units > character
+    });
+    Some(start + past.map_or(rest.len(), |(at, _)| at))
+}
+
+/// Describes how `definition` declares its variable, with the literal it is
+/// declared with.
+fn describe(definition: &Definition) -> String {
+    let name = &definition.name.lexeme;
+    let keyword = match definition.kind {
+        Kind::Variable => "var",
+        Kind::Constant => "const",
+        Kind::Loop => return format!("loop variable {name}"),
+        Kind::Catch => return format!("caught error {name}"),
+        Kind::Import => return format!("imported {name}"),
+    };
+    match &definition.value {
+        Some(value) => format!("{keyword} {name} = {}", literal(value)),
+        None => format!("{keyword} {name}"),
+    }
+}
+
+/// Writes `value` like its literal, quoting strings.
+fn literal(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{s:?}"),
+        value => value.to_string(),
+    }
+}
+
+/// Describes the native function called `name`, if there is one.
+fn native(name: &str) -> Option<String> {
+    let native = stdlib::natives().find(|native| native.name == name)?;
+    let plural = if native.arity == 1 { "" } else { "s" };
+    Some(format!(
+        "native function {name}, taking {} argument{plural}",
+        native.arity
+    ))
+}
+
+/// Serves the editor's requests over `input` and `output`, analyzing
+/// documents with the language extensions in `dialect`.
+///
+/// Returns on the `exit` notification or at the end of the input. Fails if
+/// a message cannot be read or written.
+pub fn serve(input: impl BufRead, output: impl Write, dialect: Dialect) -> io::Result<()> {
+    let mut server = Server {
+        input,
+        output,
+        dialect,
+        documents: HashMap::new(),
+        shut_down: false,
+    };
+    while let Some(message) = framing::read(&mut server.input)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        if method == "exit" {
+            break;
+        }
+        server.message(method, &message)?;
+    }
+    Ok(())
+}
+
+struct Server<R, W> {
+    input: R,
+    output: W,
+    dialect: Dialect,
+    /// The open documents, by URI
+    documents: HashMap<String, Document>,
+    /// Whether the editor asked the server to shut down, after which it only
+    /// waits to exit
+    shut_down: bool,
+}
+
+impl<R: BufRead, W: Write> Server<R, W> {
+    /// Handles a request or notification calling `method`.
+    fn message(&mut self, method: &str, message: &Json) -> io::Result<()> {
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let id = &message["id"];
+
+        let result = match method {
+            _ if self.shut_down => Err((INVALID_REQUEST, "The server is shut down.".to_string())),
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    // the editor sends the ranges that change
+                    "textDocumentSync": 2,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "hoverProvider": true,
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": { "name": "rslox", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Json::Null)
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                let analysis = incremental::Document::new(text, self.dialect);
+                return self.publish(uri, Document::new(analysis));
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                return self.change(uri, changes.map_or(&[], Vec::as_slice));
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return self.notify(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                );
+            }
+            "textDocument/definition" => Ok(self.document(uri, |document| {
+                let definition = document.definition_at(&params["position"])?;
+                let name = &document.index.definitions[definition].name;
+                Some(json!({ "uri": uri, "range": document.range(name) }))
+            })),
+            "textDocument/references" => Ok(self.document(uri, |document| {
+                let declaration = params["context"]["includeDeclaration"].as_bool();
+                let definition = document.definition_at(&params["position"])?;
+                let definitions = &document.index.definitions;
+                let declaration = declaration
+                    .unwrap_or(false)
+                    .then(|| &definitions[definition].name);
+                let references = document.index.references_to(definition);
+                let locations = declaration
+                    .into_iter()
+                    .chain(references)
+                    .map(|name| json!({ "uri": uri, "range": document.range(name) }));
+                Some(locations.collect())
+            })),
+            "textDocument/hover" => {
+                Ok(self.document(uri, |document| document.hover(&params["position"])))
+            }
+            "textDocument/documentSymbol" => {
+                Ok(self.document(uri, |document| Some(document.symbols())))
+            }
+            method => Err((METHOD_NOT_FOUND, format!("Unhandled method {method}."))),
+        };
+
+        // notifications have no id, and get no response
+        if id.is_null() {
+            return Ok(());
+        }
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        framing::write(&mut self.output, &response)
+    }
+
+    /// Applies `changes` to the document at `uri`, each the text replacing a
+    /// range of it or the whole of it, and publishes what is wrong with it.
+    fn change(&mut self, uri: &str, changes: &[Json]) -> io::Result<()> {
+        let mut analysis = match self.documents.remove(uri) {
+            Some(document) => document.analysis,
+            None => incremental::Document::new("", self.dialect),
+        };
+        for change in changes {
+            let text = change["text"].as_str().unwrap_or_default();
+            let range = &change["range"];
+            let source = analysis.source();
+            match offset(source, &range["start"]).zip(offset(source, &range["end"])) {
+                Some((start, end)) => _ = analysis.edit(Edit::new(start..end.max(start), text)),
+                None => analysis = incremental::Document::new(text, self.dialect),
+            }
+        }
+        self.publish(uri, Document::new(analysis))
+    }
+
+    /// Publishes what is wrong with `document`, the new version of the one
+    /// at `uri`, and keeps it.
+    fn publish(&mut self, uri: &str, document: Document) -> io::Result<()> {
+        let diagnostics = document.diagnostics();
+        let diagnostics = diagnostics.iter().map(|diagnostic| {
+            let severity = match diagnostic.severity {
+                Severity::Error => 1,
+                Severity::Warning => 2,
+            };
+            json!({
+                "range": document.diagnostic_range(diagnostic),
+                "severity": severity,
+                "code": diagnostic.code,
+                "source": "rslox",
+                "message": diagnostic.message,
+            })
+        });
+        let params = json!({ "uri": uri, "diagnostics": diagnostics.collect::<Vec<_>>() });
+        self.documents.insert(uri.to_string(), document);
+        self.notify("textDocument/publishDiagnostics", params)
+    }
+
+    /// Answers a request about the document at `uri` with `answer`, or with
+    /// `null` if the document is not open or there is no answer.
+    fn document(&self, uri: &str, answer: impl FnOnce(&Document) -> Option<Json>) -> Json {
+        self.documents
+            .get(uri)
+            .and_then(answer)
+            .unwrap_or(Json::Null)
+    }
+
+    fn notify(&mut self, method: &str, params: Json) -> io::Result<()> {
+        let notification = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        framing::write(&mut self.output, &notification)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::dialect::Extension;
+
+    const URI: &str = "file:///main.lox";
+
+    fn request(id: u64, method: &str, params: Json) -> Json {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn notification(method: &str, params: Json) -> Json {
+        json!({ "jsonrpc": "2.0", "method": method, "params": params })
+    }
+
+    /// Asks for `method` at `line` and `character`, both 0-based, in the
+    /// test document.
+    fn at(id: u64, method: &str, line: u32, character: u32) -> Json {
+        request(
+            id,
+            method,
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": line, "character": character },
+                "context": { "includeDeclaration": true },
+            }),
+        )
+    }
+
+    /// Serves `messages` and returns every message sent back.
+    fn serve_all(messages: &[Json]) -> Vec<Json> {
+        let mut input = Vec::new();
+        for message in messages {
+            framing::write(&mut input, message).unwrap();
+        }
+        let mut output = Vec::new();
+        let dialect = Dialect::new()
+            .with(Extension::ForIn)
+            .with(Extension::Ranges)
+            .with(Extension::Constants);
+        serve(Cursor::new(input), &mut output, dialect).unwrap();
+
+        let mut output = Cursor::new(output);
+        let mut messages = Vec::new();
+        while let Some(message) = framing::read(&mut output).unwrap() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// Opens a document with `text`, then serves `messages` and returns the
+    /// result of each request they make, by id.
+    fn results(text: &str, messages: &[Json]) -> HashMap<u64, Json> {
+        let open = notification(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": URI, "languageId": "lox", "version": 1, "text": text },
+            }),
+        );
+        let messages = [&[open][..], messages].concat();
+        let responses = serve_all(&messages)
+            .into_iter()
+            .filter(|m| !m["id"].is_null());
+        responses
+            .map(|response| (response["id"].as_u64().unwrap(), response["result"].clone()))
+            .collect()
+    }
+
+    /// Describes a range as `line:character-line:character`.
+    fn span(range: &Json) -> String {
+        let position = |p: &Json| format!("{}:{}", p["line"], p["character"]);
+        format!("{}-{}", position(&range["start"]), position(&range["end"]))
+    }
+
+    #[test]
+    fn test_lsp_lifecycle() {
+        let messages = serve_all(&[
+            request(1, "initialize", json!({ "capabilities": {} })),
+            notification("initialized", json!({})),
+            notification(
+                "textDocument/didOpen",
+                json!({
+                    "textDocument": { "uri": URI, "text": "print a;\nprint (1;\n{ var x = 1; }" },
+                }),
+            ),
+            notification(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": URI, "version": 2 },
+                    "contentChanges": [{ "text": "var a = 1;\nprint a;" }],
+                }),
+            ),
+            request(2, "workspace/symbol", json!({ "query": "" })),
+            request(3, "shutdown", Json::Null),
+            request(4, "textDocument/hover", json!({})),
+            notification("exit", Json::Null),
+            request(5, "shutdown", Json::Null),
+        ]);
+        assert_eq!(6, messages.len());
+        let capabilities = &messages[0]["result"]["capabilities"];
+        assert_eq!(json!(2), capabilities["textDocumentSync"]);
+        assert_eq!(json!(true), capabilities["definitionProvider"]);
+
+        let diagnostics = messages[1]["params"]["diagnostics"].as_array().unwrap();
+        let diagnostics = diagnostics.iter().map(|diagnostic| {
+            format!(
+                "{} {} {}: {}",
+                span(&diagnostic["range"]),
+                diagnostic["severity"],
+                diagnostic["code"].as_str().unwrap(),
+                diagnostic["message"].as_str().unwrap()
+            )
+        });
+        assert_eq!(
+            vec![
+                "0:6-0:7 2 undefined-variable: Undefined variable 'a'.",
+                "1:8-1:9 1 static: Expect ')' after expression",
+                "2:6-2:7 2 unused-variable: Local variable 'x' is never read.",
+            ],
+            diagnostics.collect::<Vec<_>>()
+        );
+        assert_eq!(json!([]), messages[2]["params"]["diagnostics"]);
+        assert_eq!(json!(METHOD_NOT_FOUND), messages[3]["error"]["code"]);
+        assert_eq!(json!(null), messages[4]["result"]);
+        assert_eq!(json!(INVALID_REQUEST), messages[5]["error"]["code"]);
+    }
+
+    #[test]
+    fn test_lsp_definition_and_references() {
+        let text = "var total = 0;\nfor (i in 0..3) {\n  total = total + i;\n}\nprint total;";
+        let results = results(
+            text,
+            &[
+                at(1, "textDocument/definition", 2, 12),
+                at(2, "textDocument/references", 0, 6),
+                at(3, "textDocument/definition", 2, 18),
+                at(4, "textDocument/definition", 1, 2),
+            ],
+        );
+        assert_eq!(json!(URI), results[&1]["uri"]);
+        assert_eq!("0:4-0:9", span(&results[&1]["range"]));
+        let references = results[&2].as_array().unwrap().iter();
+        assert_eq!(
+            vec!["0:4-0:9", "2:10-2:15", "2:2-2:7", "4:6-4:11"],
+            references.map(|r| span(&r["range"])).collect::<Vec<_>>()
+        );
+        assert_eq!("1:5-1:6", span(&results[&3]["range"]));
+        assert_eq!(json!(null), results[&4]);
+    }
+
+    #[rstest]
+    #[case(0, 5, Some("var name = \"é\""))]
+    #[case(1, 13, Some("var name = \"é\""))]
+    #[case(1, 7, Some("\"é\""))]
+    #[case(1, 0, None)]
+    #[case(2, 6, Some("native function len, taking 1 argument"))]
+    #[case(2, 10, None)]
+    #[case(3, 6, Some("const n = 2.5"))]
+    #[case(3, 10, Some("2.5"))]
+    #[case(4, 5, Some("loop variable x"))]
+    #[case(4, 13, Some("const n = 2.5"))]
+    fn test_lsp_hover(#[case] line: u32, #[case] character: u32, #[case] expected: Option<&str>) {
+        let text = "var name = \"é\";\nprint \"é\" + name;\nprint len(n);\nconst n = 2.5;\nfor (x in \
+                    0..n) print x;";
+        let results = results(text, &[at(1, "textDocument/hover", line, character)]);
+        let hover = results[&1]["contents"]["value"].as_str();
+        assert_eq!(expected, hover);
+    }
+
+    #[test]
+    fn test_lsp_utf16_positions() {
+        // the emoji takes two UTF-16 code units, but is one column
+        let text = "var s = \"😀\"; var t = s;";
+        let results = results(text, &[at(1, "textDocument/definition", 0, 22)]);
+        assert_eq!("0:4-0:5", span(&results[&1]["range"]));
+
+        let document = Document::new(incremental::Document::new(text, Dialect::default()));
+        assert_eq!(
+            Some((1, 22)),
+            document.location(&json!({ "line": 0, "character": 22 }))
+        );
+        assert_eq!(
+            json!({ "line": 0, "character": 22 }),
+            document.position(1, 22)
+        );
+        assert_eq!(
+            json!({ "line": 0, "character": 11 }),
+            document.position(1, 11)
+        );
+        assert_eq!(
+            Some(24),
+            offset(text, &json!({ "line": 0, "character": 22 }))
+        );
+        assert_eq!(
+            Some(9),
+            offset(text, &json!({ "line": 0, "character": 10 }))
+        );
+        assert_eq!(
+            Some(text.len()),
+            offset(text, &json!({ "line": 3, "character": 0 }))
+        );
+    }
+
+    #[test]
+    fn test_lsp_incremental_changes() {
+        let change = |changes: Json| {
+            let document = json!({ "uri": URI, "version": 2 });
+            notification(
+                "textDocument/didChange",
+                json!({ "textDocument": document, "contentChanges": changes }),
+            )
+        };
+        let range = |start: (u32, u32), end: (u32, u32)| {
+            json!({
+                "start": { "line": start.0, "character": start.1 },
+                "end": { "line": end.0, "character": end.1 },
+            })
+        };
+        let messages = serve_all(&[
+            notification(
+                "textDocument/didOpen",
+                json!({ "textDocument": { "uri": URI, "text": "var a = 1;\nprint a;" } }),
+            ),
+            change(json!([{ "range": range((1, 6), (1, 7)), "text": "b" }])),
+            change(json!([
+                { "range": range((1, 0), (1, 0)), "text": "var b = \"😀\"; " },
+                { "range": range((1, 9), (1, 11)), "text": "é" },
+                { "range": range((5, 0), (5, 0)), "text": "\nprint a;" },
+            ])),
+            at(1, "textDocument/definition", 1, 19),
+            change(json!([{ "text": "print c;" }])),
+        ]);
+
+        let messages = messages.iter().map(|message| match &message["params"] {
+            Json::Null => span(&message["result"]["range"]),
+            params => json!(params["diagnostics"].as_array().unwrap().len()).to_string(),
+        });
+        assert_eq!(
+            vec!["0", "1", "0", "1:4-1:5", "1"],
+            messages.collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_lsp_document_symbols() {
+        let text = "var a = 1;\nconst b = a;\n{ var c; }";
+        let results = results(
+            text,
+            &[request(
+                1,
+                "textDocument/documentSymbol",
+                json!({
+                    "textDocument": { "uri": URI },
+                }),
+            )],
+        );
+        let symbols = results[&1].as_array().unwrap().iter().map(|symbol| {
+            format!(
+                "{} {} {}",
+                symbol["name"].as_str().unwrap(),
+                symbol["kind"],
+                symbol["detail"].as_str().unwrap()
+            )
+        });
+        assert_eq!(
+            vec!["a 13 var a = 1", "b 14 const b", "c 13 var c = nil"],
+            symbols.collect::<Vec<_>>()
+        );
+    }
+}
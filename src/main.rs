@@ -1,129 +1,1020 @@
 #![allow(unused_variables)]
-use std::fs::{File, OpenOptions};
-use std::path::PathBuf;
-use std::{fs, io};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{IsTerminal as _, Write as _};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+use std::{env, fs, io, process, thread};
 
-use clap::Parser as _;
+use clap::{CommandFactory as _, Parser as _, ValueEnum as _};
 use codecrafters_interpreter::cli::{Args, Command};
-use codecrafters_interpreter::error::Report;
-use codecrafters_interpreter::interpreter::Interpreter;
+use codecrafters_interpreter::compare::{Outcome, compare};
+use codecrafters_interpreter::debugger::Debugger;
+use codecrafters_interpreter::diagnostic::{
+    Diagnostic, DiagnosticFormat, DiagnosticRenderer, Level, Levels, Severity,
+};
+use codecrafters_interpreter::dialect::{Dialect, Extension};
+use codecrafters_interpreter::diff::{diff_lines, diff_tokens};
+use codecrafters_interpreter::error::{IO_ERROR, LoxError, MISMATCH, STATIC_ERROR, StaticError};
+use codecrafters_interpreter::interpreter::limits::Limit;
+use codecrafters_interpreter::interpreter::replay::{Event, read_log, write_log};
+use codecrafters_interpreter::interpreter::{ArithmeticCheck, Interpreter};
 use codecrafters_interpreter::parser::Parser;
-use codecrafters_interpreter::parser::expr::ExprNode;
-use codecrafters_interpreter::parser::printer::AstPrinter;
-use codecrafters_interpreter::scanner::token::Token;
-use codecrafters_interpreter::scanner::{ScanItem, Scanner};
+use codecrafters_interpreter::parser::ast::{AstArena, ExprId};
+use codecrafters_interpreter::parser::dot::AstDotPrinter;
+use codecrafters_interpreter::parser::json::JsonPrinter;
+use codecrafters_interpreter::parser::printer::{AstPrinter, ParseFormat};
+use codecrafters_interpreter::repl::Repl;
+use codecrafters_interpreter::scanner::Scanner;
+use codecrafters_interpreter::scanner::record::{self, CSV_HEADER, TokenFormat};
+use codecrafters_interpreter::scanner::token::BorrowedToken;
+use codecrafters_interpreter::scanner::trivia::write_source;
+use codecrafters_interpreter::source::SourceMap;
+use codecrafters_interpreter::vm::debug::disassemble;
+use codecrafters_interpreter::vm::{Backend, Vm};
+use codecrafters_interpreter::{
+    bench, build_info, compiler, dap, format, hoist, introspect, lint, lsp, optimizer,
+};
 
-/// Parses CLI arguments and dispatches to the selected subcommand.
+/// Parses CLI arguments and dispatches to the selected subcommand, exiting
+/// with the code of the error it fails with.
 fn main() {
-    let args = Args::parse();
+    if let Err(err) = dispatch(Args::parse()) {
+        if !err.message.is_empty() {
+            eprintln!("{}", err.message);
+        }
+        process::exit(err.exit_code);
+    }
+}
+
+/// Runs the subcommand selected in `args`.
+fn dispatch(args: Args) -> Result<(), LoxError> {
+    let dialect = args.dialect();
+    let levels = levels(&args)?;
+    let check = arithmetic_check(args.arithmetic_check, &levels);
+    let unit = args.string_unit;
+    let sandbox = args.sandbox;
+    let limits = args.limits.clone();
+    let format = args.diagnostics;
+
+    if let Some(reference) = &args.compare_reference {
+        return compare_reference(reference, &args, io::stdout());
+    }
 
     match args.subcommand {
-        Command::Tokenize { filename } => {
-            tokenize(filename, io::stdout());
+        Command::Tokenize {
+            filename,
+            format: token_format,
+            verify_roundtrip,
+        } => {
+            let source = read_file(filename)?;
+            let renderer = renderer(format, &source);
+            if verify_roundtrip {
+                check_roundtrip(&source, dialect, &*renderer, io::stdout())?;
+            } else {
+                let stdout = io::stdout();
+                tokenize(&source, dialect, token_format, &limits, &*renderer, stdout)?;
+            }
         }
-        Command::Parse { filename } => {
-            parse(filename, io::stdout());
+        Command::Parse {
+            filename,
+            format: parse_format,
+            typed_literals,
+        } => {
+            let source = read_file(filename)?;
+            let renderer = renderer(format, &source);
+            let printer = AstPrinter::new().with_typed_literals(typed_literals);
+            parse(
+                &source,
+                dialect,
+                &limits,
+                parse_format,
+                printer,
+                &*renderer,
+                io::stdout(),
+            )?;
         }
-        Command::Evaluate { filename } => {
-            evaluate(filename, io::stdout());
+        Command::Evaluate { filename, trace } => {
+            let source = read_file(filename)?;
+            let renderer = renderer(format, &source);
+            let mut interpreter = Interpreter::new()
+                .with_dialect(dialect)
+                .with_arithmetic_check(check)
+                .with_string_unit(unit)
+                .with_sandbox(sandbox)
+                .with_limits(limits.clone())
+                .with_renderer(renderer.clone());
+            if let Some(trace) = &trace {
+                interpreter = interpreter.with_trace(trace_sink(trace)?);
+            }
+            let stdout = io::stdout();
+            evaluate(&source, dialect, &limits, interpreter, &*renderer, stdout)?;
+        }
+        Command::Run {
+            filename,
+            demo,
+            eval,
+            trace,
+            watch,
+            module_path,
+            record,
+            replay,
+            warnings,
+            optimize,
+            hoist,
+            show_hoisted,
+            backend,
+            dump_bytecode,
+        } => {
+            // a program read from stdin has no path to resolve imports against
+            let filename = filename.filter(|filename| filename != Path::new("-"));
+            let dialect = demo.map_or(dialect, |demo| demo.dialect(dialect));
+            if trace.is_some() && (backend == Backend::Vm || dump_bytecode) {
+                return Err(LoxError::usage("--trace only applies to the tree backend"));
+            }
+            let events = replay.map(read_events).transpose()?;
+            let options = RunOptions {
+                warnings,
+                optimize,
+                hoist,
+                show_hoisted,
+                backend: if dump_bytecode { Backend::Vm } else { backend },
+                dump_bytecode,
+            };
+
+            // returns the files the program imported along with the result
+            let run_source = |source: &str| {
+                // the renderer quotes the modules the interpreter adds to these
+                let sources = SourceMap::new();
+                sources.add("", source);
+                let renderer = format.renderer(&sources, io::stderr().is_terminal());
+                let mut interpreter = Interpreter::new()
+                    .with_dialect(dialect)
+                    .with_arithmetic_check(check)
+                    .with_string_unit(unit)
+                    .with_sandbox(sandbox)
+                    .with_limits(limits.clone())
+                    .with_module_paths(module_path.clone())
+                    .with_sources(sources)
+                    .with_renderer(renderer.clone());
+                if let Some(filename) = &filename {
+                    interpreter = interpreter.with_script(filename);
+                }
+                if record.is_some() {
+                    interpreter = interpreter.with_recording();
+                }
+                if let Some(events) = &events {
+                    interpreter = interpreter.with_replay(events.clone());
+                }
+                if let Some(trace) = &trace {
+                    match trace_sink(trace) {
+                        Ok(sink) => interpreter = interpreter.with_trace(sink),
+                        Err(err) => return (Err(err), Vec::new()),
+                    }
+                }
+
+                let res = run(
+                    source,
+                    dialect,
+                    &limits,
+                    options,
+                    &levels,
+                    &mut interpreter,
+                    &*renderer,
+                );
+                // keep the log of a failed run too, since that is the one worth replaying
+                let written = match &record {
+                    Some(record) => write_events(record.clone(), interpreter.recorded_events()),
+                    None => Ok(()),
+                };
+                let imported = interpreter.imported().map(Path::to_path_buf).collect();
+                (written.and(res), imported)
+            };
+
+            if watch {
+                let Some(filename) = &filename else {
+                    return Err(LoxError::usage("--watch needs a file, not stdin"));
+                };
+                watch_file(filename, run_source);
+            }
+            let source = match (&filename, demo, eval) {
+                (_, Some(demo), _) => demo.source().to_string(),
+                (_, _, Some(code)) => code,
+                (Some(filename), None, None) => read_file(filename.clone())?,
+                (None, None, None) => read_stdin()?,
+            };
+            run_source(&source).0?;
         }
-        Command::Run { filename } => {
-            let res = run(filename);
-            if let Err(err) = res {
-                err.exit()
+        Command::Bench {
+            filename,
+            iterations,
+            backend,
+            json,
+        } => {
+            let source = read_file(filename.clone())?;
+            let renderer = renderer(format, &source);
+            let mut samples = Vec::new();
+            for _ in 0..iterations {
+                let mut interpreter = Interpreter::new()
+                    .with_dialect(dialect)
+                    .with_arithmetic_check(check)
+                    .with_string_unit(unit)
+                    .with_sandbox(sandbox)
+                    .with_muted_output(true)
+                    .with_renderer(renderer.clone())
+                    .with_script(&filename);
+                let sample = bench::sample(&source, dialect, backend, &mut interpreter);
+                samples.push(sample.map_err(|err| LoxError::report(err, &*renderer))?);
+            }
+            let results = bench::Results::new(&samples, backend);
+            if json {
+                println!("{:#}", results.json());
+            } else {
+                print!("{results}");
             }
         }
+        Command::Debug { filename } => {
+            let source = read_file(filename.clone())?;
+            let renderer = renderer(format, &source);
+            let interpreter = Interpreter::new()
+                .with_dialect(dialect)
+                .with_arithmetic_check(check)
+                .with_string_unit(unit)
+                .with_sandbox(sandbox)
+                .with_limits(limits.clone())
+                .with_renderer(renderer.clone())
+                .with_script(&filename);
+            debug(
+                &filename,
+                &source,
+                dialect,
+                &limits,
+                interpreter,
+                &*renderer,
+            )?;
+        }
+        Command::Dap => {
+            let interpreter = Interpreter::new()
+                .with_dialect(dialect)
+                .with_arithmetic_check(check)
+                .with_string_unit(unit)
+                .with_sandbox(sandbox);
+            dap::serve(io::stdin().lock(), io::stdout(), interpreter).map_err(|err| {
+                LoxError::new(
+                    IO_ERROR,
+                    format!("Failed to serve the debug adapter: {err}"),
+                )
+            })?;
+        }
+        Command::Lsp => {
+            lsp::serve(io::stdin().lock(), io::stdout(), dialect).map_err(|err| {
+                LoxError::new(
+                    IO_ERROR,
+                    format!("Failed to serve the language server: {err}"),
+                )
+            })?;
+        }
+        Command::Repl { batch } => {
+            let source = read_file(batch)?;
+            let interpreter = Interpreter::new()
+                .with_dialect(dialect)
+                .with_arithmetic_check(check)
+                .with_string_unit(unit)
+                .with_sandbox(sandbox)
+                .with_renderer(renderer(format, &source));
+            repl_batch(&source, Repl::new(interpreter), io::stdout())?;
+        }
+        Command::Lint { filename } => {
+            let source = read_file(filename)?;
+            let renderer = renderer(format, &source);
+            lint(&source, dialect, &limits, &levels, &*renderer)?;
+        }
+        Command::Fmt { filename, check } => {
+            let source = read_file(filename.clone())?;
+            let renderer = renderer(format, &source);
+            let stdout = io::stdout();
+            fmt(
+                &filename, &source, check, dialect, &limits, &*renderer, stdout,
+            )?;
+        }
+        Command::Diff { old, new } => {
+            diff(old, new, dialect, format, io::stdout())?;
+        }
+        Command::Completions { shell } => {
+            completions(shell, io::stdout());
+        }
+        Command::Introspect { json } => {
+            introspect(json, io::stdout())?;
+        }
+        Command::Version { verbose } => {
+            version(verbose, io::stdout())?;
+        }
     };
+
+    Ok(())
 }
 
-fn run(filename: PathBuf) -> Result<(), Report> {
-    let tokens = tokenize(filename, null());
-    let mut parser = Parser::from(tokens);
-    let ast = parser.parse()?;
-    let mut interpreter = Interpreter::new();
-    interpreter.interpret(&ast)?;
+/// Writes the interface manifest, as JSON or as a plain list of subcommands.
+///
+/// Fails with code `1` if the output cannot be written.
+fn introspect(json: bool, mut sink: impl io::Write) -> Result<(), LoxError> {
+    let manifest = introspect::manifest();
+    if json {
+        return writeln!(sink, "{manifest:#}").map_err(write_failed);
+    }
 
+    for subcommand in manifest["subcommands"].as_array().into_iter().flatten() {
+        let name = subcommand["name"].as_str().unwrap_or_default();
+        let about = subcommand["about"].as_str().unwrap_or_default();
+        writeln!(sink, "{name:<12} {about}").map_err(write_failed)?;
+    }
     Ok(())
 }
 
-/// Parses and evaluates a single expression file, writing the result to `sink`.
+/// Writes the crate version and, when `verbose`, the build metadata needed to
+/// pin down exact interpreter behavior in bug reports.
+///
+/// Fails with code `1` if the output cannot be written.
+fn version(verbose: bool, mut sink: impl io::Write) -> Result<(), LoxError> {
+    writeln!(sink, "{} {}", env!("CARGO_PKG_NAME"), build_info::VERSION).map_err(write_failed)?;
+    if !verbose {
+        return Ok(());
+    }
+
+    let features = build_info::features().collect::<Vec<_>>();
+    let features = if features.is_empty() {
+        "none".to_string()
+    } else {
+        features.join(", ")
+    };
+    let extensions = Extension::value_variants()
+        .iter()
+        .map(Extension::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    writeln!(sink, "git: {}", build_info::GIT_HASH).map_err(write_failed)?;
+    writeln!(sink, "features: {features}").map_err(write_failed)?;
+    writeln!(sink, "extensions: {extensions}").map_err(write_failed)
+}
+
+/// Writes a completion script for `shell` covering every subcommand and flag.
+fn completions(shell: clap_complete::Shell, mut sink: impl io::Write) {
+    let mut command = Args::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut sink);
+}
+
+/// Runs the source subcommand in `args` with both this build and `reference`,
+/// writing every difference between their outputs to `sink`.
 ///
-/// Exits with code `70` if runtime evaluation fails.
-fn evaluate(filename: PathBuf, mut sink: impl io::Write) {
-    let expr = parse(filename, null());
-    let mut interpreter = Interpreter::new();
-    match interpreter.evaluate(&expr) {
-        Ok(val) => writeln!(sink, "{}", val).unwrap(),
-        Err(err) => {
-            eprintln!("{err}");
-            std::process::exit(70);
+/// Fails with code `3` if the outputs differ, `1` if either implementation
+/// cannot be started, and `2` for subcommands that take no source file.
+fn compare_reference(
+    reference: &Path,
+    args: &Args,
+    mut sink: impl io::Write,
+) -> Result<(), LoxError> {
+    let (subcommand, filename) = match &args.subcommand {
+        Command::Tokenize { filename, .. } => ("tokenize", filename),
+        Command::Parse { filename, .. } => ("parse", filename),
+        Command::Evaluate { filename, .. } => ("evaluate", filename),
+        Command::Run {
+            filename: Some(filename),
+            ..
+        } => ("run", filename),
+        _ => {
+            return Err(LoxError::usage(
+                "--compare-reference only applies to tokenize, parse, evaluate and run on a file",
+            ));
         }
+    };
+
+    // the reference only understands standard Lox, so only this build gets the flags
+    let current_exe = env::current_exe().expect("the running executable should have a path");
+    let mut ours = process::Command::new(current_exe);
+    ours.arg(subcommand).arg(filename);
+    if !args.extensions.is_empty() {
+        let extensions = args.extensions.iter().map(Extension::to_string);
+        ours.arg("--enable")
+            .arg(extensions.collect::<Vec<_>>().join(","));
+    }
+    let check = args.arithmetic_check.to_possible_value();
+    ours.arg("--arithmetic-checks")
+        .arg(check.unwrap().get_name());
+    let unit = args.string_unit.to_possible_value();
+    ours.arg("--string-units").arg(unit.unwrap().get_name());
+    if args.sandbox {
+        ours.arg("--sandbox");
+    }
+    let format = args.diagnostics.to_possible_value();
+    ours.arg("--diagnostics").arg(format.unwrap().get_name());
+    let levels = [
+        ("--allow", &args.allow),
+        ("--warn", &args.warn),
+        ("--deny", &args.deny),
+    ];
+    for (flag, codes) in levels.into_iter().filter(|(_, codes)| !codes.is_empty()) {
+        ours.arg(flag).arg(codes.join(","));
+    }
+    if let Some(config) = &args.lint_config {
+        ours.arg("--lint-config").arg(config);
+    }
+    if !args.limits.is_empty() {
+        let limits = args.limits.iter().map(Limit::to_string);
+        ours.arg("--limits")
+            .arg(limits.collect::<Vec<_>>().join(","));
+    }
+
+    let mut theirs = process::Command::new(reference);
+    theirs.arg(subcommand).arg(filename);
+
+    let capture = |command: &mut process::Command| {
+        Outcome::capture(command).map_err(|err| {
+            let program = Path::new(command.get_program());
+            LoxError::new(
+                IO_ERROR,
+                format!("Failed to run {}: {err}", program.display()),
+            )
+        })
+    };
+    let mismatches = compare(&capture(&mut ours)?, &capture(&mut theirs)?);
+
+    if mismatches.is_empty() {
+        writeln!(sink, "Outputs match.").map_err(write_failed)?;
+        return Ok(());
+    }
+    for mismatch in mismatches {
+        writeln!(sink, "{mismatch}").map_err(write_failed)?;
     }
+    sink.flush().map_err(write_failed)?;
+    Err(LoxError::reported(MISMATCH))
 }
 
-/// Tokenizes and parses a single expression file, prints its AST form to `sink`,
-/// and returns the parsed expression node.
+/// Writes every token removed from `old` or added in `new` to `sink`.
 ///
-/// Exits with code `65` if parsing fails.
-fn parse(filename: PathBuf, mut sink: impl io::Write) -> ExprNode {
-    let tokens = tokenize(filename, null());
-    let mut parser = Parser::from(tokens);
-    match parser.parse_expression() {
-        Ok(expr) => {
-            writeln!(sink, "{}", AstPrinter.print(&expr)).unwrap();
-            expr
+/// Fails with code `65` if either file fails to scan.
+fn diff(
+    old: PathBuf,
+    new: PathBuf,
+    dialect: Dialect,
+    format: DiagnosticFormat,
+    mut sink: impl io::Write,
+) -> Result<(), LoxError> {
+    let scan = |filename| {
+        let source = read_file(filename)?;
+        let renderer = renderer(format, &source);
+        let scanned = Scanner::new(&source).with_dialect(dialect).scan_tokens();
+        let scanned = scanned.borrowed().scan_all();
+        check_scan_errors(scanned.errors, &*renderer)?;
+        let tokens = scanned.tokens.into_iter().map(BorrowedToken::into_owned);
+        Ok::<_, LoxError>(tokens.collect::<Vec<_>>())
+    };
+    let (old, new) = (scan(old)?, scan(new)?);
+
+    let edits = diff_tokens(&old, &new);
+    if edits.is_empty() {
+        writeln!(sink, "No token changes.").map_err(write_failed)?;
+    }
+    for edit in edits {
+        writeln!(sink, "{edit}").map_err(write_failed)?;
+    }
+    Ok(())
+}
+
+/// The flags of the run subcommand that change how `run` treats a program.
+#[derive(Debug, Clone, Copy)]
+struct RunOptions {
+    /// Write the lint warnings to stderr first
+    warnings: bool,
+    /// Simplify the program with the optimizer
+    optimize: bool,
+    /// Cache constant expressions inside loops
+    hoist: bool,
+    /// Write each cached expression to stderr
+    show_hoisted: bool,
+    backend: Backend,
+    /// Write the disassembled bytecode to stderr, with the VM backend
+    dump_bytecode: bool,
+}
+
+/// Parses `source` as a program, refusing sources larger than `limits`
+/// allow, and runs it with `interpreter` as `options` ask.
+///
+/// Fails with code `65` without running the program if `levels` deny one
+/// of its lint warnings, which are checked even when `options` do not ask
+/// for the warnings.
+fn run(
+    source: &str,
+    dialect: Dialect,
+    limits: &[Limit],
+    options: RunOptions,
+    levels: &Levels,
+    interpreter: &mut Interpreter,
+    renderer: &dyn DiagnosticRenderer,
+) -> Result<(), LoxError> {
+    let scanner = Scanner::new(source)
+        .with_dialect(dialect)
+        .with_limits(limits.iter().copied())
+        .with_interner(interpreter.interner().clone());
+    let mut parser = Parser::new(scanner.scan_tokens()).with_dialect(dialect);
+    let mut ast = parser
+        .parse()
+        .map_err(|err| LoxError::report(err, renderer))?;
+    if options.warnings || levels.denies_any() {
+        let mut warnings = lint::lint(&ast);
+        if !options.warnings {
+            warnings.retain(|warning| levels.level(warning.code) == Level::Deny);
+        }
+        if report_warnings(warnings, levels, renderer) {
+            return Err(LoxError::reported(STATIC_ERROR));
+        }
+    }
+    if options.optimize {
+        optimizer::optimize(&mut ast, dialect);
+    }
+    if options.hoist {
+        let hoisted = hoist::hoist(&mut ast);
+        if options.show_hoisted {
+            for expr in hoisted {
+                eprintln!("hoisted {expr}");
+            }
         }
-        Err(err) => {
-            eprintln!("{err}");
-            std::process::exit(65);
+    }
+    match options.backend {
+        Backend::Tree => interpreter
+            .interpret(&ast)
+            .map_err(|err| LoxError::report(err, renderer))?,
+        Backend::Vm => {
+            let chunk = compiler::compile(&ast).map_err(|err| LoxError::report(err, renderer))?;
+            if options.dump_bytecode {
+                eprint!("{}", disassemble(&chunk, "script"));
+            }
+            Vm::new(interpreter)
+                .run(&chunk)
+                .map_err(|err| LoxError::report(err, renderer))?;
         }
     }
+
+    Ok(())
 }
 
-/// Scans tokens from `filename`, writing each token to `sink`, and returns all
-/// successfully scanned tokens.
+/// How often [`watch_file`] checks whether the watched files changed.
+const WATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Runs the program in `filename` with `run`, then again each time it or
+/// one of the files `run` returns changes, until the process is
+/// interrupted.
 ///
-/// Exits with code `65` if any scan error occurs.
-fn tokenize(filename: PathBuf, mut sink: impl io::Write) -> Vec<Token> {
-    let content = read_file(filename);
+/// Clears the terminal before each run, and writes the error a run fails
+/// with and a status line to stderr after it.
+fn watch_file(
+    filename: &Path,
+    mut run: impl FnMut(&str) -> (Result<(), LoxError>, Vec<PathBuf>),
+) -> ! {
+    let modified = |file: &PathBuf| fs::metadata(file).and_then(|m| m.modified()).ok();
+    let mut files = vec![filename.to_path_buf()];
+    loop {
+        if io::stdout().is_terminal() {
+            print!("\x1b[2J\x1b[H");
+        }
+        // taken before the run, so that an edit made while it runs is not
+        // missed, except to a module the program imports for the first time
+        let before = files
+            .iter()
+            .map(|file| (file.clone(), modified(file)))
+            .collect::<HashMap<_, _>>();
+        files.truncate(1);
+        let res = match read_file(filename.to_path_buf()) {
+            Ok(source) => {
+                let (res, imported) = run(&source);
+                files.extend(imported);
+                res
+            }
+            Err(err) => Err(err),
+        };
+        let last = files
+            .iter()
+            .map(|file| before.get(file).copied().unwrap_or_else(|| modified(file)))
+            .collect::<Vec<_>>();
+        // a closed stdout loses the output of the run, not the watch
+        io::stdout().flush().ok();
 
-    let mut has_error = false;
-    let mut tokens = Vec::new();
+        let status = match res {
+            Ok(()) => "finished".to_string(),
+            Err(err) => {
+                if !err.message.is_empty() {
+                    eprintln!("{}", err.message);
+                }
+                format!("failed with code {}", err.exit_code)
+            }
+        };
+        eprintln!(
+            "[watch] {} {status}, waiting for changes to {} file(s)",
+            filename.display(),
+            files.len()
+        );
 
-    let scanner = Scanner::new(&content);
-    for result in scanner.scan_tokens() {
-        match result {
-            Ok(ScanItem::Ignore) => continue,
-            Ok(ScanItem::Token(tkn)) => {
-                writeln!(sink, "{tkn}").unwrap();
-                tokens.push(tkn);
+        while files.iter().map(modified).eq(last.iter().copied()) {
+            thread::sleep(WATCH_INTERVAL);
+        }
+    }
+}
+
+/// Parses the program in `source`, read from `filename`, and runs it with
+/// `interpreter` under a debugger reading commands from stdin. Quitting the
+/// debugger ends the program without an error.
+///
+/// Fails with code `65` if the program does not parse, `70` if it fails, or
+/// `75` if it exceeds one of `limits`.
+fn debug(
+    filename: &Path,
+    source: &str,
+    dialect: Dialect,
+    limits: &[Limit],
+    interpreter: Interpreter,
+    renderer: &dyn DiagnosticRenderer,
+) -> Result<(), LoxError> {
+    let scanner = Scanner::new(source)
+        .with_dialect(dialect)
+        .with_limits(limits.iter().copied())
+        .with_interner(interpreter.interner().clone());
+    let program = Parser::new(scanner.scan_tokens())
+        .with_dialect(dialect)
+        .parse()
+        .map_err(|err| LoxError::report(err, renderer))?;
+
+    let script = Some(filename.to_path_buf());
+    let debugger = Debugger::new(source, script, io::stdin().lock(), io::stdout());
+    let mut interpreter = interpreter.with_hook(Rc::new(RefCell::new(debugger)));
+    match interpreter.interpret(&program) {
+        Err(err) if err.is_stopped() => Ok(()),
+        result => result.map_err(|err| LoxError::report(err, renderer)),
+    }
+}
+
+/// Runs each non-blank line of `source` with `repl`, writing the line and
+/// what it evaluated to as `input => result` to `sink`.
+///
+/// Fails with code `1` if the output cannot be written.
+fn repl_batch(source: &str, mut repl: Repl, mut sink: impl io::Write) -> Result<(), LoxError> {
+    for line in source.lines().filter(|line| !line.trim().is_empty()) {
+        let result = repl.eval(line);
+        writeln!(sink, "{line} => {result}").map_err(write_failed)?;
+    }
+    Ok(())
+}
+
+/// Parses a program and writes a warning to stderr for each violation of the
+/// lint rules in it, at the level `levels` sets for its code.
+///
+/// Fails with code `65` if the program does not parse or `levels` deny one
+/// of the warnings, or `75` if the source exceeds one of the token or byte
+/// `limits`.
+fn lint(
+    source: &str,
+    dialect: Dialect,
+    limits: &[Limit],
+    levels: &Levels,
+    renderer: &dyn DiagnosticRenderer,
+) -> Result<(), LoxError> {
+    let scanner = Scanner::new(source)
+        .with_dialect(dialect)
+        .with_limits(limits.iter().copied());
+    let mut parser = Parser::new(scanner.scan_tokens()).with_dialect(dialect);
+    let ast = parser
+        .parse()
+        .map_err(|err| LoxError::report(err, renderer))?;
+    if report_warnings(lint::lint(&ast), levels, renderer) {
+        return Err(LoxError::reported(STATIC_ERROR));
+    }
+    Ok(())
+}
+
+/// Writes each of `warnings` that `levels` do not allow to stderr, and
+/// returns whether any of them was denied.
+fn report_warnings(
+    warnings: Vec<Diagnostic>,
+    levels: &Levels,
+    renderer: &dyn DiagnosticRenderer,
+) -> bool {
+    let mut denied = false;
+    for warning in warnings.into_iter().filter_map(|w| levels.apply(w)) {
+        denied |= warning.severity == Severity::Error;
+        eprintln!("{}", renderer.render(&warning));
+    }
+    denied
+}
+
+/// Formats the program in `source`, read from `filename`, and writes it back
+/// to the file if it changed. With `check`, writes the lines that would
+/// change to `sink` instead.
+///
+/// Fails with code `65` if the program fails to parse or format, `1` if the
+/// file cannot be written, `3` if `check` finds changes, or `75` if the
+/// source exceeds one of the token or byte `limits`.
+fn fmt(
+    filename: &Path,
+    source: &str,
+    check: bool,
+    dialect: Dialect,
+    limits: &[Limit],
+    renderer: &dyn DiagnosticRenderer,
+    mut sink: impl io::Write,
+) -> Result<(), LoxError> {
+    let scanner = Scanner::new(source)
+        .with_dialect(dialect)
+        .with_limits(limits.iter().copied());
+    let mut parser = Parser::new(scanner.scan_tokens()).with_dialect(dialect);
+    let formatted = parser
+        .parse()
+        .and_then(|ast| format::format(source, &ast, dialect))
+        .map_err(|err| LoxError::report(err, renderer))?;
+    if formatted == source {
+        return Ok(());
+    }
+
+    if check {
+        for edit in diff_lines(source, &formatted) {
+            writeln!(sink, "{edit}").map_err(write_failed)?;
+        }
+        return Err(LoxError::reported(MISMATCH));
+    }
+    fs::write(filename, formatted).map_err(|err| {
+        let message = format!("Failed to write file {}: {err}", filename.display());
+        LoxError::new(IO_ERROR, message)
+    })
+}
+
+/// Parses and evaluates a single expression, writing the result to `sink`.
+///
+/// Fails with code `65` if parsing fails, `70` if runtime evaluation fails,
+/// or `75` if the source exceeds one of the token or byte `limits` or the
+/// evaluation one of those of `interpreter`.
+fn evaluate(
+    source: &str,
+    dialect: Dialect,
+    limits: &[Limit],
+    mut interpreter: Interpreter,
+    renderer: &dyn DiagnosticRenderer,
+    mut sink: impl io::Write,
+) -> Result<(), LoxError> {
+    let scanner = Scanner::new(source)
+        .with_dialect(dialect)
+        .with_limits(limits.iter().copied())
+        .with_interner(interpreter.interner().clone());
+    let (ast, expr) = parse_expression(scanner, dialect, renderer)?;
+    let val = interpreter
+        .evaluate(&ast, expr)
+        .map_err(|err| LoxError::report(err, renderer))?;
+    writeln!(sink, "{}", val).map_err(write_failed)?;
+    Ok(())
+}
+
+/// Parses a single expression from the tokens of `scanner`, and returns it
+/// with the arena holding its nodes.
+///
+/// Fails with code `65` if parsing fails.
+fn parse_expression(
+    scanner: Scanner,
+    dialect: Dialect,
+    renderer: &dyn DiagnosticRenderer,
+) -> Result<(AstArena, ExprId), LoxError> {
+    let mut parser = Parser::new(scanner.scan_tokens()).with_dialect(dialect);
+    parser
+        .parse_expression()
+        .map_err(|err| LoxError::report(err, renderer))
+}
+
+/// Parses a single expression and prints it to `sink` in `format`, with
+/// `printer` for s-expressions.
+///
+/// Fails with code `65` if parsing fails, or `75` if the source exceeds one
+/// of the token or byte `limits`.
+fn parse(
+    source: &str,
+    dialect: Dialect,
+    limits: &[Limit],
+    format: ParseFormat,
+    printer: AstPrinter,
+    renderer: &dyn DiagnosticRenderer,
+    mut sink: impl io::Write,
+) -> Result<(), LoxError> {
+    let scanner = Scanner::new(source)
+        .with_dialect(dialect)
+        .with_limits(limits.iter().copied());
+    let (ast, expr) = parse_expression(scanner, dialect, renderer)?;
+    match format {
+        ParseFormat::Sexpr => writeln!(sink, "{}", printer.print(&ast, expr)),
+        ParseFormat::Json => writeln!(sink, "{:#}", JsonPrinter.print(&ast, expr)),
+        ParseFormat::Dot => write!(sink, "{}", AstDotPrinter::new().print(&ast, expr)),
+    }
+    .map_err(write_failed)
+}
+
+/// Scans tokens from `source` and writes each token that scanned to `sink`
+/// in `format`.
+///
+/// Fails with code `65` if any scan error occurs, or `75` if the source
+/// exceeds one of the token or byte `limits`.
+fn tokenize(
+    source: &str,
+    dialect: Dialect,
+    format: TokenFormat,
+    limits: &[Limit],
+    renderer: &dyn DiagnosticRenderer,
+    mut sink: impl io::Write,
+) -> Result<(), LoxError> {
+    let scanner = Scanner::new(source)
+        .with_dialect(dialect)
+        .with_limits(limits.iter().copied());
+    let scanned = scanner.scan_tokens().borrowed().scan_all();
+
+    match format {
+        TokenFormat::Text => {
+            for token in &scanned.tokens {
+                writeln!(sink, "{token}").map_err(write_failed)?;
             }
-            Err(err) => {
-                has_error = true;
-                eprintln!("{err}");
+        }
+        TokenFormat::Json => {
+            let records = scanned.tokens.iter().map(|t| record::json(t, source));
+            let records = serde_json::Value::Array(records.collect());
+            writeln!(sink, "{records:#}").map_err(write_failed)?;
+        }
+        TokenFormat::Csv => {
+            writeln!(sink, "{CSV_HEADER}").map_err(write_failed)?;
+            for token in &scanned.tokens {
+                writeln!(sink, "{}", record::csv(token, source)).map_err(write_failed)?;
             }
         }
     }
+    sink.flush().map_err(write_failed)?;
+
+    check_scan_errors(scanned.errors, renderer)
+}
 
-    if has_error {
-        std::process::exit(65);
+/// Writes all but the last of `errors` to stderr, and fails with the last
+/// one, if there are any.
+fn check_scan_errors(
+    mut errors: Vec<StaticError>,
+    renderer: &dyn DiagnosticRenderer,
+) -> Result<(), LoxError> {
+    let Some(last) = errors.pop() else {
+        return Ok(());
+    };
+    for err in &errors {
+        eprintln!("{}", renderer.render(&err.into()));
     }
+    Err(LoxError::report(last, renderer))
+}
 
-    tokens
+/// Scans `source` keeping whitespace and comments, writes it back out, and
+/// checks that the result is byte for byte the source.
+///
+/// Fails with code `65` if any scan error occurs, or `3` if the written
+/// source differs, reporting the first line that does.
+fn check_roundtrip(
+    source: &str,
+    dialect: Dialect,
+    renderer: &dyn DiagnosticRenderer,
+    mut sink: impl io::Write,
+) -> Result<(), LoxError> {
+    let mut errors = Vec::new();
+    let mut pieces = Vec::new();
+    for result in Scanner::new(source).with_dialect(dialect).scan_pieces() {
+        match result {
+            Ok(piece) => pieces.push(piece),
+            Err(err) => errors.push(err),
+        }
+    }
+    check_scan_errors(errors, renderer)?;
+
+    let written = write_source(&pieces);
+    if written == source {
+        writeln!(sink, "Round trip matches {} bytes.", source.len()).map_err(write_failed)?;
+        return Ok(());
+    }
+    let same = written
+        .bytes()
+        .zip(source.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let line = source.as_bytes()[..same]
+        .iter()
+        .filter(|&&b| b == b'\n')
+        .count()
+        + 1;
+    let message = format!("Round trip differs from the source on line {line}.");
+    Err(LoxError::new(MISMATCH, message))
 }
 
-/// Reads an input file into a string.
+/// Creates the renderer for `format`, coloring its output when stderr is a
+/// terminal.
+fn renderer(format: DiagnosticFormat, source: &str) -> Rc<dyn DiagnosticRenderer> {
+    let sources = SourceMap::new();
+    sources.add("", source);
+    format.renderer(&sources, io::stderr().is_terminal())
+}
+
+/// Describes a failure to write the output of a subcommand, such as stdout
+/// having been closed by the reader of a pipe, as an error exiting with
+/// code `1` rather than a panic.
+fn write_failed(err: io::Error) -> LoxError {
+    LoxError::new(IO_ERROR, format!("Failed to write output: {err}"))
+}
+
+/// Reads the events in a log written by `--record`.
 ///
-/// Exits with code `1` when the file cannot be read.
-fn read_file(filename: PathBuf) -> String {
-    let Ok(file_contents) = fs::read_to_string(&filename) else {
-        eprintln!("Failed to read file {}", filename.display());
-        std::process::exit(1);
+/// Fails with code `1` when the log cannot be read or is invalid.
+fn read_events(filename: PathBuf) -> Result<Vec<Event>, LoxError> {
+    let log = read_file(filename.clone())?;
+    read_log(&log).map_err(|err| {
+        let message = format!("Failed to replay {}: {err}", filename.display());
+        LoxError::new(IO_ERROR, message)
+    })
+}
+
+/// Writes `events` to the log at `filename`.
+///
+/// Fails with code `1` when the log cannot be written.
+fn write_events(filename: PathBuf, events: &[Event]) -> Result<(), LoxError> {
+    let written = File::create(&filename).and_then(|file| write_log(events, file));
+    written.map_err(|err| {
+        let message = format!("Failed to write file {}: {err}", filename.display());
+        LoxError::new(IO_ERROR, message)
+    })
+}
+
+/// Builds the warning levels of the `--allow`, `--warn` and `--deny` flags
+/// on top of those in the `--lint-config` file and the default levels of the
+/// lint rules.
+///
+/// Fails with code `1` when the config cannot be read or is invalid.
+fn levels(args: &Args) -> Result<Levels, LoxError> {
+    let mut levels = lint::default_levels();
+    if let Some(filename) = &args.lint_config {
+        let config = read_file(filename.clone())?;
+        levels = levels.with_config(&config).map_err(|err| {
+            let message = format!("Failed to read lint config {}: {err}", filename.display());
+            LoxError::new(IO_ERROR, message)
+        })?;
+    }
+    Ok(args.levels(levels))
+}
+
+/// Adjusts the `--arithmetic-checks` mode to the level of the `arithmetic`
+/// code: allowing it turns the checks off and denying it makes them errors.
+/// A level set for the code itself also turns checks on that were off,
+/// while checks that are errors stay errors.
+fn arithmetic_check(check: ArithmeticCheck, levels: &Levels) -> ArithmeticCheck {
+    let level = match check {
+        ArithmeticCheck::Off => levels.get("arithmetic"),
+        ArithmeticCheck::Warn => Some(levels.level("arithmetic")),
+        ArithmeticCheck::Error => return check,
     };
-    file_contents
+    match level {
+        None => check,
+        Some(Level::Allow) => ArithmeticCheck::Off,
+        Some(Level::Warn) => ArithmeticCheck::Warn,
+        Some(Level::Deny) => ArithmeticCheck::Error,
+    }
 }
 
-/// Returns a writable sink that discards all bytes (`/dev/null`).
-fn null() -> File {
-    OpenOptions::new().write(true).open("/dev/null").unwrap()
+/// Opens where `--trace` writes: stderr for `-`, or else the file at
+/// `path`, which is created or truncated.
+///
+/// Fails with code `1` when the file cannot be created.
+fn trace_sink(path: &Path) -> Result<Box<dyn io::Write>, LoxError> {
+    if path == Path::new("-") {
+        return Ok(Box::new(io::stderr()));
+    }
+    let file = File::create(path).map_err(|err| {
+        LoxError::new(
+            IO_ERROR,
+            format!("Failed to write file {}: {err}", path.display()),
+        )
+    })?;
+    Ok(Box::new(io::BufWriter::new(file)))
+}
+
+/// Reads an input file into a string.
+///
+/// Fails with code `1` when the file cannot be read.
+fn read_file(filename: PathBuf) -> Result<String, LoxError> {
+    fs::read_to_string(&filename).map_err(|_| {
+        LoxError::new(
+            IO_ERROR,
+            format!("Failed to read file {}", filename.display()),
+        )
+    })
+}
+
+/// Reads a program from stdin into a string.
+///
+/// Fails with code `1` when stdin cannot be read.
+fn read_stdin() -> Result<String, LoxError> {
+    io::read_to_string(io::stdin())
+        .map_err(|err| LoxError::new(IO_ERROR, format!("Failed to read stdin: {err}")))
 }
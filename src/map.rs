@@ -0,0 +1,186 @@
+use std::fmt::Display;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+
+use indexmap::IndexMap;
+
+use crate::Value;
+
+/// The entries of a Lox map, in insertion order.
+///
+/// Keys are hashed with [`KeyHasher`] rather than a randomly seeded hasher,
+/// so which keys collide is the same in every run and on every platform.
+pub type Map = IndexMap<MapKey, Value, BuildHasherDefault<KeyHasher>>;
+
+/// The 64-bit FNV-1a hash, with its standard fixed offset basis as seed.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyHasher(u64);
+
+impl KeyHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+}
+
+impl Default for KeyHasher {
+    fn default() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+}
+
+impl Hasher for KeyHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// A string, number or boolean used as a map key.
+///
+/// Keys compare by value, with integers and numbers equal when they are
+/// numerically equal, so `m[1]` and `m[1.0]` name the same entry.
+#[derive(Debug, Clone)]
+pub struct MapKey(Value);
+
+/// The form of a key that is hashed and compared.
+#[derive(PartialEq, Eq)]
+enum Normalized<'a> {
+    String(&'a str),
+    Integer(i64),
+    Float(u64),
+    Boolean(bool),
+}
+
+impl MapKey {
+    /// Creates a key from `value`.
+    ///
+    /// Returns an error message if `value` cannot be used as a key.
+    pub fn new(value: Value) -> Result<Self, &'static str> {
+        match value {
+            Value::Number(n) if n.is_nan() => Err("NaN can't be used as a map key."),
+            Value::String(_) | Value::Number(_) | Value::Integer(_) | Value::Boolean(_) => {
+                Ok(Self(value))
+            }
+            _ => Err("Map keys must be strings, numbers or booleans."),
+        }
+    }
+
+    /// Returns the value the key was created from.
+    pub fn value(&self) -> &Value {
+        &self.0
+    }
+
+    fn normalized(&self) -> Normalized<'_> {
+        match self.0 {
+            Value::String(ref s) => Normalized::String(s),
+            Value::Integer(n) => Normalized::Integer(n),
+            Value::Number(n) => {
+                let in_range = n >= i64::MIN as f64 && n < i64::MAX as f64;
+                if n.fract() == 0.0 && in_range {
+                    // also folds -0.0 into 0
+                    Normalized::Integer(n as i64)
+                } else {
+                    Normalized::Float(n.to_bits())
+                }
+            }
+            Value::Boolean(b) => Normalized::Boolean(b),
+            _ => unreachable!("map keys are checked on creation"),
+        }
+    }
+}
+
+impl Hash for Normalized<'_> {
+    /// Writes a tag byte and then the little-endian bytes of the key, since
+    /// the derived hash depends on the pointer width and byte order.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Normalized::String(s) => {
+                state.write(&[0]);
+                state.write(&(s.len() as u64).to_le_bytes());
+                state.write(s.as_bytes());
+            }
+            Normalized::Integer(n) => {
+                state.write(&[1]);
+                state.write(&n.to_le_bytes());
+            }
+            Normalized::Float(bits) => {
+                state.write(&[2]);
+                state.write(&bits.to_le_bytes());
+            }
+            Normalized::Boolean(b) => state.write(&[3, u8::from(*b)]),
+        }
+    }
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.normalized() == other.normalized()
+    }
+}
+
+impl Eq for MapKey {}
+
+impl Hash for MapKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.normalized().hash(state);
+    }
+}
+
+impl Display for MapKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(Value::Integer(1), Value::Number(1.0))]
+    #[case(Value::Number(0.0), Value::Number(-0.0))]
+    #[case(Value::Number(2.5), Value::Number(2.5))]
+    #[case("a".into(), "a".into())]
+    #[case(true.into(), true.into())]
+    fn test_map_key_equal(#[case] a: Value, #[case] b: Value) {
+        let mut map = Map::default();
+        map.insert(MapKey::new(a).unwrap(), Value::Nil);
+        assert!(map.contains_key(&MapKey::new(b).unwrap()));
+    }
+
+    #[rstest]
+    #[case(Value::Number(1.0), "1".into())]
+    #[case(Value::Number(1.0), true.into())]
+    #[case(Value::Number(0.5), Value::Number(1.5))]
+    fn test_map_key_not_equal(#[case] a: Value, #[case] b: Value) {
+        assert_ne!(MapKey::new(a).unwrap(), MapKey::new(b).unwrap());
+    }
+
+    #[rstest]
+    #[case("a".into(), 15449901362193407565)]
+    #[case(Value::Integer(1), 8184434590310759885)]
+    #[case(Value::Number(1.0), 8184434590310759885)]
+    #[case(Value::Number(0.5), 1017635348980378568)]
+    #[case(true.into(), 591641742448415945)]
+    fn test_map_key_hash_is_fixed(#[case] key: Value, #[case] expected: u64) {
+        let mut hasher = KeyHasher::default();
+        MapKey::new(key).unwrap().hash(&mut hasher);
+        assert_eq!(expected, hasher.finish());
+    }
+
+    #[rstest]
+    #[case(Value::Nil, "Map keys must be strings, numbers or booleans.")]
+    #[case(
+        Value::from(Vec::new()),
+        "Map keys must be strings, numbers or booleans."
+    )]
+    #[case(Value::Number(f64::NAN), "NaN can't be used as a map key.")]
+    fn test_map_key_invalid(#[case] value: Value, #[case] expected: &str) {
+        assert_eq!(Some(expected), MapKey::new(value).err());
+    }
+}
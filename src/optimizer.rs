@@ -0,0 +1,362 @@
+//! An opt-in pass that simplifies a program before it runs, selected with
+//! `run --optimize` or [`Interpreter::with_optimizations`].
+//!
+//! It folds operators applied to literals into the literal they produce,
+//! drops a `!` applied twice to an expression that is already a boolean, and
+//! replaces a `match` on a literal, a `do`-`while` whose condition is a
+//! falsey literal or a `for` over an empty range with the statement that
+//! runs. Expressions are folded with
+//! the interpreter's own operators, and only when they succeed without an
+//! arithmetic warning, so a program that fails still fails at the same
+//! operator as without the pass.
+//!
+//! `-` is never dropped in pairs, since `- -x` fails when `x` is a string.
+
+use std::mem;
+
+use crate::Value;
+use crate::dialect::Dialect;
+use crate::interpreter::{ArithmeticCheck, Interpreter};
+use crate::parser::ast::{Ast, AstArena, ExprId, StmtId};
+use crate::parser::expr::{Binary, Coalesce, ExprNode, List, Literal, Tuple};
+use crate::parser::stmt::{Block, StmtNode};
+use crate::scanner::token::TokenType;
+
+/// Simplifies every statement of `program`, using the operators of
+/// `dialect`.
+pub fn optimize(program: &mut Ast, dialect: Dialect) {
+    let mut optimizer = Optimizer {
+        interpreter: Interpreter::new()
+            .with_dialect(dialect)
+            .with_arithmetic_check(ArithmeticCheck::Error),
+    };
+    for &stmt in &program.statements {
+        optimizer.stmt(stmt, &mut program.arena);
+    }
+}
+
+struct Optimizer {
+    /// Evaluates constant operators, refusing results that would have been
+    /// reported under an arithmetic check
+    interpreter: Interpreter,
+}
+
+// Like in the hoisting pass, the walk takes the id of a node before the
+// arena, so that the ids of its children are copied out of the node before
+// the arena is borrowed mutably.
+impl Optimizer {
+    /// Simplifies the statements and expressions in `stmt` and then `stmt`
+    /// itself.
+    fn stmt(&mut self, stmt: StmtId, ast: &mut AstArena) {
+        match &ast[stmt] {
+            StmtNode::Print(print) => self.expr(print.expr, ast),
+            StmtNode::Expression(expression) => self.expr(expression.expr, ast),
+            StmtNode::Var(var) => {
+                if let Some(initializer) = var.initializer {
+                    self.expr(initializer, ast);
+                }
+            }
+            StmtNode::VarUnpack(unpack) => self.expr(unpack.value, ast),
+            StmtNode::Throw(throw) => self.expr(throw.value, ast),
+            StmtNode::Assert(assert) => {
+                let message = assert.message;
+                self.expr(assert.condition, ast);
+                if let Some(message) = message {
+                    self.expr(message, ast);
+                }
+            }
+            StmtNode::Import(_) => {}
+            StmtNode::Block(block) => {
+                for stmt in block.statements.clone() {
+                    self.stmt(stmt, ast);
+                }
+            }
+            StmtNode::ForIn(for_in) => {
+                let body = for_in.body;
+                self.expr(for_in.iterable, ast);
+                self.stmt(body, ast);
+            }
+            StmtNode::DoWhile(do_while) => {
+                let condition = do_while.condition;
+                self.stmt(do_while.body, ast);
+                self.expr(condition, ast);
+            }
+            StmtNode::Try(try_stmt) => {
+                let (catch, finally) = (try_stmt.catch.as_ref().map(|(_, c)| *c), try_stmt.finally);
+                self.stmt(try_stmt.body, ast);
+                for stmt in catch.into_iter().chain(finally) {
+                    self.stmt(stmt, ast);
+                }
+            }
+            StmtNode::Match(match_stmt) => {
+                let bodies = match_stmt.arms.iter().map(|(_, body)| *body);
+                let bodies: Vec<_> = bodies.chain(match_stmt.otherwise).collect();
+                self.expr(match_stmt.value, ast);
+                for stmt in bodies {
+                    self.stmt(stmt, ast);
+                }
+            }
+        }
+
+        if let Some(simpler) = self.simplify_stmt(stmt, ast) {
+            ast[stmt] = simpler;
+        }
+    }
+
+    /// Returns the statement that runs in place of `stmt`, given that its
+    /// expressions are already simplified, or `None` to keep it.
+    fn simplify_stmt(&mut self, stmt: StmtId, ast: &mut AstArena) -> Option<StmtNode> {
+        let runs = match &ast[stmt] {
+            StmtNode::ForIn(for_in) if self.is_empty_range(ast, for_in.iterable) => {
+                return Some(Block::new(Vec::new()).into());
+            }
+            StmtNode::DoWhile(do_while) => match &ast[do_while.condition] {
+                ExprNode::Literal(literal) if !literal.value.is_truthy() => do_while.body,
+                _ => return None,
+            },
+            StmtNode::Match(match_stmt) => {
+                let ExprNode::Literal(literal) = &ast[match_stmt.value] else {
+                    return None;
+                };
+                let arm = match_stmt.arms.iter().find(|(patterns, _)| {
+                    patterns
+                        .iter()
+                        .any(|pattern| pattern.is_equal(&literal.value))
+                });
+                match (arm, match_stmt.otherwise) {
+                    (Some((_, body)), _) => *body,
+                    (None, Some(body)) => body,
+                    (None, None) => return Some(Block::new(Vec::new()).into()),
+                }
+            }
+            _ => return None,
+        };
+        Some(take_stmt(runs, ast))
+    }
+
+    /// Checks whether `expr` is a range between literals that holds no
+    /// numbers, such as `3..3`, which a loop never runs over.
+    fn is_empty_range(&mut self, ast: &AstArena, expr: ExprId) -> bool {
+        let ExprNode::Binary(binary) = &ast[expr] else {
+            return false;
+        };
+        let (ExprNode::Literal(left), ExprNode::Literal(right)) =
+            (&ast[binary.left], &ast[binary.right])
+        else {
+            return false;
+        };
+        if !matches!(
+            binary.operator.typ,
+            TokenType::DotDot | TokenType::DotDotEqual
+        ) {
+            return false;
+        }
+        let range =
+            self.interpreter
+                .binary(&binary.operator, left.value.clone(), right.value.clone());
+        matches!(range, Ok(Value::Range(range)) if range.split_first().is_none())
+    }
+
+    /// Simplifies the operands of `expr` and then `expr` itself.
+    fn expr(&mut self, expr: ExprId, ast: &mut AstArena) {
+        match &ast[expr] {
+            ExprNode::Grouping(grouping) => self.expr(grouping.expression, ast),
+            ExprNode::Cached(cached) => self.expr(cached.expr, ast),
+            ExprNode::Unary(unary) => self.expr(unary.right, ast),
+            ExprNode::Binary(Binary { left, right, .. })
+            | ExprNode::Coalesce(Coalesce { left, right, .. }) => {
+                let right = *right;
+                self.expr(*left, ast);
+                self.expr(right, ast);
+            }
+            ExprNode::Assign(assign) => self.expr(assign.value, ast),
+            ExprNode::AssignUnpack(unpack) => self.expr(unpack.value, ast),
+            ExprNode::Call(call) => {
+                let arguments = call.arguments.clone();
+                self.expr(call.callee, ast);
+                arguments.into_iter().for_each(|a| self.expr(a, ast));
+            }
+            ExprNode::List(List { elements }) | ExprNode::Tuple(Tuple { elements, .. }) => {
+                elements.clone().into_iter().for_each(|e| self.expr(e, ast));
+            }
+            ExprNode::Spread(spread) => self.expr(spread.expression, ast),
+            ExprNode::Map(map) => {
+                for (key, value) in map.entries.clone() {
+                    self.expr(key, ast);
+                    self.expr(value, ast);
+                }
+            }
+            ExprNode::Index(index) => {
+                let index_expr = index.index;
+                self.expr(index.object, ast);
+                self.expr(index_expr, ast);
+            }
+            ExprNode::SetIndex(set_index) => {
+                let (index, value) = (set_index.index, set_index.value);
+                self.expr(set_index.object, ast);
+                self.expr(index, ast);
+                self.expr(value, ast);
+            }
+            ExprNode::Variable(_) | ExprNode::Literal(_) => {}
+        }
+
+        if let Some(simpler) = self.simplify_expr(expr, ast) {
+            ast[expr] = simpler;
+        }
+    }
+
+    /// Returns what `expr` can be replaced with, given that its operands are
+    /// already simplified, or `None` to keep it.
+    fn simplify_expr(&mut self, expr: ExprId, ast: &mut AstArena) -> Option<ExprNode> {
+        match &ast[expr] {
+            ExprNode::Grouping(grouping) => match &ast[grouping.expression] {
+                ExprNode::Literal(_) => Some(take_expr(grouping.expression, ast)),
+                _ => None,
+            },
+            ExprNode::Unary(unary) => {
+                if let ExprNode::Literal(right) = &ast[unary.right] {
+                    let value = self.interpreter.unary(&unary.operator, right.value.clone());
+                    return value.ok().map(|value| Literal::from(value).into());
+                }
+                match &ast[unary.right] {
+                    ExprNode::Unary(inner)
+                        if unary.operator.typ == TokenType::Bang
+                            && inner.operator.typ == TokenType::Bang
+                            && is_boolean(ast, inner.right) =>
+                    {
+                        Some(take_expr(inner.right, ast))
+                    }
+                    _ => None,
+                }
+            }
+            ExprNode::Binary(binary) => {
+                // each range is a new iterable value
+                if matches!(
+                    binary.operator.typ,
+                    TokenType::DotDot | TokenType::DotDotEqual
+                ) {
+                    return None;
+                }
+                let (ExprNode::Literal(left), ExprNode::Literal(right)) =
+                    (&ast[binary.left], &ast[binary.right])
+                else {
+                    return None;
+                };
+                let value = self.interpreter.binary(
+                    &binary.operator,
+                    left.value.clone(),
+                    right.value.clone(),
+                );
+                value.ok().map(|value| Literal::from(value).into())
+            }
+            ExprNode::Coalesce(coalesce) => match &ast[coalesce.left] {
+                ExprNode::Literal(Literal {
+                    value: Value::Nil, ..
+                }) => Some(take_expr(coalesce.right, ast)),
+                ExprNode::Literal(_) => Some(take_expr(coalesce.left, ast)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Checks whether `expr` always produces a boolean, looking through
+/// parentheses.
+fn is_boolean(ast: &AstArena, expr: ExprId) -> bool {
+    match &ast[expr] {
+        ExprNode::Literal(literal) => matches!(literal.value, Value::Boolean(_)),
+        ExprNode::Grouping(grouping) => is_boolean(ast, grouping.expression),
+        ExprNode::Unary(unary) => unary.operator.typ == TokenType::Bang,
+        ExprNode::Binary(binary) => matches!(
+            binary.operator.typ,
+            TokenType::EqualEqual
+                | TokenType::BangEqual
+                | TokenType::Greater
+                | TokenType::GreaterEqual
+                | TokenType::Less
+                | TokenType::LessEqual
+        ),
+        _ => false,
+    }
+}
+
+fn take_expr(expr: ExprId, ast: &mut AstArena) -> ExprNode {
+    mem::replace(&mut ast[expr], Literal::from(Value::Nil).into())
+}
+
+fn take_stmt(stmt: StmtId, ast: &mut AstArena) -> StmtNode {
+    mem::replace(&mut ast[stmt], Block::new(Vec::new()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::dialect::Extension;
+    use crate::parser::printer::AstPrinter;
+    use crate::validate::validate_with;
+
+    fn dialect() -> Dialect {
+        [
+            Extension::DoWhile,
+            Extension::NilCoalescing,
+            Extension::Integers,
+            Extension::Ranges,
+            Extension::Match,
+            Extension::ForIn,
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    /// Returns the expression printed by the single statement of `source`,
+    /// before and after the pass.
+    fn before_and_after(source: &str) -> (String, String) {
+        let mut program = validate_with(source, dialect()).ast.unwrap();
+        let print = |program: &Ast| match program.iter().next() {
+            Some(StmtNode::Print(print)) => AstPrinter::new().print(&program.arena, print.expr),
+            stmt => panic!("expected a print statement, found {stmt:?}"),
+        };
+        let before = print(&program);
+        optimize(&mut program, dialect());
+        (before, print(&program))
+    }
+
+    #[rstest]
+    #[case("print 1 + 2 * 3;", "(+ 1 (* 2 3))", "7")]
+    #[case("print (1 + 2) * x;", "(* (group (+ 1 2)) x)", "(* 3 x)")]
+    #[case("print \"a\" + \"b\" == \"ab\";", "(== (+ a b) ab)", "true")]
+    #[case("print !!(x < 1);", "(! (! (group (< x 1))))", "(group (< x 1))")]
+    #[case("print !!!x;", "(! (! (! x)))", "(! x)")]
+    #[case("print !!x;", "(! (! x))", "(! (! x))")]
+    #[case("print - -x;", "(- (- x))", "(- (- x))")]
+    #[case("print nil ?? x;", "(?? nil x)", "x")]
+    #[case("print 1 ?? x;", "(?? 1 x)", "1")]
+    #[case("print 1 / 0;", "(/ 1 0)", "(/ 1 0)")]
+    #[case("print -\"a\" + 1;", "(+ (- a) 1)", "(+ (- a) 1)")]
+    #[case("print 0 * -1.5;", "(* 0 (- 1.5))", "(* 0 -1.5)")]
+    #[case("print 0..2;", "(.. 0 2)", "(.. 0 2)")]
+    fn test_optimize_expressions(#[case] source: &str, #[case] before: &str, #[case] after: &str) {
+        assert_eq!(
+            (before.to_string(), after.to_string()),
+            before_and_after(source)
+        );
+    }
+
+    #[rstest]
+    #[case("do print 1; while (1 > 2);", "Print")]
+    #[case("do print 1; while (x);", "DoWhile")]
+    #[case("match (1 + 1) { 1 => print \"a\"; 2 => print \"b\"; }", "Print")]
+    #[case("match (3) { 1 => print \"a\"; }", "Block")]
+    #[case("match (x) { 1 => print \"a\"; }", "Match")]
+    #[case("for (i in 2 + 1..3) print i;", "Block")]
+    #[case("for (i in 3..=3) print i;", "ForIn")]
+    fn test_optimize_dead_branches(#[case] source: &str, #[case] expected: &str) {
+        let mut program = validate_with(source, dialect()).ast.unwrap();
+        optimize(&mut program, dialect());
+        let stmt = format!("{:?}", program.iter().next().unwrap());
+        assert!(stmt.starts_with(expected), "{stmt}");
+    }
+}
@@ -0,0 +1,378 @@
+//! The nodes of a parsed program, kept side by side in an arena rather than
+//! each in a box of its own.
+//!
+//! Nodes refer to their children by [`ExprId`] and [`StmtId`], which index
+//! into the [`AstArena`] that owns them. Walking a tree then reads from two
+//! contiguous vectors, and copying one is a copy of those vectors.
+
+use std::ops;
+use std::rc::Rc;
+
+use crate::parser::expr::ExprNode;
+use crate::parser::stmt::StmtNode;
+use crate::scanner::token::Token;
+
+/// The position of an expression in an [`AstArena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(u32);
+
+/// The position of a statement in an [`AstArena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StmtId(u32);
+
+/// The first and last source lines of a statement, see [`AstArena::lines`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lines {
+    pub first: u32,
+    pub last: u32,
+}
+
+/// Owns every node of a tree. Nodes are only ever added, so an id stays
+/// valid for as long as the arena it came from.
+#[derive(Debug, Clone, Default)]
+pub struct AstArena {
+    exprs: Vec<ExprNode>,
+    stmts: Vec<StmtNode>,
+    /// The lines of each statement read by the parser, by statement id
+    lines: Vec<Option<Lines>>,
+}
+
+impl AstArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves `expr` into the arena and returns its id.
+    pub fn add_expr(&mut self, expr: impl Into<ExprNode>) -> ExprId {
+        let id = u32::try_from(self.exprs.len()).expect("fewer than 2^32 expressions");
+        self.exprs.push(expr.into());
+        ExprId(id)
+    }
+
+    /// Moves `stmt` into the arena and returns its id.
+    pub fn add_stmt(&mut self, stmt: impl Into<StmtNode>) -> StmtId {
+        let id = u32::try_from(self.stmts.len()).expect("fewer than 2^32 statements");
+        self.stmts.push(stmt.into());
+        StmtId(id)
+    }
+
+    /// Returns the expression `id` points to.
+    pub fn expr(&self, id: ExprId) -> &ExprNode {
+        &self.exprs[id.0 as usize]
+    }
+
+    /// Returns the statement `id` points to.
+    pub fn stmt(&self, id: StmtId) -> &StmtNode {
+        &self.stmts[id.0 as usize]
+    }
+
+    /// Records that the statement `id` was read from `lines` of the source.
+    pub fn set_lines(&mut self, id: StmtId, lines: Lines) {
+        let index = id.0 as usize;
+        if self.lines.len() <= index {
+            self.lines.resize(index + 1, None);
+        }
+        self.lines[index] = Some(lines);
+    }
+
+    /// Returns the lines of the source the statement `id` was read from, or
+    /// `None` for a statement made by a later pass such as the optimizer.
+    pub fn lines(&self, id: StmtId) -> Option<Lines> {
+        self.lines.get(id.0 as usize).copied().flatten()
+    }
+
+    /// Returns the number of expressions and statements in the arena.
+    pub fn len(&self) -> usize {
+        self.exprs.len() + self.stmts.len()
+    }
+
+    /// Checks whether the arena has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Moves the statement `id`, everything in it and their tokens `lines`
+    /// lines and `bytes` bytes down the source, or up if negative, as when
+    /// text is added or removed above it.
+    pub fn shift(&mut self, id: StmtId, lines: i32, bytes: isize) {
+        let shift = |token: &mut Token| {
+            token.line = token.line.saturating_add_signed(lines);
+            token.span = token.span.map(|span| span.shift(bytes));
+        };
+        let (mut stmts, mut exprs) = (vec![id], Vec::new());
+        while let Some(id) = stmts.pop() {
+            if let Some(Some(range)) = self.lines.get_mut(id.0 as usize) {
+                range.first = range.first.saturating_add_signed(lines);
+                range.last = range.last.saturating_add_signed(lines);
+            }
+            match &mut self[id] {
+                StmtNode::Print(print) => exprs.push(print.expr),
+                StmtNode::Expression(expression) => exprs.push(expression.expr),
+                StmtNode::Var(var) => {
+                    shift(&mut var.name);
+                    exprs.extend(var.initializer);
+                }
+                StmtNode::VarUnpack(unpack) => {
+                    unpack
+                        .names
+                        .iter_mut()
+                        .chain([&mut unpack.paren])
+                        .for_each(shift);
+                    exprs.push(unpack.value);
+                }
+                StmtNode::Block(block) => stmts.extend(&block.statements),
+                StmtNode::ForIn(for_in) => {
+                    shift(&mut for_in.keyword);
+                    shift(&mut for_in.name);
+                    exprs.push(for_in.iterable);
+                    stmts.push(for_in.body);
+                }
+                StmtNode::DoWhile(do_while) => {
+                    stmts.push(do_while.body);
+                    exprs.push(do_while.condition);
+                }
+                StmtNode::Try(try_stmt) => {
+                    if let Some((name, body)) = &mut try_stmt.catch {
+                        shift(name);
+                        stmts.push(*body);
+                    }
+                    stmts.push(try_stmt.body);
+                    stmts.extend(try_stmt.finally);
+                }
+                StmtNode::Throw(throw) => {
+                    shift(&mut throw.keyword);
+                    exprs.push(throw.value);
+                }
+                StmtNode::Assert(assert) => {
+                    shift(&mut assert.keyword);
+                    exprs.push(assert.condition);
+                    exprs.extend(assert.message);
+                }
+                StmtNode::Import(import) => {
+                    import
+                        .names
+                        .iter_mut()
+                        .chain([&mut import.path])
+                        .for_each(shift);
+                }
+                StmtNode::Match(match_stmt) => {
+                    exprs.push(match_stmt.value);
+                    stmts.extend(match_stmt.arms.iter().map(|(_, body)| *body));
+                    stmts.extend(match_stmt.otherwise);
+                }
+            }
+            while let Some(id) = exprs.pop() {
+                match &mut self[id] {
+                    ExprNode::Grouping(grouping) => exprs.push(grouping.expression),
+                    ExprNode::Binary(binary) => {
+                        shift(Rc::make_mut(&mut binary.operator));
+                        exprs.extend([binary.left, binary.right]);
+                    }
+                    ExprNode::Coalesce(coalesce) => {
+                        shift(Rc::make_mut(&mut coalesce.operator));
+                        exprs.extend([coalesce.left, coalesce.right]);
+                    }
+                    ExprNode::Cached(cached) => exprs.push(cached.expr),
+                    ExprNode::Unary(unary) => {
+                        shift(Rc::make_mut(&mut unary.operator));
+                        exprs.push(unary.right);
+                    }
+                    ExprNode::Variable(variable) => shift(&mut variable.name),
+                    ExprNode::Assign(assign) => {
+                        shift(&mut assign.name);
+                        exprs.push(assign.value);
+                    }
+                    ExprNode::AssignUnpack(unpack) => {
+                        unpack
+                            .names
+                            .iter_mut()
+                            .chain([&mut unpack.paren])
+                            .for_each(shift);
+                        exprs.push(unpack.value);
+                    }
+                    ExprNode::Call(call) => {
+                        shift(&mut call.paren);
+                        exprs.push(call.callee);
+                        exprs.extend(&call.arguments);
+                    }
+                    ExprNode::List(list) => exprs.extend(&list.elements),
+                    ExprNode::Tuple(tuple) => {
+                        shift(&mut tuple.paren);
+                        exprs.extend(&tuple.elements);
+                    }
+                    ExprNode::Spread(spread) => {
+                        shift(&mut spread.ellipsis);
+                        exprs.push(spread.expression);
+                    }
+                    ExprNode::Map(map) => {
+                        shift(&mut map.brace);
+                        exprs.extend(map.entries.iter().flat_map(|&(key, value)| [key, value]));
+                    }
+                    ExprNode::Index(index) => {
+                        shift(&mut index.bracket);
+                        exprs.extend([index.object, index.index]);
+                    }
+                    ExprNode::SetIndex(set_index) => {
+                        shift(&mut set_index.bracket);
+                        exprs.extend([set_index.object, set_index.index, set_index.value]);
+                    }
+                    ExprNode::Literal(literal) => {
+                        if let Some(token) = &mut literal.token {
+                            shift(Rc::make_mut(token));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the expressions `ids` point to, in order.
+    pub fn exprs<'a>(&'a self, ids: &'a [ExprId]) -> impl Iterator<Item = &'a ExprNode> {
+        ids.iter().map(|&id| self.expr(id))
+    }
+
+    /// Returns the statements `ids` point to, in order.
+    pub fn stmts<'a>(&'a self, ids: &'a [StmtId]) -> impl Iterator<Item = &'a StmtNode> {
+        ids.iter().map(|&id| self.stmt(id))
+    }
+}
+
+impl ops::Index<ExprId> for AstArena {
+    type Output = ExprNode;
+
+    fn index(&self, id: ExprId) -> &ExprNode {
+        self.expr(id)
+    }
+}
+
+impl ops::IndexMut<ExprId> for AstArena {
+    fn index_mut(&mut self, id: ExprId) -> &mut ExprNode {
+        &mut self.exprs[id.0 as usize]
+    }
+}
+
+impl ops::Index<StmtId> for AstArena {
+    type Output = StmtNode;
+
+    fn index(&self, id: StmtId) -> &StmtNode {
+        self.stmt(id)
+    }
+}
+
+impl ops::IndexMut<StmtId> for AstArena {
+    fn index_mut(&mut self, id: StmtId) -> &mut StmtNode {
+        &mut self.stmts[id.0 as usize]
+    }
+}
+
+/// A parsed program: its top-level statements, and the arena holding them
+/// and everything they contain.
+#[derive(Debug, Clone, Default)]
+pub struct Ast {
+    pub arena: AstArena,
+    pub statements: Vec<StmtId>,
+}
+
+impl Ast {
+    /// Returns the top-level statements, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &StmtNode> {
+        self.arena.stmts(&self.statements)
+    }
+
+    /// Returns the number of top-level statements.
+    pub fn len(&self) -> usize {
+        self.statements.len()
+    }
+
+    /// Checks whether the program has no statements.
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+    use crate::parser::expr::Literal;
+    use crate::parser::printer::AstPrinter;
+    use crate::parser::stmt::Block;
+    use crate::validate::validate;
+
+    #[test]
+    fn test_ast_nodes_refer_to_children_by_id() {
+        let ast = validate("print 1 + 2;\n{ print 3; }").ast.unwrap();
+        assert_eq!(2, ast.len());
+
+        let Some(StmtNode::Print(print)) = ast.iter().next() else {
+            panic!("expected a print statement");
+        };
+        assert_eq!(
+            "(+ 1.0 2.0)",
+            AstPrinter::new().print(&ast.arena, print.expr)
+        );
+    }
+
+    #[test]
+    fn test_ast_lines_of_statements() {
+        let ast = validate("print 1;\n{\n  var a =\n    2;\n}\n").ast.unwrap();
+        let lines = |id| ast.arena.lines(id).map(|lines| (lines.first, lines.last));
+        let [print, block] = ast.statements[..] else {
+            panic!("expected two statements");
+        };
+        let StmtNode::Block(Block { statements }) = &ast.arena[block] else {
+            panic!("expected a block");
+        };
+
+        assert_eq!(Some((1, 1)), lines(print));
+        assert_eq!(Some((2, 5)), lines(block));
+        assert_eq!(Some((3, 4)), lines(statements[0]));
+    }
+
+    #[test]
+    fn test_ast_shift() {
+        let mut ast = validate("print 1;\n{\n  var a =\n    -b;\n}\n")
+            .ast
+            .unwrap();
+        let block = ast.statements[1];
+        ast.arena.shift(block, 2, 3);
+        let StmtNode::Block(Block { statements }) = &ast.arena[block] else {
+            panic!("expected a block");
+        };
+        let StmtNode::Var(var) = &ast.arena[statements[0]] else {
+            panic!("expected a variable declaration");
+        };
+        let Some(ExprNode::Unary(unary)) = var.initializer.map(|id| &ast.arena[id]) else {
+            panic!("expected a negation");
+        };
+        let ExprNode::Variable(variable) = &ast.arena[unary.right] else {
+            panic!("expected a variable");
+        };
+
+        assert_eq!(Some(Lines { first: 4, last: 7 }), ast.arena.lines(block));
+        assert_eq!(
+            (5, 6, 6),
+            (var.name.line, unary.operator.line, variable.name.line)
+        );
+        let span = variable.name.span.unwrap();
+        assert_eq!(29..30, span.range());
+        assert_eq!(
+            Some(Lines { first: 1, last: 1 }),
+            ast.arena.lines(ast.statements[0])
+        );
+    }
+
+    #[test]
+    fn test_ast_clone_is_independent() {
+        let ast = validate("print 1;").ast.unwrap();
+        let mut copy = ast.clone();
+        let Some(StmtNode::Print(print)) = copy.iter().next() else {
+            panic!("expected a print statement");
+        };
+        let expr = print.expr;
+        copy.arena[expr] = Literal::from(Value::from(2.0)).into();
+
+        assert_eq!("2.0", AstPrinter::new().print(&copy.arena, expr));
+        assert_eq!("1.0", AstPrinter::new().print(&ast.arena, expr));
+    }
+}
@@ -0,0 +1,203 @@
+//! The parse tree as a Graphviz graph, to see at a glance how precedence
+//! grouped an expression.
+//!
+//! Each expression is a node labeled with its operator or value, and edges
+//! are labeled with the role of the child, such as `left` or `index`.
+
+use std::fmt::Write as _;
+
+use crate::Value;
+use crate::parser::ast::{AstArena, ExprId};
+use crate::parser::expr::{
+    Assign, AssignUnpack, Binary, Cached, Call, Coalesce, Expr, Grouping, Index, List, Literal,
+    Map, SetIndex, Spread, Tuple, Unary, Variable, Visitor,
+};
+
+#[derive(Debug, Default)]
+pub struct AstDotPrinter {
+    /// The node and edge statements written so far
+    body: String,
+    /// The number of nodes written so far, which names the next one
+    nodes: usize,
+}
+
+impl AstDotPrinter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the graph of `expr` in the DOT language.
+    pub fn print(mut self, ast: &AstArena, expr: ExprId) -> String {
+        expr.accept(ast, &mut self);
+        format!("digraph ast {{\n{}}}\n", self.body)
+    }
+
+    /// Writes a node labeled `label`, and returns its name.
+    fn node(&mut self, label: &str) -> String {
+        let name = format!("n{}", self.nodes);
+        self.nodes += 1;
+        writeln!(self.body, "  {name} [label=\"{}\"];", escape(label)).unwrap();
+        name
+    }
+
+    /// Writes the node `parent`, labeled `label`, with an edge to each of
+    /// `children` labeled with its role.
+    fn parent<'a>(
+        &mut self,
+        ast: &AstArena,
+        label: &str,
+        children: impl IntoIterator<Item = (&'a str, ExprId)>,
+    ) -> String {
+        let parent = self.node(label);
+        for (role, child) in children {
+            let child = child.accept(ast, self);
+            writeln!(self.body, "  {parent} -> {child} [label=\"{role}\"];").unwrap();
+        }
+        parent
+    }
+}
+
+/// Escapes `label` for a double-quoted DOT string.
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Numbers `elements` as the roles of their edges.
+fn numbered(elements: &[ExprId]) -> Vec<(String, ExprId)> {
+    let roles = (0..elements.len()).map(|i| i.to_string());
+    roles.zip(elements.iter().copied()).collect()
+}
+
+impl Visitor for AstDotPrinter {
+    type Output = String;
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> String {
+        match &expr.value {
+            Value::String(s) => self.node(&format!("{s:?}")),
+            Value::NativeFunction(native) => self.node(native.name),
+            value => self.node(&format!("{value:?}")),
+        }
+    }
+
+    fn visit_grouping_expr(&mut self, ast: &AstArena, expr: &Grouping) -> String {
+        self.parent(ast, "group", [("expression", expr.expression)])
+    }
+
+    fn visit_unary_expr(&mut self, ast: &AstArena, expr: &Unary) -> String {
+        self.parent(ast, &expr.operator.lexeme, [("right", expr.right)])
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> String {
+        self.node(&expr.name.lexeme)
+    }
+
+    fn visit_assign_expr(&mut self, ast: &AstArena, expr: &Assign) -> String {
+        let label = format!("= {}", expr.name.lexeme);
+        self.parent(ast, &label, [("value", expr.value)])
+    }
+
+    fn visit_assign_unpack_expr(&mut self, ast: &AstArena, expr: &AssignUnpack) -> String {
+        let names = expr.names.iter().map(|name| name.lexeme.as_str());
+        let label = format!("= ({})", names.collect::<Vec<_>>().join(" "));
+        self.parent(ast, &label, [("value", expr.value)])
+    }
+
+    fn visit_binary_expr(&mut self, ast: &AstArena, expr: &Binary) -> String {
+        let children = [("left", expr.left), ("right", expr.right)];
+        self.parent(ast, &expr.operator.lexeme, children)
+    }
+
+    fn visit_coalesce_expr(&mut self, ast: &AstArena, expr: &Coalesce) -> String {
+        let children = [("left", expr.left), ("right", expr.right)];
+        self.parent(ast, &expr.operator.lexeme, children)
+    }
+
+    fn visit_cached_expr(&mut self, ast: &AstArena, expr: &Cached) -> String {
+        expr.expr.accept(ast, self)
+    }
+
+    fn visit_call_expr(&mut self, ast: &AstArena, expr: &Call) -> String {
+        let arguments = numbered(&expr.arguments);
+        let arguments = arguments.iter().map(|(role, id)| (role.as_str(), *id));
+        self.parent(
+            ast,
+            "call",
+            [("callee", expr.callee)].into_iter().chain(arguments),
+        )
+    }
+
+    fn visit_list_expr(&mut self, ast: &AstArena, expr: &List) -> String {
+        let elements = numbered(&expr.elements);
+        self.parent(
+            ast,
+            "list",
+            elements.iter().map(|(role, id)| (role.as_str(), *id)),
+        )
+    }
+
+    fn visit_tuple_expr(&mut self, ast: &AstArena, expr: &Tuple) -> String {
+        let elements = numbered(&expr.elements);
+        self.parent(
+            ast,
+            "tuple",
+            elements.iter().map(|(role, id)| (role.as_str(), *id)),
+        )
+    }
+
+    fn visit_spread_expr(&mut self, ast: &AstArena, expr: &Spread) -> String {
+        self.parent(ast, "...", [("expression", expr.expression)])
+    }
+
+    fn visit_map_expr(&mut self, ast: &AstArena, expr: &Map) -> String {
+        let entries = expr
+            .entries
+            .iter()
+            .flat_map(|&(key, value)| [("key", key), ("value", value)]);
+        self.parent(ast, "map", entries)
+    }
+
+    fn visit_index_expr(&mut self, ast: &AstArena, expr: &Index) -> String {
+        let children = [("object", expr.object), ("index", expr.index)];
+        self.parent(ast, "index", children)
+    }
+
+    fn visit_set_index_expr(&mut self, ast: &AstArena, expr: &SetIndex) -> String {
+        let children = [
+            ("object", expr.object),
+            ("index", expr.index),
+            ("value", expr.value),
+        ];
+        self.parent(ast, "set-index", children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn test_ast_dot_printer() {
+        let mut parser = Parser::new(Scanner::new("1 + 2 * \"a\\\"").scan_tokens());
+        let (ast, expr) = parser.parse_expression().unwrap();
+
+        assert_eq!(
+            "digraph ast {\n  \
+               n0 [label=\"+\"];\n  \
+               n1 [label=\"1.0\"];\n  \
+               n0 -> n1 [label=\"left\"];\n  \
+               n2 [label=\"*\"];\n  \
+               n3 [label=\"2.0\"];\n  \
+               n2 -> n3 [label=\"left\"];\n  \
+               n4 [label=\"\\\"a\\\\\\\\\\\"\"];\n  \
+               n2 -> n4 [label=\"right\"];\n  \
+               n0 -> n2 [label=\"right\"];\n\
+             }\n",
+            AstDotPrinter::new().print(&ast, expr)
+        );
+    }
+}
@@ -1,59 +1,97 @@
+use std::cell::OnceCell;
+use std::rc::Rc;
+
 use crate::Value;
+use crate::parser::ast::{AstArena, ExprId};
 use crate::scanner::token::Token;
 
 pub trait Expr {
-    fn accept<V: Visitor>(&self, v: &mut V) -> V::Output;
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output;
 }
 
 pub trait Visitor {
     type Output;
-    fn visit_literal_expr(&self, expr: &Literal) -> Self::Output;
-    fn visit_grouping_expr(&mut self, expr: &Grouping) -> Self::Output;
-    fn visit_unary_expr(&mut self, expr: &Unary) -> Self::Output;
-    fn visit_variable_expr(&self, expr: &Variable) -> Self::Output;
-    fn visit_assign_expr(&mut self, expr: &Assign) -> Self::Output;
-    fn visit_binary_expr(&mut self, expr: &Binary) -> Self::Output;
+    fn visit_literal_expr(&mut self, expr: &Literal) -> Self::Output;
+    fn visit_grouping_expr(&mut self, ast: &AstArena, expr: &Grouping) -> Self::Output;
+    fn visit_unary_expr(&mut self, ast: &AstArena, expr: &Unary) -> Self::Output;
+    fn visit_variable_expr(&mut self, expr: &Variable) -> Self::Output;
+    fn visit_assign_expr(&mut self, ast: &AstArena, expr: &Assign) -> Self::Output;
+    fn visit_assign_unpack_expr(&mut self, ast: &AstArena, expr: &AssignUnpack) -> Self::Output;
+    fn visit_binary_expr(&mut self, ast: &AstArena, expr: &Binary) -> Self::Output;
+    fn visit_coalesce_expr(&mut self, ast: &AstArena, expr: &Coalesce) -> Self::Output;
+    fn visit_cached_expr(&mut self, ast: &AstArena, expr: &Cached) -> Self::Output;
+    fn visit_call_expr(&mut self, ast: &AstArena, expr: &Call) -> Self::Output;
+    fn visit_list_expr(&mut self, ast: &AstArena, expr: &List) -> Self::Output;
+    fn visit_tuple_expr(&mut self, ast: &AstArena, expr: &Tuple) -> Self::Output;
+    fn visit_spread_expr(&mut self, ast: &AstArena, expr: &Spread) -> Self::Output;
+    fn visit_map_expr(&mut self, ast: &AstArena, expr: &Map) -> Self::Output;
+    fn visit_index_expr(&mut self, ast: &AstArena, expr: &Index) -> Self::Output;
+    fn visit_set_index_expr(&mut self, ast: &AstArena, expr: &SetIndex) -> Self::Output;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ExprNode {
     Grouping(Grouping),
     Binary(Binary),
+    Coalesce(Coalesce),
+    Cached(Cached),
     Unary(Unary),
     Variable(Variable),
     Assign(Assign),
+    AssignUnpack(AssignUnpack),
+    Call(Call),
+    List(List),
+    Tuple(Tuple),
+    Spread(Spread),
+    Map(Map),
+    Index(Index),
+    SetIndex(SetIndex),
     Literal(Literal),
 }
 
 impl Expr for ExprNode {
-    fn accept<V: Visitor>(&self, v: &mut V) -> V::Output {
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
         match self {
-            ExprNode::Grouping(grouping) => grouping.accept(v),
-            ExprNode::Binary(binary) => binary.accept(v),
-            ExprNode::Unary(unary) => unary.accept(v),
-            ExprNode::Literal(literal) => literal.accept(v),
-            ExprNode::Variable(variable) => variable.accept(v),
-            ExprNode::Assign(assign) => assign.accept(v),
+            ExprNode::Grouping(grouping) => grouping.accept(ast, v),
+            ExprNode::Binary(binary) => binary.accept(ast, v),
+            ExprNode::Coalesce(coalesce) => coalesce.accept(ast, v),
+            ExprNode::Cached(cached) => cached.accept(ast, v),
+            ExprNode::Unary(unary) => unary.accept(ast, v),
+            ExprNode::Literal(literal) => literal.accept(ast, v),
+            ExprNode::Variable(variable) => variable.accept(ast, v),
+            ExprNode::Assign(assign) => assign.accept(ast, v),
+            ExprNode::AssignUnpack(unpack) => unpack.accept(ast, v),
+            ExprNode::Call(call) => call.accept(ast, v),
+            ExprNode::List(list) => list.accept(ast, v),
+            ExprNode::Tuple(tuple) => tuple.accept(ast, v),
+            ExprNode::Spread(spread) => spread.accept(ast, v),
+            ExprNode::Map(map) => map.accept(ast, v),
+            ExprNode::Index(index) => index.accept(ast, v),
+            ExprNode::SetIndex(set_index) => set_index.accept(ast, v),
         }
     }
 }
 
-#[derive(Debug)]
+impl Expr for ExprId {
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        ast[*self].accept(ast, v)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Grouping {
-    pub expression: Box<ExprNode>,
+    pub expression: ExprId,
 }
 
 impl Expr for Grouping {
-    fn accept<V: Visitor>(&self, v: &mut V) -> V::Output {
-        v.visit_grouping_expr(self)
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_grouping_expr(ast, self)
     }
 }
 
 impl Grouping {
-    pub fn new(expression: ExprNode) -> Self {
-        Self {
-            expression: Box::new(expression),
-        }
+    pub fn new(expression: ExprId) -> Self {
+        Self { expression }
     }
 }
 
@@ -63,25 +101,25 @@ impl From<Grouping> for ExprNode {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Binary {
-    pub left: Box<ExprNode>,
-    pub operator: Token,
-    pub right: Box<ExprNode>,
+    pub left: ExprId,
+    pub operator: Rc<Token>,
+    pub right: ExprId,
 }
 
 impl Expr for Binary {
-    fn accept<V: Visitor>(&self, v: &mut V) -> V::Output {
-        v.visit_binary_expr(self)
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_binary_expr(ast, self)
     }
 }
 
 impl Binary {
-    pub fn new(left: ExprNode, operator: Token, right: ExprNode) -> Self {
+    pub fn new(left: ExprId, operator: impl Into<Rc<Token>>, right: ExprId) -> Self {
         Self {
-            left: Box::new(left),
-            operator,
-            right: Box::new(right),
+            left,
+            operator: operator.into(),
+            right,
         }
     }
 }
@@ -92,23 +130,82 @@ impl From<Binary> for ExprNode {
     }
 }
 
-#[derive(Debug)]
+/// `left ?? right`, which only evaluates `right` when `left` is nil.
+#[derive(Debug, Clone)]
+pub struct Coalesce {
+    pub left: ExprId,
+    pub operator: Rc<Token>,
+    pub right: ExprId,
+}
+
+impl Expr for Coalesce {
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_coalesce_expr(ast, self)
+    }
+}
+
+impl Coalesce {
+    pub fn new(left: ExprId, operator: impl Into<Rc<Token>>, right: ExprId) -> Self {
+        Self {
+            left,
+            operator: operator.into(),
+            right,
+        }
+    }
+}
+
+impl From<Coalesce> for ExprNode {
+    fn from(coalesce: Coalesce) -> Self {
+        Self::Coalesce(coalesce)
+    }
+}
+
+/// An expression without side effects whose value is kept after it is first
+/// evaluated. The parser never produces one, see [`crate::hoist`].
+#[derive(Debug, Clone)]
+pub struct Cached {
+    pub expr: ExprId,
+    pub value: OnceCell<Value>,
+}
+
+impl Expr for Cached {
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_cached_expr(ast, self)
+    }
+}
+
+impl Cached {
+    pub fn new(expr: ExprId) -> Self {
+        Self {
+            expr,
+            value: OnceCell::new(),
+        }
+    }
+}
+
+impl From<Cached> for ExprNode {
+    fn from(cached: Cached) -> Self {
+        Self::Cached(cached)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Unary {
-    pub operator: Token,
-    pub right: Box<ExprNode>,
+    pub operator: Rc<Token>,
+    pub right: ExprId,
 }
 
 impl Expr for Unary {
-    fn accept<V: Visitor>(&self, v: &mut V) -> V::Output {
-        v.visit_unary_expr(self)
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_unary_expr(ast, self)
     }
 }
 
 impl Unary {
-    pub fn new(operator: Token, right: ExprNode) -> Self {
+    pub fn new(operator: impl Into<Rc<Token>>, right: ExprId) -> Self {
         Self {
-            operator,
-            right: Box::new(right),
+            operator: operator.into(),
+            right,
         }
     }
 }
@@ -119,13 +216,13 @@ impl From<Unary> for ExprNode {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Variable {
     pub name: Token,
 }
 
 impl Expr for Variable {
-    fn accept<V: Visitor>(&self, v: &mut V) -> V::Output {
+    fn accept<V: Visitor>(&self, _ast: &AstArena, v: &mut V) -> V::Output {
         v.visit_variable_expr(self)
     }
 }
@@ -142,24 +239,21 @@ impl From<Variable> for ExprNode {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Assign {
     pub name: Token,
-    pub value: Box<ExprNode>,
+    pub value: ExprId,
 }
 
 impl Expr for Assign {
-    fn accept<V: Visitor>(&self, v: &mut V) -> V::Output {
-        v.visit_assign_expr(self)
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_assign_expr(ast, self)
     }
 }
 
 impl Assign {
-    pub fn new(name: Token, value: ExprNode) -> Self {
-        Self {
-            name,
-            value: Box::new(value),
-        }
+    pub fn new(name: Token, value: ExprId) -> Self {
+        Self { name, value }
     }
 }
 
@@ -169,13 +263,254 @@ impl From<Assign> for ExprNode {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+/// `(a, b) = value`, which assigns each element of a list to a variable.
+#[derive(Debug, Clone)]
+pub struct AssignUnpack {
+    /// The opening parenthesis, used to report a length mismatch
+    pub paren: Token,
+    pub names: Vec<Token>,
+    pub value: ExprId,
+}
+
+impl Expr for AssignUnpack {
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_assign_unpack_expr(ast, self)
+    }
+}
+
+impl AssignUnpack {
+    pub fn new(paren: Token, names: Vec<Token>, value: ExprId) -> Self {
+        Self {
+            paren,
+            names,
+            value,
+        }
+    }
+}
+
+impl From<AssignUnpack> for ExprNode {
+    fn from(unpack: AssignUnpack) -> Self {
+        Self::AssignUnpack(unpack)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub callee: ExprId,
+    /// The closing parenthesis, used to report errors at the call site
+    pub paren: Token,
+    pub arguments: Vec<ExprId>,
+}
+
+impl Expr for Call {
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_call_expr(ast, self)
+    }
+}
+
+impl Call {
+    pub fn new(callee: ExprId, paren: Token, arguments: Vec<ExprId>) -> Self {
+        Self {
+            callee,
+            paren,
+            arguments,
+        }
+    }
+}
+
+impl From<Call> for ExprNode {
+    fn from(call: Call) -> Self {
+        Self::Call(call)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct List {
+    pub elements: Vec<ExprId>,
+}
+
+impl Expr for List {
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_list_expr(ast, self)
+    }
+}
+
+impl List {
+    pub fn new(elements: Vec<ExprId>) -> Self {
+        Self { elements }
+    }
+}
+
+impl From<List> for ExprNode {
+    fn from(list: List) -> Self {
+        Self::List(list)
+    }
+}
+
+/// A parenthesized `(a, b)`, which evaluates to a list but can also be the
+/// target of an assignment.
+#[derive(Debug, Clone)]
+pub struct Tuple {
+    pub paren: Token,
+    pub elements: Vec<ExprId>,
+}
+
+impl Expr for Tuple {
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_tuple_expr(ast, self)
+    }
+}
+
+impl Tuple {
+    pub fn new(paren: Token, elements: Vec<ExprId>) -> Self {
+        Self { paren, elements }
+    }
+}
+
+impl From<Tuple> for ExprNode {
+    fn from(tuple: Tuple) -> Self {
+        Self::Tuple(tuple)
+    }
+}
+
+/// A `...` expansion, which the parser only produces as a call argument or
+/// a list literal element.
+#[derive(Debug, Clone)]
+pub struct Spread {
+    /// The `...` token, used to report errors about the expanded value
+    pub ellipsis: Token,
+    pub expression: ExprId,
+}
+
+impl Expr for Spread {
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_spread_expr(ast, self)
+    }
+}
+
+impl Spread {
+    pub fn new(ellipsis: Token, expression: ExprId) -> Self {
+        Self {
+            ellipsis,
+            expression,
+        }
+    }
+}
+
+impl From<Spread> for ExprNode {
+    fn from(spread: Spread) -> Self {
+        Self::Spread(spread)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Map {
+    /// The opening brace, used to report invalid keys
+    pub brace: Token,
+    pub entries: Vec<(ExprId, ExprId)>,
+}
+
+impl Expr for Map {
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_map_expr(ast, self)
+    }
+}
+
+impl Map {
+    pub fn new(brace: Token, entries: Vec<(ExprId, ExprId)>) -> Self {
+        Self { brace, entries }
+    }
+}
+
+impl From<Map> for ExprNode {
+    fn from(map: Map) -> Self {
+        Self::Map(map)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Index {
+    pub object: ExprId,
+    /// The closing bracket, used to report errors at the index
+    pub bracket: Token,
+    pub index: ExprId,
+}
+
+impl Expr for Index {
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_index_expr(ast, self)
+    }
+}
+
+impl Index {
+    pub fn new(object: ExprId, bracket: Token, index: ExprId) -> Self {
+        Self {
+            object,
+            bracket,
+            index,
+        }
+    }
+}
+
+impl From<Index> for ExprNode {
+    fn from(index: Index) -> Self {
+        Self::Index(index)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SetIndex {
+    pub object: ExprId,
+    /// The closing bracket, used to report errors at the index
+    pub bracket: Token,
+    pub index: ExprId,
+    pub value: ExprId,
+}
+
+impl Expr for SetIndex {
+    fn accept<V: Visitor>(&self, ast: &AstArena, v: &mut V) -> V::Output {
+        v.visit_set_index_expr(ast, self)
+    }
+}
+
+impl SetIndex {
+    /// Turns the index expression `target` into an assignment of `value`.
+    pub fn new(target: Index, value: ExprId) -> Self {
+        Self {
+            object: target.object,
+            bracket: target.bracket,
+            index: target.index,
+            value,
+        }
+    }
+}
+
+impl From<SetIndex> for ExprNode {
+    fn from(set_index: SetIndex) -> Self {
+        Self::SetIndex(set_index)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Literal {
     pub value: Value,
+    /// The token the literal was written as, or `None` for a literal made up
+    /// by the parser or the optimizer
+    pub token: Option<Rc<Token>>,
+}
+
+impl Literal {
+    /// Creates the literal written as `token`, which holds `value`.
+    pub fn new(value: Value, token: impl Into<Rc<Token>>) -> Self {
+        Self {
+            value,
+            token: Some(token.into()),
+        }
+    }
 }
 
 impl Expr for Literal {
-    fn accept<V: Visitor>(&self, v: &mut V) -> V::Output {
+    fn accept<V: Visitor>(&self, _ast: &AstArena, v: &mut V) -> V::Output {
         v.visit_literal_expr(self)
     }
 }
@@ -188,6 +523,6 @@ impl From<Literal> for ExprNode {
 
 impl From<Value> for Literal {
     fn from(value: Value) -> Self {
-        Literal { value }
+        Literal { value, token: None }
     }
 }
@@ -0,0 +1,453 @@
+//! The parse tree as JSON, for external tools that consume it.
+//!
+//! Every node is an object with a `type`, such as `binary` or `literal`, its
+//! children by name, and the `span` of source bytes from its first to its
+//! last token. The tree keeps no tokens for the brackets of a list or the
+//! parentheses of a grouping, so their spans start and end at the elements.
+//! Tokens carry their lexeme, line, column and span. Nodes and tokens made up
+//! by the parser have a `null` span.
+
+use serde::Serialize;
+use serde_json::Value as Json;
+
+use crate::Value;
+use crate::parser::ast::{AstArena, ExprId};
+use crate::parser::expr::{
+    Assign, AssignUnpack, Binary, Cached, Call, Coalesce, Expr, Grouping, Index, List, Literal,
+    Map, SetIndex, Spread, Tuple, Unary, Variable, Visitor,
+};
+use crate::scanner::token::Token;
+
+#[derive(Clone, Copy)]
+pub struct JsonPrinter;
+
+impl JsonPrinter {
+    pub fn print(mut self, ast: &AstArena, expr: ExprId) -> Json {
+        let node = expr.accept(ast, &mut self);
+        serde_json::to_value(node).expect("nodes serialize to JSON")
+    }
+
+    fn all(&mut self, ast: &AstArena, exprs: &[ExprId]) -> Vec<Node> {
+        exprs.iter().map(|expr| expr.accept(ast, self)).collect()
+    }
+}
+
+/// The byte offsets a node or token covers in its source file.
+#[derive(Debug, Clone, Copy, Serialize)]
+struct Bytes {
+    start: u32,
+    end: u32,
+}
+
+/// Returns the smallest span that covers all of `spans`, or `None` if none
+/// of them is known.
+fn cover(spans: impl IntoIterator<Item = Option<Bytes>>) -> Option<Bytes> {
+    spans.into_iter().flatten().reduce(|a, b| Bytes {
+        start: a.start.min(b.start),
+        end: a.end.max(b.end),
+    })
+}
+
+/// A token by its lexeme and where it starts.
+#[derive(Debug, Serialize)]
+struct TokenNode {
+    lexeme: String,
+    line: u32,
+    column: Option<u32>,
+    span: Option<Bytes>,
+}
+
+impl From<&Token> for TokenNode {
+    fn from(token: &Token) -> Self {
+        Self {
+            lexeme: token.lexeme.to_string(),
+            line: token.line,
+            column: (token.column > 0).then_some(token.column),
+            span: bytes(token),
+        }
+    }
+}
+
+/// Returns the bytes `token` was scanned from.
+fn bytes(token: &Token) -> Option<Bytes> {
+    token.span.map(|span| Bytes {
+        start: span.start,
+        end: span.end,
+    })
+}
+
+/// A node of the parse tree with the bytes it covers.
+#[derive(Debug, Serialize)]
+pub struct Node {
+    #[serde(flatten)]
+    kind: Kind,
+    span: Option<Bytes>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Kind {
+    Literal {
+        kind: &'static str,
+        value: Json,
+    },
+    Grouping {
+        expression: Box<Node>,
+    },
+    Unary {
+        operator: TokenNode,
+        right: Box<Node>,
+    },
+    Variable {
+        name: TokenNode,
+    },
+    Assign {
+        name: TokenNode,
+        value: Box<Node>,
+    },
+    AssignUnpack {
+        paren: TokenNode,
+        names: Vec<TokenNode>,
+        value: Box<Node>,
+    },
+    Binary {
+        operator: TokenNode,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    Coalesce {
+        operator: TokenNode,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    Call {
+        callee: Box<Node>,
+        paren: TokenNode,
+        arguments: Vec<Node>,
+    },
+    List {
+        elements: Vec<Node>,
+    },
+    Tuple {
+        paren: TokenNode,
+        elements: Vec<Node>,
+    },
+    Spread {
+        ellipsis: TokenNode,
+        expression: Box<Node>,
+    },
+    Map {
+        brace: TokenNode,
+        entries: Vec<Entry>,
+    },
+    Index {
+        object: Box<Node>,
+        bracket: TokenNode,
+        index: Box<Node>,
+    },
+    SetIndex {
+        object: Box<Node>,
+        bracket: TokenNode,
+        index: Box<Node>,
+        value: Box<Node>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct Entry {
+    key: Node,
+    value: Node,
+}
+
+impl Node {
+    /// Makes the node for `kind`, spanning its tokens and children.
+    fn new(kind: Kind) -> Self {
+        let span = match &kind {
+            // Literals know their span from their token, see
+            // `visit_literal_expr`
+            Kind::Literal { .. } => None,
+            Kind::Grouping { expression } => expression.span,
+            Kind::Unary { operator, right } => cover([operator.span, right.span]),
+            Kind::Variable { name } => name.span,
+            Kind::Assign { name, value } => cover([name.span, value.span]),
+            Kind::AssignUnpack {
+                paren,
+                names,
+                value,
+            } => cover(
+                [paren.span, value.span]
+                    .into_iter()
+                    .chain(names.iter().map(|name| name.span)),
+            ),
+            Kind::Binary {
+                operator,
+                left,
+                right,
+            }
+            | Kind::Coalesce {
+                operator,
+                left,
+                right,
+            } => cover([left.span, operator.span, right.span]),
+            Kind::Call {
+                callee,
+                paren,
+                arguments,
+            } => cover(
+                [callee.span, paren.span]
+                    .into_iter()
+                    .chain(arguments.iter().map(|argument| argument.span)),
+            ),
+            Kind::List { elements } => cover(elements.iter().map(|element| element.span)),
+            Kind::Tuple { paren, elements } => cover(
+                [paren.span]
+                    .into_iter()
+                    .chain(elements.iter().map(|element| element.span)),
+            ),
+            Kind::Spread {
+                ellipsis,
+                expression,
+            } => cover([ellipsis.span, expression.span]),
+            Kind::Map { brace, entries } => cover(
+                [brace.span]
+                    .into_iter()
+                    .chain(entries.iter().flat_map(|e| [e.key.span, e.value.span])),
+            ),
+            Kind::Index {
+                object,
+                bracket,
+                index,
+            } => cover([object.span, bracket.span, index.span]),
+            Kind::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+            } => cover([object.span, bracket.span, index.span, value.span]),
+        };
+        Self { kind, span }
+    }
+}
+
+/// Converts the value of a literal, keeping numbers, strings, booleans and
+/// `nil` as their JSON counterparts and naming native functions.
+pub(crate) fn value(value: &Value) -> Json {
+    match value {
+        Value::Nil => Json::Null,
+        Value::Boolean(b) => (*b).into(),
+        Value::Integer(n) => (*n).into(),
+        Value::Number(n) => (*n).into(),
+        Value::String(s) => s.as_str().into(),
+        Value::NativeFunction(native) => native.name.into(),
+        other => other.to_string().into(),
+    }
+}
+
+impl Visitor for JsonPrinter {
+    type Output = Node;
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> Node {
+        Node {
+            kind: Kind::Literal {
+                kind: expr.value.type_name(),
+                value: value(&expr.value),
+            },
+            span: expr.token.as_deref().and_then(bytes),
+        }
+    }
+
+    fn visit_grouping_expr(&mut self, ast: &AstArena, expr: &Grouping) -> Node {
+        Node::new(Kind::Grouping {
+            expression: Box::new(expr.expression.accept(ast, self)),
+        })
+    }
+
+    fn visit_unary_expr(&mut self, ast: &AstArena, expr: &Unary) -> Node {
+        Node::new(Kind::Unary {
+            operator: expr.operator.as_ref().into(),
+            right: Box::new(expr.right.accept(ast, self)),
+        })
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> Node {
+        Node::new(Kind::Variable {
+            name: (&expr.name).into(),
+        })
+    }
+
+    fn visit_assign_expr(&mut self, ast: &AstArena, expr: &Assign) -> Node {
+        Node::new(Kind::Assign {
+            name: (&expr.name).into(),
+            value: Box::new(expr.value.accept(ast, self)),
+        })
+    }
+
+    fn visit_assign_unpack_expr(&mut self, ast: &AstArena, expr: &AssignUnpack) -> Node {
+        Node::new(Kind::AssignUnpack {
+            paren: (&expr.paren).into(),
+            names: expr.names.iter().map(TokenNode::from).collect(),
+            value: Box::new(expr.value.accept(ast, self)),
+        })
+    }
+
+    fn visit_binary_expr(&mut self, ast: &AstArena, expr: &Binary) -> Node {
+        Node::new(Kind::Binary {
+            operator: expr.operator.as_ref().into(),
+            left: Box::new(expr.left.accept(ast, self)),
+            right: Box::new(expr.right.accept(ast, self)),
+        })
+    }
+
+    fn visit_coalesce_expr(&mut self, ast: &AstArena, expr: &Coalesce) -> Node {
+        Node::new(Kind::Coalesce {
+            operator: expr.operator.as_ref().into(),
+            left: Box::new(expr.left.accept(ast, self)),
+            right: Box::new(expr.right.accept(ast, self)),
+        })
+    }
+
+    fn visit_cached_expr(&mut self, ast: &AstArena, expr: &Cached) -> Node {
+        expr.expr.accept(ast, self)
+    }
+
+    fn visit_call_expr(&mut self, ast: &AstArena, expr: &Call) -> Node {
+        Node::new(Kind::Call {
+            callee: Box::new(expr.callee.accept(ast, self)),
+            paren: (&expr.paren).into(),
+            arguments: self.all(ast, &expr.arguments),
+        })
+    }
+
+    fn visit_list_expr(&mut self, ast: &AstArena, expr: &List) -> Node {
+        Node::new(Kind::List {
+            elements: self.all(ast, &expr.elements),
+        })
+    }
+
+    fn visit_tuple_expr(&mut self, ast: &AstArena, expr: &Tuple) -> Node {
+        Node::new(Kind::Tuple {
+            paren: (&expr.paren).into(),
+            elements: self.all(ast, &expr.elements),
+        })
+    }
+
+    fn visit_spread_expr(&mut self, ast: &AstArena, expr: &Spread) -> Node {
+        Node::new(Kind::Spread {
+            ellipsis: (&expr.ellipsis).into(),
+            expression: Box::new(expr.expression.accept(ast, self)),
+        })
+    }
+
+    fn visit_map_expr(&mut self, ast: &AstArena, expr: &Map) -> Node {
+        let entries = expr.entries.iter().map(|(key, value)| Entry {
+            key: key.accept(ast, self),
+            value: value.accept(ast, self),
+        });
+        Node::new(Kind::Map {
+            brace: (&expr.brace).into(),
+            entries: entries.collect(),
+        })
+    }
+
+    fn visit_index_expr(&mut self, ast: &AstArena, expr: &Index) -> Node {
+        Node::new(Kind::Index {
+            object: Box::new(expr.object.accept(ast, self)),
+            bracket: (&expr.bracket).into(),
+            index: Box::new(expr.index.accept(ast, self)),
+        })
+    }
+
+    fn visit_set_index_expr(&mut self, ast: &AstArena, expr: &SetIndex) -> Node {
+        Node::new(Kind::SetIndex {
+            object: Box::new(expr.object.accept(ast, self)),
+            bracket: (&expr.bracket).into(),
+            index: Box::new(expr.index.accept(ast, self)),
+            value: Box::new(expr.value.accept(ast, self)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use serde_json::json;
+
+    use super::*;
+    use crate::dialect::{Dialect, Extension};
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn print(source: &str) -> Json {
+        let dialect =
+            Dialect::from_iter([Extension::Lists, Extension::Maps, Extension::NilCoalescing]);
+        let scanner = Scanner::new(source).with_dialect(dialect);
+        let mut parser = Parser::new(scanner.scan_tokens()).with_dialect(dialect);
+        let (ast, expr) = parser.parse_expression().unwrap();
+        JsonPrinter.print(&ast, expr)
+    }
+
+    #[test]
+    fn test_json_printer() {
+        assert_eq!(
+            json!({
+                "type": "binary",
+                "operator": {
+                    "lexeme": "*",
+                    "line": 1,
+                    "column": 5,
+                    "span": {"start": 4, "end": 5},
+                },
+                "left": {
+                    "type": "literal",
+                    "kind": "number",
+                    "value": 1.0,
+                    "span": {"start": 0, "end": 3},
+                },
+                "right": {
+                    "type": "grouping",
+                    "expression": {
+                        "type": "unary",
+                        "operator": {
+                            "lexeme": "-",
+                            "line": 1,
+                            "column": 8,
+                            "span": {"start": 7, "end": 8},
+                        },
+                        "right": {
+                            "type": "variable",
+                            "name": {
+                                "lexeme": "x",
+                                "line": 1,
+                                "column": 9,
+                                "span": {"start": 8, "end": 9},
+                            },
+                            "span": {"start": 8, "end": 9},
+                        },
+                        "span": {"start": 7, "end": 9},
+                    },
+                    "span": {"start": 7, "end": 9},
+                },
+                "span": {"start": 0, "end": 9},
+            }),
+            print("1.0 * (-x)")
+        );
+    }
+
+    #[test]
+    fn test_json_printer_literals() {
+        let literal = |source| print(source)["value"].clone();
+        assert_eq!(json!("true"), literal("\"true\""));
+        assert_eq!(json!(true), literal("true"));
+        assert_eq!(Json::Null, literal("nil"));
+        assert_eq!("string", print("\"a\"")["kind"]);
+    }
+
+    #[rstest]
+    #[case("f(1, 2)", 0, 7)]
+    #[case("[1, [2]]", 1, 6)]
+    #[case("m[\"k\"] = v", 0, 10)]
+    #[case("  a ?? b", 2, 8)]
+    fn test_json_printer_spans(#[case] source: &str, #[case] start: u32, #[case] end: u32) {
+        assert_eq!(json!({"start": start, "end": end}), print(source)["span"]);
+    }
+}
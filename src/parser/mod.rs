@@ -1,43 +1,172 @@
-use std::iter::Peekable;
-use std::vec;
+use std::collections::HashMap;
+use std::{iter, mem, vec};
 
-use crate::Value;
+use crate::dialect::{Dialect, Extension};
 use crate::error::StaticError;
-use crate::parser::expr::{Assign, Binary, ExprNode, Grouping, Literal, Unary, Variable};
-use crate::parser::stmt::{Block, Expression, Print, StmtNode, Var};
+use crate::parser::ast::{Ast, AstArena, ExprId, Lines, StmtId};
+use crate::parser::expr::{
+    Assign, AssignUnpack, Binary, Call, Coalesce, ExprNode, Grouping, Index, List, Literal, Map,
+    SetIndex, Spread, Tuple, Unary, Variable,
+};
+use crate::parser::stmt::{
+    Assert, Block, DoWhile, Expression, ForIn, Import, Match, Print, Throw, Try, Var, VarUnpack,
+};
+use crate::parser::tokens::Tokens;
 use crate::scanner::token::{Token, TokenType};
+use crate::scanner::{ScanItem, ScanResult};
+use crate::symbol::Symbol;
+use crate::{Value, stdlib};
 
+pub mod ast;
+pub mod dot;
 pub mod expr;
+pub mod json;
 pub mod printer;
 pub mod stmt;
-
-pub struct Parser {
-    tokens: Peekable<vec::IntoIter<Token>>,
+mod tokens;
+
+/// The maximum number of arguments a call expression may pass.
+const MAX_ARGUMENTS: usize = 255;
+
+/// The items of a parser made from a list of tokens that were already
+/// scanned.
+pub type Buffered = iter::Map<vec::IntoIter<Token>, fn(Token) -> ScanResult>;
+
+/// Parses the tokens yielded by `I`, pulling each one only when it is
+/// needed, such as straight from a [`TokenStream`](crate::scanner::TokenStream).
+pub struct Parser<I = Buffered> {
+    tokens: Tokens<I>,
+    // The nodes parsed so far, handed over once parsing ends
+    arena: AstArena,
+    // Language extensions recognized while parsing
+    dialect: Dialect,
+    // The variables declared in each enclosing scope, innermost last, and
+    // whether they are constants
+    scopes: Vec<HashMap<Symbol, bool>>,
 }
 
 impl From<Vec<Token>> for Parser {
     fn from(value: Vec<Token>) -> Self {
+        let scanned: fn(Token) -> ScanResult = |token| Ok(ScanItem::Token(token));
+        Parser::new(value.into_iter().map(scanned))
+    }
+}
+
+impl<I: Iterator<Item = ScanResult>> Parser<I> {
+    /// Creates a parser that reads tokens from `items` as it goes, reporting
+    /// every scan error among them instead of the parse errors they cause.
+    pub fn new(items: I) -> Self {
         Self {
-            tokens: value.into_iter().peekable(),
+            tokens: Tokens::new(items),
+            arena: AstArena::new(),
+            dialect: Dialect::default(),
+            scopes: vec![HashMap::new()],
         }
     }
-}
 
-impl Parser {
+    /// Enables the language extensions in `dialect` for this parser.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Adds the nodes this parser makes to `arena`, after those already in
+    /// it, so that their ids stay valid.
+    pub fn with_arena(mut self, arena: AstArena) -> Self {
+        self.arena = arena;
+        self
+    }
+
+    /// Parses as if `globals` were declared by statements before the tokens,
+    /// each with whether it is a constant.
+    pub fn with_globals(mut self, globals: impl IntoIterator<Item = (Symbol, bool)>) -> Self {
+        self.scopes[0].extend(globals);
+        self
+    }
+
     /// Parses the full token stream as a sequence of statements until EOF.
     ///
     /// Returns:
-    /// - `Ok(Vec<StmtNode>)` with all parsed statements.
+    /// - `Ok(Ast)` with all parsed statements.
     /// - `Err(Report)` when any statement cannot be parsed.
-    pub fn parse(&mut self) -> Result<Vec<StmtNode>, StaticError> {
+    pub fn parse(&mut self) -> Result<Ast, StaticError> {
+        let mut stmts = Vec::new();
+
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(s) => stmts.push(s),
+                Err(err) => {
+                    self.tokens.skip_rest();
+                    return self.scan_errors_or(Err(err));
+                }
+            }
+        }
+
+        self.scan_errors_or(Ok(()))?;
+        Ok(self.finish(stmts))
+    }
+
+    /// Parses the full token stream like [`Parser::parse`], but skips to the
+    /// next statement after an error instead of stopping.
+    ///
+    /// Returns the statements that parsed and every error encountered.
+    pub fn parse_recovering(&mut self) -> (Ast, Vec<StaticError>) {
         let mut stmts = Vec::new();
+        let mut errors = Vec::new();
 
         while !self.is_at_end() {
-            let s = self.declaration()?;
-            stmts.push(s);
+            let result = self.declaration();
+            let scanned = self.tokens.take_errors();
+            match result {
+                Ok(s) => stmts.push(s),
+                Err(err) => {
+                    if scanned.is_empty() {
+                        errors.push(err);
+                    }
+                    self.synchronize();
+                }
+            }
+            errors.extend(scanned);
         }
+        errors.extend(self.tokens.take_errors());
+
+        (self.finish(stmts), errors)
+    }
 
-        Ok(stmts)
+    /// Parses the next top-level statement, skipping to the one after it on an
+    /// error like [`Parser::parse_recovering`], or returns `None` at the end
+    /// of the tokens. The nodes stay in the arena, see
+    /// [`Parser::into_arena`].
+    pub fn parse_declaration(&mut self) -> Option<Result<StmtId, StaticError>> {
+        if self.is_at_end() {
+            return None;
+        }
+        let stmt = self.declaration();
+        let stmt = self.scan_errors_or(stmt);
+        if stmt.is_err() {
+            self.synchronize();
+        }
+        Some(stmt)
+    }
+
+    /// Checks whether every token but `EOF` has been consumed.
+    pub fn is_at_end(&mut self) -> bool {
+        self.tokens.peek().is_none_or(|t| t.typ == TokenType::Eof)
+    }
+
+    /// Returns the number of tokens consumed so far.
+    pub fn consumed(&self) -> usize {
+        self.tokens.consumed()
+    }
+
+    /// Returns the nodes parsed so far.
+    pub fn arena(&self) -> &AstArena {
+        &self.arena
+    }
+
+    /// Hands over the nodes parsed so far.
+    pub fn into_arena(mut self) -> AstArena {
+        mem::take(&mut self.arena)
     }
 
     /// Parses a single expression from the current parser position.
@@ -46,16 +175,40 @@ impl Parser {
     /// commands that do not expect statement wrappers).
     ///
     /// Returns:
-    /// - `Ok(ExprNode)` for a successfully parsed expression.
+    /// - `Ok((AstArena, ExprId))` for a successfully parsed expression, with
+    ///   the arena holding its nodes.
     /// - `Err(Report)` if expression parsing fails.
-    pub fn parse_expression(&mut self) -> Result<ExprNode, StaticError> {
-        self.expression()
+    pub fn parse_expression(&mut self) -> Result<(AstArena, ExprId), StaticError> {
+        let expr = self.expression();
+        self.tokens.skip_rest();
+        let expr = self.scan_errors_or(expr)?;
+        Ok((mem::take(&mut self.arena), expr))
     }
 
-    #[allow(unused)]
+    /// Returns the scan errors met since they were last taken, all in one
+    /// error, instead of `result`, since a parse error among them most
+    /// likely comes from the characters that failed to scan.
+    fn scan_errors_or<T>(&mut self, result: Result<T, StaticError>) -> Result<T, StaticError> {
+        match StaticError::combine(self.tokens.take_errors()) {
+            Some(err) => Err(err),
+            None => result,
+        }
+    }
+
+    /// Hands over the nodes parsed so far as a program made of `statements`.
+    fn finish(&mut self, statements: Vec<StmtId>) -> Ast {
+        Ast {
+            arena: mem::take(&mut self.arena),
+            statements,
+        }
+    }
+
+    /// Discards tokens until the end of the statement that failed to parse,
+    /// always consuming at least one token so recovery makes progress.
     fn synchronize(&mut self) {
         while !self.is_at_end() {
-            if self.next_if(TokenType::Semicolon).is_some() {
+            let token = self.tokens.next().expect("loop guard ensures a next token");
+            if token.typ == TokenType::Semicolon || self.is_at_end() {
                 return;
             }
 
@@ -67,6 +220,7 @@ impl Parser {
                 TokenType::Class
                     | TokenType::Fun
                     | TokenType::Var
+                    | TokenType::Const
                     | TokenType::For
                     | TokenType::If
                     | TokenType::While
@@ -75,21 +229,30 @@ impl Parser {
             ) {
                 return;
             }
-
-            self.tokens.next();
         }
     }
 
-    // declaration → varDecl | statement ;
-    fn declaration(&mut self) -> Result<StmtNode, StaticError> {
-        if self.next_if(TokenType::Var).is_some() {
-            return self.var_declaration();
-        }
-        self.statement()
+    // declaration → varDecl | constDecl | statement ;
+    fn declaration(&mut self) -> Result<StmtId, StaticError> {
+        let first = self.peek_line();
+        let stmt = if self.next_if(TokenType::Var).is_some() {
+            self.var_declaration()?
+        } else if self.next_if(TokenType::Const).is_some() {
+            self.const_declaration()?
+        } else {
+            return self.statement();
+        };
+        Ok(self.spanned(stmt, first))
     }
 
     // varDecl → "var" IDENTIFIER ( "=" expression )? ";" ;
-    fn var_declaration(&mut self) -> Result<StmtNode, StaticError> {
+    fn var_declaration(&mut self) -> Result<StmtId, StaticError> {
+        if self.dialect.allows(Extension::Destructuring)
+            && let Some(paren) = self.next_if(TokenType::LeftParen)
+        {
+            return self.var_unpack(paren);
+        }
+
         let name = self.next_ok(TokenType::Identifier, "Expect variable name.".into())?;
 
         let mut init = None;
@@ -98,15 +261,75 @@ impl Parser {
         }
 
         self.expect_semicolon()?;
+        self.declare(&name, false)?;
+
+        Ok(self.arena.add_stmt(Var::new(name, init)))
+    }
+
+    // constDecl → "const" IDENTIFIER "=" expression ";" ;
+    fn const_declaration(&mut self) -> Result<StmtId, StaticError> {
+        let name = self.next_ok(TokenType::Identifier, "Expect constant name.".into())?;
+        self.next_ok(TokenType::Equal, "Expect '=' after constant name.".into())?;
+        let init = self.expression()?;
+        self.expect_semicolon()?;
+        self.declare(&name, true)?;
+
+        Ok(self.arena.add_stmt(Var::constant(name, init)))
+    }
+
+    // varUnpack → "var" "(" IDENTIFIER ( "," IDENTIFIER )* ")" "=" expression ";" ;
+    fn var_unpack(&mut self, paren: Token) -> Result<StmtId, StaticError> {
+        let (names, _) = self.delimited(
+            TokenType::RightParen,
+            "Expect ')' after variable names.",
+            |parser| parser.next_ok(TokenType::Identifier, "Expect variable name.".into()),
+        )?;
+        self.next_ok(TokenType::Equal, "Expect '=' after variable names.".into())?;
+        let value = self.expression()?;
+        self.expect_semicolon()?;
+        for name in &names {
+            self.declare(name, false)?;
+        }
+
+        Ok(self.arena.add_stmt(VarUnpack::new(paren, names, value)))
+    }
 
-        Ok(Var::new(name, init).into())
+    /// Parses a statement, recording the lines it spans.
+    fn statement(&mut self) -> Result<StmtId, StaticError> {
+        let first = self.peek_line();
+        let stmt = self.bare_statement()?;
+        Ok(self.spanned(stmt, first))
     }
 
-    // statement → exprStmt | printStmt | block ;
-    fn statement(&mut self) -> Result<StmtNode, StaticError> {
+    // statement → exprStmt | forInStmt | printStmt | tryStmt | throwStmt
+    //           | assertStmt | importStmt | matchStmt | block ;
+    fn bare_statement(&mut self) -> Result<StmtId, StaticError> {
         if self.next_if(TokenType::Print).is_some() {
             return self.print_statement();
         }
+        if self.dialect.allows(Extension::ForIn)
+            && let Some(keyword) = self.next_if(TokenType::For)
+        {
+            return self.for_in_statement(keyword);
+        }
+        if self.next_if(TokenType::Do).is_some() {
+            return self.do_while_statement();
+        }
+        if self.next_if(TokenType::Try).is_some() {
+            return self.try_statement();
+        }
+        if let Some(keyword) = self.next_if(TokenType::Throw) {
+            return self.throw_statement(keyword);
+        }
+        if let Some(keyword) = self.next_if(TokenType::Assert) {
+            return self.assert_statement(keyword);
+        }
+        if self.next_if(TokenType::Import).is_some() {
+            return self.import_statement();
+        }
+        if self.next_if(TokenType::Match).is_some() {
+            return self.match_statement();
+        }
         if self.next_if(TokenType::LeftBrace).is_some() {
             return self.block_statement();
         }
@@ -114,74 +337,292 @@ impl Parser {
     }
 
     // block → "{" declaration* "}" ;
-    fn block_statement(&mut self) -> Result<StmtNode, StaticError> {
-        let mut statements = Vec::new();
+    fn block_statement(&mut self) -> Result<StmtId, StaticError> {
+        // the opening brace is already consumed
+        let first = self.tokens.last_line();
+        self.in_scope(&[], |this| {
+            let mut statements = Vec::new();
+            while !this.peek_check(TokenType::RightBrace) && !this.is_at_end() {
+                let stmt = this.declaration()?;
+                statements.push(stmt);
+            }
+
+            this.next_ok(TokenType::RightBrace, "Expect '}' after block.".into())?;
+            let block = this.arena.add_stmt(Block::new(statements));
+            Ok(this.spanned(block, first))
+        })
+    }
+
+    // forInStmt → "for" "(" "var"? IDENTIFIER "in" expression ")" statement ;
+    fn for_in_statement(&mut self, keyword: Token) -> Result<StmtId, StaticError> {
+        self.next_ok(TokenType::LeftParen, "Expect '(' after 'for'.".into())?;
+        self.next_if(TokenType::Var);
+        let name = self.next_ok(TokenType::Identifier, "Expect loop variable name.".into())?;
+
+        // `in` is only a keyword here, so it stays usable as a variable name
+        let is_in = |t: &Token| t.typ == TokenType::Identifier && t.lexeme == "in";
+        if self.tokens.next_if(is_in).is_none() {
+            return Err(self.error("Expect 'in' after loop variable.".into()));
+        }
+
+        let iterable = self.expression()?;
+        self.next_ok(
+            TokenType::RightParen,
+            "Expect ')' after for clauses.".into(),
+        )?;
+        let body = self.in_scope(&[&name], Self::statement)?;
+
+        Ok(self
+            .arena
+            .add_stmt(ForIn::new(keyword, name, iterable, body)))
+    }
+
+    // doWhileStmt → "do" statement "while" "(" expression ")" ";" ;
+    fn do_while_statement(&mut self) -> Result<StmtId, StaticError> {
+        let body = self.statement()?;
+        self.next_ok(TokenType::While, "Expect 'while' after do body.".into())?;
+        self.next_ok(TokenType::LeftParen, "Expect '(' after 'while'.".into())?;
+        let condition = self.expression()?;
+        self.next_ok(TokenType::RightParen, "Expect ')' after condition.".into())?;
+        self.next_ok(
+            TokenType::Semicolon,
+            "Expect ';' after do-while loop.".into(),
+        )?;
+
+        Ok(self.arena.add_stmt(DoWhile::new(body, condition)))
+    }
+
+    // tryStmt → "try" block ( catch finally? | finally ) ;
+    fn try_statement(&mut self) -> Result<StmtId, StaticError> {
+        self.next_ok(TokenType::LeftBrace, "Expect '{' after 'try'.".into())?;
+        let body = self.block_statement()?;
+
+        let catch = match self.next_if(TokenType::Catch) {
+            Some(_) => {
+                self.next_ok(TokenType::LeftParen, "Expect '(' after 'catch'.".into())?;
+                let name = self.next_ok(
+                    TokenType::Identifier,
+                    "Expect exception variable name.".into(),
+                )?;
+                self.next_ok(
+                    TokenType::RightParen,
+                    "Expect ')' after exception variable.".into(),
+                )?;
+                self.next_ok(TokenType::LeftBrace, "Expect '{' before catch body.".into())?;
+                let body = self.in_scope(&[&name], Self::block_statement)?;
+                Some((name, body))
+            }
+            None => None,
+        };
+
+        let finally = match self.next_if(TokenType::Finally) {
+            Some(_) => {
+                self.next_ok(TokenType::LeftBrace, "Expect '{' after 'finally'.".into())?;
+                Some(self.block_statement()?)
+            }
+            None => None,
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error("Expect 'catch' or 'finally' after try block.".into()));
+        }
+        Ok(self.arena.add_stmt(Try::new(body, catch, finally)))
+    }
+
+    // throwStmt → "throw" expression ";" ;
+    fn throw_statement(&mut self, keyword: Token) -> Result<StmtId, StaticError> {
+        let value = self.expression()?;
+        self.expect_semicolon()?;
+        Ok(self.arena.add_stmt(Throw::new(keyword, value)))
+    }
+
+    // assertStmt → "assert" expression ( "," expression )? ";" ;
+    fn assert_statement(&mut self, keyword: Token) -> Result<StmtId, StaticError> {
+        self.tokens.record();
+        let condition = self.expression()?;
+        let source = source_text(self.tokens.recorded());
+
+        let message = match self.next_if(TokenType::Comma) {
+            Some(_) => Some(self.expression()?),
+            None => None,
+        };
+        self.expect_semicolon()?;
+        Ok(self
+            .arena
+            .add_stmt(Assert::new(keyword, condition, source, message)))
+    }
+
+    // importStmt → "import" ( IDENTIFIER ( "," IDENTIFIER )* "from" )? STRING ";" ;
+    fn import_statement(&mut self) -> Result<StmtId, StaticError> {
+        let mut names = Vec::new();
+        if let Some(name) = self.next_if(TokenType::Identifier) {
+            names.push(name);
+            while self.next_if(TokenType::Comma).is_some() {
+                names.push(self.next_ok(TokenType::Identifier, "Expect variable name.".into())?);
+            }
+
+            // `from` is only a keyword here, so it stays usable as a variable name
+            let is_from = |t: &Token| t.typ == TokenType::Identifier && t.lexeme == "from";
+            if self.tokens.next_if(is_from).is_none() {
+                return Err(self.error("Expect 'from' after imported names.".into()));
+            }
+        }
+
+        let path = self.next_ok(TokenType::String, "Expect module path.".into())?;
+        self.expect_semicolon()?;
+        for name in &names {
+            self.declare(name, false)?;
+        }
+        Ok(self.arena.add_stmt(Import::new(path, names)))
+    }
+
+    // matchStmt → "match" "(" expression ")" "{" arm* ( "else" "=>" statement )? "}" ;
+    // arm       → pattern ( "," pattern )* "=>" statement ","? ;
+    fn match_statement(&mut self) -> Result<StmtId, StaticError> {
+        self.next_ok(TokenType::LeftParen, "Expect '(' after 'match'.".into())?;
+        let value = self.expression()?;
+        self.next_ok(
+            TokenType::RightParen,
+            "Expect ')' after match value.".into(),
+        )?;
+        self.next_ok(TokenType::LeftBrace, "Expect '{' before match arms.".into())?;
+
+        let mut arms = Vec::new();
+        let mut otherwise = None;
         while !self.peek_check(TokenType::RightBrace) && !self.is_at_end() {
-            let stmt = self.declaration()?;
-            statements.push(stmt);
+            if self.next_if(TokenType::Else).is_some() {
+                self.next_ok(TokenType::FatArrow, "Expect '=>' after 'else'.".into())?;
+                otherwise = Some(self.statement()?);
+                self.next_if(TokenType::Comma);
+                break;
+            }
+
+            let mut patterns = vec![self.pattern()?];
+            while self.next_if(TokenType::Comma).is_some() {
+                patterns.push(self.pattern()?);
+            }
+            self.next_ok(TokenType::FatArrow, "Expect '=>' after pattern.".into())?;
+            arms.push((patterns, self.statement()?));
+            self.next_if(TokenType::Comma);
         }
 
-        self.next_ok(TokenType::RightBrace, "Expect '}' after block.".into())?;
-        Ok(Block::new(statements).into())
+        self.next_ok(TokenType::RightBrace, "Expect '}' after match arms.".into())?;
+        Ok(self.arena.add_stmt(Match::new(value, arms, otherwise)))
+    }
+
+    // pattern → "-"? NUMBER | STRING | "true" | "false" | "nil" ;
+    fn pattern(&mut self) -> Result<Value, StaticError> {
+        let negative = self.next_if(TokenType::Minus).is_some();
+        let value = match self.tokens.peek().map(|token| token.typ) {
+            Some(TokenType::Number) => self.tokens.next().and_then(|token| token.literal),
+            Some(TokenType::String) if !negative => {
+                self.tokens.next().and_then(|token| token.literal)
+            }
+            Some(TokenType::True) if !negative => self.tokens.next().map(|_| true.into()),
+            Some(TokenType::False) if !negative => self.tokens.next().map(|_| false.into()),
+            Some(TokenType::Nil) if !negative => self.tokens.next().map(|_| Value::Nil),
+            _ => None,
+        };
+
+        match value {
+            Some(Value::Number(n)) if negative => Ok(Value::Number(-n)),
+            Some(Value::Integer(n)) if negative => Ok(Value::Integer(-n)),
+            Some(value) => Ok(value),
+            None => Err(self.error("Expect a literal pattern.".into())),
+        }
     }
 
     // printStmt → "print" expression ";" ;
-    fn print_statement(&mut self) -> Result<StmtNode, StaticError> {
+    fn print_statement(&mut self) -> Result<StmtId, StaticError> {
         let expr = self.expression()?;
         self.expect_semicolon()?;
 
-        Ok(Print::new(expr).into())
+        Ok(self.arena.add_stmt(Print::new(expr)))
     }
 
     // exprStmt → expression ";" ;
-    fn expression_statement(&mut self) -> Result<StmtNode, StaticError> {
+    fn expression_statement(&mut self) -> Result<StmtId, StaticError> {
         let expr = self.expression()?;
         self.expect_semicolon()?;
 
-        Ok(Expression::new(expr).into())
+        Ok(self.arena.add_stmt(Expression::new(expr)))
     }
 
     /// expression → assignment ;
-    fn expression(&mut self) -> Result<ExprNode, StaticError> {
+    fn expression(&mut self) -> Result<ExprId, StaticError> {
         self.assignment()
     }
 
     /// expression → equality ;
-    fn assignment(&mut self) -> Result<ExprNode, StaticError> {
-        let mut expr = self.equality()?;
+    fn assignment(&mut self) -> Result<ExprId, StaticError> {
+        let expr = self.coalesce()?;
 
         if let Some(equals) = self.next_if(TokenType::Equal) {
             let value = self.assignment()?;
 
-            let ExprNode::Variable(variable) = &expr else {
-                return Err(StaticError::error_at_token(
-                    &equals,
-                    "Invalid assignment target.".into(),
-                ));
+            // the assignment takes the place of its target in the arena
+            self.arena[expr] = match &self.arena[expr] {
+                ExprNode::Variable(variable) => {
+                    self.check_not_constant(&variable.name)?;
+                    Assign::new(variable.name.clone(), value).into()
+                }
+                ExprNode::Index(index) => SetIndex::new(index.clone(), value).into(),
+                ExprNode::Tuple(tuple) => {
+                    let names = self
+                        .arena
+                        .exprs(&tuple.elements)
+                        .map(|element| match element {
+                            ExprNode::Variable(variable) => {
+                                self.check_not_constant(&variable.name)?;
+                                Ok(variable.name.clone())
+                            }
+                            _ => Err(StaticError::error_at_token(
+                                &equals,
+                                "Invalid assignment target.".into(),
+                            )),
+                        })
+                        .collect::<Result<_, _>>()?;
+                    AssignUnpack::new(tuple.paren.clone(), names, value).into()
+                }
+                _ => {
+                    return Err(StaticError::error_at_token(
+                        &equals,
+                        "Invalid assignment target.".into(),
+                    ));
+                }
             };
+        }
 
-            let name = variable.name.clone();
-            expr = Assign::new(name, value).into();
+        Ok(expr)
+    }
+
+    /// coalesce → equality ( "??" equality )* ;
+    fn coalesce(&mut self) -> Result<ExprId, StaticError> {
+        let mut expr = self.equality()?;
+
+        while let Some(operator) = self.next_if(TokenType::QuestionQuestion) {
+            let right = self.equality()?;
+            expr = self.arena.add_expr(Coalesce::new(expr, operator, right));
         }
 
         Ok(expr)
     }
 
     /// equality → comparison ( ( "!=" | "==" ) comparison )* ;
-    fn equality(&mut self) -> Result<ExprNode, StaticError> {
+    fn equality(&mut self) -> Result<ExprId, StaticError> {
         let mut expr = self.comparison()?;
 
         while let Some(operator) = self.next_match(&[TokenType::BangEqual, TokenType::EqualEqual]) {
             let right = self.comparison()?;
-            expr = Binary::new(expr, operator, right).into();
+            expr = self.arena.add_expr(Binary::new(expr, operator, right));
         }
 
         Ok(expr)
     }
 
-    /// comparison → term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
-    fn comparison(&mut self) -> Result<ExprNode, StaticError> {
-        let mut expr = self.term()?;
+    /// comparison → range ( ( ">" | ">=" | "<" | "<=" ) range )* ;
+    fn comparison(&mut self) -> Result<ExprId, StaticError> {
+        let mut expr = self.range()?;
 
         while let Some(operator) = self.next_match(&[
             TokenType::Greater,
@@ -189,82 +630,397 @@ impl Parser {
             TokenType::Less,
             TokenType::LessEqual,
         ]) {
+            let right = self.range()?;
+            expr = self.arena.add_expr(Binary::new(expr, operator, right));
+        }
+
+        Ok(expr)
+    }
+
+    /// range → bitOr ( ( ".." | "..=" ) bitOr )? ;
+    fn range(&mut self) -> Result<ExprId, StaticError> {
+        let expr = self.bit_or()?;
+
+        match self.next_match(&[TokenType::DotDot, TokenType::DotDotEqual]) {
+            Some(operator) => {
+                let right = self.bit_or()?;
+                Ok(self.arena.add_expr(Binary::new(expr, operator, right)))
+            }
+            None => Ok(expr),
+        }
+    }
+
+    /// bitOr → bitXor ( "|" bitXor )* ;
+    fn bit_or(&mut self) -> Result<ExprId, StaticError> {
+        let mut expr = self.bit_xor()?;
+
+        while let Some(operator) = self.next_if(TokenType::Pipe) {
+            let right = self.bit_xor()?;
+            expr = self.arena.add_expr(Binary::new(expr, operator, right));
+        }
+
+        Ok(expr)
+    }
+
+    /// bitXor → bitAnd ( "^" bitAnd )* ;
+    fn bit_xor(&mut self) -> Result<ExprId, StaticError> {
+        let mut expr = self.bit_and()?;
+
+        while let Some(operator) = self.next_if(TokenType::Caret) {
+            let right = self.bit_and()?;
+            expr = self.arena.add_expr(Binary::new(expr, operator, right));
+        }
+
+        Ok(expr)
+    }
+
+    /// bitAnd → shift ( "&" shift )* ;
+    fn bit_and(&mut self) -> Result<ExprId, StaticError> {
+        let mut expr = self.shift()?;
+
+        while let Some(operator) = self.next_if(TokenType::Ampersand) {
+            let right = self.shift()?;
+            expr = self.arena.add_expr(Binary::new(expr, operator, right));
+        }
+
+        Ok(expr)
+    }
+
+    /// shift → term ( ( "<<" | ">>" ) term )* ;
+    fn shift(&mut self) -> Result<ExprId, StaticError> {
+        let mut expr = self.term()?;
+
+        while let Some(operator) =
+            self.next_match(&[TokenType::LessLess, TokenType::GreaterGreater])
+        {
             let right = self.term()?;
-            expr = Binary::new(expr, operator, right).into();
+            expr = self.arena.add_expr(Binary::new(expr, operator, right));
         }
 
         Ok(expr)
     }
 
     /// term → factor ( ( "-" | "+" ) factor )* ;
-    fn term(&mut self) -> Result<ExprNode, StaticError> {
+    fn term(&mut self) -> Result<ExprId, StaticError> {
         let mut expr = self.factor()?;
 
         while let Some(operator) = self.next_match(&[TokenType::Minus, TokenType::Plus]) {
             let right = self.factor()?;
-            expr = Binary::new(expr, operator, right).into();
+            expr = self.arena.add_expr(Binary::new(expr, operator, right));
         }
 
         Ok(expr)
     }
 
     /// factor → unary ( ( "/" | "*" ) unary )* ;
-    fn factor(&mut self) -> Result<ExprNode, StaticError> {
+    fn factor(&mut self) -> Result<ExprId, StaticError> {
         let mut expr = self.unary()?;
 
         while let Some(operator) = self.next_match(&[TokenType::Slash, TokenType::Star]) {
             let right = self.unary()?;
-            expr = Binary::new(expr, operator, right).into();
+            expr = self.arena.add_expr(Binary::new(expr, operator, right));
         }
 
         Ok(expr)
     }
 
-    /// unary → ( "!" | "-" ) unary | primary ;
-    fn unary(&mut self) -> Result<ExprNode, StaticError> {
+    /// unary → ( "!" | "-" ) unary | call ;
+    fn unary(&mut self) -> Result<ExprId, StaticError> {
         if let Some(operator) = self.next_match(&[TokenType::Bang, TokenType::Minus]) {
             let right = self.unary()?;
-            return Ok(Unary::new(operator, right).into());
+            return Ok(self.arena.add_expr(Unary::new(operator, right)));
         }
 
-        self.primary()
+        self.call()
     }
 
-    /// primary → NUMBER | STRING | "true" | "false" | "nil"| "(" expression ")" ;
-    fn primary(&mut self) -> Result<ExprNode, StaticError> {
-        if self.next_if(TokenType::True).is_some() {
+    /// call → primary ( "(" arguments? ")" | "[" expression "]" )* ;
+    fn call(&mut self) -> Result<ExprId, StaticError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.next_if(TokenType::LeftParen).is_some() {
+                expr = self.finish_call(expr)?;
+            } else if self.next_if(TokenType::LeftBracket).is_some() {
+                let index = self.expression()?;
+                let bracket =
+                    self.next_ok(TokenType::RightBracket, "Expect ']' after index.".into())?;
+                expr = self.arena.add_expr(Index::new(expr, bracket, index));
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    /// arguments → element ( "," element )* ;
+    fn finish_call(&mut self, callee: ExprId) -> Result<ExprId, StaticError> {
+        let mut count = 0;
+        let (arguments, paren) = self.delimited(
+            TokenType::RightParen,
+            "Expect ')' after arguments.",
+            |parser| {
+                if count >= MAX_ARGUMENTS {
+                    return Err(parser.error("Can't have more than 255 arguments.".into()));
+                }
+                count += 1;
+                parser.element()
+            },
+        )?;
+        Ok(self.arena.add_expr(Call::new(callee, paren, arguments)))
+    }
+
+    /// primary → NUMBER | STRING | "true" | "false" | "nil"| "(" expression ")"
+    ///         | IDENTIFIER | interpolation | list | map ;
+    fn primary(&mut self) -> Result<ExprId, StaticError> {
+        if let Some(token) = self.next_if(TokenType::True) {
             let val = Value::from(true);
-            return Ok(Literal::from(val).into());
+            return Ok(self.arena.add_expr(Literal::new(val, token)));
         }
 
-        if self.next_if(TokenType::False).is_some() {
+        if let Some(token) = self.next_if(TokenType::False) {
             let val = Value::from(false);
-            return Ok(Literal::from(val).into());
+            return Ok(self.arena.add_expr(Literal::new(val, token)));
         }
 
-        if self.next_if(TokenType::Nil).is_some() {
+        if let Some(token) = self.next_if(TokenType::Nil) {
             let val = Value::Nil;
-            return Ok(Literal::from(val).into());
+            return Ok(self.arena.add_expr(Literal::new(val, token)));
+        }
+
+        if let Some(head) = self.next_if(TokenType::Interpolation) {
+            return self.interpolation(head);
         }
 
         if let Some(token) = self.next_match(&[TokenType::Number, TokenType::String]) {
-            let value = token.literal.expect("literal value for token");
-            return Ok(Literal::from(value).into());
+            let value = token.literal.clone().expect("literal value for token");
+            return Ok(self.arena.add_expr(Literal::new(value, token)));
         }
 
-        if self.next_if(TokenType::LeftParen).is_some() {
+        if let Some(paren) = self.next_if(TokenType::LeftParen) {
             let expr = self.expression()?;
+            if self.dialect.allows(Extension::Destructuring)
+                && self.next_if(TokenType::Comma).is_some()
+            {
+                let (mut elements, _) = self.delimited(
+                    TokenType::RightParen,
+                    "Expect ')' after tuple elements.",
+                    Self::expression,
+                )?;
+                elements.insert(0, expr);
+                return Ok(self.arena.add_expr(Tuple::new(paren, elements)));
+            }
             self.next_ok(TokenType::RightParen, "Expect ')' after expression".into())?;
-            return Ok(Grouping::new(expr).into());
+            return Ok(self.arena.add_expr(Grouping::new(expr)));
         }
 
         if let Some(name) = self.next_if(TokenType::Identifier) {
-            return Ok(Variable::new(name).into());
+            return Ok(self.arena.add_expr(Variable::new(name)));
+        }
+
+        // with only maps enabled brackets index maps but make no lists
+        if self.dialect.allows(Extension::Lists) && self.next_if(TokenType::LeftBracket).is_some() {
+            return self.list();
+        }
+
+        // in expression position a brace opens a map literal rather than a block
+        if self.dialect.allows(Extension::Maps)
+            && let Some(brace) = self.next_if(TokenType::LeftBrace)
+        {
+            return self.map(brace);
         }
 
         Err(self.error("Expect expression".into()))
     }
 
+    /// list → "[" ( element ( "," element )* )? "]" ;
+    fn list(&mut self) -> Result<ExprId, StaticError> {
+        let (elements, _) = self.delimited(
+            TokenType::RightBracket,
+            "Expect ']' after list elements.",
+            Self::element,
+        )?;
+        Ok(self.arena.add_expr(List::new(elements)))
+    }
+
+    /// element → "..."? expression ;
+    fn element(&mut self) -> Result<ExprId, StaticError> {
+        if let Some(ellipsis) = self.next_if(TokenType::DotDotDot) {
+            let expr = self.expression()?;
+            return Ok(self.arena.add_expr(Spread::new(ellipsis, expr)));
+        }
+        self.expression()
+    }
+
+    /// map → "{" ( entry ( "," entry )* )? "}" ;
+    /// entry → expression ":" expression ;
+    fn map(&mut self, brace: Token) -> Result<ExprId, StaticError> {
+        let (entries, _) = self.delimited(
+            TokenType::RightBrace,
+            "Expect '}' after map entries.",
+            |parser| {
+                let key = parser.expression()?;
+                parser.next_ok(TokenType::Colon, "Expect ':' after map key.".into())?;
+                let value = parser.expression()?;
+                Ok((key, value))
+            },
+        )?;
+        Ok(self.arena.add_expr(Map::new(brace, entries)))
+    }
+
+    /// Returns the line of the next token.
+    fn peek_line(&mut self) -> u32 {
+        let last = self.tokens.last_line();
+        self.tokens.peek().map_or(last, |token| token.line)
+    }
+
+    /// Records that `stmt` spans the lines from `first` to the line of the
+    /// last token consumed, and returns it.
+    fn spanned(&mut self, stmt: StmtId, first: u32) -> StmtId {
+        let last = self.tokens.last_line();
+        self.arena.set_lines(stmt, Lines { first, last });
+        stmt
+    }
+
+    /// Records that `name` is declared in the innermost scope.
+    ///
+    /// Returns an error at `name` if it is already a constant of that scope.
+    fn declare(&mut self, name: &Token, constant: bool) -> Result<(), StaticError> {
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("the global scope is never popped");
+        if scope.get(&name.lexeme) == Some(&true) {
+            return Err(StaticError::error_at_token(
+                name,
+                format!("Cannot redeclare constant '{}'.", name.lexeme),
+            ));
+        }
+        scope.insert(name.symbol(), constant);
+        Ok(())
+    }
+
+    /// Runs `parse` in a new scope in which `names` are declared.
+    fn in_scope<T>(&mut self, names: &[&Token], parse: impl FnOnce(&mut Self) -> T) -> T {
+        let scope = names.iter().map(|name| (name.symbol(), false));
+        self.scopes.push(scope.collect());
+        let result = parse(self);
+        self.scopes.pop();
+        result
+    }
+
+    /// Returns an error at `name` if the innermost declaration of it that the
+    /// parser has seen is a constant.
+    fn check_not_constant(&self, name: &Token) -> Result<(), StaticError> {
+        let constant = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme));
+        match constant {
+            Some(true) => Err(StaticError::error_at_token(
+                name,
+                format!("Cannot assign to constant '{}'.", name.lexeme),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Parses comma-separated items up to and including the `closing` token,
+    /// which is returned along with the items.
+    ///
+    /// With the `TrailingCommas` extension, a comma may follow the last item.
+    ///
+    /// Returns an error with `message` if the `closing` token is missing.
+    fn delimited<T>(
+        &mut self,
+        closing: TokenType,
+        message: &str,
+        mut item: impl FnMut(&mut Self) -> Result<T, StaticError>,
+    ) -> Result<(Vec<T>, Token), StaticError> {
+        let trailing = self.dialect.allows(Extension::TrailingCommas);
+        let mut items = Vec::new();
+
+        if !self.peek_check(closing) {
+            loop {
+                items.push(item(self)?);
+
+                if self.next_if(TokenType::Comma).is_none()
+                    || (trailing && self.peek_check(closing))
+                {
+                    break;
+                }
+            }
+        }
+
+        let closing = self.next_ok(closing, message.into())?;
+        Ok((items, closing))
+    }
+
+    /// Desugars an interpolated string into a chain of `+` concatenations of
+    /// its non-empty pieces and of the native `str` called on each
+    /// expression, so that `+` only ever joins strings. The native is called
+    /// directly, so a variable named `str` does not change what interpolation
+    /// does.
+    ///
+    /// interpolation → INTERPOLATION expression ( INTERPOLATION expression )* STRING ;
+    fn interpolation(&mut self, head: Token) -> Result<ExprId, StaticError> {
+        fn concat(arena: &mut AstArena, left: ExprId, part: &Token, right: ExprId) -> ExprId {
+            let plus = Token::new(TokenType::Plus, "+".into(), None, part.line)
+                .with_column(part.column)
+                .with_span(part.span);
+            arena.add_expr(Binary::new(left, plus, right))
+        }
+
+        fn stringify(arena: &mut AstArena, part: &Token, expr: ExprId) -> ExprId {
+            let str = stdlib::native("str").expect("str is a native function");
+            let callee = arena.add_expr(Literal::from(Value::NativeFunction(str)));
+            let paren = Token::new(TokenType::RightParen, ")".into(), None, part.line)
+                .with_column(part.column)
+                .with_span(part.span);
+            arena.add_expr(Call::new(callee, paren, vec![expr]))
+        }
+
+        /// Appends the text of `token` to `expr`, if it has any.
+        fn piece(arena: &mut AstArena, expr: Option<ExprId>, token: &Token) -> Option<ExprId> {
+            let value = token.literal.clone().expect("literal value for token");
+            if matches!(&value, Value::String(s) if s.is_empty()) {
+                return expr;
+            }
+            let piece = arena.add_expr(Literal::from(value));
+            Some(match expr {
+                Some(expr) => concat(arena, expr, token, piece),
+                None => piece,
+            })
+        }
+
+        let mut expr = piece(&mut self.arena, None, &head);
+        let mut part = head;
+        loop {
+            let inner = self.expression()?;
+            let inner = stringify(&mut self.arena, &part, inner);
+            // every expression is stringified, so the chain stays a string
+            // even when there is no text before it
+            let joined = match expr {
+                Some(expr) => concat(&mut self.arena, expr, &part, inner),
+                None => inner,
+            };
+
+            if let Some(next) = self.next_if(TokenType::Interpolation) {
+                expr = piece(&mut self.arena, Some(joined), &next);
+                part = next;
+                continue;
+            }
+
+            let tail = self.next_ok(
+                TokenType::String,
+                "Expect '}' after interpolated expression.".into(),
+            )?;
+            return Ok(piece(&mut self.arena, Some(joined), &tail).expect("a non-empty chain"));
+        }
+    }
+
     /// Consumes the next token if it matches any of the given types.
     ///
     /// Returns
@@ -326,27 +1082,44 @@ impl Parser {
     ///
     /// Returns `true` if the current token is an EOF token or
     /// there are no more tokens in the token list.
-    fn is_at_end(&mut self) -> bool {
-        self.tokens.peek().is_none_or(|t| t.typ == TokenType::Eof)
-    }
-
     fn error(&mut self, message: String) -> StaticError {
         let token = self.tokens.peek().expect("expected a token");
         StaticError::error_at_token(token, message)
     }
 }
 
+/// Joins the lexemes of `tokens`, separated by a space where the source had
+/// whitespace between them.
+fn source_text(tokens: impl IntoIterator<Item = Token>) -> String {
+    let mut text = String::new();
+    let mut end = None;
+    for token in tokens {
+        if end.is_some_and(|end| Some(end) != token.span.map(|span| span.start)) {
+            text.push(' ');
+        }
+        end = token.span.map(|span| span.end);
+        text.push_str(&token.lexeme);
+    }
+    text
+}
+
 #[cfg(test)]
 mod tests {
     use rstest::rstest;
 
     use super::*;
+    use crate::dialect::{Dialect, Extension};
     use crate::parser::printer::AstPrinter;
     use crate::parser::stmt::StmtNode;
     use crate::scanner::{ScanItem, Scanner};
 
     fn scan(input: &str) -> Vec<Token> {
+        scan_with(input, Dialect::default())
+    }
+
+    fn scan_with(input: &str, dialect: Dialect) -> Vec<Token> {
         Scanner::new(input)
+            .with_dialect(dialect)
             .scan_tokens()
             .filter_map(|r| match r {
                 Ok(ScanItem::Token(tkn)) => Some(tkn),
@@ -404,22 +1177,30 @@ mod tests {
         let mut parser = Parser::from(tokens);
         let expr = parser.expression().unwrap();
 
-        let expr_str = AstPrinter.print(&expr);
+        let expr_str = AstPrinter::new().print(&parser.arena, expr);
         assert_eq!(expected_output, expr_str)
     }
 
-    fn parse_program(input: &str) -> Result<Vec<StmtNode>, StaticError> {
+    fn parse_program(input: &str) -> Result<Ast, StaticError> {
         let tokens = scan(input);
         let mut parser = Parser::from(tokens);
         parser.parse()
     }
 
-    fn render_stmt(stmt: &StmtNode) -> String {
+    fn render_stmt(ast: &AstArena, stmt: &StmtNode) -> String {
         match stmt {
-            StmtNode::Print(print) => format!("print {}", AstPrinter.print(&*print.expr)),
-            StmtNode::Expression(expression) => AstPrinter.print(&*expression.expr),
+            StmtNode::Print(print) => format!("print {}", AstPrinter::new().print(ast, print.expr)),
+            StmtNode::Expression(expression) => AstPrinter::new().print(ast, expression.expr),
             StmtNode::Var(_var) => todo!(),
             StmtNode::Block(_block) => todo!(),
+            StmtNode::ForIn(_for_in) => todo!(),
+            StmtNode::Try(_try) => todo!(),
+            StmtNode::Throw(_throw) => todo!(),
+            StmtNode::Assert(_assert) => todo!(),
+            StmtNode::Import(_import) => todo!(),
+            StmtNode::Match(_match) => todo!(),
+            StmtNode::DoWhile(_do_while) => todo!(),
+            StmtNode::VarUnpack(_unpack) => todo!(),
         }
     }
 
@@ -432,7 +1213,10 @@ mod tests {
         "#;
 
         let statements = parse_program(program).expect("Expected a valid program");
-        let actual = statements.iter().map(render_stmt).collect::<Vec<_>>();
+        let actual = statements
+            .iter()
+            .map(|stmt| render_stmt(&statements.arena, stmt))
+            .collect::<Vec<_>>();
         let expected = vec![
             "print baz",
             "print false",
@@ -444,6 +1228,645 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[rstest]
+    #[case(r#""true" == true"#, r#"(== (str "true") (bool true))"#)]
+    #[case(r#"-1 + "a b""#, r#"(+ (- (num 1.0)) (str "a b"))"#)]
+    #[case("(nil)", "(group nil)")]
+    fn test_parser_typed_literals(#[case] input: &str, #[case] expected_output: &str) {
+        let mut parser = Parser::from(scan(input));
+        let expr = parser.expression().unwrap();
+        let printer = AstPrinter::new().with_typed_literals(true);
+
+        assert_eq!(expected_output, printer.print(&parser.arena, expr))
+    }
+
+    #[rstest]
+    #[case(r#""a ${true} b""#, "(+ (+ a  (call str true))  b)")]
+    #[case(r#""a${x}b""#, "(+ (+ a (call str x)) b)")]
+    #[case(r#""${1}${2}""#, "(+ (call str 1.0) (call str 2.0))")]
+    #[case(r#""n: ${"${1 + 2}"}""#, "(+ n:  (call str (call str (+ 1.0 2.0))))")]
+    fn test_parser_interpolation(#[case] input: &str, #[case] expected_output: &str) {
+        let dialect = Dialect::new().with(Extension::Interpolation);
+        let tokens = scan_with(input, dialect);
+        let mut parser = Parser::from(tokens);
+        let expr = parser.expression().unwrap();
+
+        assert_eq!(
+            expected_output,
+            AstPrinter::new().print(&parser.arena, expr)
+        )
+    }
+
+    #[test]
+    fn test_parser_interpolation_requires_closing_brace() {
+        let dialect = Dialect::new().with(Extension::Interpolation);
+        let tokens = scan_with(r#""a ${x y} b""#, dialect);
+        let mut parser = Parser::from(tokens);
+        let err = parser.expression().expect_err("expected parse error");
+
+        assert_eq!(
+            "[line 1] Error at 'y': Expect '}' after interpolated expression.",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("clock()", "(call clock)")]
+    #[case("max(1, 2)(3)", "(call (call max 1.0 2.0) 3.0)")]
+    #[case("-abs(x) * 2", "(* (- (call abs x)) 2.0)")]
+    #[case("f(g(1), a = 2)", "(call f (call g 1.0) (= a 2.0))")]
+    fn test_parser_call(#[case] input: &str, #[case] expected_output: &str) {
+        let tokens = scan(input);
+        let mut parser = Parser::from(tokens);
+        let expr = parser.expression().unwrap();
+
+        assert_eq!(
+            expected_output,
+            AstPrinter::new().print(&parser.arena, expr)
+        )
+    }
+
+    #[rstest]
+    #[case("f(1, 2", "[line 1] Error at end: Expect ')' after arguments.")]
+    #[case("f(1,)", "[line 1] Error at ')': Expect expression")]
+    fn test_parser_call_errors(#[case] input: &str, #[case] expected_error: &str) {
+        let mut parser = Parser::from(scan(input));
+        let err = parser.expression().expect_err("expected parse error");
+
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[test]
+    fn test_parser_call_argument_limit() {
+        let arguments = vec!["1"; MAX_ARGUMENTS + 1].join(", ");
+        let mut parser = Parser::from(scan(&format!("f({arguments})")));
+        let err = parser.expression().expect_err("expected parse error");
+
+        assert_eq!(
+            "[line 1] Error at '1': Can't have more than 255 arguments.",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("[]", "(list)")]
+    #[case("[1, \"a\", [nil]]", "(list 1.0 a (list nil))")]
+    #[case("xs[0][1]", "(index (index xs 0.0) 1.0)")]
+    #[case("[1, 2][f(0)]", "(index (list 1.0 2.0) (call f 0.0))")]
+    #[case(
+        "xs[i + 1] = ys[i] = 2",
+        "(set-index xs (+ i 1.0) (set-index ys i 2.0))"
+    )]
+    fn test_parser_lists(#[case] input: &str, #[case] expected_output: &str) {
+        let dialect = Dialect::new().with(Extension::Lists);
+        let mut parser = Parser::from(scan_with(input, dialect)).with_dialect(dialect);
+        let expr = parser.expression().unwrap();
+
+        assert_eq!(
+            expected_output,
+            AstPrinter::new().print(&parser.arena, expr)
+        )
+    }
+
+    #[rstest]
+    #[case("[1, 2", "[line 1] Error at end: Expect ']' after list elements.")]
+    #[case("xs[0", "[line 1] Error at end: Expect ']' after index.")]
+    #[case("[1][0] + 1 = 2", "[line 1] Error at '=': Invalid assignment target.")]
+    fn test_parser_list_errors(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::Lists);
+        let mut parser = Parser::from(scan_with(input, dialect)).with_dialect(dialect);
+        let err = parser.expression().expect_err("expected parse error");
+
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case("{}", "(map)")]
+    #[case("{\"a\": 1, 2: [x]}", "(map a 1.0 2.0 (list x))")]
+    #[case("{true: {}}[true]", "(index (map true (map)) true)")]
+    #[case("m[\"k\"] = {\"n\": nil}", "(set-index m k (map n nil))")]
+    fn test_parser_maps(#[case] input: &str, #[case] expected_output: &str) {
+        let dialect = Dialect::new().with(Extension::Lists).with(Extension::Maps);
+        let mut parser = Parser::from(scan_with(input, dialect)).with_dialect(dialect);
+        let expr = parser.expression().unwrap();
+
+        assert_eq!(
+            expected_output,
+            AstPrinter::new().print(&parser.arena, expr)
+        )
+    }
+
+    #[rstest]
+    #[case("{\"a\" 1}", "[line 1] Error at '1': Expect ':' after map key.")]
+    #[case("{\"a\": 1", "[line 1] Error at end: Expect '}' after map entries.")]
+    fn test_parser_map_errors(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::Maps);
+        let mut parser = Parser::from(scan_with(input, dialect)).with_dialect(dialect);
+        let err = parser.expression().expect_err("expected parse error");
+
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case("m[\"k\"]", Ok("(index m k)"))]
+    #[case("{\"a\": 1}[\"a\"] = 2", Ok("(set-index (map a 1.0) a 2.0)"))]
+    #[case("[1]", Err("[line 1] Error at '[': Expect expression"))]
+    fn test_parser_maps_without_lists(#[case] input: &str, #[case] expected: Result<&str, &str>) {
+        let dialect = Dialect::new().with(Extension::Maps);
+        let mut parser = Parser::from(scan_with(input, dialect)).with_dialect(dialect);
+        let output = parser
+            .expression()
+            .map(|expr| AstPrinter::new().print(&parser.arena, expr))
+            .map_err(|err| err.to_string());
+
+        assert_eq!(expected.map(String::from).map_err(String::from), output);
+    }
+
+    #[test]
+    fn test_parser_map_requires_extension() {
+        let mut parser = Parser::from(scan("{}"));
+        let err = parser.expression().expect_err("expected parse error");
+
+        assert_eq!("[line 1] Error at '{': Expect expression", err.to_string());
+    }
+
+    #[test]
+    fn test_parser_brace_statement_is_block() {
+        let dialect = Dialect::new().with(Extension::Maps);
+        let tokens = scan_with("print {\"a\": 1}; { print 1; }", dialect);
+        let program = Parser::from(tokens).with_dialect(dialect).parse().unwrap();
+
+        assert!(matches!(
+            program.iter().collect::<Vec<_>>()[..],
+            [StmtNode::Print(_), StmtNode::Block(_)]
+        ));
+    }
+
+    #[rstest]
+    #[case("f(1, 2,)", "(call f 1.0 2.0)")]
+    #[case("[1,]", "(list 1.0)")]
+    #[case("[\n  [],\n  [2,],\n]", "(list (list) (list 2.0))")]
+    #[case("{\"a\": 1,}", "(map a 1.0)")]
+    fn test_parser_trailing_commas(#[case] input: &str, #[case] expected_output: &str) {
+        let dialect = Dialect::new()
+            .with(Extension::Lists)
+            .with(Extension::Maps)
+            .with(Extension::TrailingCommas);
+        let mut parser = Parser::from(scan_with(input, dialect)).with_dialect(dialect);
+        let expr = parser.expression().unwrap();
+
+        assert_eq!(
+            expected_output,
+            AstPrinter::new().print(&parser.arena, expr)
+        )
+    }
+
+    #[rstest]
+    #[case("f(,)", "[line 1] Error at ',': Expect expression", true)]
+    #[case("[1,,]", "[line 1] Error at ',': Expect expression", true)]
+    #[case("f(1,)", "[line 1] Error at ')': Expect expression", false)]
+    #[case("[1,]", "[line 1] Error at ']': Expect expression", false)]
+    #[case("{\"a\": 1,}", "[line 1] Error at '}': Expect expression", false)]
+    fn test_parser_trailing_comma_errors(
+        #[case] input: &str,
+        #[case] expected_error: &str,
+        #[case] enabled: bool,
+    ) {
+        let mut dialect = Dialect::new().with(Extension::Lists).with(Extension::Maps);
+        if enabled {
+            dialect.enable(Extension::TrailingCommas);
+        }
+        let mut parser = Parser::from(scan_with(input, dialect)).with_dialect(dialect);
+        let err = parser.expression().expect_err("expected parse error");
+
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case("for (x in xs) print x;")]
+    #[case("for (var x in [1, 2]) { print x; }")]
+    #[case("for (in in in) print in;")]
+    fn test_parse_for_in(#[case] input: &str) {
+        let dialect = Dialect::new().with(Extension::ForIn).with(Extension::Lists);
+        let program = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect("expected a valid program");
+
+        assert!(matches!(
+            program.iter().collect::<Vec<_>>()[..],
+            [StmtNode::ForIn(_)]
+        ));
+    }
+
+    #[rstest]
+    #[case(
+        "for x in xs print x;",
+        "[line 1] Error at 'x': Expect '(' after 'for'."
+    )]
+    #[case(
+        "for (x of xs) print x;",
+        "[line 1] Error at 'of': Expect 'in' after loop variable."
+    )]
+    #[case(
+        "for (x in xs print x;",
+        "[line 1] Error at 'print': Expect ')' after for clauses."
+    )]
+    fn test_parse_for_in_errors(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::ForIn);
+        let err = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect_err("expected parse error");
+
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case("try { } catch (e) { }")]
+    #[case("try { } finally { }")]
+    #[case("try { throw 1; } catch (e) { throw e; } finally { print 2; }")]
+    fn test_parse_try(#[case] input: &str) {
+        let dialect = Dialect::new().with(Extension::Exceptions);
+        let program = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect("expected a valid program");
+
+        assert!(matches!(
+            program.iter().collect::<Vec<_>>()[..],
+            [StmtNode::Try(_)]
+        ));
+    }
+
+    #[rstest]
+    #[case("try print 1;", "[line 1] Error at 'print': Expect '{' after 'try'.")]
+    #[case(
+        "try { } print 1;",
+        "[line 1] Error at 'print': Expect 'catch' or 'finally' after try block."
+    )]
+    #[case(
+        "try { } catch e { }",
+        "[line 1] Error at 'e': Expect '(' after 'catch'."
+    )]
+    #[case(
+        "try { } catch (1) { }",
+        "[line 1] Error at '1': Expect exception variable name."
+    )]
+    #[case(
+        "try { } catch (e) print e;",
+        "[line 1] Error at 'print': Expect '{' before catch body."
+    )]
+    #[case(
+        "try { } finally print 1;",
+        "[line 1] Error at 'print': Expect '{' after 'finally'."
+    )]
+    #[case("throw;", "[line 1] Error at ';': Expect expression")]
+    #[case("throw 1", "[line 1] Error at end: Expect ';' after value.")]
+    fn test_parse_try_errors(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::Exceptions);
+        let err = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect_err("expected parse error");
+
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case("assert a == 1;", "a == 1", false)]
+    #[case("assert f(x)  >=-1, \"why\";", "f(x) >=-1", true)]
+    #[case("assert !(a\n  == b);", "!(a == b)", false)]
+    fn test_parse_assert(#[case] input: &str, #[case] source: &str, #[case] has_message: bool) {
+        let dialect = Dialect::new().with(Extension::Assertions);
+        let program = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect("expected a valid program");
+
+        let [StmtNode::Assert(assert)] = program.iter().collect::<Vec<_>>()[..] else {
+            panic!("expected an assert statement");
+        };
+        assert_eq!(source, assert.source);
+        assert_eq!(has_message, assert.message.is_some());
+    }
+
+    #[rstest]
+    #[case("assert;", "[line 1] Error at ';': Expect expression")]
+    #[case("assert true, ;", "[line 1] Error at ';': Expect expression")]
+    #[case(
+        "assert true \"a\";",
+        "[line 1] Error at '\"a\"': Expect ';' after value."
+    )]
+    fn test_parse_assert_errors(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::Assertions);
+        let err = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect_err("expected parse error");
+
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case(r#"import "lib.lox";"#, 0)]
+    #[case(r#"import a, from from "lib.lox";"#, 2)]
+    fn test_parse_import(#[case] input: &str, #[case] names: usize) {
+        let dialect = Dialect::new().with(Extension::Modules);
+        let program = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect("expected a valid program");
+
+        let [StmtNode::Import(import)] = program.iter().collect::<Vec<_>>()[..] else {
+            panic!("expected an import statement");
+        };
+        assert_eq!(names, import.names.len());
+    }
+
+    #[rstest]
+    #[case(
+        "import a;",
+        "[line 1] Error at ';': Expect 'from' after imported names."
+    )]
+    #[case(
+        r#"import a, "lib.lox";"#,
+        "[line 1] Error at '\"lib.lox\"': Expect variable name."
+    )]
+    #[case(
+        "import lib;",
+        "[line 1] Error at ';': Expect 'from' after imported names."
+    )]
+    #[case("import 1;", "[line 1] Error at '1': Expect module path.")]
+    fn test_parse_import_errors(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::Modules);
+        let err = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect_err("expected parse error");
+
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case("match (x) { }", 0, false)]
+    #[case("match (x) { 1, -2.5 => print 1; \"a\" => { } }", 2, false)]
+    #[case("match (x) { true, nil => print 1;, else => print 2;, }", 1, true)]
+    fn test_parse_match(#[case] input: &str, #[case] arms: usize, #[case] has_else: bool) {
+        let dialect = Dialect::new().with(Extension::Match);
+        let program = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect("expected a valid program");
+
+        let [StmtNode::Match(match_stmt)] = program.iter().collect::<Vec<_>>()[..] else {
+            panic!("expected a match statement");
+        };
+        assert_eq!(arms, match_stmt.arms.len());
+        assert_eq!(has_else, match_stmt.otherwise.is_some());
+    }
+
+    #[rstest]
+    #[case("match x { }", "[line 1] Error at 'x': Expect '(' after 'match'.")]
+    #[case(
+        "match (x) 1 => print 1;",
+        "[line 1] Error at '1': Expect '{' before match arms."
+    )]
+    #[case(
+        "match (x) { y => print 1; }",
+        "[line 1] Error at 'y': Expect a literal pattern."
+    )]
+    #[case(
+        "match (x) { -\"a\" => print 1; }",
+        "[line 1] Error at '\"a\"': Expect a literal pattern."
+    )]
+    #[case(
+        "match (x) { 1 print 1; }",
+        "[line 1] Error at 'print': Expect '=>' after pattern."
+    )]
+    #[case(
+        "match (x) { else => print 1; 2 => print 2; }",
+        "[line 1] Error at '2': Expect '}' after match arms."
+    )]
+    fn test_parse_match_errors(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::Match);
+        let err = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect_err("expected parse error");
+
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case(
+        "do print 1 while (x);",
+        "[line 1] Error at 'while': Expect ';' after value."
+    )]
+    #[case(
+        "do print 1; (x);",
+        "[line 1] Error at '(': Expect 'while' after do body."
+    )]
+    #[case(
+        "do print 1; while x;",
+        "[line 1] Error at 'x': Expect '(' after 'while'."
+    )]
+    #[case(
+        "do print 1; while (x)",
+        "[line 1] Error at end: Expect ';' after do-while loop."
+    )]
+    fn test_parse_do_while_errors(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::DoWhile);
+        let err = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect_err("expected parse error");
+
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[test]
+    fn test_parse_for_in_requires_extension() {
+        let err = parse_program("for (x in xs) print x;").expect_err("expected parse error");
+        assert_eq!(
+            "[line 1] Error at 'for': Expect expression",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("f(...xs)", "(call f (... xs))")]
+    #[case("f(1, ...g(), 2)", "(call f 1.0 (... (call g)) 2.0)")]
+    #[case("[0, ...[1, 2], ...xs]", "(list 0.0 (... (list 1.0 2.0)) (... xs))")]
+    fn test_parser_spread(#[case] input: &str, #[case] expected_output: &str) {
+        let dialect = Dialect::new()
+            .with(Extension::Lists)
+            .with(Extension::Spread);
+        let mut parser = Parser::from(scan_with(input, dialect)).with_dialect(dialect);
+        let expr = parser.expression().unwrap();
+
+        assert_eq!(
+            expected_output,
+            AstPrinter::new().print(&parser.arena, expr)
+        )
+    }
+
+    #[rstest]
+    #[case("a ?? b", "(?? a b)")]
+    #[case("a ?? b ?? 1", "(?? (?? a b) 1.0)")]
+    #[case("a ?? b == c", "(?? a (== b c))")]
+    #[case("x = a ?? b", "(= x (?? a b))")]
+    fn test_parser_coalesce(#[case] input: &str, #[case] expected_output: &str) {
+        let dialect = Dialect::new().with(Extension::NilCoalescing);
+        let mut parser = Parser::from(scan_with(input, dialect)).with_dialect(dialect);
+        let expr = parser.expression().unwrap();
+
+        assert_eq!(
+            expected_output,
+            AstPrinter::new().print(&parser.arena, expr)
+        )
+    }
+
+    #[rstest]
+    #[case("(a, b)", "(tuple a b)")]
+    #[case("(a, b + 1, (c))", "(tuple a (+ b 1.0) (group c))")]
+    #[case("(a, b) = (b, a)", "(= (a b) (tuple b a))")]
+    #[case("(a)", "(group a)")]
+    fn test_parser_tuple(#[case] input: &str, #[case] expected_output: &str) {
+        let dialect = Dialect::new().with(Extension::Destructuring);
+        let mut parser = Parser::from(scan_with(input, dialect)).with_dialect(dialect);
+        let expr = parser.expression().unwrap();
+
+        assert_eq!(
+            expected_output,
+            AstPrinter::new().print(&parser.arena, expr)
+        )
+    }
+
+    #[rstest]
+    #[case(
+        "(a, 1) = (1, 2);",
+        "[line 1] Error at '=': Invalid assignment target."
+    )]
+    #[case("var (a, 1) = xs;", "[line 1] Error at '1': Expect variable name.")]
+    #[case(
+        "var (a, b);",
+        "[line 1] Error at ';': Expect '=' after variable names."
+    )]
+    #[case(
+        "var (a b) = xs;",
+        "[line 1] Error at 'b': Expect ')' after variable names."
+    )]
+    fn test_parse_destructuring_errors(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = Dialect::new().with(Extension::Destructuring);
+        let err = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect_err("expected parse error");
+
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[rstest]
+    #[case("const x = 1; { var x = 2; x = 3; }")]
+    #[case("const x = 1; for (x in 0..2) x = 1;")]
+    #[case("const x = 1; try { } catch (x) { x = 1; }")]
+    #[case("{ const x = 1; } x = 2;")]
+    fn test_parse_constants(#[case] input: &str) {
+        let dialect = [
+            Extension::Constants,
+            Extension::ForIn,
+            Extension::Ranges,
+            Extension::Exceptions,
+        ]
+        .into_iter()
+        .collect();
+        let result = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse();
+
+        assert!(result.is_ok(), "{input}");
+    }
+
+    #[rstest]
+    #[case(
+        "const x = 1; x = 2;",
+        "[line 1] Error at 'x': Cannot assign to constant 'x'."
+    )]
+    #[case(
+        "const x = 1; { print x = 2; }",
+        "[line 1] Error at 'x': Cannot assign to constant 'x'."
+    )]
+    #[case(
+        "const x = 1; var y; (y, x) = (1, 2);",
+        "[line 1] Error at 'x': Cannot assign to constant 'x'."
+    )]
+    #[case(
+        "const x = 1; var x = 2; print x;",
+        "[line 1] Error at 'x': Cannot redeclare constant 'x'."
+    )]
+    #[case(
+        "const x = 1; var (x, y) = (1, 2);",
+        "[line 1] Error at 'x': Cannot redeclare constant 'x'."
+    )]
+    #[case(
+        "const x = 1; const x = 2;",
+        "[line 1] Error at 'x': Cannot redeclare constant 'x'."
+    )]
+    #[case("const x;", "[line 1] Error at ';': Expect '=' after constant name.")]
+    #[case("const = 1;", "[line 1] Error at '=': Expect constant name.")]
+    fn test_parse_constant_errors(#[case] input: &str, #[case] expected_error: &str) {
+        let dialect = [Extension::Constants, Extension::Destructuring]
+            .into_iter()
+            .collect();
+        let err = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .expect_err("expected parse error");
+
+        assert_eq!(expected_error, err.to_string());
+    }
+
+    #[test]
+    fn test_parser_spread_outside_elements() {
+        let dialect = Dialect::new().with(Extension::Spread);
+        let mut parser = Parser::from(scan_with("(...xs)", dialect)).with_dialect(dialect);
+        let err = parser.expression().expect_err("expected parse error");
+
+        assert_eq!(
+            "[line 1] Error at '...': Expect expression",
+            err.to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("0..n", "(.. 0.0 n)")]
+    #[case("a - 1..=b + 1", "(..= (- a 1.0) (+ b 1.0))")]
+    #[case("0..3 == r", "(== (.. 0.0 3.0) r)")]
+    #[case("step(0..10, 2)", "(call step (.. 0.0 10.0) 2.0)")]
+    fn test_parser_ranges(#[case] input: &str, #[case] expected_output: &str) {
+        let dialect = Dialect::new().with(Extension::Ranges);
+        let mut parser = Parser::from(scan_with(input, dialect)).with_dialect(dialect);
+        let expr = parser.expression().unwrap();
+
+        assert_eq!(
+            expected_output,
+            AstPrinter::new().print(&parser.arena, expr)
+        )
+    }
+
+    #[test]
+    fn test_parser_ranges_do_not_chain() {
+        let dialect = Dialect::new().with(Extension::Ranges);
+        let mut parser = Parser::from(scan_with("print 0..1..2;", dialect)).with_dialect(dialect);
+        let err = parser.parse().expect_err("expected parse error");
+
+        assert_eq!(
+            "[line 1] Error at '..': Expect ';' after value.",
+            err.to_string()
+        );
+    }
+
     #[test]
     fn test_parse_print_requires_expression() {
         let err = parse_program("print;").expect_err("expected parse error");
@@ -462,13 +1885,15 @@ mod tests {
         let statements = parse_program(program).expect("Expected a valid program");
         assert_eq!(1, statements.len());
 
-        let StmtNode::Block(block) = &statements[0] else {
+        let Some(StmtNode::Block(block)) = statements.iter().next() else {
             panic!("expected top-level block statement");
         };
 
-        assert_eq!(2, block.statements.len());
-        assert!(matches!(block.statements[0], StmtNode::Var(_)));
-        assert!(matches!(block.statements[1], StmtNode::Print(_)));
+        let inner = statements
+            .arena
+            .stmts(&block.statements)
+            .collect::<Vec<_>>();
+        assert!(matches!(inner[..], [StmtNode::Var(_), StmtNode::Print(_)]));
     }
 
     #[test]
@@ -490,4 +1915,52 @@ mod tests {
             err.to_string()
         );
     }
+
+    #[rstest]
+    #[case("print 1 + @;", "[line 1] Error: Unexpected character: @")]
+    #[case("print 1;\nprint \"open", "[line 2] Error: Unterminated string.")]
+    #[case("print 1 +;\n@", "[line 2] Error: Unexpected character: @")]
+    #[case(
+        "print @;\nprint 1 + #;",
+        "[line 1] Error: Unexpected character: @\n[line 2] Error: Unexpected character: #"
+    )]
+    #[case("print 1 +;\nprint 2;", "[line 1] Error at ';': Expect expression")]
+    fn test_parse_streaming_reports_scan_errors(#[case] input: &str, #[case] expected: &str) {
+        let mut parser = Parser::new(Scanner::new(input).scan_tokens());
+        assert_eq!(expected, parser.parse().unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_parse_streaming_matches_buffered() {
+        let input = "var a = 1;\nassert a  +  1 == 2, \"math\";\nprint a;";
+        let dialect = Dialect::new().with(Extension::Assertions);
+        let streamed = Parser::new(Scanner::new(input).with_dialect(dialect).scan_tokens())
+            .with_dialect(dialect)
+            .parse()
+            .unwrap();
+        let buffered = Parser::from(scan_with(input, dialect))
+            .with_dialect(dialect)
+            .parse()
+            .unwrap();
+        assert_eq!(format!("{streamed:?}"), format!("{buffered:?}"));
+        let Some(StmtNode::Assert(assert)) = streamed.iter().nth(1) else {
+            panic!("expected an assert statement");
+        };
+        assert_eq!("a + 1 == 2", assert.source);
+    }
+
+    #[test]
+    fn test_parse_recovering_streaming_keeps_scan_error() {
+        let mut parser = Parser::new(Scanner::new("print 1;\nprint ;\nprint #;").scan_tokens());
+        let (stmts, errors) = parser.parse_recovering();
+        assert_eq!(1, stmts.len());
+        let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "[line 2] Error at ';': Expect expression",
+                "[line 3] Error: Unexpected character: #",
+            ],
+            errors
+        );
+    }
 }
@@ -1,22 +1,53 @@
 use crate::Value;
-use crate::parser::expr::{Binary, Expr, Grouping, Literal, Unary, Visitor};
+use crate::parser::ast::{AstArena, ExprId};
+use crate::parser::expr::{
+    Assign, AssignUnpack, Binary, Cached, Call, Coalesce, Expr, Grouping, Index, List, Literal,
+    Map, SetIndex, Spread, Tuple, Unary, Variable, Visitor,
+};
 use crate::scanner::token::{Token, TokenType};
 
-#[derive(Clone, Copy)]
-pub struct AstPrinter;
+/// How the parse subcommand prints the tree, selectable with `--format`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ParseFormat {
+    /// `(+ 1.0 2.0)`, like the reference implementation.
+    #[default]
+    Sexpr,
+    /// Nested objects with node types, operators, literal values, the lines
+    /// and columns of tokens and the source span of every node.
+    Json,
+    /// A Graphviz graph with a node for each expression.
+    Dot,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AstPrinter {
+    /// Whether literals are tagged with their kind, as in `(str "a")`
+    typed_literals: bool,
+}
 
 impl AstPrinter {
-    pub fn print<E: Expr>(mut self, expr: &E) -> String {
-        expr.accept(&mut self)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tags each literal with its kind when `typed` is true, so that the
+    /// string `"true"`, printed as `(str "true")`, differs from `(bool true)`.
+    pub fn with_typed_literals(mut self, typed: bool) -> Self {
+        self.typed_literals = typed;
+        self
+    }
+
+    pub fn print(mut self, ast: &AstArena, expr: ExprId) -> String {
+        expr.accept(ast, &mut self)
     }
 }
 
 macro_rules! parenthesize {
-    ($visitor:ident, $name:expr, $($expression:expr),+) => {{
+    ($visitor:ident, $ast:ident, $name:expr, $($expression:expr),+) => {{
         let mut output = format!("({}", $name);
         $(
             output.push(' ');
-            output.push_str(&$expression.accept($visitor));
+            output.push_str(&$expression.accept($ast, $visitor));
         )+
         output.push(')');
         output
@@ -26,35 +57,133 @@ macro_rules! parenthesize {
 impl Visitor for AstPrinter {
     type Output = String;
 
-    fn visit_grouping_expr(&mut self, expr: &Grouping) -> Self::Output {
+    fn visit_grouping_expr(&mut self, ast: &AstArena, expr: &Grouping) -> Self::Output {
         let Grouping { expression } = expr;
-        parenthesize!(self, "group", expression)
+        parenthesize!(self, ast, "group", expression)
     }
 
-    fn visit_binary_expr(&mut self, expr: &Binary) -> Self::Output {
+    fn visit_binary_expr(&mut self, ast: &AstArena, expr: &Binary) -> Self::Output {
         let Binary {
             left,
             operator,
             right,
         } = expr;
-        parenthesize!(self, operator.lexeme, left, right)
+        parenthesize!(self, ast, operator.lexeme, left, right)
     }
 
-    fn visit_unary_expr(&mut self, expr: &Unary) -> Self::Output {
+    fn visit_coalesce_expr(&mut self, ast: &AstArena, expr: &Coalesce) -> Self::Output {
+        let Coalesce {
+            left,
+            operator,
+            right,
+        } = expr;
+        parenthesize!(self, ast, operator.lexeme, left, right)
+    }
+
+    fn visit_cached_expr(&mut self, ast: &AstArena, expr: &Cached) -> Self::Output {
+        expr.expr.accept(ast, self)
+    }
+
+    fn visit_unary_expr(&mut self, ast: &AstArena, expr: &Unary) -> Self::Output {
         let Unary { operator, right } = expr;
-        parenthesize!(self, operator.lexeme, right)
+        parenthesize!(self, ast, operator.lexeme, right)
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Literal) -> Self::Output {
+        // natives the parser calls directly, as in interpolation, print by name
+        if let Value::NativeFunction(native) = &expr.value {
+            return native.name.to_string();
+        }
+        if !self.typed_literals {
+            return format!("{:?}", expr.value);
+        }
+        match &expr.value {
+            Value::String(s) => format!("(str {s:?})"),
+            Value::Number(_) | Value::Integer(_) => format!("(num {:?})", expr.value),
+            Value::Boolean(b) => format!("(bool {b})"),
+            value => format!("{value:?}"),
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Variable) -> Self::Output {
+        expr.name.lexeme.to_string()
+    }
+
+    fn visit_assign_expr(&mut self, ast: &AstArena, expr: &Assign) -> Self::Output {
+        let Assign { name, value } = expr;
+        parenthesize!(self, ast, format!("= {}", name.lexeme), value)
+    }
+
+    fn visit_assign_unpack_expr(&mut self, ast: &AstArena, expr: &AssignUnpack) -> Self::Output {
+        let AssignUnpack { names, value, .. } = expr;
+        let names = names.iter().map(|name| name.lexeme.as_str());
+        let names = names.collect::<Vec<_>>().join(" ");
+        parenthesize!(self, ast, format!("= ({names})"), value)
     }
 
-    fn visit_literal_expr(&self, expr: &Literal) -> Self::Output {
-        format!("{:?}", expr.value)
+    fn visit_call_expr(&mut self, ast: &AstArena, expr: &Call) -> Self::Output {
+        let Call {
+            callee, arguments, ..
+        } = expr;
+        let mut output = format!("(call {}", callee.accept(ast, self));
+        for argument in arguments {
+            output.push(' ');
+            output.push_str(&argument.accept(ast, self));
+        }
+        output.push(')');
+        output
+    }
+
+    fn visit_list_expr(&mut self, ast: &AstArena, expr: &List) -> Self::Output {
+        let mut output = String::from("(list");
+        for element in &expr.elements {
+            output.push(' ');
+            output.push_str(&element.accept(ast, self));
+        }
+        output.push(')');
+        output
     }
 
-    fn visit_variable_expr(&self, _expr: &super::expr::Variable) -> Self::Output {
-        todo!()
+    fn visit_tuple_expr(&mut self, ast: &AstArena, expr: &Tuple) -> Self::Output {
+        let mut output = String::from("(tuple");
+        for element in &expr.elements {
+            output.push(' ');
+            output.push_str(&element.accept(ast, self));
+        }
+        output.push(')');
+        output
     }
 
-    fn visit_assign_expr(&mut self, _expr: &super::expr::Assign) -> Self::Output {
-        todo!()
+    fn visit_spread_expr(&mut self, ast: &AstArena, expr: &Spread) -> Self::Output {
+        let Spread { expression, .. } = expr;
+        parenthesize!(self, ast, "...", expression)
+    }
+
+    fn visit_map_expr(&mut self, ast: &AstArena, expr: &Map) -> Self::Output {
+        let mut output = String::from("(map");
+        for (key, value) in &expr.entries {
+            output.push(' ');
+            output.push_str(&key.accept(ast, self));
+            output.push(' ');
+            output.push_str(&value.accept(ast, self));
+        }
+        output.push(')');
+        output
+    }
+
+    fn visit_index_expr(&mut self, ast: &AstArena, expr: &Index) -> Self::Output {
+        let Index { object, index, .. } = expr;
+        parenthesize!(self, ast, "index", object, index)
+    }
+
+    fn visit_set_index_expr(&mut self, ast: &AstArena, expr: &SetIndex) -> Self::Output {
+        let SetIndex {
+            object,
+            index,
+            value,
+            ..
+        } = expr;
+        parenthesize!(self, ast, "set-index", object, index, value)
     }
 }
 
@@ -62,11 +191,14 @@ pub fn print_example() {
     let plus = Token::new(TokenType::Plus, "+".into(), None, 1);
     let minus = Token::new(TokenType::Minus, "-".into(), None, 1);
 
-    let left = Literal::from(Value::from(0.0));
-    let right = Grouping::new(Unary::new(minus, Literal::from(Value::from(42.0)).into()).into());
-    let expr = Binary::new(left.into(), plus, right.into());
+    let mut ast = AstArena::new();
+    let left = ast.add_expr(Literal::from(Value::from(0.0)));
+    let number = ast.add_expr(Literal::from(Value::from(42.0)));
+    let negated = ast.add_expr(Unary::new(minus, number));
+    let right = ast.add_expr(Grouping::new(negated));
+    let expr = ast.add_expr(Binary::new(left, plus, right));
 
-    let printer = AstPrinter;
-    let s = printer.print(&expr);
+    let printer = AstPrinter::new();
+    let s = printer.print(&ast, expr);
     println!("{}", s);
 }
@@ -1,53 +1,82 @@
-use crate::parser::expr::ExprNode;
+use crate::Value;
+use crate::parser::ast::{AstArena, ExprId, StmtId};
 use crate::scanner::token::Token;
 
 pub trait Stmt {
-    fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Output;
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output;
 }
 
 pub trait Visitor {
     type Output;
-    fn visit_print_stmt(&mut self, stmt: &Print) -> Self::Output;
-    fn visit_expression_stmt(&mut self, stmt: &Expression) -> Self::Output;
-    fn visit_var_stmt(&mut self, stmt: &Var) -> Self::Output;
-    fn visit_block_stmt(&mut self, stmt: &Block) -> Self::Output;
+    fn visit_print_stmt(&mut self, ast: &AstArena, stmt: &Print) -> Self::Output;
+    fn visit_expression_stmt(&mut self, ast: &AstArena, stmt: &Expression) -> Self::Output;
+    fn visit_var_stmt(&mut self, ast: &AstArena, stmt: &Var) -> Self::Output;
+    fn visit_var_unpack_stmt(&mut self, ast: &AstArena, stmt: &VarUnpack) -> Self::Output;
+    fn visit_block_stmt(&mut self, ast: &AstArena, stmt: &Block) -> Self::Output;
+    fn visit_for_in_stmt(&mut self, ast: &AstArena, stmt: &ForIn) -> Self::Output;
+    fn visit_do_while_stmt(&mut self, ast: &AstArena, stmt: &DoWhile) -> Self::Output;
+    fn visit_try_stmt(&mut self, ast: &AstArena, stmt: &Try) -> Self::Output;
+    fn visit_throw_stmt(&mut self, ast: &AstArena, stmt: &Throw) -> Self::Output;
+    fn visit_assert_stmt(&mut self, ast: &AstArena, stmt: &Assert) -> Self::Output;
+    fn visit_import_stmt(&mut self, stmt: &Import) -> Self::Output;
+    fn visit_match_stmt(&mut self, ast: &AstArena, stmt: &Match) -> Self::Output;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum StmtNode {
     Print(Print),
     Expression(Expression),
     Var(Var),
+    VarUnpack(VarUnpack),
     Block(Block),
+    ForIn(ForIn),
+    DoWhile(DoWhile),
+    Try(Try),
+    Throw(Throw),
+    Assert(Assert),
+    Import(Import),
+    Match(Match),
+}
+
+impl Stmt for StmtId {
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
+        ast[*self].accept(ast, visitor)
+    }
 }
 
 impl Stmt for StmtNode {
-    fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Output {
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
         match self {
-            StmtNode::Print(print) => print.accept(visitor),
-            StmtNode::Expression(expression) => expression.accept(visitor),
-            StmtNode::Var(var) => var.accept(visitor),
-            StmtNode::Block(block) => block.accept(visitor),
+            StmtNode::Print(print) => print.accept(ast, visitor),
+            StmtNode::Expression(expression) => expression.accept(ast, visitor),
+            StmtNode::Var(var) => var.accept(ast, visitor),
+            StmtNode::VarUnpack(unpack) => unpack.accept(ast, visitor),
+            StmtNode::Block(block) => block.accept(ast, visitor),
+            StmtNode::ForIn(for_in) => for_in.accept(ast, visitor),
+            StmtNode::DoWhile(do_while) => do_while.accept(ast, visitor),
+            StmtNode::Try(try_stmt) => try_stmt.accept(ast, visitor),
+            StmtNode::Throw(throw) => throw.accept(ast, visitor),
+            StmtNode::Assert(assert) => assert.accept(ast, visitor),
+            StmtNode::Import(import) => import.accept(ast, visitor),
+            StmtNode::Match(match_stmt) => match_stmt.accept(ast, visitor),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Print {
-    pub expr: Box<ExprNode>,
+    pub expr: ExprId,
 }
 
 impl Stmt for Print {
-    fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Output {
-        visitor.visit_print_stmt(&self)
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
+        visitor.visit_print_stmt(ast, self)
     }
 }
 
 impl Print {
-    pub fn new(expr: ExprNode) -> Self {
-        Self {
-            expr: Box::new(expr),
-        }
+    pub fn new(expr: ExprId) -> Self {
+        Self { expr }
     }
 }
 
@@ -57,23 +86,35 @@ impl From<Print> for StmtNode {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Var {
     pub name: Token,
-    pub initializer: Option<Box<ExprNode>>,
+    pub initializer: Option<ExprId>,
+    /// Whether the variable was declared with `const`
+    pub constant: bool,
 }
 
 impl Stmt for Var {
-    fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Output {
-        visitor.visit_var_stmt(&self)
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
+        visitor.visit_var_stmt(ast, self)
     }
 }
 
 impl Var {
-    pub fn new(name: Token, initializer: Option<ExprNode>) -> Self {
+    pub fn new(name: Token, initializer: Option<ExprId>) -> Self {
+        Self {
+            name,
+            initializer,
+            constant: false,
+        }
+    }
+
+    /// Creates a `const` declaration, whose variable cannot be assigned again.
+    pub fn constant(name: Token, initializer: ExprId) -> Self {
         Self {
             name,
-            initializer: initializer.map(|i| Box::new(i)),
+            initializer: Some(initializer),
+            constant: true,
         }
     }
 }
@@ -84,19 +125,50 @@ impl From<Var> for StmtNode {
     }
 }
 
-#[derive(Debug)]
+/// `var (a, b) = value;`, which declares one variable per element of a list.
+#[derive(Debug, Clone)]
+pub struct VarUnpack {
+    /// The opening parenthesis, used to report a length mismatch
+    pub paren: Token,
+    pub names: Vec<Token>,
+    pub value: ExprId,
+}
+
+impl Stmt for VarUnpack {
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
+        visitor.visit_var_unpack_stmt(ast, self)
+    }
+}
+
+impl VarUnpack {
+    pub fn new(paren: Token, names: Vec<Token>, value: ExprId) -> Self {
+        Self {
+            paren,
+            names,
+            value,
+        }
+    }
+}
+
+impl From<VarUnpack> for StmtNode {
+    fn from(unpack: VarUnpack) -> Self {
+        Self::VarUnpack(unpack)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Block {
-    pub statements: Vec<StmtNode>,
+    pub statements: Vec<StmtId>,
 }
 
 impl Stmt for Block {
-    fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Output {
-        visitor.visit_block_stmt(&self)
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
+        visitor.visit_block_stmt(ast, self)
     }
 }
 
 impl Block {
-    pub fn new(statements: Vec<StmtNode>) -> Self {
+    pub fn new(statements: Vec<StmtId>) -> Self {
         Self { statements }
     }
 }
@@ -107,22 +179,223 @@ impl From<Block> for StmtNode {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct ForIn {
+    /// The `for` keyword, used to report errors about the iterable
+    pub keyword: Token,
+    pub name: Token,
+    pub iterable: ExprId,
+    pub body: StmtId,
+}
+
+impl Stmt for ForIn {
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
+        visitor.visit_for_in_stmt(ast, self)
+    }
+}
+
+impl ForIn {
+    pub fn new(keyword: Token, name: Token, iterable: ExprId, body: StmtId) -> Self {
+        Self {
+            keyword,
+            name,
+            iterable,
+            body,
+        }
+    }
+}
+
+impl From<ForIn> for StmtNode {
+    fn from(for_in: ForIn) -> Self {
+        Self::ForIn(for_in)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DoWhile {
+    pub body: StmtId,
+    /// Checked after each run of the body
+    pub condition: ExprId,
+}
+
+impl Stmt for DoWhile {
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
+        visitor.visit_do_while_stmt(ast, self)
+    }
+}
+
+impl DoWhile {
+    pub fn new(body: StmtId, condition: ExprId) -> Self {
+        Self { body, condition }
+    }
+}
+
+impl From<DoWhile> for StmtNode {
+    fn from(do_while: DoWhile) -> Self {
+        Self::DoWhile(do_while)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Try {
+    pub body: StmtId,
+    /// The name the caught value is bound to and the block handling it
+    pub catch: Option<(Token, StmtId)>,
+    pub finally: Option<StmtId>,
+}
+
+impl Stmt for Try {
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
+        visitor.visit_try_stmt(ast, self)
+    }
+}
+
+impl Try {
+    pub fn new(body: StmtId, catch: Option<(Token, StmtId)>, finally: Option<StmtId>) -> Self {
+        Self {
+            body,
+            catch,
+            finally,
+        }
+    }
+}
+
+impl From<Try> for StmtNode {
+    fn from(try_stmt: Try) -> Self {
+        Self::Try(try_stmt)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Throw {
+    /// The `throw` keyword, whose line is reported if the value is not caught
+    pub keyword: Token,
+    pub value: ExprId,
+}
+
+impl Stmt for Throw {
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
+        visitor.visit_throw_stmt(ast, self)
+    }
+}
+
+impl Throw {
+    pub fn new(keyword: Token, value: ExprId) -> Self {
+        Self { keyword, value }
+    }
+}
+
+impl From<Throw> for StmtNode {
+    fn from(throw: Throw) -> Self {
+        Self::Throw(throw)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Assert {
+    /// The `assert` keyword, whose line is reported if the assertion fails
+    pub keyword: Token,
+    pub condition: ExprId,
+    /// The source text of the condition, quoted in the failure message
+    pub source: String,
+    pub message: Option<ExprId>,
+}
+
+impl Stmt for Assert {
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
+        visitor.visit_assert_stmt(ast, self)
+    }
+}
+
+impl Assert {
+    pub fn new(keyword: Token, condition: ExprId, source: String, message: Option<ExprId>) -> Self {
+        Self {
+            keyword,
+            condition,
+            source,
+            message,
+        }
+    }
+}
+
+impl From<Assert> for StmtNode {
+    fn from(assert: Assert) -> Self {
+        Self::Assert(assert)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    /// The string literal naming the imported file
+    pub path: Token,
+    /// The top-level variables of the module to bind, possibly none
+    pub names: Vec<Token>,
+}
+
+impl Stmt for Import {
+    fn accept<V: Visitor>(&self, _ast: &AstArena, visitor: &mut V) -> V::Output {
+        visitor.visit_import_stmt(self)
+    }
+}
+
+impl Import {
+    pub fn new(path: Token, names: Vec<Token>) -> Self {
+        Self { path, names }
+    }
+}
+
+impl From<Import> for StmtNode {
+    fn from(import: Import) -> Self {
+        Self::Import(import)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub value: ExprId,
+    /// The literal patterns of each arm, with the statement run when one of
+    /// them equals the value
+    pub arms: Vec<(Vec<Value>, StmtId)>,
+    /// The statement run when no pattern matches
+    pub otherwise: Option<StmtId>,
+}
+
+impl Stmt for Match {
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
+        visitor.visit_match_stmt(ast, self)
+    }
+}
+
+impl Match {
+    pub fn new(value: ExprId, arms: Vec<(Vec<Value>, StmtId)>, otherwise: Option<StmtId>) -> Self {
+        Self {
+            value,
+            arms,
+            otherwise,
+        }
+    }
+}
+
+impl From<Match> for StmtNode {
+    fn from(match_stmt: Match) -> Self {
+        Self::Match(match_stmt)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Expression {
-    pub expr: Box<ExprNode>,
+    pub expr: ExprId,
 }
 
 impl Stmt for Expression {
-    fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Output {
-        visitor.visit_expression_stmt(&self)
+    fn accept<V: Visitor>(&self, ast: &AstArena, visitor: &mut V) -> V::Output {
+        visitor.visit_expression_stmt(ast, self)
     }
 }
 
 impl Expression {
-    pub fn new(expr: ExprNode) -> Self {
-        Self {
-            expr: Box::new(expr),
-        }
+    pub fn new(expr: ExprId) -> Self {
+        Self { expr }
     }
 }
 
@@ -0,0 +1,122 @@
+use std::mem;
+
+use crate::error::StaticError;
+use crate::scanner::token::{Token, TokenType};
+use crate::scanner::{ScanItem, ScanResult};
+
+/// The tokens a parser reads, pulled from a scanner one at a time and
+/// skipping whitespace and comments.
+///
+/// Scan errors are kept for the parser to report instead of whatever it
+/// makes of the tokens around them, and the stream goes on after them. Only
+/// exceeding a limit ends the stream, with an EOF token on the line reached.
+pub(super) struct Tokens<I> {
+    items: I,
+    /// The next token, once peeked
+    peeked: Option<Token>,
+    /// Whether the stream has ended, so that `items` is not pulled again
+    done: bool,
+    /// The scan errors met so far and not yet taken, in source order
+    errors: Vec<StaticError>,
+    /// The tokens consumed since [`Tokens::record`], if recording
+    recorded: Option<Vec<Token>>,
+    /// The line of the last token consumed
+    last_line: u32,
+    /// The number of tokens consumed so far
+    consumed: usize,
+}
+
+impl<I: Iterator<Item = ScanResult>> Tokens<I> {
+    pub(super) fn new(items: I) -> Self {
+        Self {
+            items,
+            peeked: None,
+            done: false,
+            errors: Vec::new(),
+            recorded: None,
+            last_line: 1,
+            consumed: 0,
+        }
+    }
+
+    /// Returns the next token without consuming it.
+    pub(super) fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            self.peeked = self.pull();
+        }
+        self.peeked.as_ref()
+    }
+
+    /// Consumes and returns the next token if `func` returns true for it.
+    pub(super) fn next_if(&mut self, func: impl FnOnce(&Token) -> bool) -> Option<Token> {
+        if func(self.peek()?) {
+            self.next()
+        } else {
+            None
+        }
+    }
+
+    /// Consumes and returns the next token.
+    pub(super) fn next(&mut self) -> Option<Token> {
+        let token = self.peeked.take().or_else(|| self.pull())?;
+        self.last_line = token.line;
+        self.consumed += 1;
+        if let Some(recorded) = &mut self.recorded {
+            recorded.push(token.clone());
+        }
+        Some(token)
+    }
+
+    /// Returns the line of the last token consumed, or `1` before the first.
+    pub(super) fn last_line(&self) -> u32 {
+        self.last_line
+    }
+
+    /// Returns the number of tokens consumed so far.
+    pub(super) fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    /// Starts keeping a copy of every token consumed, see [`Tokens::recorded`].
+    pub(super) fn record(&mut self) {
+        self.recorded = Some(Vec::new());
+    }
+
+    /// Stops recording and returns the tokens consumed since
+    /// [`Tokens::record`].
+    pub(super) fn recorded(&mut self) -> Vec<Token> {
+        self.recorded.take().unwrap_or_default()
+    }
+
+    /// Returns the scan errors met since they were last taken.
+    pub(super) fn take_errors(&mut self) -> Vec<StaticError> {
+        mem::take(&mut self.errors)
+    }
+
+    /// Scans the rest of the source for its scan errors, dropping its tokens.
+    pub(super) fn skip_rest(&mut self) {
+        self.peeked = None;
+        while self.pull().is_some() {}
+    }
+
+    fn pull(&mut self) -> Option<Token> {
+        while !self.done {
+            match self.items.next() {
+                Some(Ok(ScanItem::Token(token))) => {
+                    self.done = token.typ == TokenType::Eof;
+                    return Some(token);
+                }
+                Some(Ok(ScanItem::Ignore)) => continue,
+                Some(Err(err)) if err.exceeded_limit().is_some() => {
+                    self.done = true;
+                    let line = err.line();
+                    self.errors.push(err);
+                    return Some(Token::new_eof(line));
+                }
+                Some(Err(err)) => self.errors.push(err),
+                None => self.done = true,
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,89 @@
+//! Native functions and value types added to the interpreter by other
+//! crates, registered at startup with [`Interpreter::with_plugin`].
+//!
+//! Plugins are linked in at compile time rather than loaded from shared
+//! libraries, since loading one takes `unsafe` code this crate forbids.
+//! A value type is a [`Foreign`](crate::foreign::Foreign) type name, with
+//! operators on it from the plugin's [`OperatorTable`].
+
+#[cfg(doc)]
+use crate::interpreter::Interpreter;
+use crate::interpreter::operators::OperatorTable;
+use crate::stdlib::NativeFunction;
+
+/// A bundle of natives and operators supplied by another crate.
+pub trait Plugin {
+    /// Returns the natives to define in the global scope, after the ones of
+    /// the standard library, which they replace if they share a name.
+    fn natives(&self) -> &'static [NativeFunction] {
+        &[]
+    }
+
+    /// Adds the operators on the value types of the plugin to `operators`.
+    fn operators(&self, operators: OperatorTable) -> OperatorTable {
+        operators
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+    use crate::foreign::Foreign;
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+    use crate::scanner::token::TokenType;
+
+    struct Vectors;
+
+    fn vector(value: &Value) -> Option<(f64, f64)> {
+        match value {
+            Value::Foreign(handle) => handle.downcast_ref().copied(),
+            _ => None,
+        }
+    }
+
+    static NATIVES: &[NativeFunction] = &[NativeFunction {
+        name: "vec2",
+        arity: 2,
+        func: |_, args| match (args[0].as_f64(), args[1].as_f64()) {
+            (Some(x), Some(y)) => Ok(Foreign::new("Vec2", (x, y)).into()),
+            _ => Err("Arguments must be numbers.".into()),
+        },
+    }];
+
+    impl Plugin for Vectors {
+        fn natives(&self) -> &'static [NativeFunction] {
+            NATIVES
+        }
+
+        fn operators(&self, operators: OperatorTable) -> OperatorTable {
+            operators.with(TokenType::Plus, "Vec2", "Vec2", |left, right| {
+                let (Some(a), Some(b)) = (vector(left), vector(right)) else {
+                    return Err("Expected vectors.".into());
+                };
+                Ok(Foreign::new("Vec2", (a.0 + b.0, a.1 + b.1)).into())
+            })
+        }
+    }
+
+    fn evaluate(interpreter: &mut Interpreter, source: &str) -> Value {
+        let mut parser = Parser::new(Scanner::new(source).scan_tokens());
+        let (ast, expr) = parser.parse_expression().unwrap();
+        interpreter.evaluate(&ast, expr).unwrap()
+    }
+
+    #[test]
+    fn test_interpreter_with_plugin() {
+        let mut interpreter = Interpreter::new().with_plugin(&Vectors);
+
+        let sum = evaluate(&mut interpreter, "vec2(1, 2) + vec2(3, 4)");
+        assert_eq!(Some((4.0, 6.0)), vector(&sum));
+        assert_eq!(
+            Value::from("Vec2"),
+            evaluate(&mut interpreter, "type(vec2(0, 0))")
+        );
+        assert_eq!(Value::Number(3.0), evaluate(&mut interpreter, "1 + 2"));
+    }
+}
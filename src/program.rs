@@ -0,0 +1,129 @@
+//! Parsing a program once and running it many times, for hosts such as
+//! grading harnesses that run the same script against many environments.
+
+use std::rc::Rc;
+
+use crate::dialect::Dialect;
+use crate::error::StaticError;
+use crate::interpreter::Interpreter;
+use crate::interpreter::error::RuntimeError;
+use crate::interpreter::limits::Limit;
+use crate::parser::Parser;
+use crate::parser::ast::Ast;
+use crate::scanner::Scanner;
+
+/// Compiles source code into [`Program`]s.
+#[derive(Debug, Clone, Default)]
+pub struct Lox {
+    dialect: Dialect,
+    limits: Vec<Limit>,
+}
+
+impl Lox {
+    /// Creates a compiler for standard Lox.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables the language extensions in `dialect` for compiled programs.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Refuses sources with more tokens or bytes than `limits` allow.
+    pub fn with_limits(mut self, limits: impl IntoIterator<Item = Limit>) -> Self {
+        self.limits = limits.into_iter().collect();
+        self
+    }
+
+    /// Scans and parses `source`.
+    ///
+    /// Returns the first [`StaticError`] if the source does not scan or parse.
+    pub fn compile(&self, source: &str) -> Result<Program, StaticError> {
+        let scanner = Scanner::new(source)
+            .with_dialect(self.dialect)
+            .with_limits(self.limits.iter().copied());
+        let ast = Parser::new(scanner.scan_tokens())
+            .with_dialect(self.dialect)
+            .parse()?;
+        Ok(Program {
+            ast: Rc::new(ast),
+            dialect: self.dialect,
+        })
+    }
+}
+
+/// A parsed program. Cloning it shares the AST rather than copying it.
+#[derive(Debug, Clone)]
+pub struct Program {
+    ast: Rc<Ast>,
+    dialect: Dialect,
+}
+
+impl Program {
+    /// Runs the program in the global scope of `interpreter`, which should
+    /// allow the same extensions as [`Program::dialect`].
+    ///
+    /// Returns the [`RuntimeError`] that stopped the program, if any.
+    pub fn run(&self, interpreter: &mut Interpreter) -> Result<(), RuntimeError> {
+        interpreter.interpret(&self.ast)
+    }
+
+    /// Returns the language extensions the program was compiled with.
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// Returns the parsed AST, for hosts that walk it themselves.
+    pub fn ast(&self) -> &Ast {
+        &self.ast
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::dialect::Extension;
+    use crate::interpreter::limits::Resource;
+
+    #[test]
+    fn test_program_runs_against_many_environments() {
+        let dialect = Dialect::new().with(Extension::Assertions);
+        let program = Lox::new()
+            .with_dialect(dialect)
+            .compile("var doubled = n * 2; assert doubled == expected;")
+            .unwrap();
+
+        for n in [1.0, 2.5, -4.0] {
+            let mut interpreter = Interpreter::new().with_dialect(program.dialect());
+            interpreter.define("n", n.into()).unwrap();
+            interpreter.define("expected", (n * 2.0).into()).unwrap();
+            assert!(program.run(&mut interpreter).is_ok());
+        }
+
+        let mut interpreter = Interpreter::new().with_dialect(dialect);
+        interpreter.define("n", 1.0.into()).unwrap();
+        interpreter.define("expected", 3.0.into()).unwrap();
+        assert!(program.run(&mut interpreter).is_err());
+    }
+
+    #[rstest]
+    #[case(Lox::new(), "print @;", "[line 1] Error: Unexpected character: @")]
+    #[case(
+        Lox::new(),
+        "print 1",
+        "[line 1] Error at end: Expect ';' after value."
+    )]
+    #[case(
+        Lox::new().with_limits([Limit { resource: Resource::Tokens, max: 2 }]),
+        "print 1;",
+        "[line 1] Error: Input too large: tokens=2."
+    )]
+    fn test_compile_errors(#[case] lox: Lox, #[case] source: &str, #[case] expected: &str) {
+        let err = lox.compile(source).unwrap_err();
+        assert_eq!(expected, err.to_string());
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt::Display;
+
+/// A range of whole numbers created by `a..b` or `a..=b`.
+///
+/// A range counts down when `start` is greater than `end`, so `3..0` holds
+/// `3`, `2` and `1`. Every `step`-th number from `start` is part of the range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    start: i64,
+    end: i64,
+    inclusive: bool,
+    /// The positive distance between consecutive numbers
+    step: i64,
+}
+
+impl Range {
+    /// Creates a range from `start` to `end` with a step of 1, including `end`
+    /// if `inclusive` is true.
+    pub fn new(start: i64, end: i64, inclusive: bool) -> Self {
+        Self {
+            start,
+            end,
+            inclusive,
+            step: 1,
+        }
+    }
+
+    /// Returns this range with only every `step`-th number from its start.
+    ///
+    /// Returns an error message if `step` is not positive.
+    pub fn with_step(self, step: i64) -> Result<Self, &'static str> {
+        if step <= 0 {
+            return Err("Step must be a positive integer.");
+        }
+        Ok(Self { step, ..self })
+    }
+
+    /// Checks whether `n` is one of the numbers in this range.
+    pub fn contains(&self, n: i64) -> bool {
+        let within = if self.start <= self.end {
+            self.start <= n && (n < self.end || (self.inclusive && n == self.end))
+        } else {
+            self.end <= n && n <= self.start && (n > self.end || self.inclusive)
+        };
+        within && n.abs_diff(self.start).is_multiple_of(self.step as u64)
+    }
+
+    /// Returns the first number of the range and a range of the numbers
+    /// after it, or `None` if the range is empty.
+    pub fn split_first(self) -> Option<(i64, Range)> {
+        if !self.contains(self.start) {
+            return None;
+        }
+        let next = if self.start <= self.end {
+            self.start.checked_add(self.step)
+        } else {
+            self.start.checked_sub(self.step)
+        };
+        let rest = match next.filter(|&n| self.contains(n)) {
+            Some(next) => Self {
+                start: next,
+                ..self
+            },
+            None => Self::new(self.end, self.end, false),
+        };
+        Some((self.start, rest))
+    }
+}
+
+impl IntoIterator for Range {
+    type Item = i64;
+    type IntoIter = Iter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter {
+            next: Some(self.start),
+            range: self,
+        }
+    }
+}
+
+/// An iterator over the numbers of a [`Range`], in order from its start.
+#[derive(Debug, Clone)]
+pub struct Iter {
+    /// The next number to yield, or `None` once stepping past the end overflowed
+    next: Option<i64>,
+    range: Range,
+}
+
+impl Iterator for Iter {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.next.filter(|&n| self.range.contains(n))?;
+        self.next = if self.range.start <= self.range.end {
+            n.checked_add(self.range.step)
+        } else {
+            n.checked_sub(self.range.step)
+        };
+        Some(n)
+    }
+}
+
+impl Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            start,
+            end,
+            inclusive,
+            step,
+        } = *self;
+        let operator = if inclusive { "..=" } else { ".." };
+        if step == 1 {
+            write!(f, "{start}{operator}{end}")
+        } else {
+            write!(f, "step({start}{operator}{end}, {step})")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(Range::new(0, 4, false), vec![0, 1, 2, 3])]
+    #[case(Range::new(0, 4, true), vec![0, 1, 2, 3, 4])]
+    #[case(Range::new(3, 0, false), vec![3, 2, 1])]
+    #[case(Range::new(3, 0, true), vec![3, 2, 1, 0])]
+    #[case(Range::new(2, 2, false), vec![])]
+    #[case(Range::new(2, 2, true), vec![2])]
+    #[case(Range::new(0, 10, false).with_step(3).unwrap(), vec![0, 3, 6, 9])]
+    #[case(Range::new(10, 0, true).with_step(5).unwrap(), vec![10, 5, 0])]
+    #[case(Range::new(i64::MAX - 2, i64::MAX, true).with_step(2).unwrap(), vec![i64::MAX - 2, i64::MAX])]
+    fn test_range_iter(#[case] range: Range, #[case] expected: Vec<i64>) {
+        assert_eq!(expected, range.into_iter().collect::<Vec<_>>());
+    }
+
+    #[rstest]
+    #[case(Range::new(0, 4, false))]
+    #[case(Range::new(3, 0, true))]
+    #[case(Range::new(2, 2, false))]
+    #[case(Range::new(10, 0, true).with_step(5).unwrap())]
+    #[case(Range::new(0, 10, false).with_step(3).unwrap())]
+    #[case(Range::new(i64::MAX - 2, i64::MAX, true).with_step(2).unwrap())]
+    fn test_range_split_first_matches_iter(#[case] range: Range) {
+        let mut numbers = Vec::new();
+        let mut rest = range;
+        while let Some((n, after)) = rest.split_first() {
+            numbers.push(n);
+            rest = after;
+        }
+        assert_eq!(range.into_iter().collect::<Vec<_>>(), numbers);
+    }
+
+    #[rstest]
+    #[case(Range::new(0, 4, false), 3, true)]
+    #[case(Range::new(0, 4, false), 4, false)]
+    #[case(Range::new(0, 4, true), 4, true)]
+    #[case(Range::new(0, 4, false), -1, false)]
+    #[case(Range::new(3, 0, false), 0, false)]
+    #[case(Range::new(3, 0, false), 3, true)]
+    #[case(Range::new(0, 10, false).with_step(3).unwrap(), 6, true)]
+    #[case(Range::new(0, 10, false).with_step(3).unwrap(), 7, false)]
+    #[case(Range::new(i64::MIN, i64::MAX, true).with_step(2).unwrap(), i64::MAX, false)]
+    fn test_range_contains(#[case] range: Range, #[case] n: i64, #[case] expected: bool) {
+        assert_eq!(expected, range.contains(n));
+    }
+
+    #[rstest]
+    #[case(Range::new(1, 5, false), "1..5")]
+    #[case(Range::new(5, -1, true), "5..=-1")]
+    #[case(Range::new(0, 10, false).with_step(2).unwrap(), "step(0..10, 2)")]
+    fn test_range_display(#[case] range: Range, #[case] expected: &str) {
+        assert_eq!(expected, range.to_string());
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(-2)]
+    fn test_range_invalid_step(#[case] step: i64) {
+        assert_eq!(
+            Some("Step must be a positive integer."),
+            Range::new(0, 1, false).with_step(step).err()
+        );
+    }
+}
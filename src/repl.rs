@@ -0,0 +1,99 @@
+//! Running source one line at a time in the same global scope, the way a
+//! REPL does, and describing what each line evaluates to.
+
+use crate::Value;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::parser::ast::Ast;
+use crate::parser::stmt::StmtNode;
+use crate::scanner::Scanner;
+
+pub struct Repl {
+    interpreter: Interpreter,
+}
+
+impl Repl {
+    /// Creates a REPL that runs lines with `interpreter`, scanning and
+    /// parsing them with its dialect.
+    pub fn new(interpreter: Interpreter) -> Self {
+        Self { interpreter }
+    }
+
+    /// Runs `line` and describes its result: the value of a lone expression,
+    /// `nil` for other statements, or the error that stopped it.
+    ///
+    /// A lone expression does not need a closing semicolon.
+    pub fn eval(&mut self, line: &str) -> String {
+        let ast = match self.parse(line) {
+            Ok(ast) => ast,
+            Err(message) => return message,
+        };
+
+        let result = match ast.iter().collect::<Vec<_>>()[..] {
+            [StmtNode::Expression(expression)] => {
+                self.interpreter.evaluate(&ast.arena, expression.expr)
+            }
+            _ => self.interpreter.interpret(&ast).map(|()| Value::Nil),
+        };
+        match result {
+            Ok(value) => value.to_string(),
+            Err(err) => format!("Error: {}", err.message()),
+        }
+    }
+
+    /// Parses `line` as statements, or failing that as an expression missing
+    /// its semicolon. Returns the first error of the line as written.
+    fn parse(&self, line: &str) -> Result<Ast, String> {
+        let dialect = self.interpreter.dialect();
+        let parse = |source: &str| {
+            let scanner = Scanner::new(source)
+                .with_dialect(dialect)
+                .with_interner(self.interpreter.interner().clone());
+            Parser::new(scanner.scan_tokens())
+                .with_dialect(dialect)
+                .parse()
+        };
+
+        parse(line).or_else(|err| {
+            if line.trim_end().ends_with(';') {
+                return Err(err.to_string());
+            }
+            parse(&format!("{line};")).map_err(|_| err.to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::{Dialect, Extension};
+
+    #[test]
+    fn test_repl_keeps_state_between_lines() {
+        let interpreter = Interpreter::new().with_dialect(Dialect::new().with(Extension::Lists));
+        let mut repl = Repl::new(interpreter);
+        let transcript = [
+            "var a = 1;",
+            "a + 2",
+            "a = a * 10;",
+            "[a, \"b\"]",
+            "-\"a\"",
+            "print ;",
+            "b",
+        ]
+        .map(|line| repl.eval(line));
+
+        assert_eq!(
+            [
+                "nil",
+                "3",
+                "10",
+                "[10, b]",
+                "Error: Operand must be a number.",
+                "[line 1] Error at ';': Expect expression",
+                "Error: Undefined variable 'b'.",
+            ],
+            transcript
+        );
+    }
+}
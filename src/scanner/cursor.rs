@@ -0,0 +1,47 @@
+use std::str::Chars;
+
+/// A character iterator over source code that can look ahead and hand back
+/// the text it has not consumed yet, which the scanner slices lexemes from
+/// and measures byte offsets with.
+#[derive(Clone)]
+pub(super) struct Cursor<'src> {
+    /// The characters that have not been consumed yet
+    chars: Chars<'src>,
+}
+
+impl<'src> Cursor<'src> {
+    pub(super) fn new(source: &'src str) -> Self {
+        Self {
+            chars: source.chars(),
+        }
+    }
+
+    /// Returns the source text that has not been consumed yet.
+    pub(super) fn rest(&self) -> &'src str {
+        self.chars.as_str()
+    }
+
+    /// Returns the next character without consuming it.
+    pub(super) fn peek(&self) -> Option<char> {
+        self.chars.clone().next()
+    }
+
+    /// Consume and return the next character if `func` returns true for it.
+    pub(super) fn next_if(&mut self, func: impl FnOnce(&char) -> bool) -> Option<char> {
+        let c = self.peek()?;
+        if func(&c) { self.next() } else { None }
+    }
+
+    /// Consume and return the next character if it is equal to `expected`.
+    pub(super) fn next_if_eq(&mut self, expected: &char) -> Option<char> {
+        self.next_if(|c| c == expected)
+    }
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chars.next()
+    }
+}
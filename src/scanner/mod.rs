@@ -1,128 +1,419 @@
-use std::iter::Peekable;
-use std::str::Chars;
+use std::borrow::Cow;
+
+use unicode_segmentation::UnicodeSegmentation as _;
 
 use crate::Value;
+use crate::dialect::{Dialect, Extension};
 use crate::error::StaticError;
-use crate::scanner::token::{Token, TokenType};
+use crate::interpreter::limits::{Limit, Resource};
+use crate::scanner::cursor::Cursor;
+use crate::scanner::token::{BorrowedToken, Token, TokenType};
+use crate::source::{Locator, SourceId, Span};
+use crate::symbol::Interner;
 
+mod cursor;
+pub mod record;
 pub mod token;
+pub mod trivia;
 
 pub struct Scanner<'src> {
     // Raw source code
     source: &'src str,
+    // Language extensions recognized while scanning
+    dialect: Dialect,
+    // Caps on the number of tokens and bytes in the source
+    limits: Vec<Limit>,
+    // The file the source is, which token spans point into
+    file: SourceId,
+    // The byte offset, line and column of the first character of the source
+    start: (usize, u32, u32),
+    // Where the names of identifiers come from
+    interner: Interner,
 }
 
 impl<'src> Scanner<'src> {
     pub fn new(source: &'src str) -> Scanner<'src> {
-        Self { source }
+        Self {
+            source,
+            dialect: Dialect::default(),
+            limits: Vec::new(),
+            file: SourceId::default(),
+            start: (0, 1, 1),
+            interner: Interner::default(),
+        }
+    }
+
+    /// Enables the language extensions in `dialect` for this scanner.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Stops scanning with an error once the source exceeds one of the
+    /// token or byte `limits`, so that untrusted input cannot make the
+    /// parser and interpreter do unbounded work. Other limits are ignored.
+    pub fn with_limits(mut self, limits: impl IntoIterator<Item = Limit>) -> Self {
+        self.limits = limits.into_iter().collect();
+        self
+    }
+
+    /// Gives tokens spans into the file `file` of a
+    /// [`SourceMap`](crate::source::SourceMap), rather than into its first.
+    pub fn with_file(mut self, file: SourceId) -> Self {
+        self.file = file;
+        self
+    }
+
+    /// Numbers bytes from `offset`, and lines and columns from the 1-based
+    /// `line` and `column`, for a source sliced from the middle of a larger
+    /// one, such as a statement scanned again after an edit.
+    pub fn starting_at(mut self, offset: usize, line: u32, column: u32) -> Self {
+        self.start = (offset, line, column);
+        self
+    }
+
+    /// Names identifiers with the symbols of `interner`, so that they are
+    /// shared with the other sources scanned with it. Without one, each
+    /// scanner has an interner of its own.
+    pub fn with_interner(mut self, interner: Interner) -> Self {
+        self.interner = interner;
+        self
+    }
+
+    /// Returns the smallest limit on `resource`, if any.
+    fn limit(&self, resource: Resource) -> Option<Limit> {
+        self.limits
+            .iter()
+            .filter(|limit| limit.resource == resource)
+            .min_by_key(|limit| limit.max)
+            .copied()
     }
 
     pub fn scan_tokens(&self) -> TokenStream<'src> {
+        let oversized = self
+            .limit(Resource::Bytes)
+            .filter(|limit| self.source.len() as u64 > limit.max);
         TokenStream {
-            line: 1,
-            chars: self.source.chars().peekable(),
-            lead: None,
+            oversized,
+            max_tokens: self.limit(Resource::Tokens),
+            tokens: 0,
+            chars: Cursor::new(self.source),
+            source: self.source,
+            locator: Locator::new(self.source, self.start.1, self.start.2),
+            file: self.file,
+            offset: self.start.0,
+            start: self.source,
+            column: self.start.2,
             at_end: false,
+            modes: vec![Mode::Normal],
+            import_path: false,
+            dialect: self.dialect,
+            interner: self.interner.clone(),
         }
     }
 }
 
+/// What the characters being scanned belong to. Strings and interpolations
+/// nest inside each other, so a [`TokenStream`] keeps a stack of modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Tokens outside of any string
+    Normal,
+    /// The contents of a string, up to its closing quote or the next `${`
+    InString,
+    /// The expression of an interpolation, with the number of braces opened
+    /// in it and not closed yet
+    InInterpolation { depth: u32 },
+}
+
 pub struct TokenStream<'src> {
     /// The character iterator for the source code being scanned
-    chars: Peekable<Chars<'src>>,
-    /// The leading character for multi-character tokens
-    lead: Option<char>,
-    /// The current line number in the source code
-    line: u32,
+    chars: Cursor<'src>,
+    /// The whole source code being scanned
+    source: &'src str,
+    /// Finds the lines and columns of the tokens
+    locator: Locator<'src>,
+    /// The file token spans point into
+    file: SourceId,
+    /// The byte offset of the source in its file
+    offset: usize,
+    /// The source from the first character of the token being scanned, which
+    /// its lexeme is sliced from
+    start: &'src str,
+    /// The column where the token being scanned starts
+    column: u32,
     /// Whether the end of the token stream has been reached
     at_end: bool,
+    /// The modes entered and not left yet, innermost last
+    modes: Vec<Mode>,
+    /// Whether an `import` was scanned and the string naming its module was
+    /// not yet, which keeps its `${NAME}` variables out of interpolation
+    import_path: bool,
+    /// Language extensions recognized while scanning
+    dialect: Dialect,
+    /// The byte limit the source exceeds, reported instead of any token
+    oversized: Option<Limit>,
+    /// The limit on the number of tokens, if any
+    max_tokens: Option<Limit>,
+    /// The number of tokens scanned so far, not counting `EOF`
+    tokens: u64,
+    /// Where the names of identifiers come from, unless borrowed
+    interner: Interner,
 }
 
+/// A token, owned unless scanned with [`TokenStream::borrowed`], or the
+/// whitespace or comment before one.
 #[derive(Debug)]
-pub enum ScanItem {
-    Token(Token),
+pub enum ScanItem<T = Token> {
+    Token(T),
     Ignore,
 }
 
-impl From<Token> for ScanItem {
-    fn from(token: Token) -> Self {
+/// What a [`TokenStream`] yields for each token, run of whitespace or
+/// comment, or error.
+pub type ScanResult = Result<ScanItem, StaticError>;
+
+impl<T> From<T> for ScanItem<T> {
+    fn from(token: T) -> Self {
         ScanItem::Token(token)
     }
 }
 
 impl<'src> Iterator for TokenStream<'src> {
-    type Item = Result<ScanItem, StaticError>;
+    type Item = ScanResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.next_borrowed()?;
+        Some(item.map(|item| match item {
+            ScanItem::Token(token) => ScanItem::Token(token.into_interned(&self.interner)),
+            ScanItem::Ignore => ScanItem::Ignore,
+        }))
+    }
+}
+
+/// The tokens of a [`TokenStream`] with lexemes borrowed from the source.
+pub struct Borrowed<'src>(TokenStream<'src>);
+
+/// Every token scanned from a source, with the errors found between them.
+#[derive(Debug, Default)]
+pub struct Scanned<'src> {
+    /// The tokens that scanned, ending with `EOF` unless a limit stopped the
+    /// scan
+    pub tokens: Vec<BorrowedToken<'src>>,
+    /// The scan errors, in source order
+    pub errors: Vec<StaticError>,
+}
+
+impl<'src> Borrowed<'src> {
+    /// Scans the rest of the source, skipping whitespace and comments.
+    pub fn scan_all(self) -> Scanned<'src> {
+        let mut scanned = Scanned::default();
+        for item in self {
+            match item {
+                Ok(ScanItem::Token(token)) => scanned.tokens.push(token),
+                Ok(ScanItem::Ignore) => continue,
+                Err(err) => scanned.errors.push(err),
+            }
+        }
+        scanned
+    }
+}
+
+impl Borrowed<'_> {
+    /// Checks whether the next token is outside of any string or
+    /// interpolation, where scanning from it alone yields the same tokens.
+    pub fn at_top_level(&self) -> bool {
+        self.0.modes.len() == 1 && !self.0.import_path
+    }
+}
+
+impl<'src> Iterator for Borrowed<'src> {
+    type Item = Result<ScanItem<BorrowedToken<'src>>, StaticError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_borrowed()
+    }
+}
+
+impl<'src> TokenStream<'src> {
+    /// Yields tokens whose lexemes are slices of the source instead of owned
+    /// strings, for callers that do not keep tokens past the source.
+    pub fn borrowed(self) -> Borrowed<'src> {
+        Borrowed(self)
+    }
+
+    fn next_borrowed(&mut self) -> Option<Result<ScanItem<BorrowedToken<'src>>, StaticError>> {
+        if let Some(limit) = self.oversized.take() {
+            self.at_end = true;
+            return Some(Err(StaticError::limit_exceeded(1, limit)));
+        }
+
+        let item = self.scan()?;
+        if let Ok(ScanItem::Token(token)) = &item
+            && token.typ != TokenType::Eof
+        {
+            self.tokens += 1;
+            if let Some(limit) = self.max_tokens.filter(|limit| self.tokens > limit.max) {
+                self.at_end = true;
+                return Some(Err(StaticError::limit_exceeded(token.line, limit)));
+            }
+        }
+        Some(item)
+    }
+}
+
+impl<'src> TokenStream<'src> {
+    /// Scans the next token, or the whitespace or comment before it.
+    fn scan(&mut self) -> Option<Result<ScanItem<BorrowedToken<'src>>, StaticError>> {
         if self.at_end {
             return None;
         }
 
+        self.start = self.chars.rest();
+        self.column = self.locator.locate(self.offset_of(self.start)).1;
+        let rest = self.start;
+
         let token = match self.chars.next() {
             Some(c) => match c {
-                '(' => self.make_token(TokenType::LeftParen, c),
-                ')' => self.make_token(TokenType::RightParen, c),
-                '{' => self.make_token(TokenType::LeftBrace, c),
-                '}' => self.make_token(TokenType::RightBrace, c),
-                '*' => self.make_token(TokenType::Star, c),
-                '.' => self.make_token(TokenType::Dot, c),
-                ',' => self.make_token(TokenType::Comma, c),
-                '+' => self.make_token(TokenType::Plus, c),
-                '-' => self.make_token(TokenType::Minus, c),
-                ';' => self.make_token(TokenType::Semicolon, c),
+                '(' => self.make_token(TokenType::LeftParen),
+                ')' => self.make_token(TokenType::RightParen),
+                '{' => {
+                    if let Some(Mode::InInterpolation { depth }) = self.modes.last_mut() {
+                        *depth += 1;
+                    }
+                    self.make_token(TokenType::LeftBrace)
+                }
+                '}' => match self.modes.last_mut() {
+                    Some(Mode::InInterpolation { depth: 0 }) => {
+                        // this brace closes an interpolation, resume the enclosing string
+                        self.modes.pop();
+                        return Some(self.string_segment());
+                    }
+                    Some(Mode::InInterpolation { depth }) => {
+                        *depth -= 1;
+                        self.make_token(TokenType::RightBrace)
+                    }
+                    _ => self.make_token(TokenType::RightBrace),
+                },
+                '[' if self.dialect.allows(Extension::Lists)
+                    || self.dialect.allows(Extension::Maps) =>
+                {
+                    self.make_token(TokenType::LeftBracket)
+                }
+                ']' if self.dialect.allows(Extension::Lists)
+                    || self.dialect.allows(Extension::Maps) =>
+                {
+                    self.make_token(TokenType::RightBracket)
+                }
+                ':' if self.dialect.allows(Extension::Maps) => self.make_token(TokenType::Colon),
+                '*' => self.make_token(TokenType::Star),
+                '.' if self.dialect.allows(Extension::Spread)
+                    && self.chars.rest().starts_with("..") =>
+                {
+                    self.chars.by_ref().take(2).for_each(drop);
+                    self.make_token(TokenType::DotDotDot)
+                }
+                '.' if self.dialect.allows(Extension::Ranges) && self.chars.peek() == Some('.') => {
+                    self.chars.next();
+                    match self.next_match('=') {
+                        Some(_) => self.make_token(TokenType::DotDotEqual),
+                        None => self.make_token(TokenType::DotDot),
+                    }
+                }
+                '.' => self.make_token(TokenType::Dot),
+                ',' => self.make_token(TokenType::Comma),
+                '+' => self.make_token(TokenType::Plus),
+                '?' if self.dialect.allows(Extension::NilCoalescing)
+                    && self.chars.peek() == Some('?') =>
+                {
+                    self.chars.next();
+                    self.make_token(TokenType::QuestionQuestion)
+                }
+                '-' => self.make_token(TokenType::Minus),
+                ';' => {
+                    self.import_path = false;
+                    self.make_token(TokenType::Semicolon)
+                }
                 '=' => match self.next_match('=') {
-                    Some(nc) => self.make_token_from(TokenType::EqualEqual, [c, nc]),
-                    None => self.make_token(TokenType::Equal, c),
+                    Some(_) => self.make_token(TokenType::EqualEqual),
+                    None if self.dialect.allows(Extension::Match)
+                        && self.chars.peek() == Some('>') =>
+                    {
+                        self.chars.next();
+                        self.make_token(TokenType::FatArrow)
+                    }
+                    None => self.make_token(TokenType::Equal),
                 },
                 '!' => match self.next_match('=') {
-                    Some(nc) => self.make_token_from(TokenType::BangEqual, [c, nc]),
-                    None => self.make_token(TokenType::Bang, c),
+                    Some(_) => self.make_token(TokenType::BangEqual),
+                    None => self.make_token(TokenType::Bang),
                 },
                 '<' => match self.next_match('=') {
-                    Some(nc) => self.make_token_from(TokenType::LessEqual, [c, nc]),
-                    None => self.make_token(TokenType::Less, c),
+                    Some(_) => self.make_token(TokenType::LessEqual),
+                    None => match self.next_bitwise('<') {
+                        Some(_) => self.make_token(TokenType::LessLess),
+                        None => self.make_token(TokenType::Less),
+                    },
                 },
                 '>' => match self.next_match('=') {
-                    Some(nc) => self.make_token_from(TokenType::GreaterEqual, [c, nc]),
-                    None => self.make_token(TokenType::Greater, c),
+                    Some(_) => self.make_token(TokenType::GreaterEqual),
+                    None => match self.next_bitwise('>') {
+                        Some(_) => self.make_token(TokenType::GreaterGreater),
+                        None => self.make_token(TokenType::Greater),
+                    },
                 },
+                '&' if self.dialect.allows(Extension::Bitwise) => {
+                    self.make_token(TokenType::Ampersand)
+                }
+                '|' if self.dialect.allows(Extension::Bitwise) => self.make_token(TokenType::Pipe),
+                '^' if self.dialect.allows(Extension::Bitwise) => self.make_token(TokenType::Caret),
                 '/' => match self.next_match('/') {
                     Some(_) => {
-                        loop {
-                            let Some(_) = self.chars.next_if(|c| *c != '\n') else {
-                                break;
-                            };
-                        }
+                        while self.chars.next_if(|c| *c != '\n').is_some() {}
                         return Some(Ok(ScanItem::Ignore));
                     }
-                    None => self.make_token(TokenType::Slash, c),
+                    None => self.make_token(TokenType::Slash),
                 },
                 ' ' | '\t' | '\r' => return Some(Ok(ScanItem::Ignore)),
-                '\n' => {
-                    self.line += 1;
-                    return Some(Ok(ScanItem::Ignore));
-                }
+                '\n' => return Some(Ok(ScanItem::Ignore)),
                 '"' => {
-                    self.lead = Some(c);
                     return Some(self.string());
                 }
                 '0'..='9' => {
-                    self.lead = Some(c);
-                    return Some(Ok(self.number()));
+                    return Some(self.number());
                 }
                 '_' | 'a'..='z' | 'A'..='Z' => {
-                    self.lead = Some(c);
+                    return Some(Ok(self.identifier()));
+                }
+                c if self.dialect.allows(Extension::UnicodeIdentifiers)
+                    && unicode_ident::is_xid_start(c) =>
+                {
                     return Some(Ok(self.identifier()));
                 }
                 _ => {
-                    let report =
-                        StaticError::error_at_line(self.line, format!("Unexpected character: {c}"));
+                    // report the whole grapheme cluster, so that an emoji
+                    // sequence or a letter with combining marks stays intact
+                    let cluster = rest.graphemes(true).next().unwrap_or_default();
+                    let rest_of_cluster = cluster.chars().count() - 1;
+                    self.chars.by_ref().take(rest_of_cluster).for_each(drop);
+                    let report = StaticError::error_at_line(
+                        self.line(),
+                        format!("Unexpected character: {cluster}"),
+                    );
                     return Some(Err(report));
                 }
             },
             None => {
+                if self.modes.len() > 1 {
+                    self.modes.truncate(1);
+                    let report = StaticError::error_at_line(
+                        self.line(),
+                        "Unterminated interpolation.".into(),
+                    );
+                    return Some(Err(report));
+                }
                 self.at_end = true;
-                Token::new_eof(self.line)
+                self.make_token(TokenType::Eof)
             }
         };
 
@@ -136,135 +427,314 @@ impl<'src> TokenStream<'src> {
         self.chars.next_if_eq(&expected)
     }
 
+    /// Consume and return the next item if it is equal to expected and the
+    /// `Bitwise` extension is enabled.
+    fn next_bitwise(&mut self, expected: char) -> Option<char> {
+        if !self.dialect.allows(Extension::Bitwise) {
+            return None;
+        }
+        self.next_match(expected)
+    }
+
     /// Peeks at the character after the next one in the stream without consuming any characters.
     /// This method looks ahead two positions in the character stream.
     fn peek_next(&self) -> Option<char> {
         let mut cloned = self.chars.clone();
         cloned.next()?;
-        return cloned.peek().cloned();
+        cloned.peek()
     }
 
     /// Scan an identifier
-    fn identifier(&mut self) -> ScanItem {
-        let lead = self.lead.take().expect("Expected a leading character");
-        let mut lexeme = String::from(lead);
+    fn identifier(&mut self) -> ScanItem<BorrowedToken<'src>> {
+        let unicode = self.dialect.allows(Extension::UnicodeIdentifiers);
+        let exceptions = self.dialect.allows(Extension::Exceptions);
+        let assertions = self.dialect.allows(Extension::Assertions);
+        let modules = self.dialect.allows(Extension::Modules);
+        let matches = self.dialect.allows(Extension::Match);
+        let do_while = self.dialect.allows(Extension::DoWhile);
+        let constants = self.dialect.allows(Extension::Constants);
 
-        while let Some(current) = self
+        while self
             .chars
-            .next_if(|c| *c == '_' || c.is_ascii_alphanumeric())
-        {
-            lexeme.push(current);
-        }
+            .next_if(|c| {
+                *c == '_'
+                    || c.is_ascii_alphanumeric()
+                    || (unicode && unicode_ident::is_xid_continue(*c))
+            })
+            .is_some()
+        {}
 
-        let typ = match lexeme.as_str() {
+        let typ = match self.lexeme() {
             "and" => TokenType::And,
+            "assert" if assertions => TokenType::Assert,
+            "catch" if exceptions => TokenType::Catch,
             "class" => TokenType::Class,
+            "const" if constants => TokenType::Const,
+            "do" if do_while => TokenType::Do,
             "else" => TokenType::Else,
             "false" => TokenType::False,
+            "finally" if exceptions => TokenType::Finally,
             "for" => TokenType::For,
             "fun" => TokenType::Fun,
             "if" => TokenType::If,
+            "import" if modules => TokenType::Import,
+            "match" if matches => TokenType::Match,
             "nil" => TokenType::Nil,
             "or" => TokenType::Or,
             "print" => TokenType::Print,
             "return" => TokenType::Return,
             "super" => TokenType::Super,
             "this" => TokenType::This,
+            "throw" if exceptions => TokenType::Throw,
             "true" => TokenType::True,
+            "try" if exceptions => TokenType::Try,
             "var" => TokenType::Var,
             "while" => TokenType::While,
             _ => TokenType::Identifier,
         };
+        if typ == TokenType::Import {
+            self.import_path = true;
+        }
 
-        let token = self.make_token(typ, lexeme);
-
-        token.into()
+        self.make_token(typ).into()
     }
 
     /// Scan a number token
-    fn number(&mut self) -> ScanItem {
-        let lead = self.lead.take().expect("Expected a leading digit");
-        let mut lexeme = String::from(lead);
+    fn number(&mut self) -> Result<ScanItem<BorrowedToken<'src>>, StaticError> {
+        let extended = self.dialect.allows(Extension::NumericLiterals);
 
-        while let Some(current) = self.chars.next_if(char::is_ascii_digit) {
-            lexeme.push(current);
+        if extended
+            && self.lexeme() == "0"
+            && let Some(prefix) = self.chars.next_if(|c| matches!(c, 'x' | 'X' | 'b' | 'B'))
+        {
+            let radix = if prefix.eq_ignore_ascii_case(&'x') {
+                16
+            } else {
+                2
+            };
+            return self.radix_number(radix);
         }
 
+        self.digits();
+
         if let Some('.') = self.chars.peek()
             && let Some(n) = self.peek_next()
-            && n.is_ascii_digit()
+            && (n.is_ascii_digit() || (extended && n == '_'))
         {
-            // unwrap is safe since peek returned Some('.')
-            lexeme.push(self.chars.next().unwrap());
-            while let Some(current) = self.chars.next_if(char::is_ascii_alphanumeric) {
-                lexeme.push(current);
+            self.chars.next();
+            self.digits();
+        };
+
+        if extended && self.chars.next_if(|c| matches!(c, 'e' | 'E')).is_some() {
+            self.chars.next_if(|c| matches!(c, '+' | '-'));
+            let mantissa_len = self.lexeme().len();
+            self.digits();
+            if self.lexeme().len() == mantissa_len {
+                return Err(self.invalid_number());
             }
+        }
+
+        let lexeme = self.lexeme();
+        if !separators_between(lexeme, |c| c.is_ascii_digit()) {
+            return Err(self.invalid_number());
+        }
+        let digits = without_separators(lexeme);
+
+        let integer = digits
+            .parse::<i64>()
+            .ok()
+            .filter(|_| self.dialect.allows(Extension::Integers));
+        let number = match integer {
+            Some(n) => Value::Integer(n),
+            None => digits
+                .parse::<f64>()
+                .expect("Expected a valid double-precision float")
+                .into(),
         };
+        let token = self.make_literal_token(TokenType::Number, number);
 
-        let number = lexeme
-            .parse::<f64>()
-            .expect("Expected a valid double-precision float");
-        let token = self.make_literal_token(TokenType::Number, lexeme, number.into());
+        Ok(token.into())
+    }
+
+    /// Scan the digits of a hexadecimal or binary number after its `0x`/`0b` prefix.
+    ///
+    /// Every alphanumeric character is consumed so that a literal like `0b102`
+    /// is reported as a whole instead of being split into several tokens.
+    fn radix_number(&mut self, radix: u32) -> Result<ScanItem<BorrowedToken<'src>>, StaticError> {
+        while self
+            .chars
+            .next_if(|c| *c == '_' || c.is_ascii_alphanumeric())
+            .is_some()
+        {}
+
+        let digits = &self.lexeme()[2..];
+        if !separators_between(digits, |c| c.is_ascii_alphanumeric()) {
+            return Err(self.invalid_number());
+        }
+        let Ok(n) = u64::from_str_radix(&without_separators(digits), radix) else {
+            return Err(self.invalid_number());
+        };
+
+        let number = match i64::try_from(n) {
+            Ok(n) if self.dialect.allows(Extension::Integers) => Value::Integer(n),
+            _ => Value::Number(n as f64),
+        };
+        let token = self.make_literal_token(TokenType::Number, number);
 
-        token.into()
+        Ok(token.into())
+    }
+
+    /// Consume a run of decimal digits, including `_` separators when the
+    /// `NumericLiterals` extension is enabled.
+    fn digits(&mut self) {
+        let separators = self.dialect.allows(Extension::NumericLiterals);
+        while self
+            .chars
+            .next_if(|c| c.is_ascii_digit() || (separators && *c == '_'))
+            .is_some()
+        {}
+    }
+
+    /// Creates the error reported for a malformed number literal.
+    fn invalid_number(&self) -> StaticError {
+        let message = format!("Invalid number literal: {}", self.lexeme());
+        StaticError::error_at_line(self.line(), message)
     }
 
     /// Scan a string token
-    fn string(&mut self) -> Result<ScanItem, StaticError> {
-        let lead = self.lead.take().expect("Expected an opening quote");
-        let mut lexeme = String::from(lead);
+    fn string(&mut self) -> Result<ScanItem<BorrowedToken<'src>>, StaticError> {
+        if self.dialect.allows(Extension::TextBlocks) && self.chars.rest().starts_with("\"\"") {
+            return self.text_block();
+        }
+        self.modes.push(Mode::InString);
+        self.string_segment()
+    }
+
+    /// Scan a `"""` text block after its first quote.
+    ///
+    /// The contents are taken verbatim up to the closing `"""`, except for a
+    /// newline directly after the opening quotes, which is dropped so that the
+    /// text can start on its own line.
+    fn text_block(&mut self) -> Result<ScanItem<BorrowedToken<'src>>, StaticError> {
+        const DELIMITER: &str = "\"\"\"";
+
+        self.chars.by_ref().take(2).for_each(drop);
+
+        while !self.chars.rest().starts_with(DELIMITER) {
+            if self.chars.next().is_none() {
+                let report =
+                    StaticError::error_at_line(self.line(), "Unterminated text block.".into());
+                return Err(report);
+            }
+        }
+        self.chars.by_ref().take(3).for_each(drop);
+
+        let lexeme = self.lexeme();
+        let contents = &lexeme[3..lexeme.len() - 3];
+        let contents = contents.strip_prefix('\n').unwrap_or(contents);
+        let token = self.make_literal_token(TokenType::String, contents.into());
+
+        Ok(token.into())
+    }
+
+    /// Scan string contents following the opening quote or the `}` closing an
+    /// interpolation, which has already been consumed, in [`Mode::InString`].
+    ///
+    /// With the `Interpolation` extension, scanning stops after a `${` and
+    /// produces an INTERPOLATION token; the embedded expression is scanned as
+    /// regular tokens in [`Mode::InInterpolation`] until its matching `}`.
+    /// The path of an import is never interpolated, since the module loader
+    /// expands the `${NAME}` in it from the environment.
+    /// The string is left at its closing quote, or at the end of the input.
+    fn string_segment(&mut self) -> Result<ScanItem<BorrowedToken<'src>>, StaticError> {
+        let interpolation =
+            self.dialect.allows(Extension::Interpolation) && !std::mem::take(&mut self.import_path);
 
         while let Some(current) = self.chars.next_if(|c| *c != '"') {
-            if current == '\n' {
-                self.line += 1;
+            if interpolation && current == '$' && self.next_match('{').is_some() {
+                self.modes.push(Mode::InInterpolation { depth: 0 });
+                let lexeme = self.lexeme();
+                let literal = Value::from(&lexeme[1..lexeme.len() - 2]);
+                let token = self.make_literal_token(TokenType::Interpolation, literal);
+                return Ok(token.into());
             }
-            lexeme.push(current);
         }
 
+        self.modes.pop();
         // reached the end of the input without finding a closing quote
-        if self.chars.peek().is_none() {
-            let report = StaticError::error_at_line(self.line, "Unterminated string.".into());
+        if self.chars.next().is_none() {
+            let report = StaticError::error_at_line(self.line(), "Unterminated string.".into());
             return Err(report);
-        } else {
-            // consume the closing quote
-            // unwrap is safe since peek returned Some(_)
-            lexeme.push(self.chars.next().unwrap());
         }
 
+        let lexeme = self.lexeme();
         let literal = Value::from(&lexeme[1..lexeme.len() - 1]);
-        let token = self.make_literal_token(TokenType::String, lexeme, literal);
+        let token = self.make_literal_token(TokenType::String, literal);
 
         Ok(token.into())
     }
 
-    /// Creates a token at the current line with no literal value.
-    fn make_token(&self, typ: TokenType, lexeme: impl Into<String>) -> Token {
-        Token::new(typ, lexeme.into(), None, self.line)
+    /// Returns the source text of the token scanned so far.
+    fn lexeme(&self) -> &'src str {
+        &self.start[..self.start.len() - self.chars.rest().len()]
     }
 
-    /// Creates a token from items that can be collected into a String.
-    /// Useful for building lexemes from character iterators or arrays.
-    fn make_token_from<T, I>(&self, typ: TokenType, value: I) -> Token
-    where
-        I: IntoIterator<Item = T>,
-        String: FromIterator<T>,
-    {
-        let lexeme = value.into_iter().collect::<String>();
-        self.make_token(typ, lexeme)
+    /// Returns the byte offset in the source of `rest`, a suffix of it.
+    fn offset_of(&self, rest: &str) -> usize {
+        self.source.len() - rest.len()
+    }
+
+    /// Returns the line of the next character.
+    fn line(&self) -> u32 {
+        self.locator.line(self.offset_of(self.chars.rest()))
+    }
+
+    /// Creates a token at the current line with no literal value.
+    fn make_token(&self, typ: TokenType) -> BorrowedToken<'src> {
+        let start = self.offset + self.offset_of(self.start);
+        let end = self.offset + self.offset_of(self.chars.rest());
+        BorrowedToken {
+            typ,
+            lexeme: self.lexeme(),
+            literal: None,
+            line: self.line(),
+            column: self.column,
+            span: Span::new(self.file, start, end),
+        }
     }
 
     /// Creates a token with an associated literal value (e.g., the numeric value for NUMBER tokens,
     /// the string content for STRING tokens).
-    fn make_literal_token(
-        &self,
-        typ: TokenType,
-        lexeme: impl Into<String>,
-        literal: Value,
-    ) -> Token {
-        Token::new(typ, lexeme.into(), Some(literal), self.line)
+    fn make_literal_token(&self, typ: TokenType, literal: Value) -> BorrowedToken<'src> {
+        BorrowedToken {
+            literal: Some(literal),
+            ..self.make_token(typ)
+        }
+    }
+}
+
+/// Returns `literal` without its `_` separators, borrowing it when it has
+/// none.
+fn without_separators(literal: &str) -> Cow<'_, str> {
+    if literal.contains('_') {
+        Cow::Owned(literal.replace('_', ""))
+    } else {
+        Cow::Borrowed(literal)
     }
 }
 
+/// Checks that every `_` separator in a number literal sits between two digits,
+/// so it is never leading, trailing, doubled or next to the decimal point.
+fn separators_between(literal: &str, is_digit: impl Fn(char) -> bool) -> bool {
+    let chars = literal.chars().collect::<Vec<_>>();
+    chars.iter().enumerate().all(|(i, c)| {
+        *c != '_'
+            || (i > 0
+                && is_digit(chars[i - 1])
+                && chars.get(i + 1).is_some_and(|next| is_digit(*next)))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use rstest::rstest;
@@ -776,7 +1246,593 @@ mod tests {
     ])]
     fn test_scanner(#[case] input: &str, #[case] expected_output: Vec<&str>) {
         let scanner = Scanner::new(input);
+        assert_eq!(render(&scanner), expected_output);
+    }
 
+    #[rstest]
+    #[case("& | ^", vec![
+        "AMPERSAND & null",
+        "PIPE | null",
+        "CARET ^ null",
+        "EOF  null",
+    ])]
+    #[case("<<<=>>>=", vec![
+        "LESS_LESS << null",
+        "LESS_EQUAL <= null",
+        "GREATER_GREATER >> null",
+        "GREATER_EQUAL >= null",
+        "EOF  null",
+    ])]
+    fn test_scanner_bitwise(#[case] input: &str, #[case] expected_output: Vec<&str>) {
+        let scanner = Scanner::new(input).with_dialect(Dialect::new().with(Extension::Bitwise));
+        assert_eq!(render(&scanner), expected_output);
+    }
+
+    #[rstest]
+    #[case("42 4.5 007", vec![
+        "NUMBER 42 42",
+        "NUMBER 4.5 4.5",
+        "NUMBER 007 7",
+        "EOF  null",
+    ])]
+    #[case("99999999999999999999", vec![
+        "NUMBER 99999999999999999999 1e20",
+        "EOF  null",
+    ])]
+    fn test_scanner_integers(#[case] input: &str, #[case] expected_output: Vec<&str>) {
+        let scanner = Scanner::new(input).with_dialect(Dialect::new().with(Extension::Integers));
+        assert_eq!(render(&scanner), expected_output);
+    }
+
+    #[rstest]
+    #[case(r#""a ${x} b""#, vec![
+        "INTERPOLATION \"a ${ a ",
+        "IDENTIFIER x null",
+        "STRING } b\"  b",
+        "EOF  null",
+    ])]
+    #[case(r#""${ {} }${"in${1}"}""#, vec![
+        "INTERPOLATION \"${ ",
+        "LEFT_BRACE { null",
+        "RIGHT_BRACE } null",
+        "INTERPOLATION }${ ",
+        "INTERPOLATION \"in${ in",
+        "NUMBER 1 1.0",
+        "STRING }\" ",
+        "STRING }\" ",
+        "EOF  null",
+    ])]
+    #[case(r#""${x""#, vec![
+        "INTERPOLATION \"${ ",
+        "IDENTIFIER x null",
+        "[line 1] Error: Unterminated string.",
+        "[line 1] Error: Unterminated interpolation.",
+        "EOF  null",
+    ])]
+    #[case(r#""a ${x} b"#, vec![
+        "INTERPOLATION \"a ${ a ",
+        "IDENTIFIER x null",
+        "[line 1] Error: Unterminated string.",
+        "EOF  null",
+    ])]
+    fn test_scanner_interpolation(#[case] input: &str, #[case] expected_output: Vec<&str>) {
+        let dialect = Dialect::new().with(Extension::Interpolation);
+        let scanner = Scanner::new(input).with_dialect(dialect);
+        assert_eq!(render(&scanner), expected_output);
+    }
+
+    #[rstest]
+    #[case(r#"import "${LIB}/g.lox"; "${x}";"#, vec![
+        "IMPORT import null",
+        "STRING \"${LIB}/g.lox\" ${LIB}/g.lox",
+        "SEMICOLON ; null",
+        "INTERPOLATION \"${ ",
+        "IDENTIFIER x null",
+        "STRING }\" ",
+        "SEMICOLON ; null",
+        "EOF  null",
+    ])]
+    #[case(r#"import g, h from "${LIB}/g.lox";"#, vec![
+        "IMPORT import null",
+        "IDENTIFIER g null",
+        "COMMA , null",
+        "IDENTIFIER h null",
+        "IDENTIFIER from null",
+        "STRING \"${LIB}/g.lox\" ${LIB}/g.lox",
+        "SEMICOLON ; null",
+        "EOF  null",
+    ])]
+    fn test_scanner_import_path_is_not_interpolated(
+        #[case] input: &str,
+        #[case] expected_output: Vec<&str>,
+    ) {
+        let dialect = Dialect::new()
+            .with(Extension::Interpolation)
+            .with(Extension::Modules);
+        let scanner = Scanner::new(input).with_dialect(dialect);
+        assert_eq!(render(&scanner), expected_output);
+    }
+
+    #[test]
+    fn test_scanner_modes_nest() {
+        use Mode::{InInterpolation, InString, Normal};
+
+        let dialect = Dialect::new().with(Extension::Interpolation);
+        let scanner = Scanner::new(r#""a${ {"b${x}"} }""#).with_dialect(dialect);
+        let mut tokens = scanner.scan_tokens();
+        let mut modes = Vec::new();
+        while let Some(item) = tokens.next() {
+            if let Ok(ScanItem::Token(token)) = item {
+                modes.push((token.lexeme.to_string(), tokens.modes.clone()));
+            }
+        }
+
+        let interpolation = |depth| InInterpolation { depth };
+        let expected = vec![
+            ("\"a${", vec![Normal, InString, interpolation(0)]),
+            ("{", vec![Normal, InString, interpolation(1)]),
+            (
+                "\"b${",
+                vec![
+                    Normal,
+                    InString,
+                    interpolation(1),
+                    InString,
+                    interpolation(0),
+                ],
+            ),
+            (
+                "x",
+                vec![
+                    Normal,
+                    InString,
+                    interpolation(1),
+                    InString,
+                    interpolation(0),
+                ],
+            ),
+            ("}\"", vec![Normal, InString, interpolation(1)]),
+            ("}", vec![Normal, InString, interpolation(0)]),
+            ("}\"", vec![Normal]),
+            ("", vec![Normal]),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(lexeme, modes)| (lexeme.to_string(), modes))
+            .collect();
+        assert_eq!(expected, modes);
+    }
+
+    #[rstest]
+    #[case("0xFF 0Xff 0b1010 0B0", vec![
+        "NUMBER 0xFF 255.0",
+        "NUMBER 0Xff 255.0",
+        "NUMBER 0b1010 10.0",
+        "NUMBER 0B0 0.0",
+        "EOF  null",
+    ])]
+    #[case("1e9 1.5e-3 2E+2 7", vec![
+        "NUMBER 1e9 1000000000.0",
+        "NUMBER 1.5e-3 0.0015",
+        "NUMBER 2E+2 200.0",
+        "NUMBER 7 7.0",
+        "EOF  null",
+    ])]
+    #[case("0x\n0b102\n0xFG\n1e\n2e+;", vec![
+        "[line 1] Error: Invalid number literal: 0x",
+        "[line 2] Error: Invalid number literal: 0b102",
+        "[line 3] Error: Invalid number literal: 0xFG",
+        "[line 4] Error: Invalid number literal: 1e",
+        "[line 5] Error: Invalid number literal: 2e+",
+        "SEMICOLON ; null",
+        "EOF  null",
+    ])]
+    #[case("1_000_000.50 0xFF_FF 0b1010_0101 1e1_0", vec![
+        "NUMBER 1_000_000.50 1000000.5",
+        "NUMBER 0xFF_FF 65535.0",
+        "NUMBER 0b1010_0101 165.0",
+        "NUMBER 1e1_0 10000000000.0",
+        "EOF  null",
+    ])]
+    #[case("1_\n1__0\n1_.5\n1._5\n0x_FF\n1_e5", vec![
+        "[line 1] Error: Invalid number literal: 1_",
+        "[line 2] Error: Invalid number literal: 1__0",
+        "[line 3] Error: Invalid number literal: 1_.5",
+        "[line 4] Error: Invalid number literal: 1._5",
+        "[line 5] Error: Invalid number literal: 0x_FF",
+        "[line 6] Error: Invalid number literal: 1_e5",
+        "EOF  null",
+    ])]
+    fn test_scanner_numeric_literals(#[case] input: &str, #[case] expected_output: Vec<&str>) {
+        let dialect = Dialect::new().with(Extension::NumericLiterals);
+        let scanner = Scanner::new(input).with_dialect(dialect);
+        assert_eq!(render(&scanner), expected_output);
+    }
+
+    #[rstest]
+    #[case("0xFF", vec![
+        "NUMBER 0 0.0",
+        "IDENTIFIER xFF null",
+        "EOF  null",
+    ])]
+    #[case("1.5e3", vec![
+        "NUMBER 1.5 1.5",
+        "IDENTIFIER e3 null",
+        "EOF  null",
+    ])]
+    #[case("1_000", vec![
+        "NUMBER 1 1.0",
+        "IDENTIFIER _000 null",
+        "EOF  null",
+    ])]
+    fn test_scanner_numeric_literals_disabled_by_default(
+        #[case] input: &str,
+        #[case] expected_output: Vec<&str>,
+    ) {
+        assert_eq!(render(&Scanner::new(input)), expected_output);
+    }
+
+    #[rstest]
+    #[case(r#""""""""#, vec![
+        r#"STRING """""" "#,
+        "EOF  null",
+    ])]
+    #[case("\"\"\"\nline one\n  \"quoted\" ${x}\n\"\"\" 1", vec![
+        "STRING \"\"\"\nline one\n  \"quoted\" ${x}\n\"\"\" line one\n  \"quoted\" ${x}\n",
+        "NUMBER 1 1.0",
+        "EOF  null",
+    ])]
+    #[case("\"\"\"a\nb", vec![
+        "[line 2] Error: Unterminated text block.",
+        "EOF  null",
+    ])]
+    #[case(r#""" "a""#, vec![
+        "STRING \"\" ",
+        "STRING \"a\" a",
+        "EOF  null",
+    ])]
+    fn test_scanner_text_blocks(#[case] input: &str, #[case] expected_output: Vec<&str>) {
+        let dialect = Dialect::new()
+            .with(Extension::TextBlocks)
+            .with(Extension::Interpolation);
+        assert_eq!(
+            render(&Scanner::new(input).with_dialect(dialect)),
+            expected_output
+        );
+    }
+
+    #[test]
+    fn test_scanner_text_block_counts_lines() {
+        let dialect = Dialect::new().with(Extension::TextBlocks);
+        let lines = Scanner::new("\"\"\"\n\n\n\"\"\" x")
+            .with_dialect(dialect)
+            .scan_tokens()
+            .filter_map(|res| match res {
+                Ok(ScanItem::Token(t)) => Some(t.line),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![4, 4, 4]);
+    }
+
+    #[rstest]
+    #[case("f(...xs)", vec![
+        "IDENTIFIER f null",
+        "LEFT_PAREN ( null",
+        "DOT_DOT_DOT ... null",
+        "IDENTIFIER xs null",
+        "RIGHT_PAREN ) null",
+        "EOF  null",
+    ], true)]
+    #[case(".. ....", vec![
+        "DOT . null",
+        "DOT . null",
+        "DOT_DOT_DOT ... null",
+        "DOT . null",
+        "EOF  null",
+    ], true)]
+    #[case("...", vec![
+        "DOT . null",
+        "DOT . null",
+        "DOT . null",
+        "EOF  null",
+    ], false)]
+    fn test_scanner_spread(
+        #[case] input: &str,
+        #[case] expected_output: Vec<&str>,
+        #[case] enabled: bool,
+    ) {
+        let mut dialect = Dialect::new();
+        if enabled {
+            dialect.enable(Extension::Spread);
+        }
+        assert_eq!(
+            render(&Scanner::new(input).with_dialect(dialect)),
+            expected_output
+        );
+    }
+
+    #[rstest]
+    #[case("0..n", vec![
+        "NUMBER 0 0.0",
+        "DOT_DOT .. null",
+        "IDENTIFIER n null",
+        "EOF  null",
+    ], Dialect::new().with(Extension::Ranges))]
+    #[case("1..=3.5", vec![
+        "NUMBER 1 1.0",
+        "DOT_DOT_EQUAL ..= null",
+        "NUMBER 3.5 3.5",
+        "EOF  null",
+    ], Dialect::new().with(Extension::Ranges))]
+    #[case("[...0..2]", vec![
+        "LEFT_BRACKET [ null",
+        "DOT_DOT_DOT ... null",
+        "NUMBER 0 0.0",
+        "DOT_DOT .. null",
+        "NUMBER 2 2.0",
+        "RIGHT_BRACKET ] null",
+        "EOF  null",
+    ], [Extension::Lists, Extension::Spread, Extension::Ranges].into_iter().collect())]
+    #[case("0..1", vec![
+        "NUMBER 0 0.0",
+        "DOT . null",
+        "DOT . null",
+        "NUMBER 1 1.0",
+        "EOF  null",
+    ], Dialect::new())]
+    fn test_scanner_ranges(
+        #[case] input: &str,
+        #[case] expected_output: Vec<&str>,
+        #[case] dialect: Dialect,
+    ) {
+        assert_eq!(
+            render(&Scanner::new(input).with_dialect(dialect)),
+            expected_output
+        );
+    }
+
+    #[rstest]
+    #[case(Dialect::new(), ["IDENTIFIER"; 4])]
+    #[case(
+        Dialect::new().with(Extension::Exceptions),
+        ["TRY", "CATCH", "FINALLY", "THROW"]
+    )]
+    fn test_scanner_exception_keywords(#[case] dialect: Dialect, #[case] expected: [&str; 4]) {
+        let types = Scanner::new("try catch finally throw")
+            .with_dialect(dialect)
+            .scan_tokens()
+            .filter_map(|res| match res {
+                Ok(ScanItem::Token(t)) if t.typ != TokenType::Eof => Some(t.typ.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(expected.to_vec(), types);
+    }
+
+    #[rstest]
+    #[case(Dialect::new(), "IDENTIFIER assert null")]
+    #[case(Dialect::new().with(Extension::Assertions), "ASSERT assert null")]
+    fn test_scanner_assert_keyword(#[case] dialect: Dialect, #[case] expected: &str) {
+        let scanner = Scanner::new("assert").with_dialect(dialect);
+        assert_eq!(expected, render(&scanner)[0]);
+    }
+
+    #[rstest]
+    #[case(Resource::Tokens, 2, 2, true)]
+    #[case(Resource::Tokens, 3, 3, false)]
+    #[case(Resource::Bytes, 5, 0, true)]
+    #[case(Resource::Bytes, 6, 3, false)]
+    #[case(Resource::Statements, 0, 3, false)]
+    fn test_scanner_limits(
+        #[case] resource: Resource,
+        #[case] max: u64,
+        #[case] scanned: usize,
+        #[case] exceeded: bool,
+    ) {
+        let limit = Limit { resource, max };
+        let items = Scanner::new("1 + 2\n")
+            .with_limits([limit])
+            .scan_tokens()
+            .collect::<Vec<_>>();
+
+        let tokens = items
+            .iter()
+            .filter(|item| matches!(item, Ok(ScanItem::Token(t)) if t.typ != TokenType::Eof))
+            .count();
+        assert_eq!(scanned, tokens);
+        let err = items.last().unwrap().as_ref().err();
+        assert_eq!(
+            exceeded,
+            err.and_then(StaticError::exceeded_limit) == Some(limit)
+        );
+    }
+
+    #[rstest]
+    #[case(Dialect::new(), vec!["EQUAL = null", "GREATER > null", "EQUAL_EQUAL == null"])]
+    #[case(Dialect::new().with(Extension::Match), vec!["FAT_ARROW => null", "EQUAL_EQUAL == null"])]
+    fn test_scanner_fat_arrow(#[case] dialect: Dialect, #[case] expected: Vec<&str>) {
+        let mut tokens = render(&Scanner::new("=> ==").with_dialect(dialect));
+        tokens.pop();
+        assert_eq!(expected, tokens);
+    }
+
+    #[rstest]
+    #[case(Dialect::new(), None)]
+    #[case(Dialect::new().with(Extension::NilCoalescing), Some("QUESTION_QUESTION ?? null"))]
+    fn test_scanner_question_question(#[case] dialect: Dialect, #[case] expected: Option<&str>) {
+        let scanner = Scanner::new("a ?? b").with_dialect(dialect);
+        let items: Vec<_> = scanner.scan_tokens().collect();
+        match expected {
+            Some(expected) => assert_eq!(expected, render(&scanner)[1]),
+            None => assert!(items.iter().any(Result::is_err)),
+        }
+    }
+
+    #[test]
+    fn test_scanner_lists() {
+        let scanner =
+            Scanner::new("xs[0] = [];").with_dialect(Dialect::new().with(Extension::Lists));
+        assert_eq!(
+            render(&scanner),
+            vec![
+                "IDENTIFIER xs null",
+                "LEFT_BRACKET [ null",
+                "NUMBER 0 0.0",
+                "RIGHT_BRACKET ] null",
+                "EQUAL = null",
+                "LEFT_BRACKET [ null",
+                "RIGHT_BRACKET ] null",
+                "SEMICOLON ; null",
+                "EOF  null",
+            ]
+        );
+        assert_eq!(
+            render(&Scanner::new("[]")),
+            vec![
+                "[line 1] Error: Unexpected character: [",
+                "[line 1] Error: Unexpected character: ]",
+                "EOF  null",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scanner_bitwise_disabled_by_default() {
+        let scanner = Scanner::new("&");
+        assert_eq!(
+            render(&scanner),
+            vec!["[line 1] Error: Unexpected character: &", "EOF  null"]
+        );
+    }
+
+    #[rstest]
+    #[case("var 变量 = 1;", vec![
+        "VAR var null",
+        "IDENTIFIER 变量 null",
+        "EQUAL = null",
+        "NUMBER 1 1.0",
+        "SEMICOLON ; null",
+        "EOF  null",
+    ])]
+    #[case("café naïve_2 Ωmega", vec![
+        "IDENTIFIER café null",
+        "IDENTIFIER naïve_2 null",
+        "IDENTIFIER Ωmega null",
+        "EOF  null",
+    ])]
+    #[case("e\u{301}t\u{e9}", vec![
+        "IDENTIFIER e\u{301}t\u{e9} null",
+        "EOF  null",
+    ])]
+    #[case("x🎉y", vec![
+        "IDENTIFIER x null",
+        "[line 1] Error: Unexpected character: 🎉",
+        "IDENTIFIER y null",
+        "EOF  null",
+    ])]
+    fn test_scanner_unicode_identifiers(#[case] input: &str, #[case] expected_output: Vec<&str>) {
+        let scanner =
+            Scanner::new(input).with_dialect(Dialect::new().with(Extension::UnicodeIdentifiers));
+        assert_eq!(render(&scanner), expected_output);
+    }
+
+    #[rstest]
+    #[case("变量", vec![
+        "[line 1] Error: Unexpected character: 变",
+        "[line 1] Error: Unexpected character: 量",
+        "EOF  null",
+    ])]
+    #[case("👨\u{200d}👩\u{200d}👧", vec![
+        "[line 1] Error: Unexpected character: 👨\u{200d}👩\u{200d}👧",
+        "EOF  null",
+    ])]
+    #[case("🇫🇷 + \u{301}", vec![
+        "[line 1] Error: Unexpected character: 🇫🇷",
+        "PLUS + null",
+        "[line 1] Error: Unexpected character: \u{301}",
+        "EOF  null",
+    ])]
+    fn test_scanner_unexpected_grapheme_clusters(
+        #[case] input: &str,
+        #[case] expected_output: Vec<&str>,
+    ) {
+        assert_eq!(render(&Scanner::new(input)), expected_output);
+    }
+
+    #[test]
+    fn test_scanner_columns_count_characters() {
+        let dialect = Dialect::new().with(Extension::UnicodeIdentifiers);
+        let scanner = Scanner::new("var 名前 = \"héllo\";\n  print 名前;").with_dialect(dialect);
+        let positions = scanner
+            .scan_tokens()
+            .filter_map(|res| match res {
+                Ok(ScanItem::Token(t)) => Some((t.lexeme.to_string(), t.line, t.column)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let expected = [
+            ("var", 1, 1),
+            ("名前", 1, 5),
+            ("=", 1, 8),
+            ("\"héllo\"", 1, 10),
+            (";", 1, 17),
+            ("print", 2, 3),
+            ("名前", 2, 9),
+            (";", 2, 11),
+            ("", 2, 12),
+        ];
+        let expected = expected
+            .map(|(lexeme, line, column)| (lexeme.to_string(), line, column))
+            .to_vec();
+        assert_eq!(positions, expected);
+    }
+
+    #[test]
+    fn test_scanner_borrowed_lexemes_slice_the_source() {
+        let source = "var s = \"a${1_000 + 0x1F}b\";\nprint s == \"\"\"\ntext\"\"\";";
+        let dialect: Dialect = [
+            Extension::Interpolation,
+            Extension::NumericLiterals,
+            Extension::TextBlocks,
+        ]
+        .into_iter()
+        .collect();
+        let scanner = Scanner::new(source).with_dialect(dialect);
+
+        let source_range = source.as_bytes().as_ptr_range();
+        let mut borrowed = Vec::new();
+        for item in scanner.scan_tokens().borrowed() {
+            if let ScanItem::Token(token) = item.unwrap() {
+                assert!(source_range.contains(&token.lexeme.as_ptr()) || token.lexeme.is_empty());
+                borrowed.push(token.to_string());
+            }
+        }
+        assert_eq!(render(&scanner), borrowed);
+        assert_eq!("NUMBER 1_000 1000.0", borrowed[4]);
+    }
+
+    #[test]
+    fn test_scan_all_keeps_going_after_errors() {
+        let scanned = Scanner::new("1 @ 2 #").scan_tokens().borrowed().scan_all();
+
+        let tokens = scanned.tokens.iter().map(ToString::to_string);
+        assert_eq!(
+            vec!["NUMBER 1 1.0", "NUMBER 2 2.0", "EOF  null"],
+            tokens.collect::<Vec<_>>()
+        );
+        let errors = scanned.errors.iter().map(ToString::to_string);
+        assert_eq!(
+            vec![
+                "[line 1] Error: Unexpected character: @",
+                "[line 1] Error: Unexpected character: #"
+            ],
+            errors.collect::<Vec<_>>()
+        );
+    }
+
+    fn render(scanner: &Scanner) -> Vec<String> {
         let mut output = Vec::new();
         for res in scanner.scan_tokens() {
             let s = match res {
@@ -786,7 +1842,6 @@ mod tests {
             };
             output.push(s);
         }
-
-        assert_eq!(output, expected_output);
+        output
     }
 }
@@ -0,0 +1,125 @@
+//! Tokens as structured records, for tools such as syntax highlighters that
+//! read the output of the tokenize subcommand.
+//!
+//! A record holds the type, lexeme and literal of a token, the line and
+//! column it starts at, and its span: the byte range of the lexeme in the
+//! source.
+
+use std::ops::Range;
+
+use serde_json::{Value as Json, json};
+
+use crate::scanner::token::BorrowedToken;
+
+/// How the tokenize subcommand prints tokens, selectable with `--format`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TokenFormat {
+    /// `NUMBER 1 1.0`, like the reference implementation.
+    #[default]
+    Text,
+    /// An array with an object for each token.
+    Json,
+    /// A header row, then a row for each token.
+    Csv,
+}
+
+/// The header row of the csv format, naming the fields of [`csv`].
+pub const CSV_HEADER: &str = "type,lexeme,literal,line,column,start,end";
+
+/// Returns the byte range of the lexeme of `token` in `source`, which the
+/// token must have been scanned from.
+pub fn span(token: &BorrowedToken<'_>, source: &str) -> Range<usize> {
+    let start = token.lexeme.as_ptr() as usize - source.as_ptr() as usize;
+    start..start + token.lexeme.len()
+}
+
+/// Describes `token`, scanned from `source`, as a JSON object. The literal
+/// keeps its JSON type, or is `null` for tokens without one.
+pub fn json(token: &BorrowedToken<'_>, source: &str) -> Json {
+    let span = span(token, source);
+    json!({
+        "type": token.typ.to_string(),
+        "lexeme": token.lexeme,
+        "literal": token.literal.as_ref().map_or(Json::Null, crate::parser::json::value),
+        "line": token.line,
+        "column": token.column,
+        "span": {"start": span.start, "end": span.end},
+    })
+}
+
+/// Describes `token`, scanned from `source`, as a csv row without its line
+/// break. The literal is empty for tokens without one.
+pub fn csv(token: &BorrowedToken<'_>, source: &str) -> String {
+    let span = span(token, source);
+    let literal = token.literal.as_ref().map(|l| format!("{l:?}"));
+    let fields = [
+        token.typ.to_string(),
+        token.lexeme.to_string(),
+        literal.unwrap_or_default(),
+        token.line.to_string(),
+        token.column.to_string(),
+        span.start.to_string(),
+        span.end.to_string(),
+    ];
+    let fields = fields.iter().map(|field| csv_field(field));
+    fields.collect::<Vec<_>>().join(",")
+}
+
+/// Quotes `field` if it holds a comma, quote or line break, doubling its
+/// quotes.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{ScanItem, Scanner};
+
+    fn tokens(source: &str) -> Vec<BorrowedToken<'_>> {
+        let items = Scanner::new(source).scan_tokens().borrowed();
+        items
+            .filter_map(|item| match item.unwrap() {
+                ScanItem::Token(token) => Some(token),
+                ScanItem::Ignore => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_token_records() {
+        let source = "x = \"a, \"\"b\";\n1.5";
+        let tokens = tokens(source);
+
+        let rows = tokens.iter().map(|token| csv(token, source));
+        assert_eq!(
+            vec![
+                "IDENTIFIER,x,,1,1,0,1",
+                "EQUAL,=,,1,3,2,3",
+                "STRING,\"\"\"a, \"\"\",\"a, \",1,5,4,9",
+                "STRING,\"\"\"b\"\"\",b,1,10,9,12",
+                "SEMICOLON,;,,1,13,12,13",
+                "NUMBER,1.5,1.5,2,1,14,17",
+                "EOF,,,2,4,17,17",
+            ],
+            rows.collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            json!({
+                "type": "NUMBER",
+                "lexeme": "1.5",
+                "literal": 1.5,
+                "line": 2,
+                "column": 1,
+                "span": {"start": 14, "end": 17},
+            }),
+            json(&tokens[5], source)
+        );
+        assert_eq!(Json::Null, json(&tokens[0], source)["literal"]);
+    }
+}
@@ -2,53 +2,144 @@ use std::borrow::Cow;
 use std::fmt::Display;
 
 use crate::Value;
+use crate::source::Span;
+use crate::symbol::{Interner, Symbol};
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub typ: TokenType,
-    pub lexeme: String,
+    /// The source text of the token, shared with the copies of the token
+    pub lexeme: Symbol,
     pub literal: Option<Value>,
     pub line: u32,
+    /// The 1-based column of the token's first character, counted in `char`s,
+    /// or `0` for tokens that do not come from source code
+    pub column: u32,
+    /// The bytes of the source file the token was scanned from, or `None`
+    /// for tokens that do not come from source code
+    pub span: Option<Span>,
 }
 
 impl Token {
-    pub fn new(typ: TokenType, lexeme: String, literal: Option<Value>, line: u32) -> Self {
+    pub fn new(typ: TokenType, lexeme: Symbol, literal: Option<Value>, line: u32) -> Self {
         Self {
             typ,
-            lexeme: lexeme.into(),
+            lexeme,
             literal,
             line,
+            column: 0,
+            span: None,
         }
     }
 
+    /// Returns the lexeme as the name scopes are keyed by.
+    pub fn symbol(&self) -> Symbol {
+        self.lexeme.clone()
+    }
+
+    /// Returns this token positioned at `column`.
+    pub fn with_column(mut self, column: u32) -> Self {
+        self.column = column;
+        self
+    }
+
+    /// Returns this token covering `span` of its source file.
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        self.span = span;
+        self
+    }
+
     /// Return a new EOF token with the given line number.
     pub fn new_eof(line: u32) -> Self {
-        Self::new(TokenType::Eof, "".to_string(), None, line)
+        Self::new(TokenType::Eof, "".into(), None, line)
     }
 }
 
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let literal = match &self.literal {
-            Some(l) => Cow::Owned(format!("{:?}", l)),
-            None => Cow::Borrowed("null"),
+        write_token(f, self.typ, &self.lexeme, self.literal.as_ref())
+    }
+}
+
+/// Writes a token in the format of the tokenize subcommand.
+fn write_token(
+    f: &mut std::fmt::Formatter<'_>,
+    typ: TokenType,
+    lexeme: &str,
+    literal: Option<&Value>,
+) -> std::fmt::Result {
+    let literal = match literal {
+        Some(l) => Cow::Owned(format!("{:?}", l)),
+        None => Cow::Borrowed("null"),
+    };
+
+    write!(f, "{} {} {}", typ, lexeme, literal)
+}
+
+/// A token whose lexeme is a slice of the source it was scanned from, see
+/// [`TokenStream::borrowed`](super::TokenStream::borrowed).
+#[derive(Debug, Clone)]
+pub struct BorrowedToken<'src> {
+    pub typ: TokenType,
+    pub lexeme: &'src str,
+    pub literal: Option<Value>,
+    pub line: u32,
+    pub column: u32,
+    pub span: Span,
+}
+
+impl BorrowedToken<'_> {
+    /// Copies the lexeme into a [`Token`] that outlives the source.
+    pub fn into_owned(self) -> Token {
+        Token::new(self.typ, self.lexeme.into(), self.literal, self.line)
+            .with_column(self.column)
+            .with_span(Some(self.span))
+    }
+
+    /// Copies the lexeme into a [`Token`] like [`Self::into_owned`], taking
+    /// the name of an identifier from `interner`.
+    pub fn into_interned(self, interner: &Interner) -> Token {
+        let lexeme = match self.typ {
+            TokenType::Identifier => interner.intern(self.lexeme),
+            _ => self.lexeme.into(),
         };
+        Token::new(self.typ, lexeme, self.literal, self.line)
+            .with_column(self.column)
+            .with_span(Some(self.span))
+    }
+}
+
+impl From<BorrowedToken<'_>> for Token {
+    fn from(token: BorrowedToken<'_>) -> Self {
+        token.into_owned()
+    }
+}
 
-        write!(f, "{} {} {}", self.typ, self.lexeme, literal)
+impl Display for BorrowedToken<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_token(f, self.typ, self.lexeme, self.literal.as_ref())
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum TokenType {
     // Single-character tokens.
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
+    DotDotDot,
+    DotDot,
+    DotDotEqual,
     Minus,
     Plus,
+    /// `??` between a value and its fallback for nil
+    QuestionQuestion,
     Semicolon,
     Slash,
     Star,
@@ -58,31 +149,51 @@ pub enum TokenType {
     BangEqual,
     Equal,
     EqualEqual,
+    /// `=>` between the patterns and body of a `match` arm
+    FatArrow,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
 
+    // Bitwise operators.
+    Ampersand,
+    Pipe,
+    Caret,
+    LessLess,
+    GreaterGreater,
+
     // Literals.
     Identifier,
     String,
     Number,
+    /// A string piece that ends at an interpolated `${`.
+    Interpolation,
 
     // Keywords.
     And,
+    Assert,
+    Catch,
     Class,
+    Const,
+    Do,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
+    Match,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
@@ -96,10 +207,17 @@ impl Display for TokenType {
             TokenType::RightParen => "RIGHT_PAREN",
             TokenType::LeftBrace => "LEFT_BRACE",
             TokenType::RightBrace => "RIGHT_BRACE",
+            TokenType::LeftBracket => "LEFT_BRACKET",
+            TokenType::RightBracket => "RIGHT_BRACKET",
+            TokenType::Colon => "COLON",
             TokenType::Comma => "COMMA",
             TokenType::Dot => "DOT",
+            TokenType::DotDotDot => "DOT_DOT_DOT",
+            TokenType::DotDot => "DOT_DOT",
+            TokenType::DotDotEqual => "DOT_DOT_EQUAL",
             TokenType::Minus => "MINUS",
             TokenType::Plus => "PLUS",
+            TokenType::QuestionQuestion => "QUESTION_QUESTION",
             TokenType::Semicolon => "SEMICOLON",
             TokenType::Slash => "SLASH",
             TokenType::Star => "STAR",
@@ -107,27 +225,43 @@ impl Display for TokenType {
             TokenType::BangEqual => "BANG_EQUAL",
             TokenType::Equal => "EQUAL",
             TokenType::EqualEqual => "EQUAL_EQUAL",
+            TokenType::FatArrow => "FAT_ARROW",
             TokenType::Greater => "GREATER",
             TokenType::GreaterEqual => "GREATER_EQUAL",
             TokenType::Less => "LESS",
             TokenType::LessEqual => "LESS_EQUAL",
+            TokenType::Ampersand => "AMPERSAND",
+            TokenType::Pipe => "PIPE",
+            TokenType::Caret => "CARET",
+            TokenType::LessLess => "LESS_LESS",
+            TokenType::GreaterGreater => "GREATER_GREATER",
             TokenType::Identifier => "IDENTIFIER",
             TokenType::String => "STRING",
             TokenType::Number => "NUMBER",
+            TokenType::Interpolation => "INTERPOLATION",
             TokenType::And => "AND",
+            TokenType::Assert => "ASSERT",
+            TokenType::Catch => "CATCH",
             TokenType::Class => "CLASS",
+            TokenType::Const => "CONST",
+            TokenType::Do => "DO",
             TokenType::Else => "ELSE",
             TokenType::False => "FALSE",
+            TokenType::Finally => "FINALLY",
             TokenType::Fun => "FUN",
             TokenType::For => "FOR",
             TokenType::If => "IF",
+            TokenType::Import => "IMPORT",
+            TokenType::Match => "MATCH",
             TokenType::Nil => "NIL",
             TokenType::Or => "OR",
             TokenType::Print => "PRINT",
             TokenType::Return => "RETURN",
             TokenType::Super => "SUPER",
             TokenType::This => "THIS",
+            TokenType::Throw => "THROW",
             TokenType::True => "TRUE",
+            TokenType::Try => "TRY",
             TokenType::Var => "VAR",
             TokenType::While => "WHILE",
             TokenType::Eof => "EOF",
@@ -0,0 +1,119 @@
+//! Scanning that keeps whitespace and comments, for tools that rewrite
+//! source code and must write back every character they leave alone.
+
+use crate::error::StaticError;
+use crate::scanner::token::Token;
+use crate::scanner::{ScanItem, Scanner, TokenStream};
+
+/// A token, or the whitespace and comments between two tokens.
+#[derive(Debug, Clone)]
+pub enum Piece {
+    Token(Token),
+    Trivia(String),
+}
+
+impl Piece {
+    /// Returns the source text the piece was scanned from.
+    pub fn text(&self) -> &str {
+        match self {
+            Piece::Token(token) => &token.lexeme,
+            Piece::Trivia(text) => text,
+        }
+    }
+}
+
+impl<'src> Scanner<'src> {
+    /// Scans the source into tokens and the trivia between them, so that
+    /// [`write_source`] can put the source back together.
+    pub fn scan_pieces(&self) -> Pieces<'src> {
+        Pieces {
+            tokens: self.scan_tokens(),
+            pending: None,
+        }
+    }
+}
+
+pub struct Pieces<'src> {
+    tokens: TokenStream<'src>,
+    /// The item scanned just after a run of trivia, returned next
+    pending: Option<Result<Token, StaticError>>,
+}
+
+impl Iterator for Pieces<'_> {
+    type Item = Result<Piece, StaticError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(item) = self.pending.take() {
+            return Some(item.map(Piece::Token));
+        }
+
+        let start = self.tokens.chars.rest();
+        let mut end = start;
+        // consecutive whitespace and comments become a single piece
+        let item = loop {
+            match self.tokens.next() {
+                Some(Ok(ScanItem::Ignore)) => end = self.tokens.chars.rest(),
+                Some(Ok(ScanItem::Token(token))) => break Some(Ok(token)),
+                Some(Err(err)) => break Some(Err(err)),
+                None => break None,
+            }
+        };
+
+        let trivia = &start[..start.len() - end.len()];
+        if trivia.is_empty() {
+            return item.map(|item| item.map(Piece::Token));
+        }
+        self.pending = item;
+        Some(Ok(Piece::Trivia(trivia.to_string())))
+    }
+}
+
+/// Writes `pieces` back out as source text. For the pieces of a source that
+/// scanned without errors, this is exactly the source.
+pub fn write_source<'a>(pieces: impl IntoIterator<Item = &'a Piece>) -> String {
+    pieces.into_iter().map(Piece::text).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::dialect::{Dialect, Extension};
+
+    fn pieces(source: &str, dialect: Dialect) -> Vec<Piece> {
+        Scanner::new(source)
+            .with_dialect(dialect)
+            .scan_pieces()
+            .collect::<Result<_, _>>()
+            .expect("source should scan")
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("print 1;")]
+    #[case("  // leading comment\n\tvar a = \"text\";\r\n// trailing")]
+    #[case("print \"a${1 + {\"k\": 2}[\"k\"]}b\";")]
+    #[case("var n = 0x1F + 1_000;\nvar t = \"\"\"\n  hi\n  \"\"\";\n")]
+    #[case("var ünï = \"✓\"; // 😀\n")]
+    fn test_write_source_round_trips(#[case] source: &str) {
+        let dialect: Dialect = [
+            Extension::Interpolation,
+            Extension::Lists,
+            Extension::Maps,
+            Extension::NumericLiterals,
+            Extension::TextBlocks,
+            Extension::UnicodeIdentifiers,
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(source, write_source(&pieces(source, dialect)));
+    }
+
+    #[test]
+    fn test_scan_pieces_groups_trivia() {
+        let pieces = pieces("print  // note\n 1;", Dialect::default());
+        let texts = pieces.iter().map(Piece::text).collect::<Vec<_>>();
+        assert_eq!(vec!["print", "  // note\n ", "1", ";", ""], texts);
+    }
+}
@@ -0,0 +1,305 @@
+//! The source files of a program, such as a script and the modules it
+//! imports, and where each of their lines starts.
+//!
+//! Tokens and diagnostics point into a file with a [`Span`] of byte offsets,
+//! and the line and column of an offset are worked out here, for the scanner
+//! as it goes and for the renderers quoting a file. Lines and columns are
+//! 1-based, and columns count `char`s.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// The position of a file in a [`SourceMap`]. The default is the first file
+/// added, which is the one scanners attribute tokens to unless told
+/// otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SourceId(u32);
+
+/// The bytes of a file a token or diagnostic points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub source: SourceId,
+    /// The byte offset of the first character
+    pub start: u32,
+    /// The byte offset just past the last character
+    pub end: u32,
+}
+
+impl Span {
+    pub fn new(source: SourceId, start: usize, end: usize) -> Self {
+        Self {
+            source,
+            start: u32::try_from(start).expect("sources are smaller than 4 GiB"),
+            end: u32::try_from(end).expect("sources are smaller than 4 GiB"),
+        }
+    }
+
+    /// Returns the span `delta` bytes further into the file, or back if
+    /// negative, as when text is added or removed before it.
+    pub fn shift(self, delta: isize) -> Self {
+        let shift = |offset: u32| {
+            let offset = (offset as usize).saturating_add_signed(delta);
+            u32::try_from(offset).expect("sources are smaller than 4 GiB")
+        };
+        Self {
+            start: shift(self.start),
+            end: shift(self.end),
+            ..self
+        }
+    }
+
+    /// Returns the byte offsets the span covers.
+    pub fn range(self) -> std::ops::Range<usize> {
+        self.start as usize..self.end as usize
+    }
+}
+
+/// A line and column.
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    /// The byte offset of the character
+    offset: usize,
+    line: u32,
+    column: u32,
+}
+
+/// Finds the line and column of byte offsets into a text, counting the
+/// characters from the offset found before, so that finding those of every
+/// token of a source in order reads it once.
+#[derive(Debug, Clone)]
+pub(crate) struct Locator<'src> {
+    text: &'src str,
+    /// The line and column of the first character of the text
+    start: Position,
+    /// The last offset found
+    last: Cell<Position>,
+}
+
+impl<'src> Locator<'src> {
+    /// Creates a locator for `text`, which starts at the 1-based `line` and
+    /// `column` of the text it was sliced from.
+    pub(crate) fn new(text: &'src str, line: u32, column: u32) -> Self {
+        let start = Position {
+            offset: 0,
+            line,
+            column,
+        };
+        Self {
+            text,
+            start,
+            last: Cell::new(start),
+        }
+    }
+
+    /// Returns the line and column of the character at byte `offset`.
+    /// Offsets before the last one found are counted from the start again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is past the end of the text or not on a character
+    /// boundary.
+    pub(crate) fn locate(&self, offset: usize) -> (u32, u32) {
+        let mut from = self.last.get();
+        if offset < from.offset {
+            from = self.start;
+        }
+        let skipped = &self.text[from.offset..offset];
+        let position = match skipped.rfind('\n') {
+            Some(newline) => Position {
+                offset,
+                line: from.line + newlines(skipped),
+                column: skipped[newline + 1..].chars().count() as u32 + 1,
+            },
+            None => Position {
+                offset,
+                column: from.column + skipped.chars().count() as u32,
+                ..from
+            },
+        };
+        self.last.set(position);
+        (position.line, position.column)
+    }
+
+    /// Returns the line of the character at byte `offset`, see
+    /// [`Self::locate`].
+    pub(crate) fn line(&self, offset: usize) -> u32 {
+        self.locate(offset).0
+    }
+}
+
+/// Returns the number of line breaks in `text`.
+fn newlines(text: &str) -> u32 {
+    text.bytes().filter(|&byte| byte == b'\n').count() as u32
+}
+
+/// A named source text, with the byte offset each of its lines starts at.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    id: SourceId,
+    name: String,
+    text: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    fn new(id: SourceId, name: String, text: String) -> Self {
+        let newlines = text.match_indices('\n').map(|(i, _)| i + 1);
+        let line_starts = std::iter::once(0).chain(newlines).collect();
+        Self {
+            id,
+            name,
+            text,
+            line_starts,
+        }
+    }
+
+    pub fn id(&self) -> SourceId {
+        self.id
+    }
+
+    /// Returns the name the file was added with, usually its path.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the text of `line` without its line break, or `None` past the
+    /// end of the file.
+    pub fn line(&self, line: u32) -> Option<&str> {
+        let index = (line as usize).checked_sub(1)?;
+        let start = *self.line_starts.get(index)?;
+        let end = self
+            .line_starts
+            .get(index + 1)
+            .map_or(self.text.len(), |next| next - 1);
+        let text = &self.text[start..end];
+        Some(text.strip_suffix('\r').unwrap_or(text))
+    }
+
+    /// Returns the line and column of the character at byte `offset`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is past the end of the text or not on a character
+    /// boundary.
+    pub fn location(&self, offset: usize) -> (u32, u32) {
+        let index = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[index];
+        Locator::new(&self.text[start..], index as u32 + 1, 1).locate(offset - start)
+    }
+}
+
+/// Every source file of a program, looked up by id or by name.
+///
+/// Clones share their files, so that a renderer made before a program runs
+/// can quote the modules it imports.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Rc<RefCell<Vec<Rc<SourceFile>>>>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the file `name` holding `text`, and returns its id.
+    pub fn add(&self, name: impl Into<String>, text: impl Into<String>) -> SourceId {
+        let mut files = self.files.borrow_mut();
+        let id = SourceId(u32::try_from(files.len()).expect("fewer than 2^32 files"));
+        files.push(Rc::new(SourceFile::new(id, name.into(), text.into())));
+        id
+    }
+
+    /// Returns the file `id` points to, if it is one of this map's.
+    pub fn get(&self, id: SourceId) -> Option<Rc<SourceFile>> {
+        self.files.borrow().get(id.0 as usize).cloned()
+    }
+
+    /// Returns the first file added as `name`, if any.
+    pub fn find(&self, name: &str) -> Option<Rc<SourceFile>> {
+        let files = self.files.borrow();
+        files.iter().find(|file| file.name == name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::dialect::{Dialect, Extension};
+    use crate::scanner::{ScanItem, Scanner};
+
+    #[rstest]
+    #[case("", vec![Some(""), None])]
+    #[case("a\r\nb\n", vec![Some("a"), Some("b"), Some(""), None])]
+    #[case("print 1;\n\nprint 2;", vec![Some("print 1;"), Some(""), Some("print 2;"), None])]
+    fn test_source_file_lines(#[case] text: &str, #[case] expected: Vec<Option<&str>>) {
+        let sources = SourceMap::new();
+        let id = sources.add("main.lox", text);
+        let file = sources.get(id).unwrap();
+        let lines = (1..=expected.len() as u32).map(|n| file.line(n));
+        assert_eq!(expected, lines.collect::<Vec<_>>());
+        assert_eq!(None, file.line(0));
+    }
+
+    #[test]
+    fn test_source_file_locations_match_the_scanner() {
+        let text = "var ünï = 1;\n  print ünï + 2;\r\n\n// 😀\nprint \"🎉\";";
+        let sources = SourceMap::new();
+        let id = sources.add("main.lox", text);
+        let file = sources.get(id).unwrap();
+
+        let dialect = Dialect::new().with(Extension::UnicodeIdentifiers);
+        for item in Scanner::new(text)
+            .with_dialect(dialect)
+            .scan_tokens()
+            .borrowed()
+        {
+            let Ok(ScanItem::Token(token)) = item else {
+                continue;
+            };
+            let offset = token.lexeme.as_ptr() as usize - text.as_ptr() as usize;
+            assert_eq!(offset..offset + token.lexeme.len(), token.span.range());
+            let location = file.location(token.span.start as usize);
+            assert_eq!((token.line, token.column), location, "{token}");
+        }
+    }
+
+    #[rstest]
+    #[case(&[0, 6, 9, 16], &[(3, 5), (3, 9), (4, 1), (4, 5)])]
+    #[case(&[16, 6, 11], &[(4, 5), (3, 9), (4, 3)])]
+    fn test_locator_numbers_from_its_start(
+        #[case] offsets: &[usize],
+        #[case] expected: &[(u32, u32)],
+    ) {
+        let locator = Locator::new("ünï b\r\nc 🎉 d", 3, 5);
+        let locations = offsets.iter().map(|&offset| locator.locate(offset));
+        assert_eq!(expected, locations.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_span_shift() {
+        let span = Span::new(SourceId::default(), 4, 7);
+        assert_eq!(6..9, span.shift(2).range());
+        assert_eq!(1..4, span.shift(-3).range());
+    }
+
+    #[test]
+    fn test_source_map_finds_files_by_name() {
+        let sources = SourceMap::new();
+        let main = sources.add("main.lox", "import \"lib.lox\";");
+        // clones share their files
+        let lib = sources.clone().add("lib.lox", "var x = 1;");
+
+        assert_ne!(main, lib);
+        assert_eq!(main, SourceId::default());
+        assert_eq!(Some(lib), sources.find("lib.lox").map(|file| file.id()));
+        assert_eq!("var x = 1;", sources.get(lib).unwrap().text());
+        assert!(sources.find("other.lox").is_none());
+    }
+}
@@ -0,0 +1,61 @@
+use super::NativeFunction;
+use crate::Value;
+use crate::interpreter::Interpreter;
+use crate::interpreter::error::ErrorValue;
+
+pub(super) static NATIVES: &[NativeFunction] = &[
+    NativeFunction {
+        name: "errorMessage",
+        arity: 1,
+        func: error_message,
+    },
+    NativeFunction {
+        name: "errorLine",
+        arity: 1,
+        func: error_line,
+    },
+];
+
+/// Converts the native function argument into a caught runtime error.
+///
+/// Returns an error message if it is not one.
+fn error(args: &[Value]) -> Result<&ErrorValue, String> {
+    match &args[0] {
+        Value::Error(error) => Ok(error),
+        _ => Err("Argument must be an error.".into()),
+    }
+}
+
+/// `errorMessage(e)` returns the message of the runtime error `e`.
+fn error_message(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    Ok(error(args)?.message.as_str().into())
+}
+
+/// `errorLine(e)` returns the line the runtime error `e` was raised on.
+fn error_line(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    Ok(interpreter.integral(error(args)?.line.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_accessors() {
+        let mut interpreter = Interpreter::new();
+        let args = [Value::Error(ErrorValue {
+            message: "Division by 0".into(),
+            line: 3,
+        })];
+
+        assert_eq!(
+            Ok("Division by 0".into()),
+            error_message(&mut interpreter, &args)
+        );
+        assert_eq!(Ok(Value::Number(3.0)), error_line(&mut interpreter, &args));
+        assert_eq!(
+            Err("Argument must be an error.".to_string()),
+            error_line(&mut interpreter, &["oops".into()])
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::fs;
+use std::io::{self, BufRead as _};
+
+use super::{NativeFunction, strings};
+use crate::Value;
+use crate::interpreter::Interpreter;
+
+pub(super) static NATIVES: &[NativeFunction] = &[
+    NativeFunction {
+        name: "readLine",
+        arity: 0,
+        func: read_line,
+    },
+    NativeFunction {
+        name: "readFile",
+        arity: 1,
+        func: read_file,
+    },
+    NativeFunction {
+        name: "writeFile",
+        arity: 2,
+        func: write_file,
+    },
+    NativeFunction {
+        name: "eprint",
+        arity: 1,
+        func: eprint,
+    },
+];
+
+/// Returns an error message if the interpreter is sandboxed.
+fn check_filesystem(interpreter: &Interpreter) -> Result<(), String> {
+    if interpreter.is_sandboxed() {
+        return Err("Filesystem access is disabled.".into());
+    }
+    Ok(())
+}
+
+/// `readLine()` returns the next line of standard input without its line
+/// ending, or `nil` at the end of input.
+fn read_line(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, String> {
+    interpreter.nondeterministic("readLine", || {
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) => Ok(Value::Nil),
+            Ok(_) => {
+                let len = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(len);
+                Ok(line.into())
+            }
+            Err(err) => Err(format!("Failed to read standard input: {err}.")),
+        }
+    })
+}
+
+/// `readFile(path)` returns the contents of the file at `path`.
+fn read_file(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [path] = strings(args)?;
+    check_filesystem(interpreter)?;
+    interpreter.nondeterministic("readFile", || {
+        fs::read_to_string(path)
+            .map(Value::from)
+            .map_err(|err| format!("Failed to read file {path}: {err}."))
+    })
+}
+
+/// `writeFile(path, contents)` replaces the file at `path` with `contents`,
+/// creating it if needed.
+fn write_file(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [path, contents] = strings(args)?;
+    check_filesystem(interpreter)?;
+    fs::write(path, contents)
+        .map(|_| Value::Nil)
+        .map_err(|err| format!("Failed to write file {path}: {err}."))
+}
+
+/// `eprint(value)` prints `value` to standard error, like `print` does to
+/// standard output.
+fn eprint(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    eprintln!("{}", args[0]);
+    Ok(Value::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_write_then_read_file() {
+        let tempdir = TempDir::new("rslox-io").expect("should create temp dir");
+        let path = tempdir.path().join("out.txt");
+        let path: Value = path.to_str().unwrap().into();
+        let mut interpreter = Interpreter::new();
+
+        let written = write_file(&mut interpreter, &[path.clone(), "héllo\n".into()]);
+        assert_eq!(Ok(Value::Nil), written);
+        assert_eq!(Ok("héllo\n".into()), read_file(&mut interpreter, &[path]));
+    }
+
+    #[test]
+    fn test_read_missing_file() {
+        let tempdir = TempDir::new("rslox-io").expect("should create temp dir");
+        let path = tempdir.path().join("missing.txt");
+        let err = read_file(&mut Interpreter::new(), &[path.to_str().unwrap().into()])
+            .expect_err("expected missing file to fail");
+        assert!(err.starts_with("Failed to read file "), "{err}");
+    }
+
+    #[test]
+    fn test_sandbox_denies_filesystem() {
+        let mut interpreter = Interpreter::new().with_sandbox(true);
+        let denied = Err("Filesystem access is disabled.".to_string());
+        assert_eq!(denied, read_file(&mut interpreter, &["a.txt".into()]));
+        assert_eq!(
+            denied,
+            write_file(&mut interpreter, &["a.txt".into(), "".into()])
+        );
+    }
+
+    #[test]
+    fn test_write_file_requires_strings() {
+        let output = write_file(&mut Interpreter::new(), &["a.txt".into(), 1.0.into()]);
+        assert_eq!(Err("Arguments must be strings.".to_string()), output);
+    }
+}
@@ -0,0 +1,87 @@
+use super::{NativeFunction, numbers};
+use crate::Value;
+use crate::interpreter::Interpreter;
+
+pub(super) static NATIVES: &[NativeFunction] = &[
+    NativeFunction {
+        name: "approxEquals",
+        arity: 3,
+        func: approx_equals,
+    },
+    NativeFunction {
+        name: "isNan",
+        arity: 1,
+        func: is_nan,
+    },
+    NativeFunction {
+        name: "isInfinite",
+        arity: 1,
+        func: is_infinite,
+    },
+];
+
+/// `approxEquals(a, b, eps)` checks whether `a` and `b` differ by at most `eps`.
+///
+/// Equal infinities are approximately equal; NaN is not approximately equal to
+/// anything.
+fn approx_equals(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [a, b, eps] = numbers(args)?;
+    if eps.is_nan() || eps < 0.0 {
+        return Err("Epsilon must be a non-negative number.".into());
+    }
+    Ok((a == b || (a - b).abs() <= eps).into())
+}
+
+/// `isNan(x)` checks whether `x` is NaN.
+fn is_nan(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [x] = numbers(args)?;
+    Ok(x.is_nan().into())
+}
+
+/// `isInfinite(x)` checks whether `x` is positive or negative infinity.
+fn is_infinite(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [x] = numbers(args)?;
+    Ok(x.is_infinite().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(1.0, 1.0, 0.0, true)]
+    #[case(1.0, 1.05, 0.1, true)]
+    #[case(1.0, 1.2, 0.1, false)]
+    #[case(f64::INFINITY, f64::INFINITY, 0.0, true)]
+    #[case(f64::INFINITY, f64::NEG_INFINITY, 1.0, false)]
+    #[case(f64::NAN, f64::NAN, 1.0, false)]
+    fn test_approx_equals(
+        #[case] a: f64,
+        #[case] b: f64,
+        #[case] eps: f64,
+        #[case] expected: bool,
+    ) {
+        let args = [a.into(), b.into(), eps.into()];
+        let output = approx_equals(&mut Interpreter::new(), &args);
+        assert_eq!(Ok(Value::Boolean(expected)), output);
+    }
+
+    #[rstest]
+    #[case(f64::NAN, true, false)]
+    #[case(f64::INFINITY, false, true)]
+    #[case(f64::NEG_INFINITY, false, true)]
+    #[case(0.0, false, false)]
+    fn test_float_classification(#[case] x: f64, #[case] nan: bool, #[case] infinite: bool) {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            Ok(Value::Boolean(nan)),
+            is_nan(&mut interpreter, &[x.into()])
+        );
+        assert_eq!(
+            Ok(Value::Boolean(infinite)),
+            is_infinite(&mut interpreter, &[x.into()])
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//! Native functions defined in the global scope of every program.
+
+use std::fmt::{Debug, Display};
+
+use crate::Value;
+use crate::interpreter::Interpreter;
+
+mod error;
+mod io;
+mod math;
+mod range;
+mod stats;
+mod string;
+mod types;
+
+/// A function implemented in Rust and callable from Lox.
+pub struct NativeFunction {
+    /// The global name the function is bound to
+    pub name: &'static str,
+    /// The number of arguments the function expects
+    pub arity: usize,
+    /// The implementation, called with exactly `arity` arguments.
+    /// An `Err` message is reported as a runtime error at the call site.
+    pub func: fn(&mut Interpreter, &[Value]) -> Result<Value, String>,
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Display for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
+
+/// Returns every native function, in the order they are defined.
+pub fn natives() -> impl Iterator<Item = &'static NativeFunction> {
+    math::NATIVES
+        .iter()
+        .chain(range::NATIVES)
+        .chain(string::NATIVES)
+        .chain(io::NATIVES)
+        .chain(types::NATIVES)
+        .chain(error::NATIVES)
+        .chain(stats::NATIVES)
+}
+
+/// Returns the native function bound to `name`, if there is one.
+pub fn native(name: &str) -> Option<&'static NativeFunction> {
+    natives().find(|native| native.name == name)
+}
+
+/// Converts native function arguments into numbers.
+///
+/// Returns an error message if any argument is not a number.
+fn numbers<const N: usize>(args: &[Value]) -> Result<[f64; N], String> {
+    let mut numbers = [0.0; N];
+    for (number, arg) in numbers.iter_mut().zip(args) {
+        *number = match arg.as_f64() {
+            Some(n) => n,
+            None if N == 1 => return Err("Argument must be a number.".into()),
+            None => return Err("Arguments must be numbers.".into()),
+        };
+    }
+    Ok(numbers)
+}
+
+/// Converts native function arguments into strings.
+///
+/// Returns an error message if any argument is not a string.
+fn strings<const N: usize>(args: &[Value]) -> Result<[&str; N], String> {
+    let mut strings = [""; N];
+    for (string, arg) in strings.iter_mut().zip(args) {
+        *string = match arg {
+            Value::String(s) => s,
+            _ if N == 1 => return Err("Argument must be a string.".into()),
+            _ => return Err("Arguments must be strings.".into()),
+        };
+    }
+    Ok(strings)
+}
@@ -0,0 +1,89 @@
+use super::NativeFunction;
+use crate::Value;
+use crate::interpreter::Interpreter;
+use crate::range::Range;
+
+pub(super) static NATIVES: &[NativeFunction] = &[
+    NativeFunction {
+        name: "contains",
+        arity: 2,
+        func: contains,
+    },
+    NativeFunction {
+        name: "step",
+        arity: 2,
+        func: step,
+    },
+];
+
+/// Converts the first native function argument into a range.
+///
+/// Returns an error message if it is not a range.
+fn range(args: &[Value]) -> Result<Range, String> {
+    match args.first() {
+        Some(Value::Range(range)) => Ok(*range),
+        _ => Err("First argument must be a range.".into()),
+    }
+}
+
+/// `contains(range, x)` checks whether the number `x` is one of the numbers of
+/// `range`.
+fn contains(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let range = range(args)?;
+    if args[1].as_f64().is_none() {
+        return Err("Second argument must be a number.".into());
+    }
+    // a fractional `x` is never in a range of whole numbers
+    Ok(args[1]
+        .as_integer()
+        .is_some_and(|n| range.contains(n))
+        .into())
+}
+
+/// `step(range, n)` returns a copy of `range` with only every `n`-th number
+/// from its start.
+fn step(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let range = range(args)?;
+    let step = args[1]
+        .as_integer()
+        .ok_or("Step must be a positive integer.")?;
+    Ok(range.with_step(step)?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(Range::new(0, 5, false), 3.0.into(), true)]
+    #[case(Range::new(0, 5, false), Value::Integer(5), false)]
+    #[case(Range::new(0, 5, true), Value::Integer(5), true)]
+    #[case(Range::new(5, 0, false), 0.5.into(), false)]
+    fn test_contains(#[case] range: Range, #[case] x: Value, #[case] expected: bool) {
+        let output = contains(&mut Interpreter::new(), &[range.into(), x]);
+        assert_eq!(Ok(Value::Boolean(expected)), output);
+    }
+
+    #[rstest]
+    #[case(vec![Value::Nil, 1.0.into()], "First argument must be a range.")]
+    #[case(vec![Range::new(0, 1, false).into(), "1".into()], "Second argument must be a number.")]
+    fn test_contains_errors(#[case] args: Vec<Value>, #[case] expected: &str) {
+        let output = contains(&mut Interpreter::new(), &args);
+        assert_eq!(Err(expected.to_string()), output);
+    }
+
+    #[rstest]
+    #[case(2.0.into(), Ok(Range::new(0, 10, false).with_step(2).unwrap().into()))]
+    #[case(0.0.into(), Err("Step must be a positive integer.".to_string()))]
+    #[case(1.5.into(), Err("Step must be a positive integer.".to_string()))]
+    #[case(Value::Nil, Err("Step must be a positive integer.".to_string()))]
+    fn test_step(#[case] n: Value, #[case] expected: Result<Value, String>) {
+        let output = step(
+            &mut Interpreter::new(),
+            &[Range::new(0, 10, false).into(), n],
+        );
+        assert_eq!(expected, output);
+    }
+}
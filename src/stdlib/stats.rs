@@ -0,0 +1,70 @@
+use super::NativeFunction;
+use crate::Value;
+use crate::dialect::Extension;
+use crate::interpreter::Interpreter;
+use crate::map::{Map, MapKey};
+
+pub(super) static NATIVES: &[NativeFunction] = &[NativeFunction {
+    name: "stats",
+    arity: 0,
+    func: stats,
+}];
+
+/// `stats()` returns a map of what the program has used so far: the
+/// `statements` executed and `calls` made, counting this call, and the
+/// seconds `elapsed` since the interpreter started.
+///
+/// The counts are integers with the `integers` extension. The elapsed time
+/// is recorded and replayed like input, so a replayed run reports the same.
+fn stats(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, String> {
+    let stats = interpreter.stats();
+    let elapsed = stats.elapsed.as_secs_f64();
+    let elapsed = interpreter.nondeterministic("stats", || Ok(elapsed.into()))?;
+
+    let integers = interpreter.dialect().allows(Extension::Integers);
+    let count = |n: u64| match i64::try_from(n) {
+        Ok(n) if integers => Value::Integer(n),
+        _ => Value::Number(n as f64),
+    };
+    let entries = [
+        ("statements", count(stats.statements)),
+        ("calls", count(stats.calls)),
+        ("elapsed", elapsed),
+    ];
+    let entries = entries
+        .into_iter()
+        .map(|(key, value)| (MapKey::new(key.into()).expect("strings are keys"), value));
+    Ok(entries.collect::<Map>().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Dialect;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn test_stats_counts_statements_and_calls() {
+        let dialect = Dialect::new().with(Extension::Integers);
+        let source = "var a = 1;\nvar b = str(a);\nvar s = stats();";
+        let program = Parser::new(Scanner::new(source).with_dialect(dialect).scan_tokens())
+            .with_dialect(dialect)
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::new().with_dialect(dialect).with_sandbox(true);
+        interpreter.interpret(&program).unwrap();
+
+        let used = interpreter.stats();
+        assert_eq!((3, 2), (used.statements, used.calls));
+
+        let Value::Map(map) = stats(&mut interpreter, &[]).unwrap() else {
+            panic!("stats() should return a map");
+        };
+        let map = map.borrow();
+        let get = |key: &str| map.get(&MapKey::new(key.into()).unwrap()).cloned();
+        assert_eq!(Some(Value::Integer(3)), get("statements"));
+        assert_eq!(Some(Value::Integer(2)), get("calls"));
+        assert!(matches!(get("elapsed"), Some(Value::Number(n)) if n >= 0.0));
+    }
+}
@@ -0,0 +1,270 @@
+use unicode_segmentation::UnicodeSegmentation as _;
+
+use super::{NativeFunction, strings};
+use crate::Value;
+use crate::interpreter::{Interpreter, StringUnit};
+
+pub(super) static NATIVES: &[NativeFunction] = &[
+    NativeFunction {
+        name: "len",
+        arity: 1,
+        func: len,
+    },
+    NativeFunction {
+        name: "substr",
+        arity: 3,
+        func: substr,
+    },
+    NativeFunction {
+        name: "indexOf",
+        arity: 2,
+        func: index_of,
+    },
+    NativeFunction {
+        name: "split",
+        arity: 2,
+        func: split,
+    },
+    NativeFunction {
+        name: "upper",
+        arity: 1,
+        func: upper,
+    },
+    NativeFunction {
+        name: "lower",
+        arity: 1,
+        func: lower,
+    },
+    NativeFunction {
+        name: "trim",
+        arity: 1,
+        func: trim,
+    },
+];
+
+/// Converts a count or position in string units into a Lox number.
+fn count(interpreter: &Interpreter, n: usize) -> Value {
+    interpreter.integral(n as i64)
+}
+
+/// Returns the byte offset where each unit of `s` starts, followed by the
+/// length of `s`.
+fn boundaries(s: &str, unit: StringUnit) -> Vec<usize> {
+    let mut bounds: Vec<_> = match unit {
+        StringUnit::Bytes => (0..s.len()).collect(),
+        StringUnit::Chars => s.char_indices().map(|(i, _)| i).collect(),
+        StringUnit::Graphemes => s.grapheme_indices(true).map(|(i, _)| i).collect(),
+    };
+    bounds.push(s.len());
+    bounds
+}
+
+/// `len(s)` returns the number of units in `s`, characters unless the
+/// interpreter counts in another [`StringUnit`].
+fn len(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [s] = strings(args)?;
+    let units = boundaries(s, interpreter.string_unit()).len() - 1;
+    Ok(count(interpreter, units))
+}
+
+/// `substr(s, start, len)` returns the `len` units of `s` beginning at unit
+/// `start`.
+fn substr(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let Value::String(s) = &args[0] else {
+        return Err("First argument must be a string.".into());
+    };
+    let bound = |arg: &Value| arg.as_integer().and_then(|n| usize::try_from(n).ok());
+    let (Some(start), Some(len)) = (bound(&args[1]), bound(&args[2])) else {
+        return Err("Start and length must be non-negative integers.".into());
+    };
+
+    let bounds = boundaries(s, interpreter.string_unit());
+    let units = bounds.len() - 1;
+    if start.saturating_add(len) > units {
+        return Err(format!(
+            "Substring of length {len} at {start} is out of bounds for length {units}."
+        ));
+    }
+    // only byte offsets can fall inside a character
+    s.get(bounds[start]..bounds[start + len])
+        .map(Value::from)
+        .ok_or_else(|| format!("Substring of length {len} at {start} splits a character."))
+}
+
+/// `indexOf(s, needle)` returns the position in units of the first
+/// occurrence of `needle` in `s`, or `-1` if there is none.
+fn index_of(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [s, needle] = strings(args)?;
+    let bounds = boundaries(s, interpreter.string_unit());
+    Ok(match s.find(needle) {
+        Some(byte) => count(interpreter, bounds.partition_point(|&b| b < byte)),
+        None => interpreter.integral(-1),
+    })
+}
+
+/// `split(s, separator)` returns a list of the parts of `s` between each
+/// `separator`, or of its characters when `separator` is empty.
+fn split(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [s, separator] = strings(args)?;
+    let parts = if separator.is_empty() {
+        s.chars().map(|c| c.to_string().into()).collect()
+    } else {
+        s.split(separator).map(Value::from).collect::<Vec<_>>()
+    };
+    Ok(parts.into())
+}
+
+/// `upper(s)` returns `s` in upper case.
+fn upper(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [s] = strings(args)?;
+    Ok(s.to_uppercase().into())
+}
+
+/// `lower(s)` returns `s` in lower case.
+fn lower(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [s] = strings(args)?;
+    Ok(s.to_lowercase().into())
+}
+
+/// `trim(s)` returns `s` without leading and trailing whitespace.
+fn trim(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [s] = strings(args)?;
+    Ok(s.trim().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::dialect::{Dialect, Extension};
+
+    type Func = fn(&mut Interpreter, &[Value]) -> Result<Value, String>;
+
+    fn call(func: Func, args: &[Value]) -> Result<String, String> {
+        func(&mut Interpreter::new(), args).map(|value| value.to_string())
+    }
+
+    #[rstest]
+    #[case("", "0")]
+    #[case("lox", "3")]
+    #[case("变量🎉", "3")]
+    fn test_len(#[case] s: &str, #[case] expected: &str) {
+        assert_eq!(Ok(expected.into()), call(len, &[s.into()]));
+    }
+
+    #[test]
+    fn test_len_is_integral_with_integers() {
+        let mut interpreter =
+            Interpreter::new().with_dialect(Dialect::new().with(Extension::Integers));
+        assert_eq!(
+            Ok(Value::Integer(3)),
+            len(&mut interpreter, &["abc".into()])
+        );
+    }
+
+    #[rstest]
+    #[case("hello", 1.0, 3.0, Ok("ell"))]
+    #[case("hello", 5.0, 0.0, Ok(""))]
+    #[case("é变🎉x", 1.0, 2.0, Ok("变🎉"))]
+    #[case(
+        "hello",
+        3.0,
+        3.0,
+        Err("Substring of length 3 at 3 is out of bounds for length 5.")
+    )]
+    #[case("hello", -1.0, 1.0, Err("Start and length must be non-negative integers."))]
+    #[case(
+        "hello",
+        0.5,
+        1.0,
+        Err("Start and length must be non-negative integers.")
+    )]
+    fn test_substr(
+        #[case] s: &str,
+        #[case] start: f64,
+        #[case] len: f64,
+        #[case] expected: Result<&str, &str>,
+    ) {
+        let output = call(substr, &[s.into(), start.into(), len.into()]);
+        assert_eq!(expected.map(String::from).map_err(String::from), output);
+    }
+
+    #[rstest]
+    #[case(
+        StringUnit::Bytes,
+        "12",
+        "11",
+        Err("Substring of length 1 at 1 splits a character.")
+    )]
+    #[case(StringUnit::Chars, "5", "4", Ok("\u{301}"))]
+    #[case(StringUnit::Graphemes, "3", "2", Ok("👍🏽"))]
+    fn test_string_units(
+        #[case] unit: StringUnit,
+        #[case] length: &str,
+        #[case] position: &str,
+        #[case] second: Result<&str, &str>,
+    ) {
+        // an accented e, a thumbs up with a skin tone and a plain x
+        let s = Value::from("e\u{301}👍🏽x");
+        let call = |func: Func, args: &[Value]| {
+            let mut interpreter = Interpreter::new().with_string_unit(unit);
+            func(&mut interpreter, args).map(|value| value.to_string())
+        };
+
+        assert_eq!(Ok(length.into()), call(len, std::slice::from_ref(&s)));
+        assert_eq!(
+            Ok(position.into()),
+            call(index_of, &[s.clone(), "x".into()])
+        );
+        assert_eq!(
+            second.map(String::from).map_err(String::from),
+            call(substr, &[s, 1.0.into(), 1.0.into()])
+        );
+    }
+
+    #[rstest]
+    #[case("hello", "l", "2")]
+    #[case("hello", "", "0")]
+    #[case("hello", "z", "-1")]
+    #[case("é变🎉x", "x", "3")]
+    fn test_index_of(#[case] s: &str, #[case] needle: &str, #[case] expected: &str) {
+        assert_eq!(
+            Ok(expected.into()),
+            call(index_of, &[s.into(), needle.into()])
+        );
+    }
+
+    #[rstest]
+    #[case("a,b,,c", ",", "[a, b, , c]")]
+    #[case("a, b", ", ", "[a, b]")]
+    #[case("abc", "", "[a, b, c]")]
+    #[case("a", ",", "[a]")]
+    fn test_split(#[case] s: &str, #[case] separator: &str, #[case] expected: &str) {
+        assert_eq!(
+            Ok(expected.into()),
+            call(split, &[s.into(), separator.into()])
+        );
+    }
+
+    #[rstest]
+    #[case(upper, "Straße", "STRASSE")]
+    #[case(lower, "ÀB", "àb")]
+    #[case(trim, " \t lox \n", "lox")]
+    fn test_case_and_trim(#[case] func: Func, #[case] s: &str, #[case] expected: &str) {
+        assert_eq!(Ok(expected.into()), call(func, &[s.into()]));
+    }
+
+    #[rstest]
+    #[case(len, vec![Value::Nil], "Argument must be a string.")]
+    #[case(substr, vec![1.0.into(), 0.0.into(), 0.0.into()], "First argument must be a string.")]
+    #[case(index_of, vec!["a".into(), 1.0.into()], "Arguments must be strings.")]
+    #[case(split, vec![true.into(), ",".into()], "Arguments must be strings.")]
+    fn test_string_type_errors(
+        #[case] func: Func,
+        #[case] args: Vec<Value>,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(Err(expected.into()), call(func, &args));
+    }
+}
@@ -0,0 +1,155 @@
+use super::NativeFunction;
+use crate::Value;
+use crate::dialect::Extension;
+use crate::interpreter::Interpreter;
+
+pub(super) static NATIVES: &[NativeFunction] = &[
+    NativeFunction {
+        name: "type",
+        arity: 1,
+        func: type_of,
+    },
+    NativeFunction {
+        name: "str",
+        arity: 1,
+        func: str,
+    },
+    NativeFunction {
+        name: "num",
+        arity: 1,
+        func: num,
+    },
+    NativeFunction {
+        name: "bool",
+        arity: 1,
+        func: bool,
+    },
+];
+
+/// `type(v)` returns the name of the type of `v`, such as `"number"` or
+/// `"list"`. Integers are numbers, and foreign values report the type name
+/// they were created with.
+fn type_of(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    Ok(args[0].type_name().into())
+}
+
+/// `str(v)` returns `v` as `print` would write it.
+fn str(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    Ok(args[0].to_string().into())
+}
+
+/// `num(v)` returns the number written in the string `v`, or `nil` if it does
+/// not hold one. Numbers are returned unchanged.
+///
+/// The string may be surrounded by whitespace and must otherwise look like a
+/// Lox number literal, with an optional leading `-`.
+fn num(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let s = match &args[0] {
+        Value::Number(_) | Value::Integer(_) => return Ok(args[0].clone()),
+        Value::String(s) => s.trim(),
+        _ => return Ok(Value::Nil),
+    };
+
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (digits, None),
+    };
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits(whole) || !fraction.is_none_or(is_digits) {
+        return Ok(Value::Nil);
+    }
+
+    if fraction.is_none()
+        && interpreter.dialect().allows(Extension::Integers)
+        && let Ok(n) = s.parse::<i64>()
+    {
+        return Ok(n.into());
+    }
+    Ok(s.parse::<f64>().map_or(Value::Nil, Value::from))
+}
+
+/// `bool(v)` returns whether `v` is truthy: everything but `nil` and `false`.
+fn bool(_: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    Ok(args[0].is_truthy().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::dialect::Dialect;
+    use crate::interpreter::error::ErrorValue;
+    use crate::map::{Map, MapKey};
+    use crate::range::Range;
+
+    /// One value of every variant, with its `type`, `str` and `bool` results.
+    fn every_variant() -> Vec<(Value, &'static str, &'static str, bool)> {
+        let mut map = Map::default();
+        map.insert(MapKey::new("k".into()).unwrap(), 1.0.into());
+        vec![
+            (Value::Number(1.5), "number", "1.5", true),
+            (Value::Integer(0), "number", "0", true),
+            ("".into(), "string", "", true),
+            (false.into(), "boolean", "false", false),
+            (
+                Value::NativeFunction(&NATIVES[0]),
+                "function",
+                "<native fn>",
+                true,
+            ),
+            (vec![Value::Nil].into(), "list", "[nil]", true),
+            (map.into(), "map", "{k: 1}", true),
+            (Range::new(0, 3, true).into(), "range", "0..=3", true),
+            (
+                Value::Error(ErrorValue {
+                    message: "Division by 0".into(),
+                    line: 1,
+                }),
+                "error",
+                "Division by 0",
+                true,
+            ),
+            (Value::Nil, "nil", "nil", false),
+        ]
+    }
+
+    #[test]
+    fn test_type_str_and_bool_of_every_variant() {
+        let mut interpreter = Interpreter::new();
+        for (value, type_name, string, truthy) in every_variant() {
+            let args = [value];
+            assert_eq!(Ok(type_name.into()), type_of(&mut interpreter, &args));
+            assert_eq!(Ok(string.into()), str(&mut interpreter, &args));
+            assert_eq!(Ok(truthy.into()), bool(&mut interpreter, &args));
+        }
+    }
+
+    #[rstest]
+    #[case("42".into(), Value::Number(42.0))]
+    #[case(" -3.25\n".into(), Value::Number(-3.25))]
+    #[case(Value::Integer(7), Value::Integer(7))]
+    #[case("".into(), Value::Nil)]
+    #[case("1.".into(), Value::Nil)]
+    #[case(".5".into(), Value::Nil)]
+    #[case("1e3".into(), Value::Nil)]
+    #[case("inf".into(), Value::Nil)]
+    #[case("--1".into(), Value::Nil)]
+    #[case(true.into(), Value::Nil)]
+    #[case(Value::Nil, Value::Nil)]
+    fn test_num(#[case] value: Value, #[case] expected: Value) {
+        assert_eq!(Ok(expected), num(&mut Interpreter::new(), &[value]));
+    }
+
+    #[rstest]
+    #[case("42", Value::Integer(42))]
+    #[case("-42", Value::Integer(-42))]
+    #[case("4.0", Value::Number(4.0))]
+    #[case("99999999999999999999", Value::Number(1e20))]
+    fn test_num_with_integers(#[case] s: &str, #[case] expected: Value) {
+        let mut interpreter =
+            Interpreter::new().with_dialect(Dialect::new().with(Extension::Integers));
+        assert_eq!(Ok(expected), num(&mut interpreter, &[s.into()]));
+    }
+}
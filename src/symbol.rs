@@ -0,0 +1,165 @@
+//! Identifier names shared between the tokens, scopes and exports that refer
+//! to them, so that copying a name never copies its text.
+//!
+//! An [`Interner`] hands out one symbol per distinct name, so that the names
+//! scanned with it compare by pointer. The interpreter owns one for the
+//! scripts and modules it runs, and a name is freed once the interner and
+//! the last token or scope holding it are dropped.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// A shared name. Two symbols are equal exactly when their names are.
+///
+/// The hash of the name is computed once, when the symbol is made, so that
+/// hashing a symbol never reads its text. Symbols from the same
+/// [`Interner`] share their text and compare by pointer, others compare the
+/// text only when their hashes match.
+#[derive(Clone)]
+pub struct Symbol {
+    name: Rc<str>,
+    hash: u64,
+}
+
+impl Symbol {
+    /// Returns the name of the symbol.
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && (Rc::ptr_eq(&self.name, &other.name) || self.name == other.name)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.name.cmp(&other.name)
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.name
+    }
+}
+
+impl From<Rc<str>> for Symbol {
+    fn from(name: Rc<str>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        name.hash(&mut hasher);
+        Self {
+            hash: hasher.finish(),
+            name,
+        }
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Rc::<str>::from(name).into()
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Rc::<str>::from(name).into()
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Hands out the same [`Symbol`] for every occurrence of a name. Clones
+/// share their symbols.
+#[derive(Debug, Clone, Default)]
+pub struct Interner(Rc<RefCell<HashMap<Rc<str>, Symbol>>>);
+
+impl Interner {
+    /// Returns the symbol for `name`, making it the first time it is asked
+    /// for.
+    pub fn intern(&self, name: &str) -> Symbol {
+        let mut symbols = self.0.borrow_mut();
+        if let Some(symbol) = symbols.get(name) {
+            return symbol.clone();
+        }
+        let symbol = Symbol::from(name);
+        symbols.insert(Rc::clone(&symbol.name), symbol.clone());
+        symbol
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol() {
+        let a = Symbol::from("alpha");
+        let b = Symbol::from("beta");
+
+        assert_eq!(a, Symbol::from(String::from("alpha")));
+        assert_eq!(a, a.clone());
+        assert_ne!(a, b);
+        assert_eq!("alpha", a.as_str());
+        assert_eq!("beta", b.to_string());
+    }
+
+    #[test]
+    fn test_interner_shares_symbols() {
+        let interner = Interner::default();
+        let a = interner.intern("alpha");
+
+        assert!(Rc::ptr_eq(&a.name, &interner.intern("alpha").name));
+        assert!(Rc::ptr_eq(&a.name, &interner.clone().intern("alpha").name));
+        assert!(!Rc::ptr_eq(
+            &a.name,
+            &Interner::default().intern("alpha").name
+        ));
+        assert_eq!(a, Interner::default().intern("alpha"));
+        assert_ne!(a, interner.intern("beta"));
+    }
+}
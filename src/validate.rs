@@ -0,0 +1,158 @@
+//! Syntax checking that never fails, for editors that re-check the source on
+//! every keystroke and want whatever could be recovered from it.
+
+use crate::diagnostic::Diagnostic;
+use crate::dialect::Dialect;
+use crate::error::StaticError;
+use crate::lint::lint;
+use crate::parser::Parser;
+use crate::parser::ast::Ast;
+use crate::scanner::token::Token;
+use crate::scanner::{ScanItem, Scanner};
+
+/// Everything recovered from a source file, with every problem found in it.
+#[derive(Debug)]
+pub struct Validation {
+    /// The tokens that scanned, ending with `EOF`
+    pub tokens: Vec<Token>,
+    /// The statements that parsed, or `None` if scanning failed. Like the
+    /// CLI, the parser does not run on a source with scan errors, since the
+    /// missing tokens would only produce misleading parse errors.
+    pub ast: Option<Ast>,
+    /// Scan errors, or parse errors if scanning succeeded, in source order
+    pub diagnostics: Vec<StaticError>,
+    /// What [`lint`] warns about in the statements that parsed. Warnings
+    /// never make a source invalid.
+    pub warnings: Vec<Diagnostic>,
+}
+
+impl Validation {
+    /// Checks whether the source is free of errors.
+    pub fn is_valid(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// Validates `source` as standard Lox.
+pub fn validate(source: &str) -> Validation {
+    validate_with(source, Dialect::default())
+}
+
+/// Validates `source` with the language extensions in `dialect`.
+pub fn validate_with(source: &str, dialect: Dialect) -> Validation {
+    validate_scanned(Scanner::new(source).with_dialect(dialect), dialect)
+}
+
+/// Validates the source of `scanner` like [`validate_with`], with the
+/// tokens it scans in `dialect`, such as those of a module sharing the
+/// interner and source map of the program importing it.
+pub fn validate_scanned(scanner: Scanner, dialect: Dialect) -> Validation {
+    let mut tokens = Vec::new();
+    let mut diagnostics = Vec::new();
+    for item in scanner.scan_tokens() {
+        match item {
+            Ok(ScanItem::Token(token)) => tokens.push(token),
+            Ok(ScanItem::Ignore) => continue,
+            Err(err) => diagnostics.push(err),
+        }
+    }
+
+    let ast = diagnostics.is_empty().then(|| {
+        let mut parser = Parser::from(tokens.clone()).with_dialect(dialect);
+        let (ast, errors) = parser.parse_recovering();
+        diagnostics = errors;
+        ast
+    });
+
+    let warnings = ast.as_ref().map(lint).unwrap_or_default();
+    Validation {
+        tokens,
+        ast,
+        diagnostics,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Extension;
+
+    fn messages(validation: &Validation) -> Vec<String> {
+        validation
+            .diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_valid_source() {
+        let validation = validate("var a = 1;\nprint a;");
+        assert!(validation.is_valid());
+        assert_eq!(9, validation.tokens.len());
+        assert_eq!(2, validation.ast.unwrap().len());
+    }
+
+    #[test]
+    fn test_validate_recovers_after_parse_errors() {
+        let validation = validate("print ;\nvar a = 1;\nvar = 2;\nprint a");
+        assert_eq!(
+            vec![
+                "[line 1] Error at ';': Expect expression",
+                "[line 3] Error at '=': Expect variable name.",
+                "[line 4] Error at end: Expect ';' after value.",
+            ],
+            messages(&validation)
+        );
+        assert_eq!(1, validation.ast.unwrap().len());
+    }
+
+    #[test]
+    fn test_validate_reports_every_scan_error() {
+        let validation = validate("var a = @;\nprint #;");
+        assert_eq!(
+            vec![
+                "[line 1] Error: Unexpected character: @",
+                "[line 2] Error: Unexpected character: #",
+            ],
+            messages(&validation)
+        );
+        assert!(validation.ast.is_none());
+        assert_eq!(
+            Some("EOF"),
+            validation
+                .tokens
+                .last()
+                .map(|t| t.typ.to_string())
+                .as_deref()
+        );
+    }
+
+    #[test]
+    fn test_validate_does_not_loop_on_keywords() {
+        let validation = validate("fun class while");
+        assert!(!validation.is_valid());
+        assert_eq!(Some(0), validation.ast.map(|ast| ast.len()));
+    }
+
+    #[test]
+    fn test_validate_warnings_keep_source_valid() {
+        let validation = validate("var f;\n{ var x = 1; }\nf();");
+        assert!(validation.is_valid());
+        let warnings = validation.warnings.iter().map(|w| w.message.as_str());
+        assert_eq!(
+            vec![
+                "Local variable 'x' is never read.",
+                "Calling nil always fails."
+            ],
+            warnings.collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_validate_with_dialect() {
+        assert!(!validate("print [1];").is_valid());
+        assert!(validate_with("print [1];", Dialect::new().with(Extension::Lists)).is_valid());
+    }
+}
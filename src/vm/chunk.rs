@@ -0,0 +1,143 @@
+use std::rc::Rc;
+
+use crate::Value;
+use crate::scanner::token::Token;
+
+/// An instruction of the virtual machine. Operands follow the opcode in the
+/// chunk's code, and indexes into the constant and token tables take two
+/// bytes, most significant first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OpCode {
+    /// Counts the start of a statement against the statement limit
+    Statement,
+    /// Pushes the constant at its operand
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    /// Pushes the local in the stack slot at its one-byte operand
+    GetLocal,
+    /// Stores the top of the stack in the slot at its operand, leaving it
+    /// pushed
+    SetLocal,
+    /// Pushes the global named by the token at its operand
+    GetGlobal,
+    /// Pops a value into a new global named by the token at its operand
+    DefineGlobal,
+    /// Like [`OpCode::DefineGlobal`], for a global declared with `const`
+    DefineConstant,
+    /// Stores the top of the stack in the global named by the token at its
+    /// operand, leaving it pushed
+    SetGlobal,
+    /// Applies the operator token at its operand to the top of the stack
+    Unary,
+    /// Applies the operator token at its operand to the top two values
+    Binary,
+    Print,
+    /// Jumps forward by its two-byte operand
+    Jump,
+    /// Jumps forward by its operand if the top of the stack is falsey,
+    /// without popping it
+    JumpIfFalse,
+    /// Jumps forward by its operand unless the top of the stack is nil,
+    /// without popping it
+    JumpIfNotNil,
+    /// Jumps backward by its operand
+    Loop,
+    /// Replaces the range on top of the stack with the numbers after its
+    /// first and pushes the first, or jumps forward by its operand if the
+    /// range is empty
+    ForRange,
+    /// Calls the value below the arguments on top of the stack, taking the
+    /// parenthesis token errors are reported at and then a one-byte count
+    /// of arguments
+    Call,
+    Return,
+}
+
+impl OpCode {
+    const ALL: [OpCode; 22] = [
+        OpCode::Statement,
+        OpCode::Constant,
+        OpCode::Nil,
+        OpCode::True,
+        OpCode::False,
+        OpCode::Pop,
+        OpCode::GetLocal,
+        OpCode::SetLocal,
+        OpCode::GetGlobal,
+        OpCode::DefineGlobal,
+        OpCode::DefineConstant,
+        OpCode::SetGlobal,
+        OpCode::Unary,
+        OpCode::Binary,
+        OpCode::Print,
+        OpCode::Jump,
+        OpCode::JumpIfFalse,
+        OpCode::JumpIfNotNil,
+        OpCode::Loop,
+        OpCode::ForRange,
+        OpCode::Call,
+        OpCode::Return,
+    ];
+}
+
+impl TryFrom<u8> for OpCode {
+    type Error = u8;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        Self::ALL.get(byte as usize).copied().ok_or(byte)
+    }
+}
+
+/// A sequence of bytecode with the values and tokens it refers to.
+#[derive(Debug, Clone, Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    /// The source line of each byte in `code`
+    pub lines: Vec<u32>,
+    pub constants: Vec<Value>,
+    /// Variable names and operators, kept whole so that runtime errors
+    /// point at them exactly like the tree-walking interpreter's. Operators
+    /// are shared with the tree they were compiled from.
+    pub tokens: Vec<Rc<Token>>,
+}
+
+impl Chunk {
+    pub fn write(&mut self, byte: u8, line: u32) {
+        self.code.push(byte);
+        self.lines.push(line);
+    }
+
+    /// Adds `value` to the constant table and returns its index.
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    /// Adds `token` to the token table and returns its index.
+    pub fn add_token(&mut self, token: Rc<Token>) -> usize {
+        self.tokens.push(token);
+        self.tokens.len() - 1
+    }
+
+    /// Reads the two-byte operand at `offset`.
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.code[offset], self.code[offset + 1]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opcode_round_trips_through_bytes() {
+        for op in OpCode::ALL {
+            assert_eq!(Ok(op), OpCode::try_from(op as u8));
+        }
+        assert_eq!(Err(200), OpCode::try_from(200));
+    }
+}
@@ -0,0 +1,218 @@
+//! Printing chunks as a listing of their instructions, for `run
+//! --dump-bytecode`.
+//!
+//! Each line holds the offset of an instruction, its source line or `|` when
+//! it is on the same line as the previous one, the opcode name and its
+//! operands, such as `0003    | OP_GET_GLOBAL       1 'x'`.
+
+use std::fmt::Write as _;
+
+use crate::vm::chunk::{Chunk, OpCode};
+
+impl OpCode {
+    /// Returns the name of the opcode in the style of clox, such as
+    /// `OP_GET_LOCAL`.
+    pub fn name(self) -> &'static str {
+        match self {
+            OpCode::Statement => "OP_STATEMENT",
+            OpCode::Constant => "OP_CONSTANT",
+            OpCode::Nil => "OP_NIL",
+            OpCode::True => "OP_TRUE",
+            OpCode::False => "OP_FALSE",
+            OpCode::Pop => "OP_POP",
+            OpCode::GetLocal => "OP_GET_LOCAL",
+            OpCode::SetLocal => "OP_SET_LOCAL",
+            OpCode::GetGlobal => "OP_GET_GLOBAL",
+            OpCode::DefineGlobal => "OP_DEFINE_GLOBAL",
+            OpCode::DefineConstant => "OP_DEFINE_CONSTANT",
+            OpCode::SetGlobal => "OP_SET_GLOBAL",
+            OpCode::Unary => "OP_UNARY",
+            OpCode::Binary => "OP_BINARY",
+            OpCode::Print => "OP_PRINT",
+            OpCode::Jump => "OP_JUMP",
+            OpCode::JumpIfFalse => "OP_JUMP_IF_FALSE",
+            OpCode::JumpIfNotNil => "OP_JUMP_IF_NOT_NIL",
+            OpCode::Loop => "OP_LOOP",
+            OpCode::ForRange => "OP_FOR_RANGE",
+            OpCode::Call => "OP_CALL",
+            OpCode::Return => "OP_RETURN",
+        }
+    }
+}
+
+/// Returns a listing of every instruction in `chunk` under a `== name ==`
+/// header.
+pub fn disassemble(chunk: &Chunk, name: &str) -> String {
+    let mut listing = format!("== {name} ==\n");
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        let (line, next) = disassemble_instruction(chunk, offset);
+        listing.push_str(&line);
+        listing.push('\n');
+        offset = next;
+    }
+    listing
+}
+
+/// Returns the listing line of the instruction at `offset` and the offset of
+/// the instruction after it.
+pub fn disassemble_instruction(chunk: &Chunk, offset: usize) -> (String, usize) {
+    let mut line = format!("{offset:04} ");
+    if offset > 0 && chunk.lines[offset] == chunk.lines[offset - 1] {
+        line.push_str("   | ");
+    } else {
+        write!(line, "{:4} ", chunk.lines[offset]).unwrap();
+    }
+
+    let op = match OpCode::try_from(chunk.code[offset]) {
+        Ok(op) => op,
+        Err(byte) => {
+            write!(line, "Unknown opcode {byte}").unwrap();
+            return (line, offset + 1);
+        }
+    };
+    let name = op.name();
+    let next = match op {
+        OpCode::Constant => {
+            let index = chunk.read_u16(offset + 1);
+            let value = &chunk.constants[index as usize];
+            write!(line, "{name:<18} {index:4} '{value}'").unwrap();
+            offset + 3
+        }
+        OpCode::GetGlobal
+        | OpCode::DefineGlobal
+        | OpCode::DefineConstant
+        | OpCode::SetGlobal
+        | OpCode::Unary
+        | OpCode::Binary => {
+            let index = chunk.read_u16(offset + 1);
+            let token = &chunk.tokens[index as usize];
+            write!(line, "{name:<18} {index:4} '{}'", token.lexeme).unwrap();
+            offset + 3
+        }
+        OpCode::GetLocal | OpCode::SetLocal => {
+            let slot = chunk.code[offset + 1];
+            write!(line, "{name:<18} {slot:4}").unwrap();
+            offset + 2
+        }
+        OpCode::Jump
+        | OpCode::JumpIfFalse
+        | OpCode::JumpIfNotNil
+        | OpCode::Loop
+        | OpCode::ForRange => {
+            let distance = chunk.read_u16(offset + 1) as usize;
+            let target = match op {
+                OpCode::Loop => offset + 3 - distance,
+                _ => offset + 3 + distance,
+            };
+            write!(line, "{name:<18} {offset:4} -> {target}").unwrap();
+            offset + 3
+        }
+        OpCode::Call => {
+            let count = chunk.code[offset + 3];
+            write!(line, "{name:<18} {count:4} args").unwrap();
+            offset + 4
+        }
+        OpCode::Statement
+        | OpCode::Nil
+        | OpCode::True
+        | OpCode::False
+        | OpCode::Pop
+        | OpCode::Print
+        | OpCode::Return => {
+            line.push_str(name);
+            offset + 1
+        }
+    };
+    (line, next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::compile;
+    use crate::dialect::{Dialect, Extension};
+    use crate::validate::validate_with;
+
+    fn listing(source: &str) -> String {
+        let dialect: Dialect = [Extension::DoWhile, Extension::ForIn, Extension::Ranges]
+            .into_iter()
+            .collect();
+        let chunk = compile(&validate_with(source, dialect).ast.unwrap()).unwrap();
+        disassemble(&chunk, "script")
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let source = "var x = 1;\n{\n  var y = x + 2;\n  print len(\"ab\") == y;\n}\n";
+        let expected = "\
+== script ==
+0000    1 OP_STATEMENT
+0001    | OP_CONSTANT           0 '1'
+0004    | OP_DEFINE_GLOBAL      0 'x'
+0007    2 OP_STATEMENT
+0008    3 OP_STATEMENT
+0009    | OP_GET_GLOBAL         1 'x'
+0012    | OP_CONSTANT           1 '2'
+0015    | OP_BINARY             2 '+'
+0018    4 OP_STATEMENT
+0019    | OP_GET_GLOBAL         3 'len'
+0022    | OP_CONSTANT           2 'ab'
+0025    | OP_CALL               1 args
+0029    | OP_GET_LOCAL          0
+0031    | OP_BINARY             5 '=='
+0034    | OP_PRINT
+0035    | OP_POP
+0036    | OP_RETURN
+";
+        assert_eq!(expected, listing(source));
+    }
+
+    #[test]
+    fn test_disassemble_jumps() {
+        let expected = "\
+== script ==
+0000    1 OP_STATEMENT
+0001    | OP_STATEMENT
+0002    | OP_TRUE
+0003    | OP_PRINT
+0004    | OP_FALSE
+0005    | OP_JUMP_IF_FALSE      5 -> 12
+0008    | OP_POP
+0009    | OP_LOOP               9 -> 1
+0012    | OP_POP
+0013    | OP_RETURN
+";
+        assert_eq!(expected, listing("do print true; while (false);"));
+    }
+
+    #[test]
+    fn test_disassemble_for_range() {
+        let expected = "\
+== script ==
+0000    1 OP_STATEMENT
+0001    | OP_CONSTANT           0 '0'
+0004    | OP_CONSTANT           1 '2'
+0007    | OP_BINARY             0 '..'
+0010    | OP_FOR_RANGE         10 -> 21
+0013    | OP_STATEMENT
+0014    | OP_GET_LOCAL          1
+0016    | OP_PRINT
+0017    | OP_POP
+0018    | OP_LOOP              18 -> 10
+0021    | OP_POP
+0022    | OP_RETURN
+";
+        assert_eq!(expected, listing("for (i in 0..2) print i;"));
+    }
+
+    #[test]
+    fn test_disassemble_unknown_opcode() {
+        let mut chunk = Chunk::default();
+        chunk.write(255, 1);
+        assert_eq!(
+            "== bad ==\n0000    1 Unknown opcode 255\n",
+            disassemble(&chunk, "bad")
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! A stack-based virtual machine that runs the bytecode produced by the
+//! [`compiler`](crate::compiler), selected with `run --backend vm`.
+//!
+//! Globals, natives and the interpreter settings, such as the dialect and
+//! arithmetic checks, come from the [`Interpreter`] the VM runs in.
+
+use crate::Value;
+use crate::interpreter::Interpreter;
+use crate::interpreter::error::RuntimeError;
+use crate::vm::chunk::{Chunk, OpCode};
+
+pub mod chunk;
+pub mod debug;
+
+/// What runs a program.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    /// The tree-walking interpreter, which supports the whole language
+    #[default]
+    Tree,
+    /// The bytecode virtual machine
+    Vm,
+}
+
+pub struct Vm<'a> {
+    interpreter: &'a mut Interpreter,
+    stack: Vec<Value>,
+}
+
+impl<'a> Vm<'a> {
+    /// Creates a VM using the globals and settings of `interpreter`.
+    pub fn new(interpreter: &'a mut Interpreter) -> Self {
+        Self {
+            interpreter,
+            stack: Vec::new(),
+        }
+    }
+
+    /// Runs `chunk` until it returns.
+    ///
+    /// Returns the [`RuntimeError`] that stopped it, if any.
+    pub fn run(&mut self, chunk: &Chunk) -> Result<(), RuntimeError> {
+        self.stack.clear();
+        let mut ip = 0;
+        loop {
+            let byte = chunk.code[ip];
+            let op = OpCode::try_from(byte)
+                .unwrap_or_else(|byte| panic!("the compiler never emits opcode {byte}"));
+            ip += 1;
+            match op {
+                OpCode::Statement => self.interpreter.spend_statement()?,
+                OpCode::Constant => {
+                    let index = chunk.read_u16(ip) as usize;
+                    ip += 2;
+                    self.stack.push(chunk.constants[index].clone());
+                }
+                OpCode::Nil => self.stack.push(Value::Nil),
+                OpCode::True => self.stack.push(true.into()),
+                OpCode::False => self.stack.push(false.into()),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::GetLocal => {
+                    let slot = chunk.code[ip] as usize;
+                    ip += 1;
+                    self.stack.push(self.stack[slot].clone());
+                }
+                OpCode::SetLocal => {
+                    let slot = chunk.code[ip] as usize;
+                    ip += 1;
+                    self.stack[slot] = self.peek().clone();
+                }
+                OpCode::GetGlobal => {
+                    let name = &chunk.tokens[chunk.read_u16(ip) as usize];
+                    ip += 2;
+                    let value = self.interpreter.get(name)?;
+                    self.stack.push(value);
+                }
+                OpCode::DefineGlobal | OpCode::DefineConstant => {
+                    let name = &chunk.tokens[chunk.read_u16(ip) as usize];
+                    ip += 2;
+                    let value = self.pop();
+                    let constant = op == OpCode::DefineConstant;
+                    self.interpreter.declare(name, value, constant)?;
+                }
+                OpCode::SetGlobal => {
+                    let name = &chunk.tokens[chunk.read_u16(ip) as usize];
+                    ip += 2;
+                    self.interpreter.assign(name, self.peek().clone())?;
+                }
+                OpCode::Unary => {
+                    let op = &chunk.tokens[chunk.read_u16(ip) as usize];
+                    ip += 2;
+                    let right = self.pop();
+                    let value = self.interpreter.unary(op, right)?;
+                    self.stack.push(value);
+                }
+                OpCode::Binary => {
+                    let op = &chunk.tokens[chunk.read_u16(ip) as usize];
+                    ip += 2;
+                    let right = self.pop();
+                    let left = self.pop();
+                    let value = self.interpreter.binary(op, left, right)?;
+                    self.stack.push(value);
+                }
+                OpCode::Print => {
+                    let value = self.pop();
+                    self.interpreter.print(&value);
+                }
+                OpCode::Jump => ip += 2 + chunk.read_u16(ip) as usize,
+                OpCode::JumpIfFalse => {
+                    let jump = !self.peek().is_truthy();
+                    ip += 2 + if jump { chunk.read_u16(ip) as usize } else { 0 };
+                }
+                OpCode::JumpIfNotNil => {
+                    let jump = *self.peek() != Value::Nil;
+                    ip += 2 + if jump { chunk.read_u16(ip) as usize } else { 0 };
+                }
+                OpCode::Loop => ip = ip + 2 - chunk.read_u16(ip) as usize,
+                OpCode::ForRange => {
+                    let Value::Range(range) = *self.peek() else {
+                        panic!("the compiler only loops over ranges");
+                    };
+                    match range.split_first() {
+                        Some((n, rest)) => {
+                            *self.stack.last_mut().unwrap() = rest.into();
+                            self.stack.push(self.interpreter.integral(n));
+                            ip += 2;
+                        }
+                        None => ip += 2 + chunk.read_u16(ip) as usize,
+                    }
+                }
+                OpCode::Call => {
+                    let paren = &chunk.tokens[chunk.read_u16(ip) as usize];
+                    let count = chunk.code[ip + 2] as usize;
+                    ip += 3;
+                    let arguments = self.stack.split_off(self.stack.len() - count);
+                    let callee = self.pop();
+                    let value = self.interpreter.call(callee, &arguments, paren)?;
+                    self.stack.push(value);
+                }
+                OpCode::Return => return Ok(()),
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("the compiler balances the stack")
+    }
+
+    fn peek(&self) -> &Value {
+        self.stack.last().expect("the compiler balances the stack")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::compiler::compile;
+    use crate::dialect::{Dialect, Extension};
+    use crate::interpreter::limits::{Limit, Resource};
+    use crate::scanner::token::{Token, TokenType};
+    use crate::validate::validate_with;
+
+    fn dialect() -> Dialect {
+        [
+            Extension::DoWhile,
+            Extension::NilCoalescing,
+            Extension::Integers,
+            Extension::Constants,
+            Extension::ForIn,
+            Extension::Ranges,
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    /// Runs `source` on the VM and returns the global `out`.
+    fn run(source: &str) -> Result<String, RuntimeError> {
+        let program = validate_with(source, dialect()).ast.unwrap();
+        let chunk = compile(&program).unwrap();
+        let mut interpreter = Interpreter::new().with_dialect(dialect());
+        Vm::new(&mut interpreter).run(&chunk)?;
+        let out = Token::new(TokenType::Identifier, "out".into(), None, 1);
+        Ok(interpreter.get(&out)?.to_string())
+    }
+
+    #[rstest]
+    #[case("var out = 1 + 2 * 3;", "7")]
+    #[case("var out = -(1 - 4) == 3 == !nil;", "true")]
+    #[case("var out = \"a\" + \"b\";", "ab")]
+    #[case("var out; { var a = 1; { var b = a + 1; out = b; } }", "2")]
+    #[case("var out; { var a = 1; { var a = 5; } out = a; }", "1")]
+    #[case("var out = 0; do out = out + 1; while (out < 10);", "10")]
+    #[case(
+        "var out = 0; { var i = 0; do { out = out + i; i = i + 1; } while (i < 4); }",
+        "6"
+    )]
+    #[case("var x; var out = x ?? \"fallback\";", "fallback")]
+    #[case("var out = 1 ?? undefined;", "1")]
+    #[case("var out = len(\"four\");", "4")]
+    #[case("const c = 2; var out = c * 21;", "42")]
+    #[case("var out = 0; for (var i in 1..=4) out = out + i;", "10")]
+    #[case(
+        "var out = 0; for (i in 3..0) { var d = i; out = out * 10 + d; }",
+        "321"
+    )]
+    #[case("var out = 0; for (i in 0..2) for (j in 0..3) out = out + 1;", "6")]
+    #[case("var out = \"never\"; for (i in 5..5) out = i;", "never")]
+    fn test_vm_runs(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(
+            Ok(expected.to_string()),
+            run(source).map_err(|e| e.to_string())
+        );
+    }
+
+    #[rstest]
+    #[case("var out = -\"a\";", "Operand must be a number.\n[line 1]")]
+    #[case("var out = missing;", "Undefined variable 'missing'.\n[line 1]")]
+    #[case(
+        "var out;\nfor (i in 0..1.5) out = i;",
+        "Operands must be integers.\n[line 2]"
+    )]
+    #[case(
+        "var out = 1;\nout();",
+        "Can only call functions and classes.\n[line 2]"
+    )]
+    fn test_vm_errors(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(
+            Err(expected.to_string()),
+            run(source).map_err(|e| e.to_string())
+        );
+    }
+
+    #[test]
+    fn test_vm_counts_statements_like_the_tree() {
+        let source = "var out = 0; for (i in 0..3) { out = out + i; }";
+        let program = validate_with(source, dialect()).ast.unwrap();
+        let chunk = compile(&program).unwrap();
+        let limit = Limit {
+            resource: Resource::Statements,
+            max: 7,
+        };
+
+        let mut tree = Interpreter::new().with_dialect(dialect());
+        tree.interpret(&program).unwrap();
+        let mut interpreter = Interpreter::new().with_dialect(dialect());
+        Vm::new(&mut interpreter).run(&chunk).unwrap();
+        assert_eq!(tree.stats().statements, interpreter.stats().statements);
+
+        let mut interpreter = Interpreter::new()
+            .with_dialect(dialect())
+            .with_limits([limit]);
+        let err = Vm::new(&mut interpreter).run(&chunk).unwrap_err();
+        assert_eq!(Some(limit), err.exceeded_limit());
+    }
+}
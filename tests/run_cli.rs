@@ -1,6 +1,6 @@
 use std::fs;
 use std::path::PathBuf;
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 use rstest::rstest;
 use tempdir::TempDir;
@@ -13,6 +13,10 @@ fn write_temp_lox(tempdir: &TempDir, source: &str) -> PathBuf {
 }
 
 fn run_source(source: &str) -> std::process::Output {
+    run_source_with_args(source, &[])
+}
+
+fn run_source_with_args(source: &str, args: &[&str]) -> std::process::Output {
     // TempDir is removed automatically when dropped at the end of the helper scope.
     let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
     let file = write_temp_lox(&tempdir, source);
@@ -20,6 +24,7 @@ fn run_source(source: &str) -> std::process::Output {
     // Cargo injects this env var for integration tests; it points to the built CLI binary.
     Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
         .arg("run")
+        .args(args)
         .arg(&file)
         .output()
         .expect("binary should run")
@@ -71,6 +76,36 @@ fn test_block_requires_closing_brace_reports_static_error_and_exit_65() {
     assert!(stderr.contains("[line 10] Error at end: Expect '}' after block."));
 }
 
+#[rstest]
+#[case("run", "@ # $ print 1;", "@#$")]
+#[case("run", "and or & | ^", "&|^")]
+#[case("parse", "@ # $ print 1;", "@#$")]
+#[case("parse", "and or & | ^", "&|^")]
+#[case("evaluate", "@ # $ print 1;", "@#$")]
+#[case("evaluate", "and or & | ^", "&|^")]
+fn test_scan_errors_are_all_reported_before_parsing(
+    #[case] subcommand: &str,
+    #[case] source: &str,
+    #[case] unexpected: &str,
+) {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, source);
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg(subcommand)
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert_eq!(Some(65), output.status.code());
+
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    let expected = unexpected
+        .chars()
+        .map(|c| format!("[line 1] Error: Unexpected character: {c}\n"))
+        .collect::<String>();
+    assert_eq!(expected, stderr);
+}
+
 #[rstest]
 #[case(
     r#"
@@ -552,3 +587,1415 @@ fn test_scope_runtime_error_reports_stderr_and_exit_70() {
     assert!(stderr.contains("Undefined variable 'world'."));
     assert!(stderr.contains("[line 17]"));
 }
+
+#[test]
+fn test_enable_string_repetition_extension() {
+    let source = r#"
+    print "ab" * 3;
+    "#;
+
+    let output = run_source_with_args(source, &["--enable", "string-repetition"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("ababab\n", stdout);
+
+    let output = run_source(source);
+    assert_eq!(Some(70), output.status.code());
+}
+
+#[test]
+fn test_lists_share_elements_by_reference() {
+    let source = r#"
+    var xs = [1, 2, 3];
+    var ys = xs;
+    ys[0] = "one";
+    print xs;
+    print xs == ys;
+    xs[2] = xs;
+    print ys;
+    print xs[2][2][1];
+    "#;
+
+    let output = run_source_with_args(source, &["--enable", "lists"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("[one, 2, 3]\ntrue\n[one, 2, [...]]\n2\n", stdout);
+}
+
+#[test]
+fn test_maps() {
+    let source = r#"
+    var ages = {"ada": 36, "alan": 41};
+    ages["grace"] = 85;
+    ages["ada"] = ages["ada"] + 1;
+    print ages;
+    var self = {};
+    self["self"] = self;
+    print self;
+    "#;
+
+    let output = run_source_with_args(source, &["--enable", "lists,maps"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("{ada: 37, alan: 41, grace: 85}\n{self: {...}}\n", stdout);
+}
+
+#[test]
+fn test_for_in_loops() {
+    let source = r#"
+    for (x in [1, "two"]) print x;
+    for (key in {"a": 1, "b": 2}) print key;
+    for (c in "hi") print c;
+    for (i in 3) print i;
+    "#;
+
+    let output = run_source_with_args(source, &["--enable", "for-in,lists,maps"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("1\ntwo\na\nb\nh\ni\n0\n1\n2\n", stdout);
+}
+
+#[test]
+fn test_ranges() {
+    let source = r#"
+    print 0..3;
+    for (i in 5..=1) print i;
+    for (i in step(0..10, 4)) print i;
+    print contains(0..=3, 3);
+    "#;
+
+    let output = run_source_with_args(source, &["--enable", "ranges,for-in,integers"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("0..3\n5\n4\n3\n2\n1\n0\n4\n8\ntrue\n", stdout);
+}
+
+#[test]
+fn test_list_index_out_of_bounds() {
+    let output = run_source_with_args("var xs = [1];\nprint xs[1];", &["--enable", "lists"]);
+    assert_eq!(Some(70), output.status.code());
+
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!(
+        "List index 1 is out of bounds for length 1.\n[line 2]\n",
+        stderr
+    );
+}
+
+#[test]
+fn test_native_functions() {
+    let source = r#"
+    var sum = 0.1 + 0.2;
+    print sum == 0.3;
+    print approxEquals(sum, 0.3, 0.000001);
+    print isNan(sum);
+    print isInfinite;
+    "#;
+
+    assert_success_output(source, "false\ntrue\nfalse\n<native fn>\n");
+}
+
+#[test]
+fn test_arithmetic_checks_warn() {
+    let source = r#"
+    var big = 1e308 * 10;
+    print big - big;
+    "#;
+
+    let args = [
+        "--enable",
+        "numeric-literals",
+        "--arithmetic-checks",
+        "warn",
+    ];
+    let output = run_source_with_args(source, &args);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("NaN\n", stdout);
+
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!(
+        "[line 2] Warning at '*': Arithmetic overflowed to infinity.\n\
+         [line 3] Warning at '-': Arithmetic produced NaN.\n",
+        stderr
+    );
+}
+
+#[test]
+fn test_arithmetic_checks_error() {
+    let output = run_source_with_args("print -0;", &["--arithmetic-checks", "error"]);
+    assert_eq!(Some(70), output.status.code());
+
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!("Arithmetic produced negative zero.\n[line 1]\n", stderr);
+
+    let output = run_source("print -0;");
+    assert!(output.status.success());
+}
+
+#[rstest]
+#[case(&["--arithmetic-checks", "warn", "--deny", "arithmetic"], Some(70))]
+#[case(&["--deny", "arithmetic"], Some(70))]
+#[case(&["--arithmetic-checks", "warn", "--allow", "warnings"], Some(0))]
+fn test_arithmetic_checks_levels(#[case] args: &[&str], #[case] code: Option<i32>) {
+    let output = run_source_with_args("print -0;", args);
+    assert_eq!(code, output.status.code());
+    if code == Some(0) {
+        assert!(output.stderr.is_empty());
+    }
+}
+
+#[rstest]
+#[case("bytes", "5\n")]
+#[case("chars", "4\n")]
+#[case("graphemes", "3\n")]
+fn test_string_units(#[case] unit: &str, #[case] expected_stdout: &str) {
+    let output = run_source_with_args("print len(\"o\u{308}k!\");", &["--string-units", unit]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!(expected_stdout, stdout);
+}
+
+#[rstest]
+#[case("bash")]
+#[case("zsh")]
+#[case("fish")]
+#[case("powershell")]
+fn test_completions_cover_subcommands(#[case] shell: &str) {
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["completions", shell])
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    for subcommand in ["tokenize", "parse", "evaluate", "run"] {
+        assert!(stdout.contains(subcommand), "missing {subcommand}");
+    }
+    assert!(stdout.contains("enable"));
+}
+
+#[test]
+fn test_version_verbose_reports_build_info() {
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["version", "--verbose"])
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(
+        format!("codecrafters-interpreter {}", env!("CARGO_PKG_VERSION")),
+        lines[0]
+    );
+    assert!(lines[1].starts_with("git: "));
+    assert!(lines[2].starts_with("features: "));
+    assert!(lines[3].starts_with("extensions: "));
+    assert!(lines[3].contains("string-repetition"));
+}
+
+#[test]
+fn test_closed_stdout_fails_with_io_error() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    // more tokens than a pipe buffers, so that writing them outlasts the reader
+    let file = write_temp_lox(&tempdir, &"a ".repeat(100_000));
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("tokenize")
+        .arg(&file)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("binary should run");
+    drop(child.stdout.take());
+    let output = child.wait_with_output().expect("binary should finish");
+
+    assert_eq!(Some(1), output.status.code());
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert!(stderr.starts_with("Failed to write output: "), "{stderr}");
+}
+
+#[test]
+fn test_introspect_json_describes_cli() {
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["introspect", "--json"])
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be json");
+    assert_eq!(1, manifest["schema_version"]);
+
+    let subcommands = manifest["subcommands"]
+        .as_array()
+        .expect("subcommands should be an array")
+        .iter()
+        .map(|s| s["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    for name in ["tokenize", "parse", "evaluate", "run", "introspect"] {
+        assert!(subcommands.contains(&name), "missing {name}");
+    }
+
+    assert_eq!("enable", manifest["global_flags"][0]["long"]);
+    assert!(
+        manifest["diagnostic_codes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|d| d["code"] == "runtime" && d["exit_code"] == 70)
+    );
+    assert!(
+        manifest["lint_rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|r| r["code"] == "empty-block" && r["level"] == "allow")
+    );
+    assert!(
+        manifest["natives"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|n| n["name"] == "approxEquals" && n["arity"] == 3)
+    );
+}
+
+#[rstest]
+#[case(&[], "Outputs match.\n", Some(0))]
+#[case(
+    &["--enable", "integers"],
+    "stdout line 2:\n  ours:      NUMBER 1 1\n  reference: NUMBER 1 1.0\n",
+    Some(3)
+)]
+fn test_compare_reference(
+    #[case] args: &[&str],
+    #[case] expected_stdout: &str,
+    #[case] expected_code: Option<i32>,
+) {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "print 1;\n");
+
+    // the standard dialect of this binary stands in for the reference implementation
+    let binary = env!("CARGO_BIN_EXE_codecrafters-interpreter");
+    let output = Command::new(binary)
+        .args(["--compare-reference", binary, "tokenize"])
+        .args(args)
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert_eq!(expected_code, output.status.code());
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!(expected_stdout, stdout);
+}
+
+#[test]
+fn test_compare_reference_requires_source_subcommand() {
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["version", "--compare-reference", "jlox"])
+        .output()
+        .expect("binary should run");
+
+    assert_eq!(Some(2), output.status.code());
+}
+
+#[test]
+fn test_read_line_and_eprint() {
+    use std::io::Write as _;
+    use std::process::Stdio;
+
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(
+        &tempdir,
+        "print readLine();\neprint(readLine());\nprint readLine();\n",
+    );
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("run")
+        .arg(&file)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("binary should run");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"first\r\nsecond\n")
+        .expect("should write stdin");
+    let output = child.wait_with_output().expect("binary should finish");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("first\nnil\n", stdout);
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!("second\n", stderr);
+}
+
+#[test]
+fn test_sandbox_denies_read_file() {
+    let output = run_source_with_args("print readFile(\"test.lox\");", &["--sandbox"]);
+    assert_eq!(Some(70), output.status.code());
+
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!("Filesystem access is disabled.\n[line 1]\n", stderr);
+}
+
+#[test]
+fn test_limits_abort_with_exit_75() {
+    let source = "var i = 0;\nfor (x in 100) i = i + 1;\nprint i;\n";
+
+    let output = run_source_with_args(source, &["--enable", "for-in", "--limits", "statements=50"]);
+    assert_eq!(Some(75), output.status.code());
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!("Limit exceeded: statements=50.\n", stderr);
+
+    let output = run_source_with_args(
+        source,
+        &["--enable", "for-in", "--limits", "statements=103"],
+    );
+    assert!(output.status.success());
+}
+
+#[rstest]
+#[case("tree")]
+#[case("vm")]
+fn test_statement_limit_applies_to_every_backend(#[case] backend: &str) {
+    let source = "print 1;\nprint 2;\nprint 3;\n";
+
+    let output = run_source_with_args(source, &["--backend", backend, "--limits", "statements=1"]);
+    assert_eq!(Some(75), output.status.code());
+    assert_eq!("1\n", String::from_utf8_lossy(&output.stdout));
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!("Limit exceeded: statements=1.\n", stderr);
+}
+
+#[rstest]
+#[case("tokens=5", "[line 2] Error: Input too large: tokens=5.\n")]
+#[case("bytes=16", "[line 1] Error: Input too large: bytes=16.\n")]
+fn test_source_limits_abort_with_exit_75(#[case] limit: &str, #[case] expected: &str) {
+    let source = "print 1;\nprint 2;\n";
+
+    let output = run_source_with_args(source, &["--limits", limit]);
+    assert_eq!(Some(75), output.status.code());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!(expected, stderr);
+
+    let output = run_source_with_args(source, &["--limits", "tokens=6,bytes=18"]);
+    assert!(output.status.success());
+}
+
+#[rstest]
+#[case("tokenize")]
+#[case("parse")]
+#[case("evaluate")]
+#[case("lint")]
+fn test_source_limits_apply_to_every_subcommand(#[case] subcommand: &str) {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "1 + 2 + 3");
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args([subcommand, "--limits", "tokens=3"])
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert_eq!(Some(75), output.status.code());
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert!(stderr.ends_with("[line 1] Error: Input too large: tokens=3.\n"));
+}
+
+#[test]
+fn test_uncaught_throw_reports_runtime_error() {
+    let source =
+        "try {\n  print 1 / 0;\n} catch (e) {\n  print errorMessage(e);\n}\nthrow \"done\";\n";
+
+    let output = run_source_with_args(source, &["--enable", "exceptions"]);
+    assert_eq!(Some(70), output.status.code());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("Division by 0\n", stdout);
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!("done\n[line 6]\n", stderr);
+}
+
+#[test]
+fn test_diagnostics_json() {
+    let output = run_source_with_args("print 1 +;", &["--diagnostics", "json"]);
+    assert_eq!(Some(65), output.status.code());
+
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!(
+        "{\"code\":\"static\",\"column\":10,\"file\":null,\"line\":1,\"location\":\"at ';'\",\
+         \"message\":\"Expect expression\",\"severity\":\"error\"}\n",
+        stderr
+    );
+}
+
+#[test]
+fn test_diagnostics_rich() {
+    let output = run_source_with_args("var a = 1;\nprint -\"a\";", &["--diagnostics", "rich"]);
+    assert_eq!(Some(70), output.status.code());
+
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!(
+        "error[runtime]: Operand must be a number.\n \
+         --> 2:7\n  \
+         |\n\
+         2 | print -\"a\";\n  \
+         |       ^\n",
+        stderr
+    );
+}
+
+#[test]
+fn test_diagnostics_rich_quotes_imported_modules() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    fs::write(tempdir.path().join("lib.lox"), "var a = 1;\nprint -\"a\";")
+        .expect("should write module");
+    let main = write_temp_lox(&tempdir, "import \"lib.lox\";");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["--diagnostics", "rich", "run", "--enable", "modules"])
+        .arg(&main)
+        .output()
+        .expect("binary should run");
+    assert_eq!(Some(70), output.status.code());
+
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!(
+        "error[runtime]: Operand must be a number.\n \
+         --> lib.lox:2:7\n  \
+         |\n\
+         2 | print -\"a\";\n  \
+         |       ^\n",
+        stderr
+    );
+}
+
+#[test]
+fn test_diff_ignores_whitespace_and_comments() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let old = tempdir.path().join("old.lox");
+    let new = tempdir.path().join("new.lox");
+    fs::write(&old, "var a = 1;\nprint a;").expect("should write old file");
+    fs::write(&new, "// renamed\nvar b =   1;\n\nprint b;").expect("should write new file");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("diff")
+        .args([&old, &new])
+        .output()
+        .expect("binary should run");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!(
+        "- 1:5 IDENTIFIER a null\n\
+         + 2:5 IDENTIFIER b null\n\
+         - 2:7 IDENTIFIER a null\n\
+         + 4:7 IDENTIFIER b null\n",
+        stdout
+    );
+}
+
+#[test]
+fn test_assert_failure_exits_70() {
+    let source = "var xs = 3;\nassert xs > 1;\nassert xs == 4, \"count\";\nprint \"unreachable\";";
+    let output = run_source_with_args(source, &["--enable", "assertions"]);
+    assert_eq!(Some(70), output.status.code());
+    assert!(output.stdout.is_empty());
+
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!("Assertion failed: xs == 4: count\n[line 3]\n", stderr);
+}
+
+#[test]
+fn test_import_relative_to_script() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let main = tempdir.path().join("main.lox");
+    fs::create_dir(tempdir.path().join("lib")).expect("should create lib dir");
+    fs::write(
+        &main,
+        "import \"lib/greet.lox\";\nimport greeting from \"lib/greet.lox\";\nprint greeting;",
+    )
+    .expect("should write main file");
+    fs::write(
+        tempdir.path().join("lib/greet.lox"),
+        "print \"loading\";\nvar greeting = \"hi\";",
+    )
+    .expect("should write module");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["run", "--enable", "modules"])
+        .arg(&main)
+        .output()
+        .expect("binary should run");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("loading\nhi\n", stdout);
+}
+
+#[test]
+fn test_import_reports_every_error_in_module() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    fs::write(tempdir.path().join("lib.lox"), "print;\nvar = 2;").expect("should write module");
+    let main = write_temp_lox(&tempdir, "print 1;\nimport \"lib.lox\";");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["--diagnostics", "json", "run", "--enable", "modules"])
+        .arg(&main)
+        .output()
+        .expect("binary should run");
+    assert_eq!(Some(70), output.status.code());
+
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    let diagnostics = stderr
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .map(|d| (d["file"].clone(), d["line"].clone(), d["code"].clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            (serde_json::Value::Null, 2.into(), "runtime".into()),
+            ("lib.lox".into(), 1.into(), "static".into()),
+            ("lib.lox".into(), 2.into(), "static".into()),
+        ],
+        diagnostics
+    );
+}
+
+#[test]
+fn test_import_path_from_environment() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let shared = tempdir.path().join("shared");
+    fs::create_dir(&shared).expect("should create shared dir");
+    fs::write(shared.join("greet.lox"), "var greeting = \"hi\";").expect("should write module");
+    let main = write_temp_lox(
+        &tempdir,
+        "import greeting from \"${LOX_LIB}/greet.lox\";\nprint greeting;",
+    );
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+            .args(["run", "--enable", "modules,interpolation"])
+            .args(args)
+            .arg(&main)
+            .env("LOX_LIB", &shared)
+            .output()
+            .expect("binary should run")
+    };
+
+    let output = run(&[]);
+    assert!(output.status.success());
+    assert_eq!("hi\n", String::from_utf8_lossy(&output.stdout));
+
+    let output = run(&["--sandbox"]);
+    assert_eq!(Some(70), output.status.code());
+    assert_eq!(
+        "Filesystem access is disabled.\n[line 1]\n",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_import_from_module_path() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let shared = tempdir.path().join("shared");
+    fs::create_dir(&shared).expect("should create shared dir");
+    fs::write(shared.join("greet.lox"), "var greeting = \"hi\";").expect("should write module");
+    let main = write_temp_lox(
+        &tempdir,
+        "import greeting from \"greet.lox\";\nprint \"${greeting}!\";",
+    );
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+            .args(["run", "--enable", "modules,interpolation"])
+            .args(args)
+            .arg(&main)
+            .env("LOX_LIB", &shared)
+            .output()
+            .expect("binary should run")
+    };
+
+    let output = run(&["--module-path", "${LOX_LIB}"]);
+    assert!(output.status.success());
+    assert_eq!("hi!\n", String::from_utf8_lossy(&output.stdout));
+
+    let output = run(&["--module-path", "${RSLOX_UNSET_LIBRARY}"]);
+    assert_eq!(Some(70), output.status.code());
+    assert_eq!(
+        "Environment variable 'RSLOX_UNSET_LIBRARY' is not set.\n[line 1]\n",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_run_eval() {
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["run", "-e", "print 1 + 2;"])
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+    assert_eq!("3\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn test_run_stdin() {
+    use std::io::Write as _;
+    use std::process::Stdio;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["run", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("binary should run");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"var x = 2;\nprint x * 3;\nprint y;\n")
+        .expect("should write stdin");
+    let output = child.wait_with_output().expect("binary should finish");
+
+    assert_eq!(Some(70), output.status.code());
+    assert_eq!("6\n", String::from_utf8_lossy(&output.stdout));
+    assert_eq!(
+        "Undefined variable 'y'.\n[line 3]\n",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_record_then_replay_read_line() {
+    use std::io::Write as _;
+    use std::process::Stdio;
+
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "print readLine();\nprint readLine();\n");
+    let log = tempdir.path().join("run.log");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("run")
+        .arg("--record")
+        .arg(&log)
+        .arg(&file)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("binary should run");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"typed\n")
+        .expect("should write stdin");
+    let recorded = child.wait_with_output().expect("binary should finish");
+    assert!(recorded.status.success());
+    assert_eq!(
+        "{\"native\":\"readLine\",\"ok\":\"typed\"}\n{\"native\":\"readLine\",\"ok\":null}\n",
+        fs::read_to_string(&log).expect("should read log")
+    );
+
+    // replaying ignores stdin entirely
+    let replayed = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("run")
+        .arg("--replay")
+        .arg(&log)
+        .arg(&file)
+        .stdin(Stdio::null())
+        .output()
+        .expect("binary should run");
+    assert!(replayed.status.success());
+    assert_eq!(recorded.stdout, replayed.stdout);
+    assert_eq!(b"typed\nnil\n", &replayed.stdout[..]);
+}
+
+#[test]
+fn test_run_demo() {
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["run", "--demo", "binary-trees"])
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("depth 10: 2047 nodes\n", stdout);
+}
+
+#[rstest]
+#[case(&["run"])]
+#[case(&["run", "--demo", "zoo"])]
+#[case(&["run", "--demo", "fib", "main.lox"])]
+#[case(&["run", "-e", "print 1;", "main.lox"])]
+#[case(&["run", "-e", "print 1;", "--demo", "fib"])]
+fn test_run_usage_errors(#[case] args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(args)
+        .output()
+        .expect("binary should run");
+
+    assert_eq!(Some(2), output.status.code());
+}
+
+#[test]
+fn test_lint_warns_without_running() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "var f;\nprint \"ran\";\nf();\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("lint")
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!(
+        "[line 3] Warning at ')': Calling nil always fails.\n",
+        stderr
+    );
+}
+
+#[rstest]
+#[case(&["--allow", "nil-safety"], 0, "")]
+#[case(&["--deny", "nil-safety"], 65, "[line 3] Error at ')': Calling nil always fails.\n")]
+#[case(&["--deny", "warnings", "--warn", "nil-safety"], 0, "[line 3] Warning at ')': Calling nil always fails.\n")]
+#[case(&["--deny", "unused-variable"], 0, "[line 3] Warning at ')': Calling nil always fails.\n")]
+fn test_lint_levels(#[case] flags: &[&str], #[case] code: i32, #[case] expected: &str) {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "var f;\nprint \"ran\";\nf();\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("lint")
+        .arg(&file)
+        .args(flags)
+        .output()
+        .expect("binary should run");
+
+    assert_eq!(Some(code), output.status.code());
+    assert_eq!(expected, String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn test_lint_config() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "var f;\nf();\n");
+    let config = tempdir.path().join("lint.json");
+    fs::write(&config, r#"{"deny": ["warnings"]}"#).expect("should write config");
+
+    let lint = |flags: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+            .arg("lint")
+            .arg(&file)
+            .arg("--lint-config")
+            .arg(&config)
+            .args(flags)
+            .output()
+            .expect("binary should run")
+    };
+
+    assert_eq!(Some(65), lint(&[]).status.code());
+    assert_eq!(Some(0), lint(&["--warn", "nil-safety"]).status.code());
+}
+
+#[test]
+fn test_run_show_hoisted() {
+    let output = run_source_with_args(
+        "for (i in 0..3)\n  print i * (2 + 3);\n",
+        &[
+            "--enable",
+            "for-in,ranges,integers",
+            "--hoist",
+            "--show-hoisted",
+        ],
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("0\n5\n10\n", stdout);
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!("hoisted [line 2] (group (+ 2 3))\n", stderr);
+}
+
+#[test]
+fn test_run_warnings_before_running() {
+    let source = "{\n  var unused = 1;\n}\nprint \"ran\";\n";
+
+    let output = run_source_with_args(source, &["--warnings"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("ran\n", stdout);
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!(
+        "[line 2] Warning at 'unused': Local variable 'unused' is never read.\n",
+        stderr
+    );
+
+    let output = run_source(source);
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_run_deny_without_warnings() {
+    let source = "{\n  var unused = 1;\n}\nprint \"ran\";\n";
+
+    let output = run_source_with_args(source, &["--deny", "unused-variable"]);
+    assert_eq!(Some(65), output.status.code());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!(
+        "[line 2] Error at 'unused': Local variable 'unused' is never read.\n",
+        stderr
+    );
+
+    // warnings that are not denied stay quiet without --warnings
+    let source = "{\n  var unused = 1;\n}\nvar f;\nf();\n";
+    let output = run_source_with_args(source, &["--deny", "nil-safety"]);
+    assert_eq!(Some(65), output.status.code());
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert!(!stderr.contains("unused"), "{stderr}");
+}
+
+#[test]
+fn test_tokenize_verify_roundtrip() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "// greeting\nprint  \"hi\";\t\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["tokenize", "--verify-roundtrip"])
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("Round trip matches 26 bytes.\n", stdout);
+}
+
+#[rstest]
+#[case(
+    "csv",
+    "type,lexeme,literal,line,column,start,end\nSTRING,\"\"\"a\"\"\",a,1,1,0,3\nEOF,,,1,4,3,3\n"
+)]
+#[case(
+    "json",
+    "[\n  {\n    \"column\": 1,\n    \"lexeme\": \"\\\"a\\\"\",\n    \"line\": 1,\n    \"literal\": \"a\",\n    \"span\": {\n      \"end\": 3,\n      \"start\": 0\n    },\n    \"type\": \"STRING\"\n  },\n  {\n    \"column\": 4,\n    \"lexeme\": \"\",\n    \"line\": 1,\n    \"literal\": null,\n    \"span\": {\n      \"end\": 3,\n      \"start\": 3\n    },\n    \"type\": \"EOF\"\n  }\n]\n"
+)]
+fn test_tokenize_format(#[case] format: &str, #[case] expected: &str) {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "\"a\"");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["tokenize", "--format", format])
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+    assert_eq!(expected, String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn test_run_vm_backend_matches_tree() {
+    let source = "var n = 0;\n{\n  var i = 1;\n  do {\n    n = n + i;\n    i = i + 1;\n  } while (i <= 10);\n}\nprint \"sum: \" + str(n);\nprint nil ?? 1 / 0;\n";
+    let enable = ["--enable", "do-while,nil-coalescing"];
+
+    let tree = run_source_with_args(source, &enable);
+    let vm = run_source_with_args(source, &[&enable[..], &["--backend", "vm"]].concat());
+
+    assert_eq!(Some(70), vm.status.code());
+    assert_eq!(tree.status.code(), vm.status.code());
+    assert_eq!("sum: 55\n", String::from_utf8_lossy(&vm.stdout));
+    assert_eq!(tree.stdout, vm.stdout);
+    assert_eq!(tree.stderr, vm.stderr);
+}
+
+#[test]
+fn test_run_vm_backend_rejects_unsupported_code() {
+    let output = run_source_with_args(
+        "print 1;\nfor (c in \"ab\") print c;\n",
+        &["--enable", "for-in", "--backend", "vm"],
+    );
+
+    assert_eq!(Some(65), output.status.code());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!(
+        "[line 2] Error at 'for': The vm backend does not support 'for' outside of ranges yet.\n",
+        stderr
+    );
+}
+
+#[test]
+fn test_run_dump_bytecode() {
+    let output = run_source_with_args("print 1 + 2;\n", &["--dump-bytecode"]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("3\n", stdout);
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert_eq!(
+        "== script ==\n\
+         0000    1 OP_STATEMENT\n\
+         0001    | OP_CONSTANT           0 '1'\n\
+         0004    | OP_CONSTANT           1 '2'\n\
+         0007    | OP_BINARY             0 '+'\n\
+         0010    | OP_PRINT\n\
+         0011    | OP_RETURN\n",
+        stderr
+    );
+}
+
+#[test]
+fn test_run_optimize_folds_constants() {
+    let output = run_source_with_args(
+        "print 2 * 3 + 1;\nprint -\"a\";\n",
+        &["--optimize", "--dump-bytecode"],
+    );
+
+    assert_eq!(Some(70), output.status.code());
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!("7\n", stdout);
+    let stderr = String::from_utf8(output.stderr).expect("stderr should be utf8");
+    assert!(stderr.contains("0001    | OP_CONSTANT           0 '7'\n0004    | OP_PRINT\n"));
+    assert!(stderr.ends_with("Operand must be a number.\n[line 2]\n"));
+}
+
+#[test]
+fn test_parse_format_json() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "-x == \"true\"");
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["parse", "--format", "json"])
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+    let tree: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be json");
+    assert_eq!("binary", tree["type"]);
+    assert_eq!("==", tree["operator"]["lexeme"]);
+    assert_eq!(4, tree["operator"]["column"]);
+    assert_eq!("unary", tree["left"]["type"]);
+    assert_eq!("string", tree["right"]["kind"]);
+    assert_eq!("true", tree["right"]["value"]);
+    assert_eq!(serde_json::json!({"start": 0, "end": 12}), tree["span"]);
+    assert_eq!(
+        serde_json::json!({"start": 6, "end": 12}),
+        tree["right"]["span"]
+    );
+}
+
+#[test]
+fn test_parse_format_dot() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "-(x)");
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["parse", "--format", "dot"])
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+    assert_eq!(
+        "digraph ast {\n  \
+           n0 [label=\"-\"];\n  \
+           n1 [label=\"group\"];\n  \
+           n2 [label=\"x\"];\n  \
+           n1 -> n2 [label=\"expression\"];\n  \
+           n0 -> n1 [label=\"right\"];\n\
+         }\n",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+#[test]
+fn test_fmt_rewrites_file() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "var a=1;// one\n{print a+2;}");
+    let fmt = || {
+        Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+            .arg("fmt")
+            .arg(&file)
+            .output()
+            .expect("binary should run")
+    };
+
+    assert!(fmt().status.success());
+    let formatted = fs::read_to_string(&file).expect("should read formatted file");
+    assert_eq!("var a = 1; // one\n{\n  print a + 2;\n}\n", formatted);
+
+    assert!(fmt().status.success());
+    assert_eq!(formatted, fs::read_to_string(&file).unwrap());
+}
+
+#[test]
+fn test_fmt_check() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "print 1;\nprint  2 ;\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["fmt", "--check"])
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert_eq!(Some(3), output.status.code());
+    assert_eq!(
+        "- 2: print  2 ;\n+ 2: print 2;\n",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!("print 1;\nprint  2 ;\n", fs::read_to_string(&file).unwrap());
+}
+
+#[test]
+fn test_repl_batch() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "var a = 2;\n\nprint a;\na * 3\nb\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["repl", "--batch"])
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be utf8");
+    assert_eq!(
+        "var a = 2; => nil\n2\nprint a; => nil\na * 3 => 6\nb => Error: Undefined variable 'b'.\n",
+        stdout
+    );
+}
+
+#[test]
+fn test_run_watch_reruns_on_change() {
+    use std::io::{BufRead as _, BufReader};
+    use std::process::Stdio;
+
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let lib = tempdir.path().join("lib.lox");
+    fs::write(&lib, "var n = 1;").expect("should write module");
+    let file = write_temp_lox(&tempdir, "import n from \"lib.lox\";\nprint n;");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["run", "--enable", "modules", "--watch"])
+        .arg(&file)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("binary should run");
+    let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap()).lines();
+    // waits for the status line, since only then are the files watched
+    let mut next_run = || {
+        let mut errors = Vec::new();
+        let status = loop {
+            let line = stderr.next().unwrap().expect("stderr should be utf8");
+            match line.strip_prefix("[watch] ") {
+                Some(status) => break status.to_string(),
+                None => errors.push(line),
+            }
+        };
+        let printed = stdout.next().unwrap().expect("stdout should be utf8");
+        (printed, errors, status)
+    };
+    let watched = |status: &str| format!("{} {status}", file.display());
+    // replaces the file in one step like editors do, so a run never reads
+    // it half written, after waiting out the coarse clock of file times
+    let save = |path: &PathBuf, source: &str| {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let saved = path.with_extension("tmp");
+        fs::write(&saved, source).expect("should write source");
+        fs::rename(saved, path).expect("should replace source");
+    };
+
+    let (printed, errors, status) = next_run();
+    assert_eq!(("1", errors.len()), (printed.as_str(), 0));
+    assert_eq!(
+        watched("finished, waiting for changes to 2 file(s)"),
+        status
+    );
+
+    save(&lib, "var n = 2;");
+    assert_eq!("2", next_run().0);
+
+    save(&file, "import n from \"lib.lox\";\nprint n * 10;\nprint m;");
+    let (printed, errors, status) = next_run();
+    assert_eq!("20", printed);
+    assert_eq!(vec!["Undefined variable 'm'.", "[line 3]"], errors);
+    assert_eq!(
+        watched("failed with code 70, waiting for changes to 2 file(s)"),
+        status
+    );
+
+    child.kill().expect("watch should run until killed");
+    child.wait().expect("binary should finish");
+}
+
+#[rstest]
+#[case(&["--watch", "-"], "--watch needs a file, not stdin\n")]
+#[case(&["--watch", "-e", "print 1;"], "cannot be used with")]
+fn test_run_watch_usage_errors(#[case] args: &[&str], #[case] expected: &str) {
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("run")
+        .args(args)
+        .output()
+        .expect("binary should run");
+
+    assert_eq!(Some(2), output.status.code());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(expected), "{stderr}");
+}
+
+#[test]
+fn test_bench_json() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "var a = 1;\nprint a + 2;");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["bench", "--iterations", "3", "--backend", "vm", "--json"])
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+    // the program's own output is discarded, leaving only the report
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be json");
+    assert_eq!(3, report["iterations"]);
+    assert_eq!("vm", report["backend"]);
+    for phase in ["scan", "parse", "compile", "execute"] {
+        let summary = &report["phases"][phase];
+        assert!(summary["min"].as_f64().unwrap() <= summary["median"].as_f64().unwrap());
+    }
+    assert!(report["total"]["mean"].as_f64().unwrap() > 0.0);
+}
+
+#[rstest]
+#[case(&["-n", "2"], "print x;", Some(70), "Undefined variable 'x'.\n[line 1]\n")]
+#[case(&["-n", "0"], "print 1;", Some(2), "invalid value '0'")]
+fn test_bench_errors(
+    #[case] args: &[&str],
+    #[case] source: &str,
+    #[case] code: Option<i32>,
+    #[case] expected: &str,
+) {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, source);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("bench")
+        .args(args)
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert_eq!(code, output.status.code());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(expected), "{stderr}");
+}
+
+#[test]
+fn test_run_trace_to_file() {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "var a = 2;\nprint a * 3;");
+    let trace = tempdir.path().join("trace.txt");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("run")
+        .arg(format!("--trace={}", trace.display()))
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert!(output.status.success());
+    assert_eq!("6\n", String::from_utf8_lossy(&output.stdout));
+    assert!(output.stderr.is_empty());
+    assert_eq!(
+        "[line 1] var a\n  2.0 => 2\n[line 2] print\n    a => 2\n    3.0 => 3\n  (* a 3.0) => 6\n",
+        fs::read_to_string(trace).expect("should write trace")
+    );
+}
+
+#[rstest]
+#[case("evaluate", &[], "1 + 2", Some(0), "  1.0 => 1\n  2.0 => 2\n(+ 1.0 2.0) => 3\n")]
+#[case("run", &["--backend", "vm"], "print 1;", Some(2), "--trace only applies to the tree backend\n")]
+fn test_trace_to_stderr(
+    #[case] subcommand: &str,
+    #[case] args: &[&str],
+    #[case] source: &str,
+    #[case] code: Option<i32>,
+    #[case] expected_stderr: &str,
+) {
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, source);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args([subcommand, "--trace"])
+        .args(args)
+        .arg(&file)
+        .output()
+        .expect("binary should run");
+
+    assert_eq!(code, output.status.code());
+    assert_eq!(expected_stderr, String::from_utf8_lossy(&output.stderr));
+}
+
+#[rstest]
+#[case(
+    "b 3\nc\np x * 2\nc\n",
+    Some(70),
+    "[line 1] var x = 1;\n[line 3] print x;\n4\n2\n"
+)]
+#[case("n\nq\n", Some(0), "[line 1] var x = 1;\n[line 2] x = x + 1;\n")]
+#[case("c\n", Some(70), "[line 1] var x = 1;\n2\n")]
+fn test_debug(#[case] commands: &str, #[case] code: Option<i32>, #[case] expected_stdout: &str) {
+    use std::io::Write as _;
+    use std::process::Stdio;
+
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "var x = 1;\nx = x + 1;\nprint x;\nprint y;");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("debug")
+        .arg(&file)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("binary should run");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(commands.as_bytes())
+        .expect("should write stdin");
+    let output = child.wait_with_output().expect("binary should finish");
+
+    assert_eq!(code, output.status.code());
+    // the debugger and the program share stdout
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(expected_stdout, stdout.replace("(debug) ", ""));
+}
+
+#[test]
+fn test_dap() {
+    use std::io::Write as _;
+    use std::process::Stdio;
+
+    let tempdir = TempDir::new("codecrafters-interpreter").expect("should create temp dir");
+    let file = write_temp_lox(&tempdir, "var x = 1;\nprint x;\nprint y;");
+    let requests = [
+        r#"{"seq":1,"type":"request","command":"initialize","arguments":{}}"#.to_string(),
+        format!(
+            r#"{{"seq":2,"type":"request","command":"launch","arguments":{{"program":{}}}}}"#,
+            serde_json::to_string(&file).unwrap()
+        ),
+        r#"{"seq":3,"type":"request","command":"configurationDone"}"#.to_string(),
+        r#"{"seq":4,"type":"request","command":"disconnect"}"#.to_string(),
+    ];
+    let input = requests
+        .iter()
+        .map(|body| format!("Content-Length: {}\r\n\r\n{body}", body.len()))
+        .collect::<String>();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("dap")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("binary should run");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .expect("should write stdin");
+    let output = child.wait_with_output().expect("binary should finish");
+
+    assert_eq!(Some(0), output.status.code());
+    // what the program prints and fails with arrives as events, in order
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let events = [
+        r#""event":"initialized""#,
+        r#""category":"stdout","output":"1\n""#,
+        r#""category":"stderr","output":"Undefined variable 'y'.\n[line 3]\n""#,
+        r#""body":{"exitCode":70}"#,
+        r#""event":"terminated""#,
+        r#""command":"disconnect""#,
+    ];
+    let mut rest = &*stdout;
+    for event in events {
+        let at = rest
+            .find(event)
+            .unwrap_or_else(|| panic!("{event} should follow in {stdout}"));
+        rest = &rest[at + event.len()..];
+    }
+}
+
+#[test]
+fn test_lsp() {
+    use std::io::Write as _;
+    use std::process::Stdio;
+
+    let messages = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"capabilities":{}}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.lox","text":"print x;"}}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ];
+    let input = messages
+        .iter()
+        .map(|body| format!("Content-Length: {}\r\n\r\n{body}", body.len()))
+        .collect::<String>();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("binary should run");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .expect("should write stdin");
+    let output = child.wait_with_output().expect("binary should finish");
+
+    assert_eq!(Some(0), output.status.code());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = [
+        r#""hoverProvider":true"#,
+        r#""method":"textDocument/publishDiagnostics""#,
+        r#""message":"Undefined variable 'x'.""#,
+        r#""id":2,"jsonrpc":"2.0","result":null"#,
+    ];
+    let mut rest = &*stdout;
+    for message in expected {
+        let at = rest
+            .find(message)
+            .unwrap_or_else(|| panic!("{message} should follow in {stdout}"));
+        rest = &rest[at + message.len()..];
+    }
+}